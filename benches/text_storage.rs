@@ -0,0 +1,114 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use tui_studio::utils::text_storage::TextStorage;
+
+const WINDOW_HEIGHT: usize = 50;
+
+// 以前のバッファ (Vec<Vec<char>>) と同じ操作を再現したもの
+struct VecStorage {
+    content: Vec<Vec<char>>,
+}
+
+impl VecStorage {
+    fn from(text: &str) -> Self {
+        Self {
+            content: text
+                .split('\n')
+                .map(|line| line.chars().collect())
+                .collect(),
+        }
+    }
+
+    fn insert_char(&mut self, x: usize, y: usize, ch: char) {
+        self.content[y].insert(x, ch);
+    }
+
+    fn split_line(&mut self, x: usize, y: usize) {
+        let original = self.content[y].clone();
+        let (p0, p1) = original.split_at(x);
+        self.content[y] = p0.to_vec();
+        self.content.insert(y + 1, p1.to_vec());
+    }
+
+    fn join_lines(&mut self, y: usize) {
+        let combined = self.content[y].iter().collect::<String>()
+            + &self.content[y + 1].iter().collect::<String>();
+        self.content[y] = combined.chars().collect();
+        self.content.remove(y + 1);
+    }
+
+    fn get_lines(&self, start: usize, end: usize) -> Vec<String> {
+        self.content[start..end.min(self.content.len())]
+            .iter()
+            .map(|line| line.iter().collect())
+            .collect()
+    }
+}
+
+fn generate_text(line_count: usize) -> String {
+    (0..line_count)
+        .map(|i| {
+            format!(
+                "{:>8} lorem ipsum dolor sit amet, consectetur adipiscing elit",
+                i
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// 1回のキー入力で行われる処理 (編集 + 描画に必要な行の取得、ハイライトも表示範囲だけが対象)
+fn bench_keystroke(c: &mut Criterion) {
+    let mut group = c.benchmark_group("keystroke");
+
+    for line_count in [1_000, 10_000, 100_000] {
+        let text = generate_text(line_count);
+        let y = line_count / 2;
+
+        group.bench_with_input(BenchmarkId::new("vec", line_count), &text, |b, text| {
+            let mut storage = VecStorage::from(text);
+            b.iter(|| {
+                storage.insert_char(10, y, 'a');
+                black_box(storage.get_lines(y, y + WINDOW_HEIGHT));
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("rope", line_count), &text, |b, text| {
+            let mut storage = TextStorage::from(text.as_str());
+            b.iter(|| {
+                storage.insert_char(10, y, 'a');
+                black_box(storage.lines(y, y + WINDOW_HEIGHT));
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_split_join(c: &mut Criterion) {
+    let mut group = c.benchmark_group("split_join");
+
+    for line_count in [1_000, 10_000, 100_000] {
+        let text = generate_text(line_count);
+
+        group.bench_with_input(BenchmarkId::new("vec", line_count), &text, |b, text| {
+            let mut storage = VecStorage::from(text);
+            b.iter(|| {
+                storage.split_line(20, 0);
+                storage.join_lines(0);
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("rope", line_count), &text, |b, text| {
+            let mut storage = TextStorage::from(text.as_str());
+            b.iter(|| {
+                storage.insert_char(20, 0, '\n');
+                storage.remove(storage.line_len(0), 0, 1);
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_keystroke, bench_split_join);
+criterion_main!(benches);
@@ -1,7 +1,20 @@
-use crate::editor::action::EditorAction;
+use crate::{
+    editor::action::EditorAction, explorer::action::ExplorerAction,
+    quickfix::action::QuickfixAction, replace::action::ReplaceAction,
+    terminal::action::TerminalAction,
+};
 
 #[derive(Debug, Clone, Hash)]
 pub enum AppAction {
     Quit,
     EditorAction(EditorAction),
+    Count(usize, Box<AppAction>), // 回数指定付きのアクション (例: 5j, 3dd)
+    RecordMacro(char),
+    StopMacroRecording,
+    PlayMacro(char), // '@' は直前に再生したマクロ
+    ExplorerAction(ExplorerAction),
+    OpenFinder,
+    QuickfixAction(QuickfixAction),
+    ReplaceAction(ReplaceAction),
+    TerminalAction(TerminalAction),
 }
@@ -1,4 +1,6 @@
 use std::{
+    io::{stdout, Write},
+    path::Path,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc, RwLock,
@@ -9,37 +11,109 @@ use std::{
 
 use crate::{action::AppAction, utils::vec2::UVec2};
 use crate::{
-    editor::editor::Editor,
+    editor::{
+        action::{EditorAction, EditorWindowAction},
+        editor::Editor,
+        mode::EditorMode,
+        register::EditorRegisters,
+        search::split_pattern,
+        window::SplitDirection,
+    },
+    explorer::{
+        action::ExplorerAction,
+        explorer::{ExplorerEvent, FileExplorer},
+        renderer::ExplorerRenderer,
+    },
+    finder::{
+        finder::{FileFinder, FinderEvent, FinderTarget},
+        renderer::FinderRenderer,
+    },
+    quickfix::{
+        action::QuickfixAction,
+        quickfix::{QuickfixEvent, QuickfixList},
+        renderer::QuickfixRenderer,
+    },
+    replace::{
+        action::ReplaceAction,
+        renderer::ReplaceRenderer,
+        replace::{write_files, FileEdit, ReplaceEvent, WorkspaceReplace},
+    },
+    terminal::{
+        action::TerminalAction,
+        renderer::TerminalRenderer,
+        terminal::{TerminalEvent, TerminalPane},
+    },
     utils::{
-        command::CommandManager,
+        command::{CommandManager, ExCommand},
         event::Event,
-        key_binding::{Key, KeyConfig},
+        key_binding::{Key, KeyConfig, KeyConfigType},
         rect::Rect,
         term::get_term_size,
         vec2::IVec2,
     },
 };
+use anyhow::anyhow;
 use chrono::{DateTime, Duration, Utc};
-use crossterm::event::{self, Event as CrosstermEvent, MouseEventKind};
+use crossterm::{
+    cursor::{Hide, MoveTo, RestorePosition, SavePosition, Show},
+    event::{self, Event as CrosstermEvent, MouseEventKind},
+    queue,
+};
 
 pub struct App {
     editor: Editor,
+    explorer: FileExplorer,
+    explorer_renderer: ExplorerRenderer,
+    finder: FileFinder,
+    finder_renderer: FinderRenderer,
+    quickfix: QuickfixList,
+    quickfix_renderer: QuickfixRenderer,
+    replace: WorkspaceReplace,
+    replace_renderer: ReplaceRenderer,
+    terminal: TerminalPane,
+    terminal_renderer: TerminalRenderer,
     key_config: KeyConfig,
     cmd_mgr: CommandManager,
     first_key_time: Option<DateTime<Utc>>,
     key_buf: Vec<Key>,
+    macro_recording: Option<(char, Vec<Key>)>,
+    last_macro: Option<char>,
+    macro_depth: usize,
+    in_global: bool,
 }
 
+const MAX_MACRO_DEPTH: usize = 100;
+
+// 入力がないときに on_idle を呼ぶ間隔
+const IDLE_INTERVAL: StdDuration = StdDuration::from_millis(50);
+
+// execute_command で処理するコマンド (補完用)
+const APP_COMMANDS: &[&str] = &["global", "grep", "replace", "vglobal"];
+
 impl App {
-    pub(crate) fn new(path: Option<String>) -> anyhow::Result<Self> {
+    pub(crate) fn new(paths: Vec<String>) -> anyhow::Result<Self> {
         let term_size = get_term_size()?;
 
         Ok(Self {
-            editor: Editor::new(path, Rect::new(UVec2::default(), term_size))?,
+            editor: Editor::new(paths, Rect::new(UVec2::default(), term_size))?,
+            explorer: FileExplorer::new(std::env::current_dir()?),
+            explorer_renderer: ExplorerRenderer::default(),
+            finder: FileFinder::new(std::env::current_dir()?),
+            finder_renderer: FinderRenderer::default(),
+            quickfix: QuickfixList::default(),
+            quickfix_renderer: QuickfixRenderer::default(),
+            replace: WorkspaceReplace::default(),
+            replace_renderer: ReplaceRenderer::default(),
+            terminal: TerminalPane::default(),
+            terminal_renderer: TerminalRenderer::default(),
             key_config: KeyConfig::default(),
             cmd_mgr: CommandManager::default(),
             key_buf: Vec::new(),
             first_key_time: None,
+            macro_recording: None,
+            last_macro: None,
+            macro_depth: 0,
+            in_global: false,
         })
     }
 
@@ -48,6 +122,50 @@ impl App {
         self.editor.register_keybindings(&mut self.key_config);
         self.editor.register_commands(&mut self.cmd_mgr);
 
+        // Explorer
+        self.explorer.register_keybindings(&mut self.key_config);
+        self.explorer.register_commands(&mut self.cmd_mgr);
+
+        // Finder
+        self.finder.register_keybindings(&mut self.key_config);
+        self.finder.register_commands(&mut self.cmd_mgr);
+
+        // Quickfix
+        self.quickfix.register_keybindings(&mut self.key_config);
+        self.quickfix.register_commands(&mut self.cmd_mgr);
+
+        // Replace
+        self.replace.register_keybindings(&mut self.key_config);
+
+        // Terminal
+        self.terminal.register_keybindings(&mut self.key_config);
+        self.terminal.register_commands(&mut self.cmd_mgr);
+
+        let mut command_names = self.cmd_mgr.get_names();
+        command_names.extend(APP_COMMANDS.iter().map(|name| name.to_string()));
+        self.editor.set_command_names(command_names);
+
+        // Macro
+        for name in (0..=u8::MAX).map(char::from) {
+            if EditorRegisters::is_valid_name(name) {
+                self.key_config.register(
+                    KeyConfigType::Normal,
+                    vec![Key::Char('q'), Key::Char(name)],
+                    AppAction::RecordMacro(name),
+                );
+                self.key_config.register(
+                    KeyConfigType::Normal,
+                    vec![Key::Char('@'), Key::Char(name)],
+                    AppAction::PlayMacro(name),
+                );
+            }
+        }
+        self.key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Char('@'), Key::Char('@')],
+            AppAction::PlayMacro('@'),
+        );
+
         Ok(())
     }
 
@@ -57,44 +175,32 @@ impl App {
     ) -> anyhow::Result<Option<Event>> {
         match evt {
             CrosstermEvent::Key(evt) => {
-                if self.key_buf.is_empty() {
-                    self.first_key_time = Some(Utc::now())
-                } else if let Some(first_key_time) = self.first_key_time {
-                    let now = Utc::now();
-                    let elapsed = now - first_key_time;
-
-                    if elapsed >= Duration::milliseconds(500) {
-                        self.key_buf = Vec::new();
-                    }
-                }
-
-                self.key_buf.push(Key::from(evt));
+                let key = Key::from(evt);
+                self.editor.clear_message();
 
-                match self
-                    .key_config
-                    .get_action(self.editor.get_mode(), self.key_buf.clone())
-                {
-                    None => {}
-                    Some(action) => {
-                        self.key_buf = Vec::new();
-                        return Ok(Some(Event::Action(action.clone())));
+                if let Some((_, keys)) = &mut self.macro_recording {
+                    if key == Key::Char('q')
+                        && self.key_buf.is_empty()
+                        && self.editor.get_mode() == EditorMode::Normal
+                    {
+                        return Ok(Some(Event::Action(AppAction::StopMacroRecording)));
                     }
-                };
 
-                return Ok(Some(Event::Input(Key::from(evt))));
+                    keys.push(key.clone());
+                }
+
+                return self.on_key(key);
             }
             CrosstermEvent::Mouse(evt) => match evt.kind {
                 MouseEventKind::ScrollUp => return Ok(Some(Event::Scroll(IVec2::up()))),
                 MouseEventKind::ScrollDown => return Ok(Some(Event::Scroll(IVec2::down()))),
                 MouseEventKind::ScrollLeft => return Ok(Some(Event::Scroll(IVec2::left()))),
                 MouseEventKind::ScrollRight => return Ok(Some(Event::Scroll(IVec2::right()))),
-                MouseEventKind::Down(btn) => {
-                    if btn == crossterm::event::MouseButton::Left {
-                        return Ok(Some(Event::Click(UVec2::new(
-                            evt.column as usize,
-                            evt.row as usize,
-                        ))));
-                    }
+                MouseEventKind::Down(crossterm::event::MouseButton::Left) => {
+                    return Ok(Some(Event::Click(UVec2::new(
+                        evt.column as usize,
+                        evt.row as usize,
+                    ))));
                 }
                 _ => {}
             },
@@ -104,32 +210,509 @@ impl App {
         Ok(None)
     }
 
+    // キー入力をキーバインドに従ってイベントに変換する (マクロの再生もここを通る)
+    fn on_key(&mut self, key: Key) -> anyhow::Result<Option<Event>> {
+        // ファインダーやエクスプローラーの入力欄にはキーバインドを使わずにそのまま渡す
+        if self.finder.is_open()
+            || (self.explorer.is_focused() && self.explorer.get_prompt().is_some())
+        {
+            return Ok(Some(Event::Input(key)));
+        }
+
+        let mode = self.editor.get_mode();
+
+        // 端末への入力中はすべてのキーをシェルに送る
+        if self.terminal.is_terminal_mode() && mode == EditorMode::Normal {
+            self.key_buf = Vec::new();
+            return Ok(Some(Event::Input(key)));
+        }
+
+        // 回数の入力中はタイムアウトさせない
+        if self.key_buf.len() == Self::parse_count(&mode, &self.key_buf).1 {
+            self.first_key_time = Some(Utc::now())
+        } else if let Some(first_key_time) = self.first_key_time {
+            let now = Utc::now();
+            let elapsed = now - first_key_time;
+
+            if elapsed >= Duration::milliseconds(500) {
+                self.key_buf = Vec::new();
+            }
+        }
+
+        self.key_buf.push(key.clone());
+        let (mut count, mut count_len) = Self::parse_count(&mode, &self.key_buf);
+        let config_types = self.get_key_config_types();
+
+        // 続きのないキー列は最後のキーだけで判定し直す
+        if self.key_buf.len() > count_len + 1
+            && self
+                .key_config
+                .get_action(&config_types, self.key_buf[count_len..].to_vec())
+                .is_none()
+            && !self
+                .key_config
+                .is_prefix(&config_types, &self.key_buf[count_len..])
+        {
+            self.key_buf = vec![key.clone()];
+            (count, count_len) = Self::parse_count(&mode, &self.key_buf);
+        }
+
+        let sequence = self.key_buf[count_len..].to_vec();
+        if !sequence.is_empty() {
+            if let Some(action) = self.key_config.get_action(&config_types, sequence.clone()) {
+                let action = match count {
+                    Some(count) => AppAction::Count(count, Box::new(action.clone())),
+                    None => action.clone(),
+                };
+
+                self.key_buf = Vec::new();
+                self.editor.set_pending_keys(&self.key_buf);
+                return Ok(Some(Event::Action(action)));
+            }
+
+            if !self.key_config.is_prefix(&config_types, &sequence) {
+                self.key_buf = Vec::new();
+            }
+        }
+
+        self.editor.set_pending_keys(&self.key_buf);
+        Ok(Some(Event::Input(key)))
+    }
+
+    // エクスプローラーや一覧にフォーカスがあるときはそれぞれのキーバインドを使う
+    fn get_key_config_types(&self) -> Vec<KeyConfigType> {
+        let mode = self.editor.get_mode();
+        if self.explorer.is_focused() {
+            vec![KeyConfigType::All, KeyConfigType::Explorer]
+        } else if self.quickfix.is_focused() && mode == EditorMode::Normal {
+            vec![KeyConfigType::All, KeyConfigType::Quickfix]
+        } else if self.replace.is_focused() && mode == EditorMode::Normal {
+            vec![KeyConfigType::All, KeyConfigType::Replace]
+        } else if self.terminal.is_focused() && mode == EditorMode::Normal {
+            vec![KeyConfigType::All, KeyConfigType::Terminal]
+        } else {
+            KeyConfig::get_config_types(mode)
+        }
+    }
+
+    // key_buf の先頭にある回数指定を取り出す ('0' から始まる場合は行頭への移動として扱う)
+    fn parse_count(mode: &EditorMode, keys: &[Key]) -> (Option<usize>, usize) {
+        if !(mode.is_visual()
+            || matches!(mode, EditorMode::Normal | EditorMode::OperatorPending(_)))
+        {
+            return (None, 0);
+        }
+
+        let digits: Vec<usize> = keys
+            .iter()
+            .map_while(|key| match key {
+                Key::Char(c) => c.to_digit(10).map(|digit| digit as usize),
+                _ => None,
+            })
+            .collect();
+
+        match digits.first() {
+            None | Some(0) => (None, 0),
+            Some(_) => (
+                Some(digits.iter().fold(0usize, |count, digit| {
+                    count.saturating_mul(10).saturating_add(*digit)
+                })),
+                digits.len(),
+            ),
+        }
+    }
+
     pub(crate) fn on_action(&mut self, action: AppAction) -> anyhow::Result<bool> {
+        self.on_action_with_count(action, None)
+    }
+
+    fn on_action_with_count(
+        &mut self,
+        action: AppAction,
+        count: Option<usize>,
+    ) -> anyhow::Result<bool> {
         match action {
-            AppAction::Quit => return Ok(true),
-            AppAction::EditorAction(action) => self.editor.on_action(action)?,
+            AppAction::Quit => {
+                // 分割中やタブが複数あるときはウィンドウを閉じるだけにする
+                if self.editor.get_window_count() > 1 || self.editor.get_tab_count() > 1 {
+                    self.editor.close_window()?;
+                } else {
+                    return Ok(true);
+                }
+            }
+            AppAction::EditorAction(EditorAction::Window(EditorWindowAction::FocusLeft))
+                if self.explorer.is_visible() && self.editor.is_leftmost_window() =>
+            {
+                self.editor.set_mode(EditorMode::Normal)?;
+                self.explorer.set_focused(true);
+            }
+            AppAction::EditorAction(EditorAction::Window(EditorWindowAction::FocusDown))
+                if self.editor.get_panel_rect().is_some() && self.editor.is_bottommost_window() =>
+            {
+                self.editor.set_mode(EditorMode::Normal)?;
+                self.quickfix.set_focused(self.quickfix.is_visible());
+                self.replace.set_focused(self.replace.is_visible());
+                self.terminal.set_focused(self.terminal.is_visible());
+            }
+            AppAction::EditorAction(action) => self.editor.on_action(action, count)?,
+            AppAction::Count(count, action) => {
+                return self.on_action_with_count(*action, Some(count))
+            }
+            AppAction::RecordMacro(name) => self.start_macro_recording(name),
+            AppAction::StopMacroRecording => self.stop_macro_recording(),
+            AppAction::PlayMacro(name) => return self.play_macro(name, count),
+            AppAction::ExplorerAction(action) => self.on_explorer_action(action, count)?,
+            AppAction::OpenFinder => {
+                self.editor.set_mode(EditorMode::Normal)?;
+                self.finder.open();
+            }
+            AppAction::QuickfixAction(action) => self.on_quickfix_action(action, count)?,
+            AppAction::ReplaceAction(action) => self.on_replace_action(action, count)?,
+            AppAction::TerminalAction(action) => self.on_terminal_action(action, count)?,
         };
 
         Ok(false)
     }
 
+    // サイドバーのツリーを表示できる行数 (最上行のディレクトリ名と最下行のコマンドラインを除く)
+    fn get_explorer_height(&self) -> anyhow::Result<usize> {
+        Ok(get_term_size()?.y.saturating_sub(2))
+    }
+
+    fn on_explorer_action(
+        &mut self,
+        action: ExplorerAction,
+        count: Option<usize>,
+    ) -> anyhow::Result<()> {
+        if let ExplorerAction::Toggle | ExplorerAction::Focus = action {
+            self.editor.set_mode(EditorMode::Normal)?;
+        }
+
+        let height = self.get_explorer_height()?;
+        let event = self.explorer.on_action(action, count, height)?;
+        if self.explorer.is_focused() {
+            self.quickfix.set_focused(false);
+            self.replace.set_focused(false);
+            self.terminal.set_focused(false);
+        }
+        self.editor.set_sidebar_width(self.explorer.get_width());
+        self.on_explorer_event(event)
+    }
+
+    fn on_explorer_event(&mut self, event: Option<ExplorerEvent>) -> anyhow::Result<()> {
+        match event {
+            Some(ExplorerEvent::Open(path)) => {
+                // 作業ディレクトリからの相対パスで開く (:ls などで表示される名前)
+                let current_dir = std::env::current_dir()?;
+                let path = path
+                    .strip_prefix(&current_dir)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .to_string();
+                self.editor.edit_file(&path)
+            }
+            Some(ExplorerEvent::Moved { from, to }) => self.editor.rename_buffer_paths(&from, &to),
+            Some(ExplorerEvent::Deleted(path)) => self.editor.mark_buffers_deleted(&path),
+            None => Ok(()),
+        }
+    }
+
+    fn on_quickfix_action(
+        &mut self,
+        action: QuickfixAction,
+        count: Option<usize>,
+    ) -> anyhow::Result<()> {
+        if let QuickfixAction::Open = action {
+            self.editor.set_mode(EditorMode::Normal)?;
+            self.replace.close();
+            self.terminal.close();
+        }
+
+        let event = self.quickfix.on_action(action, count)?;
+        if self.quickfix.is_focused() {
+            self.explorer.set_focused(false);
+        }
+        self.update_panel_height();
+        self.on_quickfix_event(event)
+    }
+
+    // 下のパネルは置換候補・端末・一覧のどれか1つだけを表示する
+    fn update_panel_height(&mut self) {
+        let height = if self.replace.is_visible() {
+            self.replace.get_height()
+        } else if self.terminal.is_visible() {
+            self.terminal.get_height()
+        } else {
+            self.quickfix.get_height()
+        };
+        self.editor.set_panel_height(height);
+        self.sync_terminal_size();
+    }
+
+    // 端末の大きさをパネルに合わせる (タイトル行を除く)
+    fn sync_terminal_size(&mut self) {
+        if !self.terminal.is_visible() {
+            return;
+        }
+
+        if let Some(rect) = self.editor.get_panel_rect() {
+            let size = UVec2::new(rect.size.x, rect.size.y.saturating_sub(1));
+            if let Err(err) = self.terminal.resize(size) {
+                log::warn!("Failed to resize terminal: {}", err);
+            }
+        }
+    }
+
+    // :grep pattern [path]
+    fn grep(&mut self, args: &str) -> anyhow::Result<()> {
+        self.quickfix.grep(args)?;
+        self.replace.close();
+        self.terminal.close();
+        self.explorer.set_focused(false);
+        self.update_panel_height();
+        Ok(())
+    }
+
+    fn on_quickfix_event(&mut self, event: Option<QuickfixEvent>) -> anyhow::Result<()> {
+        let Some(QuickfixEvent::Jump(item)) = event else {
+            return Ok(());
+        };
+
+        self.explorer.set_focused(false);
+        self.editor.edit_file(&item.path)?;
+        self.editor.move_to(UVec2::new(item.column, item.line));
+        Ok(())
+    }
+
+    // :replace pattern replacement
+    fn replace(&mut self, args: &str) -> anyhow::Result<()> {
+        self.replace.search(args, self.editor.get_buffer_lines())?;
+        self.quickfix.close();
+        self.terminal.close();
+        self.explorer.set_focused(false);
+        self.update_panel_height();
+        Ok(())
+    }
+
+    fn on_replace_action(
+        &mut self,
+        action: ReplaceAction,
+        count: Option<usize>,
+    ) -> anyhow::Result<()> {
+        let event = self.replace.on_action(action, count)?;
+        self.update_panel_height();
+
+        match event {
+            Some(ReplaceEvent::Jump { path, line, column }) => {
+                self.explorer.set_focused(false);
+                self.editor.edit_file(&path)?;
+                self.editor.move_to(UVec2::new(column, line));
+            }
+            Some(ReplaceEvent::Apply(files)) => self.apply_replace(files)?,
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    fn on_terminal_action(
+        &mut self,
+        action: TerminalAction,
+        count: Option<usize>,
+    ) -> anyhow::Result<()> {
+        if let TerminalAction::Open = action {
+            self.editor.set_mode(EditorMode::Normal)?;
+            self.explorer.set_focused(false);
+            self.quickfix.close();
+            self.replace.close();
+            self.editor
+                .set_panel_height(self.terminal.get_height().max(1));
+
+            let rect = self.editor.get_panel_rect().unwrap_or_default();
+            self.terminal
+                .open(UVec2::new(rect.size.x, rect.size.y.saturating_sub(1)))?;
+        }
+
+        let event = self.terminal.on_action(action, count)?;
+        self.update_panel_height();
+
+        if let Some(TerminalEvent::Yank(register)) = event {
+            self.editor.get_registers_mut().yank(register);
+        }
+
+        Ok(())
+    }
+
+    // 開いているバッファは書き換え (undo できる)、開いていないファイルは直接書き込む。
+    // どちらも先にすべて検索したときの内容のままか確かめてから置換する
+    fn apply_replace(&mut self, files: Vec<FileEdit>) -> anyhow::Result<()> {
+        let (open_files, closed_files): (Vec<_>, Vec<_>) = files
+            .into_iter()
+            .partition(|file| self.editor.has_buffer(Path::new(&file.path)));
+
+        for file in &open_files {
+            self.editor
+                .check_line_edits(Path::new(&file.path), &file.edits)?;
+        }
+        write_files(&closed_files)?;
+        for file in &open_files {
+            self.editor
+                .apply_line_edits(Path::new(&file.path), &file.edits)?;
+        }
+
+        let (count, _) = self.replace.get_counts();
+        self.replace.close();
+        self.update_panel_height();
+        self.editor.set_message(format!(
+            "{} substitutions in {} files",
+            count,
+            open_files.len() + closed_files.len()
+        ));
+        Ok(())
+    }
+
+    fn on_finder_event(&mut self, event: Option<FinderEvent>) -> anyhow::Result<()> {
+        let Some(FinderEvent::Open(path, target)) = event else {
+            return Ok(());
+        };
+
+        // 選んだファイルはエディタで開くので、サイドバーからはフォーカスを外す
+        self.explorer.set_focused(false);
+
+        match target {
+            FinderTarget::Buffer => self.editor.edit_file(&path),
+            FinderTarget::Split => self.editor.split_window(SplitDirection::Horizontal, &path),
+            FinderTarget::VerticalSplit => {
+                self.editor.split_window(SplitDirection::Vertical, &path)
+            }
+            FinderTarget::Tab => self.editor.new_tab(&path),
+        }
+    }
+
+    fn start_macro_recording(&mut self, name: char) {
+        self.macro_recording = Some((name, Vec::new()));
+        self.editor.set_recording_macro(Some(name));
+    }
+
+    // 記録したキー列はテキストとしてレジスタに保存する
+    fn stop_macro_recording(&mut self) {
+        if let Some((name, keys)) = self.macro_recording.take() {
+            let text = keys.iter().map(|key| key.to_string()).collect();
+            self.editor.get_registers_mut().set_macro(name, text);
+        }
+
+        self.editor.set_recording_macro(None);
+    }
+
+    fn play_macro(&mut self, name: char, count: Option<usize>) -> anyhow::Result<bool> {
+        let name = match name {
+            '@' => self
+                .last_macro
+                .ok_or_else(|| anyhow!("No previously used register"))?,
+            name => name,
+        };
+
+        let Some(register) = self.editor.get_registers_mut().get(name)? else {
+            return Err(anyhow!("Register {} is empty", name));
+        };
+
+        if self.macro_depth >= MAX_MACRO_DEPTH {
+            return Err(anyhow!("Macro recursion is too deep"));
+        }
+
+        self.last_macro = Some(name);
+        self.macro_depth += 1;
+
+        let keys = Key::parse_sequence(&register.text);
+        let result = self.play_keys(&keys, count.unwrap_or(1));
+
+        self.macro_depth -= 1;
+        result
+    }
+
+    // 最初のエラーで再生を中断する
+    fn play_keys(&mut self, keys: &[Key], count: usize) -> anyhow::Result<bool> {
+        for _ in 0..count {
+            for key in keys {
+                if let Some(event) = self.on_key(key.clone())? {
+                    if self.on_event(event)? {
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
     pub(crate) fn on_event(&mut self, evt: Event) -> anyhow::Result<bool> {
         match evt {
             Event::Quit => {
                 return Ok(true);
             }
-            Event::Command(cmd) => {
-                let commands = self.cmd_mgr.clone();
-                if let Some(actions) = commands.get_command(cmd.as_str()) {
-                    for action in actions {
-                        if self.on_event(Event::Action(action.clone()))? {
-                            return Ok(true);
-                        }
+            Event::Command(cmd) => return self.execute_command(&cmd),
+            Event::Action(action) => return self.on_action(action),
+            Event::Input(key) if self.finder.is_open() => {
+                let event = self.finder.on_key(key);
+                self.on_finder_event(event)?;
+            }
+            Event::Input(key) if self.explorer.is_focused() => {
+                let event = self.explorer.on_prompt_key(key)?;
+                self.on_explorer_event(event)?;
+            }
+            Event::Input(key)
+                if self.terminal.is_focused() && self.editor.get_mode() == EditorMode::Normal =>
+            {
+                self.terminal.on_key(key)?;
+                self.update_panel_height();
+            }
+            // 一覧ではキーバインド以外のキーは使わない
+            Event::Input(_)
+                if (self.quickfix.is_focused() || self.replace.is_focused())
+                    && self.editor.get_mode() == EditorMode::Normal => {}
+            Event::Click(pos) if pos.x < self.explorer.get_width() => {
+                self.editor.set_mode(EditorMode::Normal)?;
+                let event = self.explorer.on_click(pos.y)?;
+                self.on_explorer_event(event)?;
+            }
+            Event::Click(pos)
+                if self
+                    .editor
+                    .get_panel_rect()
+                    .is_some_and(|rect| rect.contains(pos)) =>
+            {
+                self.editor.set_mode(EditorMode::Normal)?;
+                self.explorer.set_focused(false);
+                let rect = self.editor.get_panel_rect().unwrap();
+                if self.replace.is_visible() {
+                    if let Some(ReplaceEvent::Jump { path, line, column }) =
+                        self.replace.on_click(pos.y - rect.pos.y)
+                    {
+                        self.editor.edit_file(&path)?;
+                        self.editor.move_to(UVec2::new(column, line));
                     }
+                } else if self.terminal.is_visible() {
+                    self.terminal.on_click();
+                } else {
+                    let event = self.quickfix.on_click(pos.y - rect.pos.y);
+                    self.on_quickfix_event(event)?;
                 }
             }
-            Event::Action(action) => return self.on_action(action),
+            Event::Scroll(offset) if self.explorer.is_focused() => {
+                let height = self.get_explorer_height()?;
+                self.explorer.on_scroll(offset, height);
+            }
+            Event::Scroll(offset) if self.quickfix.is_focused() => self.quickfix.on_scroll(offset),
+            Event::Scroll(offset) if self.replace.is_focused() => self.replace.on_scroll(offset),
+            Event::Scroll(offset) if self.terminal.is_focused() => self.terminal.on_scroll(offset),
             evt => {
+                if let Event::Click(_) = evt {
+                    self.explorer.set_focused(false);
+                    self.quickfix.set_focused(false);
+                    self.replace.set_focused(false);
+                    self.terminal.set_focused(false);
+                }
+
                 for event in self.editor.on_event(evt)? {
                     if self.on_event(event)? {
                         return Ok(true);
@@ -141,13 +724,165 @@ impl App {
         Ok(false)
     }
 
+    fn execute_command(&mut self, input: &str) -> anyhow::Result<bool> {
+        if input.trim().is_empty() {
+            return Ok(false);
+        }
+
+        let command = ExCommand::parse(input)?;
+
+        match command.name.as_str() {
+            "g" | "global" => return self.execute_global(&command, !command.bang),
+            "gr" | "grep" => {
+                self.grep(&command.args)?;
+                return Ok(false);
+            }
+            "replace" => {
+                self.replace(&command.args)?;
+                return Ok(false);
+            }
+            "v" | "vglobal" => return self.execute_global(&command, false),
+            _ => {}
+        }
+
+        if self.editor.execute_command(&command)? {
+            return Ok(false);
+        }
+
+        let commands = self.cmd_mgr.clone();
+        let Some(actions) = commands.get_command(&command.name) else {
+            return Err(anyhow!("Not an editor command: {}", input));
+        };
+
+        for action in actions {
+            if self.on_event(Event::Action(action.clone()))? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    // :g/pat/cmd 全体を1回の undo で戻せるようにまとめる
+    fn execute_global(&mut self, command: &ExCommand, matching: bool) -> anyhow::Result<bool> {
+        if self.in_global {
+            return Err(anyhow!("Cannot do :global recursive"));
+        }
+
+        let (_, pattern, cmd) = split_pattern(&command.args)?;
+        let lines = self
+            .editor
+            .get_global_lines(&pattern, command.range.as_ref(), matching)?;
+
+        self.in_global = true;
+        self.editor.begin_change();
+        self.editor.set_line_marks(lines);
+
+        let result = self.execute_on_lines(&cmd);
+
+        self.editor.clear_line_marks();
+        self.editor.end_change();
+        self.in_global = false;
+        result
+    }
+
+    // 印を付けた行で順に実行する (印は編集に合わせて移動し、削除された行は飛ばす)
+    fn execute_on_lines(&mut self, cmd: &str) -> anyhow::Result<bool> {
+        while let Some(y) = self.editor.take_line_mark() {
+            if y >= self.editor.get_line_count() {
+                continue;
+            }
+
+            self.editor.move_to_line(y);
+            if !cmd.trim().is_empty() && self.execute_command(cmd)? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.terminal.take_dirty()
+    }
+
+    // 入力のたびと、入力がない間も一定時間ごとに呼ぶ (再描画が必要なら true)
+    fn on_idle(&mut self) -> bool {
+        let is_changed = self.editor.sync_language_servers();
+        let is_linted = self.editor.update_lint();
+
+        // gd, gr で複数の位置が見つかったときや :problems は一覧から選ぶ
+        if let Some((title, items)) = self.editor.take_locations() {
+            self.quickfix.set_items(title, items);
+            self.replace.close();
+            self.terminal.close();
+            self.explorer.set_focused(false);
+            self.update_panel_height();
+        }
+
+        is_changed || is_linted
+    }
+
+    fn shutdown(&mut self) {
+        self.editor.shutdown_language_servers();
+    }
+
     pub(crate) fn draw(&self) -> anyhow::Result<()> {
         self.editor.draw()?;
+
+        let mut terminal_cursor = None;
+        if let Some(rect) = self.editor.get_panel_rect() {
+            queue!(stdout(), SavePosition)?;
+            if self.replace.is_visible() {
+                self.replace_renderer.render(&self.replace, &rect);
+            } else if self.terminal.is_visible() {
+                terminal_cursor = self.terminal_renderer.render(&self.terminal, &rect);
+            } else {
+                self.quickfix_renderer.render(&self.quickfix, &rect);
+            }
+            queue!(stdout(), RestorePosition)?;
+        }
+
+        if self.explorer.is_visible() {
+            // フォーカスがないときはエディタのカーソル位置に戻す
+            queue!(stdout(), SavePosition)?;
+            let cursor_pos = self
+                .explorer_renderer
+                .render(&self.explorer, get_term_size()?);
+
+            match cursor_pos {
+                Some(pos) => queue!(stdout(), MoveTo(pos.x as u16, pos.y as u16), Show)?,
+                None if self.explorer.is_focused() => queue!(stdout(), Hide)?,
+                None => queue!(stdout(), RestorePosition)?,
+            }
+        }
+
+        if (self.quickfix.is_focused() || self.replace.is_focused())
+            && self.editor.get_mode() == EditorMode::Normal
+        {
+            queue!(stdout(), Hide)?;
+        }
+
+        if self.terminal.is_focused() && self.editor.get_mode() == EditorMode::Normal {
+            match terminal_cursor {
+                Some(pos) => queue!(stdout(), MoveTo(pos.x as u16, pos.y as u16), Show)?,
+                None => queue!(stdout(), Hide)?,
+            }
+        }
+
+        self.editor.draw_wildmenu()?;
+
+        if self.finder.is_open() {
+            let pos = self.finder_renderer.render(&self.finder, get_term_size()?);
+            queue!(stdout(), MoveTo(pos.x as u16, pos.y as u16), Show)?;
+        }
+
+        stdout().flush()?;
         Ok(())
     }
 
-    pub fn run(path: Option<String>) -> anyhow::Result<()> {
-        let app = Arc::new(RwLock::new(App::new(path)?));
+    pub fn run(paths: Vec<String>) -> anyhow::Result<()> {
+        let app = Arc::new(RwLock::new(App::new(paths)?));
         let running = Arc::new(AtomicBool::new(true));
 
         app.write().unwrap().init()?;
@@ -165,9 +900,12 @@ impl App {
             let _handle = thread::spawn(move || {
                 while running_clone.load(Ordering::Relaxed) {
                     {
+                        // 端末の出力などで画面が変わったときだけ描画し直す
                         if let Ok(app) = app_clone.read() {
-                            if let Err(err) = app.draw() {
-                                log::error!("{}", err);
+                            if app.needs_redraw() {
+                                if let Err(err) = app.draw() {
+                                    log::error!("{}", err);
+                                }
                             }
                         }
                     }
@@ -177,17 +915,37 @@ impl App {
 
             loop {
                 {
+                    // 入力を待っている間も描画スレッドがロックを取れるようにする
+                    // (言語サーバーからの通知も処理するため、入力がなくても一定時間で戻る)
+                    let event = if event::poll(IDLE_INTERVAL)? {
+                        Some(event::read()?)
+                    } else {
+                        None
+                    };
+
                     if let Ok(mut app) = app.write() {
-                        if let Some(event) = app.crossterm_event_to_editor_event(event::read()?)? {
-                            match app.on_event(event) {
-                                Err(err) => log::error!("{}", err),
-                                Ok(is_quit) => {
-                                    if is_quit {
-                                        break;
+                        let mut needs_draw = false;
+
+                        if let Some(event) = event {
+                            if let Some(event) = app.crossterm_event_to_editor_event(event)? {
+                                match app.on_event(event) {
+                                    Err(err) => {
+                                        log::error!("{}", err);
+                                        app.editor.set_message(err.to_string());
                                     }
-                                }
-                            };
+                                    Ok(is_quit) => {
+                                        if is_quit {
+                                            app.shutdown();
+                                            break;
+                                        }
+                                    }
+                                };
+                                app.sync_terminal_size();
+                                needs_draw = true;
+                            }
+                        }
 
+                        if app.on_idle() || needs_draw {
                             if let Err(err) = app.draw() {
                                 log::error!("{}", err);
                             }
@@ -1,4 +1,7 @@
-use crate::{action::AppAction, editor::mode::EditorMode};
+use crate::{
+    action::AppAction,
+    editor::{mode::EditorMode, window::SplitDirection},
+};
 
 #[derive(Debug, Clone, Hash)]
 pub enum EditorCursorAction {
@@ -20,13 +23,33 @@ impl EditorCursorAction {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EditorOperator {
+    Delete,
+    Change,
+    Yank,
+    IndentRight,
+    IndentLeft,
+    Uppercase,
+    Lowercase,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EditorTextObject {
+    Word,
+    Quote(char),
+    Bracket(char, char),
+    Tag,
+    Paragraph,
+}
+
 #[derive(Debug, Clone, Hash)]
 pub enum EditorEditAction {
-    DeleteLine,
     YankLine,
-    DeleteSelection,
-    YankSelection,
     Paste,
+    PasteBefore,
+    Undo,
+    Redo,
 }
 
 impl EditorEditAction {
@@ -48,10 +71,79 @@ impl EditorBufferAction {
     }
 }
 
+#[derive(Debug, Clone, Hash)]
+pub enum EditorWindowAction {
+    Split(SplitDirection),
+    Close,
+    FocusLeft,
+    FocusDown,
+    FocusUp,
+    FocusRight,
+    FocusNext,
+    Resize {
+        direction: SplitDirection,
+        delta: isize,
+    },
+    Equalize,
+}
+
+impl EditorWindowAction {
+    pub fn into_app(self) -> AppAction {
+        EditorAction::Window(self).into_app()
+    }
+}
+
+#[derive(Debug, Clone, Hash)]
+pub enum EditorTabAction {
+    Next,
+    Previous,
+}
+
+impl EditorTabAction {
+    pub fn into_app(self) -> AppAction {
+        EditorAction::Tab(self).into_app()
+    }
+}
+
+// 言語サーバーへの問い合わせ
+#[derive(Debug, Clone, Hash)]
+pub enum EditorLspAction {
+    GoToDefinition,
+    FindReferences,
+    Hover,
+    Rename(String),
+}
+
+impl EditorLspAction {
+    pub fn into_app(self) -> AppAction {
+        EditorAction::Lsp(self).into_app()
+    }
+}
+
 #[derive(Debug, Clone, Hash)]
 pub enum EditorAction {
     SetMode(EditorMode),
+    SelectRegister(char),
+    RepeatLastChange,
+    SearchNext {
+        reverse: bool,
+    },
+    ClearSearchHighlight,
+    DiagnosticNext {
+        reverse: bool,
+    },
+    Operator(EditorOperator),
+    BlockInsert {
+        append: bool,
+    },
+    TextObject {
+        object: EditorTextObject,
+        inner: bool,
+    },
     Buffer(EditorBufferAction),
+    Window(EditorWindowAction),
+    Tab(EditorTabAction),
+    Lsp(EditorLspAction),
 }
 
 impl EditorAction {
@@ -1,66 +1,184 @@
-use std::fmt::Display;
+use std::{fmt::Display, time::Instant};
 
-use super::EditorBuffer;
+use crate::utils::vec2::UVec2;
+
+use super::{history::EditOperation, EditorBuffer};
 
 impl EditorBuffer {
     pub fn get_line_count(&self) -> usize {
-        self.content.len()
+        self.content.line_count()
     }
 
     pub fn get_line_length(&self, y: usize) -> usize {
-        self.content[y].len()
+        self.content.line_len(y)
     }
 
-    pub fn get_lines(&self) -> Vec<String> {
-        self.content
-            .iter()
-            .map(|line| line.iter().collect())
-            .collect()
+    pub fn get_lines(&self, start: usize, end: usize) -> Vec<String> {
+        self.content.lines(start, end)
     }
 
     pub fn get_line(&self, y: usize) -> String {
-        self.content[y].iter().collect()
+        self.content.line(y)
+    }
+
+    pub fn get_char_count(&self) -> usize {
+        self.content.char_count()
+    }
+
+    pub fn pos_to_index(&self, pos: UVec2) -> usize {
+        self.content.pos_to_index(pos.x, pos.y)
+    }
+
+    pub fn index_to_pos(&self, index: usize) -> UVec2 {
+        self.content.index_to_pos(index).into()
+    }
+
+    pub fn get_text(&self, start: usize, end: usize) -> String {
+        self.content.slice(
+            self.content.index_to_pos(start),
+            self.content.index_to_pos(end),
+        )
+    }
+
+    pub fn get_revision(&self) -> usize {
+        self.revision
+    }
+
+    pub fn insert_text(&mut self, x: usize, y: usize, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        let operation = EditOperation::Insert {
+            x,
+            y,
+            text: text.to_string(),
+        };
+        self.apply_operation(&operation);
+        self.record_operation(operation);
+    }
+
+    pub fn delete_text(&mut self, x: usize, y: usize, len: usize) {
+        let start = self.content.pos_to_index(x, y);
+        let len = len.min(self.content.char_count().saturating_sub(start));
+        if len == 0 {
+            return;
+        }
+
+        let end = self.content.index_to_pos(start + len);
+        let operation = EditOperation::Delete {
+            x,
+            y,
+            text: self.content.slice((x, y), end),
+        };
+        self.apply_operation(&operation);
+        self.record_operation(operation);
+    }
+
+    pub fn insert_line(&mut self, y: usize, line: &str) {
+        if y < self.get_line_count() {
+            self.insert_text(0, y, &format!("{}\n", line));
+        } else {
+            let last = self.get_line_count() - 1;
+            self.insert_text(self.get_line_length(last), last, &format!("\n{}", line));
+        }
+    }
+
+    pub fn delete_range(&mut self, start: usize, end: usize) {
+        let pos = self.index_to_pos(start);
+        self.delete_text(pos.x, pos.y, end - start);
     }
 
     pub fn delete_line(&mut self, y: usize) {
-        self.content.remove(y);
+        let len = self.get_line_length(y);
+
+        if y + 1 < self.get_line_count() {
+            self.delete_text(0, y, len + 1);
+        } else if y > 0 {
+            self.delete_text(self.get_line_length(y - 1), y - 1, len + 1);
+        } else {
+            self.delete_text(0, y, len);
+        }
     }
 
     pub fn split_line(&mut self, x: usize, y: usize) {
-        let original = self.content[y].clone();
-        let (p0, p1) = original.split_at(x);
-        self.content[y] = p0.to_vec();
-        self.content.insert(y + 1, p1.to_vec());
+        self.insert_text(x, y, "\n");
     }
 
     pub fn join_lines(&mut self, y: usize) {
-        if y + 1 < self.content.len() {
-            let combined = self.content[y].iter().collect::<String>().clone()
-                + &self.content[y + 1].iter().collect::<String>().clone();
-            self.content[y] = combined.chars().collect();
-            self.content.remove(y + 1);
+        if y + 1 < self.get_line_count() {
+            self.delete_text(self.get_line_length(y), y, 1);
         }
     }
 
     pub fn insert_char(&mut self, x: usize, y: usize, ch: char) {
-        self.content[y].insert(x, ch);
+        self.insert_text(x, y, ch.to_string().as_str());
     }
 
     pub fn delete_char(&mut self, x: usize, y: usize) {
-        self.content[y].remove(x);
+        self.delete_text(x, y, 1);
+    }
+
+    pub(super) fn apply_operation(&mut self, operation: &EditOperation) {
+        self.record_document_change(operation);
+        self.update_line_marks(operation);
+
+        match operation {
+            EditOperation::Insert { x, y, text } => self.content.insert(*x, *y, text),
+            EditOperation::Delete { x, y, text } => {
+                self.content.remove(*x, *y, text.chars().count());
+            }
+        }
+
+        self.revision += 1;
+        self.changed_at = Some(Instant::now());
     }
 }
 
 impl Display for EditorBuffer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            self.content
-                .iter()
-                .map(|line| line.iter().collect())
-                .collect::<Vec<String>>()
-                .join("\n")
-        )
+        write!(f, "{}", self.content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::buffer::EditorBuffer;
+
+    fn buffer(text: &str) -> EditorBuffer {
+        let mut buffer = EditorBuffer::new();
+        buffer.insert_text(0, 0, text);
+        buffer
+    }
+
+    #[test]
+    fn delete_at_last_position_is_noop() {
+        let mut buffer = buffer("ab\ncd");
+        let revision = buffer.get_revision();
+
+        buffer.delete_text(2, 1, 1);
+
+        assert_eq!(buffer.to_string(), "ab\ncd");
+        assert_eq!(buffer.get_revision(), revision);
+    }
+
+    #[test]
+    fn delete_is_clamped_to_end_of_text() {
+        let mut buffer = buffer("ab\ncd");
+
+        buffer.delete_text(1, 1, 10);
+
+        assert_eq!(buffer.to_string(), "ab\nc");
+    }
+
+    #[test]
+    fn delete_line_handles_last_and_only_line() {
+        let mut buffer = buffer("ab\ncd");
+
+        buffer.delete_line(1);
+        assert_eq!(buffer.to_string(), "ab");
+        buffer.delete_line(0);
+        assert_eq!(buffer.to_string(), "");
+        assert_eq!(buffer.get_line_count(), 1);
     }
 }
@@ -0,0 +1,124 @@
+use crate::utils::vec2::UVec2;
+
+use super::EditorBuffer;
+
+impl EditorBuffer {
+    // 標準入力から受け取って整形する外部コマンド
+    pub fn get_format_cmd(&self) -> Option<&'static str> {
+        self.file_type.get_format_cmd()
+    }
+
+    // 言語ごとの組み込みのフォーマッター
+    pub fn format_text(&self) -> Option<String> {
+        self.language_support.as_ref()?.format(&self.to_string())
+    }
+
+    // 整形した内容に置き換える (変わった部分だけを書き換え、1回の undo で戻せる)
+    // カーソルは前にある空白以外の文字の数が変わらない位置に移す (変わらなければ None)
+    pub fn replace_formatted(&mut self, formatted: &str, cursor: UVec2) -> Option<UVec2> {
+        // 末尾の改行はバッファのまま残す (ファイルを開くときに改行を1つ足しているため)
+        let text = self.to_string();
+        let trailing = &text[text.trim_end_matches('\n').len()..];
+        let formatted = format!("{}{}", formatted.trim_end_matches('\n'), trailing);
+
+        let old: Vec<char> = text.chars().collect();
+        let new: Vec<char> = formatted.chars().collect();
+        let start = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        if start == old.len() && start == new.len() {
+            return None;
+        }
+        let suffix = old[start..]
+            .iter()
+            .rev()
+            .zip(new[start..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+
+        let index = self.pos_to_index(cursor);
+        let new_index = if index < start {
+            index
+        } else if index >= old_end {
+            index - old_end + new_end
+        } else {
+            let count = old[start..index]
+                .iter()
+                .filter(|c| !c.is_whitespace())
+                .count();
+            let mut new_index = start;
+            let mut seen = 0;
+            while new_index < new_end && (seen < count || new[new_index].is_whitespace()) {
+                if !new[new_index].is_whitespace() {
+                    seen += 1;
+                }
+                new_index += 1;
+            }
+            new_index
+        };
+
+        let pos = self.index_to_pos(start);
+        let replacement: String = new[start..new_end].iter().collect();
+        self.begin_change(cursor);
+        self.delete_text(pos.x, pos.y, old_end - start);
+        self.insert_text(pos.x, pos.y, &replacement);
+        let new_cursor = self.index_to_pos(new_index);
+        self.end_change(new_cursor);
+
+        Some(new_cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        editor::buffer::EditorBuffer, language_support::langs::json::JSONLanguageSupport,
+        utils::vec2::UVec2,
+    };
+
+    fn buffer(text: &str) -> EditorBuffer {
+        let mut buffer = EditorBuffer::new();
+        buffer.insert_text(0, 0, text);
+        buffer.history = Default::default();
+        buffer.language_support = Some(Box::new(JSONLanguageSupport::new()));
+        buffer
+    }
+
+    #[test]
+    fn replaces_with_formatted_text_in_one_undo_step() {
+        let mut buffer = buffer("{\"a\":1,\"b\":2}\n");
+
+        let formatted = buffer.format_text().unwrap();
+        buffer.replace_formatted(&formatted, UVec2::default());
+        assert_eq!(buffer.to_string(), "{\n  \"a\": 1,\n  \"b\": 2\n}\n");
+
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "{\"a\":1,\"b\":2}\n");
+        assert_eq!(buffer.undo(), None);
+    }
+
+    #[test]
+    fn keeps_cursor_on_the_same_text() {
+        let mut buffer = buffer("{\"a\":1,\"b\":2}");
+
+        // "b" の上のカーソル
+        let formatted = buffer.format_text().unwrap();
+        let cursor = buffer.replace_formatted(&formatted, UVec2::new(8, 0));
+        assert_eq!(cursor, Some(UVec2::new(3, 2)));
+    }
+
+    #[test]
+    fn keeps_trailing_newlines_of_the_buffer() {
+        let mut buffer = buffer("[1,2]");
+
+        buffer.replace_formatted("[\n  1,\n  2\n]\n", UVec2::default());
+        assert_eq!(buffer.to_string(), "[\n  1,\n  2\n]");
+    }
+
+    #[test]
+    fn unchanged_text_is_not_an_edit() {
+        let mut buffer = buffer("[]\n");
+
+        assert_eq!(buffer.replace_formatted("[]\n", UVec2::default()), None);
+        assert_eq!(buffer.undo(), None);
+    }
+}
@@ -1,13 +1,257 @@
+use crate::utils::vec2::UVec2;
+
 use super::EditorBuffer;
 
+#[derive(Debug, Clone)]
+pub(super) enum EditOperation {
+    Insert { x: usize, y: usize, text: String },
+    Delete { x: usize, y: usize, text: String },
+}
+
+impl EditOperation {
+    fn inverse(&self) -> Self {
+        match self.clone() {
+            Self::Insert { x, y, text } => Self::Delete { x, y, text },
+            Self::Delete { x, y, text } => Self::Insert { x, y, text },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EditTransaction {
+    id: usize,
+    operations: Vec<EditOperation>,
+    cursor_before: UVec2,
+    cursor_after: UVec2,
+}
+
+#[derive(Default)]
+pub struct EditorHistory {
+    undo_stack: Vec<EditTransaction>,
+    redo_stack: Vec<EditTransaction>,
+    current: Option<EditTransaction>,
+    depth: usize,
+    next_id: usize,
+}
+
+impl EditorHistory {
+    // 現在の内容に対応する履歴上の位置 (undo/redo で同じ内容に戻れば同じ値になる)
+    pub(super) fn get_position(&self) -> usize {
+        self.undo_stack
+            .last()
+            .map(|transaction| transaction.id)
+            .unwrap_or(0)
+    }
+
+    // まだ確定していない編集があるか
+    pub(super) fn has_pending_change(&self) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|transaction| !transaction.operations.is_empty())
+    }
+}
+
 impl EditorBuffer {
-    #[allow(unused)]
-    pub fn undo() {
-        todo!()
+    /// 編集のまとまりを開始する (入れ子にできる)
+    pub fn begin_change(&mut self, cursor: UVec2) {
+        if self.history.depth == 0 {
+            self.history.next_id += 1;
+            self.history.current = Some(EditTransaction {
+                id: self.history.next_id,
+                operations: vec![],
+                cursor_before: cursor,
+                cursor_after: cursor,
+            });
+        }
+
+        self.history.depth += 1;
+    }
+
+    /// 編集のまとまりを終了し、最も外側であれば1回の undo 単位として確定する
+    pub fn end_change(&mut self, cursor: UVec2) {
+        if self.history.depth == 0 {
+            return;
+        }
+
+        self.history.depth -= 1;
+        if self.history.depth > 0 {
+            return;
+        }
+
+        if let Some(mut transaction) = self.history.current.take() {
+            if !transaction.operations.is_empty() {
+                transaction.cursor_after = cursor;
+                self.history.undo_stack.push(transaction);
+                self.history.redo_stack.clear();
+            }
+        }
     }
 
-    #[allow(unused)]
-    pub fn redo() {
-        todo!()
+    pub(super) fn record_operation(&mut self, operation: EditOperation) {
+        if self.history.depth == 0 {
+            self.begin_change(UVec2::default());
+            self.record_operation(operation);
+            self.end_change(UVec2::default());
+            return;
+        }
+
+        if let Some(transaction) = &mut self.history.current {
+            transaction.operations.push(operation);
+        }
+    }
+
+    // 戻した後のカーソル位置を返す
+    pub fn undo(&mut self) -> Option<UVec2> {
+        let transaction = self.history.undo_stack.pop()?;
+
+        for operation in transaction.operations.iter().rev() {
+            self.apply_operation(&operation.inverse());
+        }
+
+        let cursor = transaction.cursor_before;
+        self.history.redo_stack.push(transaction);
+        Some(cursor)
+    }
+
+    pub fn redo(&mut self) -> Option<UVec2> {
+        let transaction = self.history.redo_stack.pop()?;
+
+        for operation in transaction.operations.iter() {
+            self.apply_operation(operation);
+        }
+
+        let cursor = transaction.cursor_after;
+        self.history.undo_stack.push(transaction);
+        Some(cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{editor::buffer::EditorBuffer, utils::vec2::UVec2};
+
+    fn buffer(text: &str) -> EditorBuffer {
+        let mut buffer = EditorBuffer::new();
+        buffer.insert_text(0, 0, text);
+        // 初期内容の挿入は undo の対象にしない
+        buffer.history = Default::default();
+        buffer
+    }
+
+    #[test]
+    fn groups_operations_into_one_undo_step() {
+        let mut buffer = buffer("abc");
+
+        buffer.begin_change(UVec2::new(1, 0));
+        buffer.insert_char(1, 0, 'x');
+        buffer.insert_char(2, 0, 'y');
+        buffer.delete_char(0, 0);
+        buffer.end_change(UVec2::new(2, 0));
+        assert_eq!(buffer.to_string(), "xybc");
+
+        assert_eq!(buffer.undo(), Some(UVec2::new(1, 0)));
+        assert_eq!(buffer.to_string(), "abc");
+        assert_eq!(buffer.undo(), None);
+    }
+
+    #[test]
+    fn operations_outside_a_change_are_separate_steps() {
+        let mut buffer = buffer("abc");
+
+        buffer.insert_char(3, 0, 'd');
+        buffer.insert_char(4, 0, 'e');
+
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "abcd");
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "abc");
+    }
+
+    #[test]
+    fn nested_changes_commit_only_at_the_outermost_end() {
+        let mut buffer = buffer("abc");
+
+        buffer.begin_change(UVec2::new(0, 0));
+        buffer.insert_char(0, 0, '1');
+        buffer.begin_change(UVec2::new(1, 0));
+        buffer.insert_char(1, 0, '2');
+        buffer.end_change(UVec2::new(2, 0));
+
+        // 内側を閉じただけではまだ確定しない
+        assert_eq!(buffer.history.depth, 1);
+        assert!(buffer.history.undo_stack.is_empty());
+
+        buffer.insert_char(2, 0, '3');
+        buffer.end_change(UVec2::new(3, 0));
+        assert_eq!(buffer.history.depth, 0);
+        assert_eq!(buffer.history.undo_stack.len(), 1);
+
+        assert_eq!(buffer.undo(), Some(UVec2::new(0, 0)));
+        assert_eq!(buffer.to_string(), "abc");
+    }
+
+    #[test]
+    fn unbalanced_end_change_is_ignored() {
+        let mut buffer = buffer("abc");
+
+        buffer.end_change(UVec2::default());
+        assert_eq!(buffer.history.depth, 0);
+
+        buffer.insert_char(0, 0, 'x');
+        assert_eq!(buffer.undo(), Some(UVec2::default()));
+        assert_eq!(buffer.to_string(), "abc");
+    }
+
+    #[test]
+    fn empty_change_does_not_create_an_undo_step() {
+        let mut buffer = buffer("abc");
+
+        buffer.begin_change(UVec2::default());
+        buffer.end_change(UVec2::default());
+
+        assert!(buffer.history.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn redo_restores_text_and_cursor() {
+        let mut buffer = buffer("one\ntwo");
+
+        buffer.begin_change(UVec2::new(3, 0));
+        buffer.delete_text(3, 0, 1);
+        buffer.insert_text(3, 0, " and ");
+        buffer.end_change(UVec2::new(8, 0));
+
+        assert_eq!(buffer.undo(), Some(UVec2::new(3, 0)));
+        assert_eq!(buffer.to_string(), "one\ntwo");
+        assert_eq!(buffer.redo(), Some(UVec2::new(8, 0)));
+        assert_eq!(buffer.to_string(), "one and two");
+        assert_eq!(buffer.redo(), None);
+    }
+
+    #[test]
+    fn new_change_clears_redo_stack() {
+        let mut buffer = buffer("abc");
+
+        buffer.insert_char(3, 0, 'd');
+        buffer.undo();
+        buffer.insert_char(0, 0, 'z');
+
+        assert_eq!(buffer.redo(), None);
+        assert_eq!(buffer.to_string(), "zabc");
+    }
+
+    #[test]
+    fn inverse_operations_restore_multi_line_deletes() {
+        let mut buffer = buffer("first\nsecond\nthird");
+
+        buffer.delete_line(1);
+        assert_eq!(buffer.to_string(), "first\nthird");
+        buffer.delete_line(1);
+        assert_eq!(buffer.to_string(), "first");
+
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "first\nthird");
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "first\nsecond\nthird");
     }
 }
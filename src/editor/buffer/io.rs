@@ -1,29 +1,36 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
 
 use crate::{
     editor::utils::file::EditorFile,
     language_support::{
         langs::{
             commit_message::CommitMessageLanguageSupport, css::CSSLanguageSupport,
-            html::HTMLLanguageSupport, markdown::MarkdownLanguageSupport,
+            html::HTMLLanguageSupport, json::JSONLanguageSupport,
+            markdown::MarkdownLanguageSupport,
         },
         LanguageSupport,
     },
-    utils::file_type::{FileType, COMMIT_MESSAGE, CSS, HTML, MARKDOWN},
+    utils::{
+        file_type::{FileType, COMMIT_MESSAGE, CSS, HTML, JSON, MARKDOWN},
+        text_storage::TextStorage,
+    },
 };
 
 use super::EditorBuffer;
 
 impl EditorBuffer {
     pub fn new() -> Self {
-        Self {
-            content: vec![vec![]],
-            ..Default::default()
-        }
+        Self::default()
     }
 
     pub fn open(path: PathBuf) -> anyhow::Result<Self> {
-        let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("Not a file: {}", path.display()))?
+            .to_string_lossy()
+            .to_string();
         let mut file = EditorFile::open(path)?;
         let buf = file.read()?;
 
@@ -31,6 +38,7 @@ impl EditorBuffer {
         let language_support: Option<Box<dyn LanguageSupport>> = match file_type.get().as_str() {
             HTML => Some(Box::new(HTMLLanguageSupport::new())),
             CSS => Some(Box::new(CSSLanguageSupport::new())),
+            JSON => Some(Box::new(JSONLanguageSupport::new())),
             MARKDOWN => Some(Box::new(MarkdownLanguageSupport::new())),
             COMMIT_MESSAGE => Some(Box::new(CommitMessageLanguageSupport::new())),
             _ => None,
@@ -38,7 +46,8 @@ impl EditorBuffer {
 
         Ok(Self {
             file,
-            content: buf.split("\n").map(|line| line.chars().collect()).collect(),
+            content: TextStorage::from(buf.as_str()),
+            file_type,
             language_support,
             ..Default::default()
         })
@@ -46,6 +55,112 @@ impl EditorBuffer {
 
     pub fn save(&mut self) -> anyhow::Result<()> {
         self.file.write(&self.to_string())?;
+        self.saved_position = self.history.get_position();
+        self.is_deleted = false;
+        self.on_document_saved();
         Ok(())
     }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.file.set_path(path);
+    }
+
+    pub fn get_path(&self) -> Option<&Path> {
+        self.file.get_path().map(|path| path.as_path())
+    }
+
+    // :ls などで表示する名前
+    pub fn get_name(&self) -> String {
+        match self.get_path() {
+            Some(path) => path.to_string_lossy().to_string(),
+            None => "[No Name]".to_string(),
+        }
+    }
+
+    // ファイルが削除されたら、内容を失わないように :q などで警告する
+    pub fn set_deleted(&mut self) {
+        self.file.close();
+        self.is_deleted = true;
+    }
+
+    // undo で保存時の内容に戻した場合は変更なしとする
+    pub fn is_modified(&self) -> bool {
+        self.is_deleted
+            || self.history.has_pending_change()
+            || self.history.get_position() != self.saved_position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::utils::vec2::UVec2;
+
+    use super::EditorBuffer;
+
+    #[test]
+    fn open_without_file_name_is_an_error() {
+        for path in ["..", "/", "a/.."] {
+            let err = EditorBuffer::open(PathBuf::from(path)).err().unwrap();
+            assert_eq!(err.to_string(), format!("Not a file: {}", path));
+        }
+    }
+
+    #[test]
+    fn undo_back_to_saved_text_is_not_modified() {
+        let mut buffer = EditorBuffer::new();
+        assert!(!buffer.is_modified());
+
+        buffer.insert_text(0, 0, "abc");
+        assert!(buffer.is_modified());
+
+        buffer.undo();
+        assert!(!buffer.is_modified());
+        buffer.redo();
+        assert!(buffer.is_modified());
+    }
+
+    #[test]
+    fn different_change_at_same_depth_is_modified() {
+        let mut buffer = EditorBuffer::new();
+
+        buffer.insert_text(0, 0, "abc");
+        buffer.undo();
+        buffer.insert_text(0, 0, "xyz");
+
+        assert!(buffer.is_modified());
+    }
+
+    #[test]
+    fn pending_change_is_modified() {
+        let mut buffer = EditorBuffer::new();
+
+        buffer.begin_change(UVec2::default());
+        buffer.insert_text(0, 0, "abc");
+        assert!(buffer.is_modified());
+
+        buffer.delete_text(0, 0, 3);
+        buffer.end_change(UVec2::default());
+        assert!(buffer.is_modified());
+    }
+
+    #[test]
+    fn deleted_file_is_modified_until_written_again() {
+        let dir = std::env::temp_dir().join(format!("tui-studio-io-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.txt");
+        std::fs::write(&path, "abc\n").unwrap();
+
+        let mut buffer = EditorBuffer::open(path.clone()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        buffer.set_deleted();
+        assert!(buffer.is_modified());
+
+        buffer.save().unwrap();
+        assert!(!buffer.is_modified());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), buffer.to_string());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
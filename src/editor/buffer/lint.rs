@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use crate::utils::vec2::UVec2;
+
+use super::{BufferDiagnostic, EditorBuffer};
+
+impl EditorBuffer {
+    // 最後の変更から delay 経ってからリンターを実行する (実行したら true)
+    pub fn update_lint(&mut self, delay: Duration) -> bool {
+        if self.lint_revision == Some(self.revision) {
+            return false;
+        }
+        if self
+            .changed_at
+            .is_some_and(|changed_at| changed_at.elapsed() < delay)
+        {
+            return false;
+        }
+        self.lint_revision = Some(self.revision);
+
+        let Some(errors) = self
+            .language_support
+            .as_ref()
+            .and_then(|language_support| language_support.lint(&self.to_string()))
+        else {
+            return false;
+        };
+
+        self.lint_diagnostics = errors
+            .into_iter()
+            .map(|error| BufferDiagnostic {
+                start: UVec2::new(error.column, error.line),
+                end: UVec2::new(error.end_column, error.end_line),
+                severity: error.severity,
+                message: error.message,
+                source: Some(error.source),
+            })
+            .collect();
+        self.merge_diagnostics();
+        true
+    }
+
+    pub(super) fn merge_diagnostics(&mut self) {
+        let mut diagnostics: Vec<BufferDiagnostic> = self
+            .lsp_diagnostics
+            .iter()
+            .chain(&self.lint_diagnostics)
+            .cloned()
+            .collect();
+        diagnostics.sort_by_key(|diagnostic| {
+            (diagnostic.start.y, diagnostic.start.x, diagnostic.severity)
+        });
+        self.diagnostics = diagnostics;
+    }
+
+    // カーソルより後ろ (reverse なら前) にある診断の位置 (端まで行ったら反対側から探す)
+    pub fn find_diagnostic(&self, cursor: UVec2, reverse: bool) -> Option<&BufferDiagnostic> {
+        let key = |diagnostic: &&BufferDiagnostic| (diagnostic.start.y, diagnostic.start.x);
+        let cursor = (cursor.y, cursor.x);
+
+        if reverse {
+            let mut before = self.diagnostics.iter().filter(|d| key(d) < cursor);
+            before.next_back().or_else(|| self.diagnostics.last())
+        } else {
+            let mut after = self.diagnostics.iter().filter(|d| key(d) > cursor);
+            after.next().or_else(|| self.diagnostics.first())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        editor::buffer::EditorBuffer, language_support::langs::css::CSSLanguageSupport,
+        utils::vec2::UVec2,
+    };
+
+    fn buffer(text: &str) -> EditorBuffer {
+        let mut buffer = EditorBuffer::new();
+        buffer.insert_text(0, 0, text);
+        buffer.language_support = Some(Box::new(CSSLanguageSupport::new()));
+        buffer
+    }
+
+    #[test]
+    fn lints_after_delay_only_once_per_revision() {
+        let mut buffer = buffer("a {\n}\n}\nb {");
+
+        assert!(!buffer.update_lint(Duration::from_secs(60)));
+        assert!(buffer.update_lint(Duration::ZERO));
+        assert!(!buffer.update_lint(Duration::ZERO));
+
+        let diagnostics: Vec<(UVec2, &str)> = buffer
+            .get_diagnostics()
+            .iter()
+            .map(|diagnostic| (diagnostic.start, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (UVec2::new(0, 2), "Unexpected '}'"),
+                (UVec2::new(2, 3), "Unclosed '{'"),
+            ]
+        );
+
+        buffer.delete_line(2);
+        assert!(buffer.update_lint(Duration::ZERO));
+        assert_eq!(buffer.get_diagnostics().len(), 1);
+    }
+
+    #[test]
+    fn finds_diagnostics_around_cursor_and_wraps() {
+        let mut buffer = buffer("}\na {}\n}");
+        buffer.update_lint(Duration::ZERO);
+
+        let find = |cursor: UVec2, reverse: bool| {
+            buffer
+                .find_diagnostic(cursor, reverse)
+                .map(|diagnostic| diagnostic.start)
+        };
+
+        assert_eq!(find(UVec2::new(0, 0), false), Some(UVec2::new(0, 2)));
+        assert_eq!(find(UVec2::new(0, 2), false), Some(UVec2::new(0, 0)));
+        assert_eq!(find(UVec2::new(0, 1), true), Some(UVec2::new(0, 0)));
+        assert_eq!(find(UVec2::new(0, 0), true), Some(UVec2::new(0, 2)));
+    }
+}
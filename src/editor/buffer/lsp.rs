@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    language_support::lsp::{ContentChange, LspDiagnostic, LspPosition, LspRange, TextEdit},
+    utils::vec2::UVec2,
+};
+
+use super::{history::EditOperation, BufferDiagnostic, DocumentSync, EditorBuffer};
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+// 行の先頭から x 文字目までの UTF-16 での長さ
+fn char_to_utf16(line: &str, x: usize) -> usize {
+    line.chars().take(x).map(char::len_utf16).sum()
+}
+
+fn utf16_to_char(line: &str, character: usize) -> usize {
+    let mut len = 0;
+    line.chars()
+        .take_while(|c| {
+            len += c.len_utf16();
+            len <= character
+        })
+        .count()
+}
+
+impl EditorBuffer {
+    pub fn get_language_id(&self) -> String {
+        self.file_type.get_language_id()
+    }
+
+    pub fn get_language_server_cmd(&self) -> Option<String> {
+        self.language_support.as_ref()?.get_lsp_server_cmd()
+    }
+
+    // didOpen したときのパス (パスが変わったら開き直す)
+    pub fn get_document_path(&self) -> Option<&Path> {
+        self.document_sync.as_ref().map(|sync| sync.path.as_path())
+    }
+
+    // これ以降の変更を didChange 用に記録する
+    pub fn open_document(&mut self, path: PathBuf) {
+        self.document_sync = Some(DocumentSync {
+            path,
+            changes: vec![],
+            is_saved: false,
+        });
+    }
+
+    // 言語サーバーを使えないときは変更を記録しない
+    pub fn close_document(&mut self) {
+        self.document_sync = None;
+    }
+
+    // まだ送っていない変更と、保存したかどうか
+    pub fn take_document_changes(&mut self) -> (Vec<ContentChange>, bool) {
+        match &mut self.document_sync {
+            Some(sync) => (
+                std::mem::take(&mut sync.changes),
+                std::mem::take(&mut sync.is_saved),
+            ),
+            None => (vec![], false),
+        }
+    }
+
+    pub(super) fn on_document_saved(&mut self) {
+        if let Some(sync) = &mut self.document_sync {
+            sync.is_saved = true;
+        }
+    }
+
+    // 内容を変更する前に呼ぶ (変更後の内容からは削除した範囲が分からないため)
+    pub(super) fn record_document_change(&mut self, operation: &EditOperation) {
+        if self.document_sync.is_none() {
+            return;
+        }
+
+        let (x, y, text, is_insert) = match operation {
+            EditOperation::Insert { x, y, text } => (*x, *y, text, true),
+            EditOperation::Delete { x, y, text } => (*x, *y, text, false),
+        };
+
+        let start = LspPosition::new(y, char_to_utf16(&self.content.line(y), x));
+        let end = if is_insert {
+            start
+        } else {
+            let line_count = text.matches('\n').count();
+            let last = text.rsplit('\n').next().unwrap_or_default();
+            if line_count == 0 {
+                LspPosition::new(y, start.character + utf16_len(text))
+            } else {
+                LspPosition::new(y + line_count, utf16_len(last))
+            }
+        };
+
+        let change = ContentChange {
+            range: LspRange { start, end },
+            text: if is_insert {
+                text.clone()
+            } else {
+                String::new()
+            },
+        };
+        if let Some(sync) = &mut self.document_sync {
+            sync.changes.push(change);
+        }
+    }
+
+    // 言語サーバーの位置 (UTF-16) を文字単位の位置にする
+    pub fn position_from_lsp(&self, pos: LspPosition) -> UVec2 {
+        let y = pos.line.min(self.get_line_count() - 1);
+        UVec2::new(utf16_to_char(&self.content.line(y), pos.character), y)
+    }
+
+    pub fn position_to_lsp(&self, pos: UVec2) -> LspPosition {
+        LspPosition::new(pos.y, char_to_utf16(&self.content.line(pos.y), pos.x))
+    }
+
+    // rename などの編集をまとめて適用する (1回の undo で戻せる)
+    pub fn apply_text_edits(&mut self, edits: &[TextEdit]) {
+        let mut edits: Vec<(UVec2, UVec2, &str)> = edits
+            .iter()
+            .map(|edit| {
+                (
+                    self.position_from_lsp(edit.range.start),
+                    self.position_from_lsp(edit.range.end),
+                    edit.new_text.as_str(),
+                )
+            })
+            .collect();
+        // 後ろから適用すれば前の編集の位置はずれない
+        edits.sort_by_key(|(start, _, _)| std::cmp::Reverse((start.y, start.x)));
+
+        let cursor = edits.last().map(|(start, _, _)| *start).unwrap_or_default();
+        self.begin_change(cursor);
+        for (start, end, text) in edits {
+            let len = self
+                .pos_to_index(end)
+                .saturating_sub(self.pos_to_index(start));
+            self.delete_text(start.x, start.y, len);
+            self.insert_text(start.x, start.y, text);
+        }
+        self.end_change(cursor);
+    }
+
+    pub fn get_diagnostics(&self) -> &[BufferDiagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<LspDiagnostic>) {
+        self.lsp_diagnostics = diagnostics
+            .into_iter()
+            .map(|diagnostic| BufferDiagnostic {
+                start: self.position_from_lsp(diagnostic.range.start),
+                end: self.position_from_lsp(diagnostic.range.end),
+                severity: diagnostic.severity,
+                message: diagnostic.message,
+                source: diagnostic.source,
+            })
+            .collect();
+        self.merge_diagnostics();
+    }
+
+    // 行番号の左に表示する診断 (同じ行では重要なものを優先する)
+    pub fn get_line_diagnostic(&self, y: usize) -> Option<&BufferDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.start.y <= y && y <= diagnostic.end.y)
+            .min_by_key(|diagnostic| diagnostic.severity)
+    }
+}
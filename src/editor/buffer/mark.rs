@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+
+use super::{history::EditOperation, EditorBuffer};
+
+impl EditorBuffer {
+    // :g で対象にする行に印を付ける (編集で行がずれても同じ行を指し、削除された行の印は消える)
+    pub fn set_line_marks(&mut self, lines: Vec<usize>) {
+        self.line_marks = VecDeque::from(lines);
+    }
+
+    // 印の付いた次の行を取り出す
+    pub fn take_line_mark(&mut self) -> Option<usize> {
+        self.line_marks.pop_front()
+    }
+
+    pub fn clear_line_marks(&mut self) {
+        self.line_marks.clear();
+    }
+
+    pub(super) fn update_line_marks(&mut self, operation: &EditOperation) {
+        if self.line_marks.is_empty() {
+            return;
+        }
+
+        match operation {
+            EditOperation::Insert { x, y, text } => {
+                let count = text.matches('\n').count();
+                if count == 0 {
+                    return;
+                }
+
+                // 行頭に行を挿入したときは元の行が後ろにずれる
+                let is_before = *x == 0 && text.ends_with('\n');
+                for mark in self.line_marks.iter_mut() {
+                    if *mark > *y || (*mark == *y && is_before) {
+                        *mark += count;
+                    }
+                }
+            }
+            EditOperation::Delete { x, y, text } => {
+                let count = text.matches('\n').count();
+                if count == 0 {
+                    return;
+                }
+
+                // 行頭から行単位で消した場合は y..y+count の行がなくなり、
+                // それ以外では y+1..=y+count の行が y に連結される
+                let removed = if *x == 0 && text.ends_with('\n') {
+                    *y..*y + count
+                } else {
+                    *y + 1..*y + count + 1
+                };
+
+                self.line_marks.retain(|mark| !removed.contains(mark));
+                for mark in self.line_marks.iter_mut() {
+                    if *mark >= removed.end {
+                        *mark -= count;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::buffer::EditorBuffer;
+
+    fn buffer(text: &str, marks: Vec<usize>) -> EditorBuffer {
+        let mut buffer = EditorBuffer::new();
+        buffer.insert_text(0, 0, text);
+        buffer.set_line_marks(marks);
+        buffer
+    }
+
+    fn marks(buffer: &mut EditorBuffer) -> Vec<usize> {
+        std::iter::from_fn(|| buffer.take_line_mark()).collect()
+    }
+
+    #[test]
+    fn deleted_lines_lose_their_marks() {
+        let mut buffer = buffer("0\n1\n2\n3\n4", vec![1, 2, 4]);
+
+        buffer.delete_line(2);
+        assert_eq!(marks(&mut buffer), vec![1, 3]);
+    }
+
+    #[test]
+    fn deleting_the_last_line_removes_its_mark() {
+        let mut buffer = buffer("0\n1\n2", vec![0, 2]);
+
+        buffer.delete_line(2);
+        assert_eq!(marks(&mut buffer), vec![0]);
+    }
+
+    #[test]
+    fn inserted_lines_shift_marks() {
+        let mut buffer = buffer("0\n1\n2", vec![0, 1, 2]);
+
+        buffer.insert_line(1, "new");
+        assert_eq!(marks(&mut buffer), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn appended_line_after_empty_last_line_keeps_mark() {
+        let mut buffer = buffer("0\n", vec![1]);
+
+        buffer.insert_line(2, "new");
+        assert_eq!(marks(&mut buffer), vec![1]);
+    }
+
+    #[test]
+    fn joined_lines_lose_their_marks() {
+        let mut buffer = buffer("0\n1\n2\n3", vec![0, 1, 3]);
+
+        buffer.join_lines(0);
+        assert_eq!(marks(&mut buffer), vec![0, 2]);
+    }
+
+    #[test]
+    fn split_keeps_mark_on_the_first_half() {
+        let mut buffer = buffer("ab\ncd", vec![0, 1]);
+
+        buffer.split_line(1, 0);
+        assert_eq!(marks(&mut buffer), vec![0, 2]);
+    }
+}
@@ -1,175 +1,223 @@
 mod content;
-mod cursor;
+mod format;
 mod history;
 mod io;
-mod scroll;
+mod lint;
+mod lsp;
+mod mark;
 
-use arboard::Clipboard;
-use unicode_width::UnicodeWidthChar;
+use std::{collections::VecDeque, ops::Range, path::PathBuf, time::Instant};
 
 use crate::{
-    editor::utils::file::EditorFile,
-    language_support::{highlight::HighlightToken, LanguageSupport},
-    utils::{event::Event, key_binding::Key, vec2::UVec2},
+    editor::{completion::collect_words, utils::file::EditorFile},
+    language_support::{
+        complete::CompletionItem,
+        highlight::HighlightToken,
+        lsp::{ContentChange, DiagnosticSeverity},
+        LanguageSupport,
+    },
+    utils::{file_type::FileType, text_storage::TextStorage, vec2::UVec2},
 };
 
-use super::{
-    action::{EditorBufferAction, EditorCursorAction, EditorEditAction},
-    mode::EditorMode,
-};
+use history::EditorHistory;
+
+// 言語サーバーやリンターの診断 (位置は文字単位)
+#[derive(Debug, Clone)]
+pub struct BufferDiagnostic {
+    pub start: UVec2,
+    pub end: UVec2,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    pub source: Option<String>,
+}
+
+// didOpen したファイルと、まだ送っていない変更
+struct DocumentSync {
+    path: PathBuf,
+    changes: Vec<ContentChange>,
+    is_saved: bool,
+}
 
 #[derive(Default)]
 pub struct EditorBuffer {
     file: EditorFile,
-    content: Vec<Vec<char>>,
-    cursor: UVec2,
-    visual_start: UVec2,
-    scroll: UVec2,
+    content: TextStorage,
+    revision: usize,
+    saved_position: usize,
+    is_deleted: bool, // 開いた後でファイルが削除された
+    history: EditorHistory,
+    visual_marks: Option<(usize, usize)>,
+    last_cursor: UVec2,
+    file_type: FileType,
     language_support: Option<Box<dyn LanguageSupport>>,
+    document_sync: Option<DocumentSync>,
+    lsp_diagnostics: Vec<BufferDiagnostic>,
+    lint_diagnostics: Vec<BufferDiagnostic>,
+    diagnostics: Vec<BufferDiagnostic>, // 両方を合わせて位置順に並べたもの
+    lint_revision: Option<usize>,
+    changed_at: Option<Instant>,
+    highlight_tokens: Vec<HighlightToken>,
+    highlight_revision: Option<usize>,
+    highlight_range: Range<usize>,
+    line_marks: VecDeque<usize>,
+    word_index: Vec<String>,
+    word_index_revision: Option<usize>,
 }
 
+// 言語ごとの補完に渡すカーソルより前の行数
+const COMPLETION_CONTEXT_LINES: usize = 100;
+
+// 表示範囲の前後にも余分にハイライトする行数 (範囲外から続く複数行のコメントなどのため)
+const HIGHLIGHT_CONTEXT_LINES: usize = 100;
+
 impl EditorBuffer {
-    pub fn highlight(&self) -> Option<Vec<HighlightToken>> {
-        if let Some(language_support) = &self.language_support {
-            language_support.highlight(self.to_string().as_str())
-        } else {
-            None
+    // start_y..end_y の行が表示できるようにハイライトする
+    // (ファイル全体ではなく前後を含めた範囲だけを対象にし、変更か範囲外へのスクロールがあったときだけやり直す)
+    pub fn update_highlight(&mut self, start_y: usize, end_y: usize) {
+        let line_count = self.get_line_count();
+        let end_y = end_y.min(line_count);
+
+        if self.highlight_revision == Some(self.revision)
+            && self.highlight_range.start <= start_y
+            && end_y <= self.highlight_range.end
+        {
+            return;
         }
+
+        let range = start_y.saturating_sub(HIGHLIGHT_CONTEXT_LINES)
+            ..(end_y + HIGHLIGHT_CONTEXT_LINES).min(line_count);
+
+        self.highlight_tokens = match &self.language_support {
+            Some(language_support) => {
+                let source = self.content.lines(range.start, range.end).join("\n");
+                let mut tokens = language_support
+                    .highlight(source.as_str())
+                    .unwrap_or_default();
+                for token in &mut tokens {
+                    token.start.y += range.start;
+                    token.end.y += range.start;
+                }
+                tokens
+            }
+            None => vec![],
+        };
+        self.highlight_revision = Some(self.revision);
+        self.highlight_range = range;
     }
 
-    // TODO この関数はuiに移動予定
-    pub fn delete_key(&mut self, mode: &EditorMode) {
-        let cursor = self.get_position(mode);
+    // 言語ごとの補完候補 (ファイル全体ではなくカーソルより前の数行だけを渡す)
+    pub fn get_completions(&self, pos: UVec2) -> Vec<CompletionItem> {
+        let Some(language_support) = &self.language_support else {
+            return vec![];
+        };
+        let start = (0, pos.y.saturating_sub(COMPLETION_CONTEXT_LINES));
+        let source = self.content.slice(start, (pos.x, pos.y));
+        language_support
+            .complete(source.chars().count(), &source)
+            .unwrap_or_default()
+    }
 
-        if cursor.x == self.get_line_length(cursor.y) {
-            self.join_lines(cursor.y);
-        } else {
-            self.delete_char(cursor.x, cursor.y);
+    // 補完に使うバッファ内の単語の一覧を、変更があったときだけ作り直す
+    pub fn update_word_index(&mut self) {
+        if self.word_index_revision == Some(self.revision) {
+            return;
         }
-    }
 
-    // TODO この関数はuiに移動予定
-    pub fn backspace_key(&mut self, mode: &EditorMode, window_size: UVec2) -> anyhow::Result<()> {
-        let cursor = self.get_position(mode);
+        let line_count = self.get_line_count();
+        self.word_index = collect_words((0..line_count).map(|y| self.content.line(y)));
+        self.word_index_revision = Some(self.revision);
+    }
 
-        if cursor.x == 0 {
-            if cursor.y == 0 {
-                return Ok(());
-            }
+    pub fn get_word_index(&self) -> &[String] {
+        &self.word_index
+    }
 
-            let line_length = self.get_line_length(cursor.y - 1);
-            self.move_by_y(-1, mode, window_size);
+    pub fn get_highlight_tokens(&self) -> &[HighlightToken] {
+        &self.highlight_tokens
+    }
 
-            // line_length - 1 するのが本来は良いが usize が 0 以下になるのを防ぐため、- 1 はしない
-            self.move_to_x(line_length);
-            self.join_lines(cursor.y - 1);
-        } else {
-            let remove_x = cursor.x - 1;
+    // '<, '> で参照する直前の選択範囲の行
+    pub fn get_visual_marks(&self) -> Option<(usize, usize)> {
+        self.visual_marks
+    }
 
-            self.move_by_x(-1, mode);
-            self.delete_char(remove_x, cursor.y);
-        }
+    pub fn set_visual_marks(&mut self, start_y: usize, end_y: usize) {
+        self.visual_marks = Some((start_y.min(end_y), start_y.max(end_y)));
+    }
 
-        Ok(())
+    // 別のバッファに切り替えて戻ってきたときのカーソル位置
+    pub fn get_last_cursor(&self) -> UVec2 {
+        self.last_cursor
     }
 
-    // TODO この関数はuiに移動予定
-    pub fn get_draw_position(&self, mode: &EditorMode) -> UVec2 {
-        let line = self.get_line(self.cursor.y);
-        let x = self.clamp_x(self.cursor.x, mode);
-
-        UVec2::new(
-            line.to_string()
-                .chars()
-                .take(x)
-                .filter_map(|c| c.width())
-                .sum(),
-            self.cursor.y,
-        )
+    pub fn set_last_cursor(&mut self, cursor: UVec2) {
+        self.last_cursor = cursor;
     }
+}
 
-    pub fn on_action(
-        &mut self,
-        action: EditorBufferAction,
-        mode: &EditorMode,
-        _clipboard: &mut Option<Clipboard>,
-        window_size: UVec2,
-    ) -> anyhow::Result<()> {
-        match action {
-            EditorBufferAction::Save => self.save()?,
-            EditorBufferAction::Cursor(action) => match action {
-                EditorCursorAction::Left => self.move_by_x(-1, mode),
-                EditorCursorAction::Down => self.move_by_y(1, mode, window_size),
-                EditorCursorAction::Up => self.move_by_y(-1, mode, window_size),
-                EditorCursorAction::Right => self.move_by_x(1, mode),
-                EditorCursorAction::LineStart => self.move_to_x(0),
-                EditorCursorAction::LineEnd => self.move_to_x(usize::MAX),
-                EditorCursorAction::Top => self.move_to_top(mode, window_size),
-                EditorCursorAction::Bottom => self.move_to_bottom(mode, window_size),
-                EditorCursorAction::NextWord => self.move_to_next_word(),
-                EditorCursorAction::BackWord => self.move_to_back_word(),
-            },
-            EditorBufferAction::Edit(action) => match action {
-                EditorEditAction::DeleteLine => self.delete_line(self.cursor.y),
-                // EditorEditAction::DeleteSelection => {
-                //     self.delete_selection(cursor, mode, clipboard)?
-                // }
-                // EditorEditAction::YankLine => self.yank_line(cursor, mode, clipboard)?,
-                // EditorEditAction::YankSelection => self.yank_selection(cursor, mode, clipboard)?,
-                // EditorEditAction::Paste => self.paste(cursor, mode, clipboard, window_size)?,
-                _ => {}
-            },
-        };
+#[cfg(test)]
+mod tests {
+    use crate::{language_support::langs::css::CSSLanguageSupport, utils::vec2::UVec2};
+
+    use super::{EditorBuffer, HIGHLIGHT_CONTEXT_LINES};
+
+    #[test]
+    fn word_index_is_rebuilt_only_after_changes() {
+        let mut buffer = EditorBuffer::new();
+        buffer.insert_text(0, 0, "foo bar-baz 1x\nfoo qux a");
 
-        Ok(())
+        buffer.update_word_index();
+        assert_eq!(buffer.get_word_index(), ["foo", "bar-baz", "qux"]);
+
+        buffer.word_index.clear();
+        buffer.update_word_index();
+        assert!(buffer.get_word_index().is_empty());
+
+        buffer.insert_text(0, 1, "new ");
+        buffer.update_word_index();
+        assert_eq!(buffer.get_word_index(), ["foo", "bar-baz", "new", "qux"]);
     }
 
-    pub fn on_event(
-        &mut self,
-        evt: Event,
-        mode: &EditorMode,
-        window_size: UVec2,
-    ) -> anyhow::Result<Option<EditorMode>> {
-        let cursor_pos = self.get_position(mode);
-        let cursor_x = cursor_pos.x;
-        let cursor_y = cursor_pos.y;
-
-        match mode {
-            EditorMode::Normal => match evt {
-                Event::Click(pos) => {
-                    let num_len = (self.get_line_count() - 1).to_string().len();
-                    let offset_x = num_len + 1;
-                    let scroll_y = self.get_offset().y;
-
-                    let x = pos.x.checked_sub(offset_x).unwrap_or_default();
-
-                    self.move_to_y(pos.y + scroll_y, mode, window_size);
-                    self.move_to_x(x);
-                }
-                Event::Scroll(scroll) => self.scroll_by(scroll),
-                _ => {}
-            },
-            EditorMode::Insert { append: _ } => {
-                if let Event::Input(key) = evt {
-                    match key {
-                        Key::Delete => self.delete_key(mode),
-                        Key::Backspace => self.backspace_key(mode, window_size)?,
-                        Key::Char('\n') => {
-                            self.split_line(cursor_x, cursor_y);
-                            self.move_by_y(1, mode, window_size);
-                            self.move_to_x(0);
-                        }
-                        Key::Char(c) => {
-                            self.insert_char(cursor_x, cursor_y, c);
-                            self.move_by_x(1, mode);
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            _ => {}
-        }
+    #[test]
+    fn language_completions_use_text_before_cursor() {
+        let mut buffer = EditorBuffer::new();
+        buffer.insert_text(0, 0, "a {\n  col\n}\nb { color: red; }");
+        buffer.language_support = Some(Box::new(CSSLanguageSupport::new()));
+
+        assert!(!buffer.get_completions(UVec2::new(5, 1)).is_empty());
+        assert!(buffer.get_completions(UVec2::new(12, 3)).is_empty());
+    }
 
-        Ok(None)
+    #[test]
+    fn highlights_only_around_visible_lines() {
+        let text = (0..1000)
+            .map(|i| format!(".c{} {{ color: red; }}", i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut buffer = EditorBuffer::new();
+        buffer.insert_text(0, 0, &text);
+        buffer.language_support = Some(Box::new(CSSLanguageSupport::new()));
+
+        buffer.update_highlight(500, 550);
+
+        let tokens = buffer.get_highlight_tokens();
+        assert!(!tokens.is_empty());
+        assert!(tokens.iter().any(|token| token.start.y == 500));
+        assert!(tokens.iter().all(|token| {
+            token.start.y >= 500 - HIGHLIGHT_CONTEXT_LINES
+                && token.end.y < 550 + HIGHLIGHT_CONTEXT_LINES
+        }));
+
+        // 範囲内のスクロールではやり直さない
+        buffer.highlight_tokens.clear();
+        buffer.update_highlight(520, 570);
+        assert!(buffer.get_highlight_tokens().is_empty());
+
+        // 範囲外へのスクロールでやり直す
+        buffer.update_highlight(900, 950);
+        assert!(buffer
+            .get_highlight_tokens()
+            .iter()
+            .any(|token| token.start.y == 949));
     }
 }
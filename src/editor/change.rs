@@ -0,0 +1,136 @@
+use crate::{action::AppAction, utils::event::Event};
+
+use super::{
+    action::{EditorAction, EditorCursorAction},
+    mode::EditorMode,
+};
+
+// ビジュアルモードで変更した範囲の大きさ (「.」ではカーソル位置から同じ大きさを選択し直す)
+#[derive(Debug, Clone)]
+pub struct VisualRegion {
+    pub mode: EditorMode,
+    pub line_count: usize,
+    // 最後の行で選択した文字数 (複数行の文字単位の選択では行頭からの文字数)
+    pub width: usize,
+}
+
+impl VisualRegion {
+    // 選択し直すための操作
+    fn to_events(&self) -> Vec<Event> {
+        let cursor = |action: EditorCursorAction, count: usize| {
+            Event::Action(AppAction::Count(count, Box::new(action.into_app())))
+        };
+
+        let mut events = vec![Event::Action(
+            EditorAction::SetMode(self.mode.clone()).into_app(),
+        )];
+        if self.line_count > 1 {
+            events.push(cursor(EditorCursorAction::Down, self.line_count - 1));
+        }
+
+        if self.mode != EditorMode::VisualLine {
+            if self.mode == EditorMode::Visual && self.line_count > 1 {
+                events.push(Event::Action(EditorCursorAction::LineStart.into_app()));
+            }
+            if self.width > 1 {
+                events.push(cursor(EditorCursorAction::Right, self.width - 1));
+            }
+        }
+
+        events
+    }
+}
+
+// 「.」で繰り返すための直前の変更の記録
+#[derive(Default)]
+pub struct EditorChangeRecorder {
+    last_change: Vec<Event>,
+    recording: Vec<Event>,
+    start_revision: Option<usize>,
+    is_replaying: bool,
+}
+
+impl EditorChangeRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.start_revision.is_some()
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.is_replaying
+    }
+
+    pub fn set_replaying(&mut self, is_replaying: bool) {
+        self.is_replaying = is_replaying;
+    }
+
+    pub fn start(&mut self, revision: usize) {
+        self.recording = Vec::new();
+        self.start_revision = Some(revision);
+    }
+
+    // ビジュアルモードでの移動の代わりに選択範囲の大きさを記録する
+    pub fn set_visual_region(&mut self, region: VisualRegion) {
+        if self.is_recording() {
+            self.recording = region.to_events();
+        }
+    }
+
+    pub fn push(&mut self, event: Event) {
+        if self.is_recording() {
+            self.recording.push(event);
+        }
+    }
+
+    // バッファが変更されていれば直前の変更として残す
+    pub fn finish(&mut self, revision: usize) {
+        if let Some(start_revision) = self.start_revision.take() {
+            if start_revision != revision {
+                self.last_change = std::mem::take(&mut self.recording);
+            }
+        }
+
+        self.recording = Vec::new();
+    }
+
+    pub fn cancel(&mut self) {
+        self.recording = Vec::new();
+        self.start_revision = None;
+    }
+
+    // 回数を指定した場合は記録時の回数を置き換える (以降の繰り返しもその回数になる)
+    pub fn get_last_change(&mut self, count: Option<usize>) -> Vec<Event> {
+        let Some(count) = count else {
+            return self.last_change.clone();
+        };
+
+        let mut is_first = true;
+
+        self.last_change = self
+            .last_change
+            .iter()
+            .map(|event| match event {
+                Event::Action(action) => {
+                    let mut action = action.clone();
+                    while let AppAction::Count(_, inner) = action {
+                        action = *inner;
+                    }
+
+                    if is_first
+                        && !matches!(
+                            action,
+                            AppAction::EditorAction(EditorAction::SelectRegister(_))
+                        )
+                    {
+                        is_first = false;
+                        Event::Action(AppAction::Count(count, Box::new(action)))
+                    } else {
+                        Event::Action(action)
+                    }
+                }
+                event => event.clone(),
+            })
+            .collect();
+
+        self.last_change.clone()
+    }
+}
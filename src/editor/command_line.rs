@@ -0,0 +1,430 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::utils::{command::ExCommand, key_binding::Key};
+
+use super::option::complete_option;
+
+// 種類ごとに覚えておく履歴の数
+const MAX_HISTORY: usize = 100;
+
+// ファイル名を補完するコマンド
+const FILE_COMMANDS: &[&str] = &[
+    "e", "edit", "r", "read", "w", "write", "sp", "split", "vs", "vsplit", "tabnew", "tabe",
+    "tabedit",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandLineKind {
+    Command,
+    Search,
+}
+
+impl CommandLineKind {
+    // 履歴ファイルで行の先頭に付ける文字
+    fn get_prefix(&self) -> char {
+        match self {
+            CommandLineKind::Command => ':',
+            CommandLineKind::Search => '/',
+        }
+    }
+}
+
+pub enum CommandLineEvent {
+    None,
+    Changed,
+    Submit(String),
+    Cancel,
+}
+
+struct Completion {
+    start: usize,       // 補完する単語の開始位置 (バイト)
+    label_start: usize, // 候補のうち一覧に表示する部分の開始位置 (ディレクトリ名を除く)
+    original: String,
+    candidates: Vec<String>,
+    selected: Option<usize>, // None なら補完前の単語に戻す
+}
+
+// コマンドラインと検索の入力欄
+pub struct EditorCommandLine {
+    kind: CommandLineKind,
+    text: String,
+    cursor: usize, // バイト単位
+    command_history: Vec<String>,
+    search_history: Vec<String>,
+    history_index: Option<usize>,
+    history_prefix: String, // 履歴をたどる前に入力していた文字列
+    command_names: Vec<String>,
+    completion: Option<Completion>,
+    history_path: Option<PathBuf>,
+}
+
+fn get_history_path() -> Option<PathBuf> {
+    let dir = dirs::state_dir().or_else(dirs::data_local_dir)?;
+    Some(dir.join("tui-studio").join("history"))
+}
+
+// 直前の単語の開始位置 (<C-w> 用)
+fn find_word_start(text: &str) -> usize {
+    let trimmed = text.trim_end();
+    let Some(last) = trimmed.chars().next_back() else {
+        return 0;
+    };
+
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let start = if is_word(last) {
+        trimmed.rfind(|c| !is_word(c))
+    } else {
+        trimmed.rfind(|c: char| is_word(c) || c.is_whitespace())
+    };
+
+    start.map_or(0, |i| i + trimmed[i..].chars().next().unwrap().len_utf8())
+}
+
+// 空白で区切った最後の単語の開始位置
+fn find_arg_start(text: &str) -> usize {
+    text.rfind(char::is_whitespace).map_or(0, |i| i + 1)
+}
+
+// ファイル名の補完候補 (ディレクトリには / を付ける)
+fn complete_path(word: &str) -> Vec<String> {
+    let (dir, prefix) = match word.rfind('/') {
+        Some(i) => word.split_at(i + 1),
+        None => ("", word),
+    };
+    let Ok(entries) = fs::read_dir(if dir.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(dir)
+    }) else {
+        return vec![];
+    };
+
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            // 隠しファイルは . から入力したときだけ候補にする
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+
+            let is_dir = entry.path().is_dir();
+            Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+        })
+        .collect();
+
+    candidates.sort();
+    candidates
+}
+
+impl EditorCommandLine {
+    // 前回までの履歴を読み込む
+    pub fn load() -> Self {
+        let history_path = get_history_path();
+        let mut command_history = vec![];
+        let mut search_history = vec![];
+
+        if let Some(text) = history_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+        {
+            for line in text.lines() {
+                if let Some(command) = line.strip_prefix(':') {
+                    command_history.push(command.to_string());
+                } else if let Some(pattern) = line.strip_prefix('/') {
+                    search_history.push(pattern.to_string());
+                }
+            }
+        }
+
+        Self {
+            kind: CommandLineKind::Command,
+            text: String::new(),
+            cursor: 0,
+            command_history,
+            search_history,
+            history_index: None,
+            history_prefix: String::new(),
+            command_names: vec![],
+            completion: None,
+            history_path,
+        }
+    }
+
+    pub fn start(&mut self, kind: CommandLineKind, text: &str) {
+        self.kind = kind;
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+        self.history_index = None;
+        self.completion = None;
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    // プロンプトを除いたカーソルの表示位置
+    pub fn get_cursor_column(&self) -> usize {
+        self.text[..self.cursor].width()
+    }
+
+    // 補完候補の一覧と選択中の位置 (候補が1つ以下なら None)
+    pub fn get_completion(&self) -> Option<(Vec<&str>, Option<usize>)> {
+        let completion = self.completion.as_ref()?;
+        if completion.candidates.len() <= 1 {
+            return None;
+        }
+
+        let labels = completion
+            .candidates
+            .iter()
+            .map(|candidate| &candidate[completion.label_start..])
+            .collect();
+        Some((labels, completion.selected))
+    }
+
+    pub fn set_command_names(&mut self, names: Vec<String>) {
+        self.command_names = names;
+    }
+
+    fn get_history(&self) -> &Vec<String> {
+        match self.kind {
+            CommandLineKind::Command => &self.command_history,
+            CommandLineKind::Search => &self.search_history,
+        }
+    }
+
+    fn get_history_mut(&mut self) -> &mut Vec<String> {
+        match self.kind {
+            CommandLineKind::Command => &mut self.command_history,
+            CommandLineKind::Search => &mut self.search_history,
+        }
+    }
+
+    fn add_history(&mut self, text: &str) {
+        if text.trim().is_empty() {
+            return;
+        }
+
+        let history = self.get_history_mut();
+        history.retain(|entry| entry != text);
+        history.push(text.to_string());
+        if history.len() > MAX_HISTORY {
+            history.remove(0);
+        }
+
+        // 書き込めなくても入力したコマンドは実行する
+        if let Err(err) = self.save_history() {
+            log::warn!("Failed to save history: {}", err);
+        }
+    }
+
+    fn save_history(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.history_path else {
+            return Ok(());
+        };
+
+        let mut text = String::new();
+        for (kind, history) in [
+            (CommandLineKind::Command, &self.command_history),
+            (CommandLineKind::Search, &self.search_history),
+        ] {
+            for entry in history.iter().filter(|entry| !entry.contains('\n')) {
+                text.push(kind.get_prefix());
+                text.push_str(entry);
+                text.push('\n');
+            }
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    // 入力済みの文字列で始まる履歴だけをたどる
+    fn move_history(&mut self, forward: bool) {
+        let index = match self.history_index {
+            Some(index) => index,
+            None => {
+                self.history_prefix = self.text.clone();
+                self.get_history().len()
+            }
+        };
+
+        let history = self.get_history();
+        let found = if forward {
+            (index + 1..history.len()).find(|i| history[*i].starts_with(&self.history_prefix))
+        } else {
+            (0..index)
+                .rev()
+                .find(|i| history[*i].starts_with(&self.history_prefix))
+        };
+
+        match found {
+            Some(i) => {
+                self.text = history[i].clone();
+                self.history_index = Some(i);
+            }
+            // 最後まで進んだら入力していた文字列に戻す
+            None if forward => {
+                self.text = self.history_prefix.clone();
+                self.history_index = None;
+            }
+            None => {}
+        }
+
+        self.cursor = self.text.len();
+    }
+
+    fn get_candidates(&self, before: &str) -> (usize, Vec<String>) {
+        let command = ExCommand::parse(before).ok();
+        let is_name = command
+            .as_ref()
+            .is_some_and(|command| !command.bang && command.args.is_empty());
+
+        // コマンド名の途中
+        if is_name && !before.ends_with(char::is_whitespace) {
+            let name = command.map(|command| command.name).unwrap_or_default();
+            let start = before.len() - name.len();
+            let mut candidates: Vec<String> = self
+                .command_names
+                .iter()
+                .filter(|candidate| candidate.starts_with(&name))
+                .cloned()
+                .collect();
+            candidates.sort();
+            candidates.dedup();
+            return (start, candidates);
+        }
+
+        let start = find_arg_start(before);
+        let word = &before[start..];
+        let candidates = match command.as_ref().map(|command| command.name.as_str()) {
+            Some(name) if FILE_COMMANDS.contains(&name) => complete_path(word),
+            Some("se" | "set") => complete_option(word),
+            _ => vec![],
+        };
+
+        (start, candidates)
+    }
+
+    // <Tab> (補完中なら次の候補)
+    fn complete(&mut self, forward: bool) {
+        if self.completion.is_none() {
+            let before = &self.text[..self.cursor];
+            let (start, candidates) = self.get_candidates(before);
+            if candidates.is_empty() {
+                return;
+            }
+
+            let original = before[start..].to_string();
+            let label_start = original.rfind('/').map_or(0, |i| i + 1);
+
+            self.completion = Some(Completion {
+                start,
+                label_start,
+                original,
+                candidates,
+                selected: None,
+            });
+        }
+
+        let completion = self.completion.as_mut().unwrap();
+        let len = completion.candidates.len();
+        completion.selected = match (completion.selected, forward) {
+            (None, true) => Some(0),
+            (None, false) => Some(len - 1),
+            (Some(i), true) if i + 1 < len => Some(i + 1),
+            (Some(i), false) if i > 0 => Some(i - 1),
+            // 端まで行ったら一度元の単語に戻る
+            _ => None,
+        };
+
+        let word = match completion.selected {
+            Some(i) => &completion.candidates[i],
+            None => &completion.original,
+        };
+        let end = self.cursor;
+        self.text.replace_range(completion.start..end, word);
+        self.cursor = completion.start + word.len();
+
+        // 候補が1つだけなら確定する
+        if len == 1 {
+            self.completion = None;
+        }
+    }
+
+    fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    fn move_left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    fn move_right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    fn delete_before(&mut self, start: usize) {
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn on_key(&mut self, key: Key) -> CommandLineEvent {
+        let is_completing = self.completion.is_some();
+        if !matches!(key, Key::Char('\t') | Key::Ctrl('n') | Key::Ctrl('p')) {
+            self.completion = None;
+        }
+        if !matches!(key, Key::Up | Key::Down | Key::Ctrl('n') | Key::Ctrl('p')) {
+            self.history_index = None;
+        }
+
+        match key {
+            Key::Char('\n') => {
+                let text = self.text.clone();
+                self.add_history(&text);
+                return CommandLineEvent::Submit(text);
+            }
+            Key::Backspace if self.text.is_empty() => return CommandLineEvent::Cancel,
+            Key::Backspace => {
+                let end = self.cursor;
+                self.move_left();
+                self.text.replace_range(self.cursor..end, "");
+            }
+            Key::Delete => {
+                if self.cursor < self.text.len() {
+                    self.text.remove(self.cursor);
+                }
+            }
+            Key::Left => self.move_left(),
+            Key::Right => self.move_right(),
+            Key::Ctrl('b') => self.cursor = 0,
+            Key::Ctrl('e') => self.cursor = self.text.len(),
+            Key::Ctrl('w') => self.delete_before(find_word_start(&self.text[..self.cursor])),
+            Key::Ctrl('u') => self.delete_before(0),
+            Key::Char('\t') if self.kind == CommandLineKind::Command => self.complete(true),
+            Key::Ctrl('n') if is_completing => self.complete(true),
+            Key::Ctrl('p') if is_completing => self.complete(false),
+            Key::Up | Key::Ctrl('p') => self.move_history(false),
+            Key::Down | Key::Ctrl('n') => self.move_history(true),
+            Key::Char(c) => self.insert(c),
+            _ => return CommandLineEvent::None,
+        }
+
+        CommandLineEvent::Changed
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashSet;
+
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+
+use crate::{language_support::complete::CompletionItem, utils::vec2::UVec2};
+
+// 一度に表示する候補の数
+pub const COMPLETION_MENU_HEIGHT: usize = 10;
+
+// 入力中の単語がこの文字数になったら自動で補完を始める
+const AUTO_COMPLETE_MIN_LENGTH: usize = 2;
+
+// CSS のプロパティ名などハイフンを含む単語もまとめて補完する
+pub fn is_completion_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+// バッファ内の単語 (重複は除き、出てきた順に並べる)
+pub fn collect_words(lines: impl Iterator<Item = String>) -> Vec<String> {
+    let mut words = vec![];
+    let mut seen = HashSet::new();
+
+    for line in lines {
+        for word in line.split(|c: char| !is_completion_char(c)) {
+            let word = word.trim_matches('-');
+            if word.chars().count() < AUTO_COMPLETE_MIN_LENGTH
+                || word.starts_with(|c: char| c.is_numeric())
+            {
+                continue;
+            }
+            if seen.insert(word.to_string()) {
+                words.push(word.to_string());
+            }
+        }
+    }
+
+    words
+}
+
+// 挿入モードの補完メニュー
+pub struct EditorCompletion {
+    start: UVec2, // 置き換える単語の先頭
+    prefix: String,
+    candidates: Vec<CompletionItem>,
+    items: Vec<CompletionItem>,
+    selected: Option<usize>, // 自動で開いた場合は <C-n> を押すまで選択しない
+    scroll: usize,
+    is_manual: bool, // <C-n> で開いた場合は候補がなくても閉じない
+    matcher: SkimMatcherV2,
+}
+
+impl EditorCompletion {
+    pub fn new(start: UVec2, prefix: String, is_manual: bool) -> Self {
+        Self {
+            start,
+            prefix,
+            candidates: vec![],
+            items: vec![],
+            selected: is_manual.then_some(0),
+            scroll: 0,
+            is_manual,
+            matcher: SkimMatcherV2::default(),
+        }
+    }
+
+    // 自動で始めるのは単語をある程度入力してから
+    pub fn should_start(prefix: &str) -> bool {
+        prefix.chars().count() >= AUTO_COMPLETE_MIN_LENGTH
+    }
+
+    pub fn get_start(&self) -> UVec2 {
+        self.start
+    }
+
+    pub fn get_prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn get_items(&self) -> &[CompletionItem] {
+        &self.items
+    }
+
+    pub fn get_selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn get_scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn is_manual(&self) -> bool {
+        self.is_manual
+    }
+
+    // 同じラベルの候補は先に追加したものを残す (説明のないバッファ内の単語は後の候補で置き換える)
+    pub fn add_candidates(&mut self, items: Vec<CompletionItem>) {
+        for item in items {
+            match self
+                .candidates
+                .iter_mut()
+                .find(|candidate| candidate.label == item.label)
+            {
+                Some(candidate) if candidate.detail.is_none() => *candidate = item,
+                Some(_) => {}
+                None => self.candidates.push(item),
+            }
+        }
+        self.update_items();
+    }
+
+    // 入力が変わったら選択をやり直す
+    pub fn set_prefix(&mut self, prefix: String) {
+        if self.prefix != prefix {
+            self.prefix = prefix;
+            self.selected = self.is_manual.then_some(0);
+            self.scroll = 0;
+            self.update_items();
+        }
+    }
+
+    // あいまい検索のスコア順 (同じスコアなら短いラベルを優先する)
+    fn update_items(&mut self) {
+        let selected_label = self.get_selected_item().map(|item| item.label.clone());
+
+        let mut items: Vec<(i64, &CompletionItem)> = self
+            .candidates
+            .iter()
+            .filter(|item| item.label != self.prefix)
+            .filter_map(|item| {
+                if self.prefix.is_empty() {
+                    return Some((0, item));
+                }
+                let score = self.matcher.fuzzy_match(&item.label, &self.prefix)?;
+                Some((score, item))
+            })
+            .collect();
+        items.sort_by_key(|(score, item)| (-score, item.label.chars().count()));
+        self.items = items.into_iter().map(|(_, item)| item.clone()).collect();
+
+        // 言語サーバーの候補が後から届いても選択中の候補は変えない
+        if let Some(label) = selected_label {
+            self.selected = self.items.iter().position(|item| item.label == label);
+        }
+        if self.is_manual && self.selected.is_none() {
+            self.selected = Some(0);
+        }
+        self.sync_scroll();
+    }
+
+    // 未選択から <C-n> なら先頭、<C-p> なら末尾を選ぶ
+    pub fn select_by(&mut self, offset: isize) {
+        let len = self.items.len() as isize;
+        if len == 0 {
+            return;
+        }
+        let selected = match self.selected {
+            Some(selected) => selected as isize + offset,
+            None if offset > 0 => offset - 1,
+            None => len + offset,
+        };
+        self.selected = Some(selected.rem_euclid(len) as usize);
+        self.sync_scroll();
+    }
+
+    fn sync_scroll(&mut self) {
+        let selected = self.selected.unwrap_or(0);
+        if selected < self.scroll {
+            self.scroll = selected;
+        } else if selected >= self.scroll + COMPLETION_MENU_HEIGHT {
+            self.scroll = selected + 1 - COMPLETION_MENU_HEIGHT;
+        }
+        self.scroll = self
+            .scroll
+            .min(self.items.len().saturating_sub(COMPLETION_MENU_HEIGHT));
+    }
+
+    pub fn get_selected_item(&self) -> Option<&CompletionItem> {
+        self.items.get(self.selected?)
+    }
+}
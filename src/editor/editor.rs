@@ -1,60 +1,171 @@
 use std::{
-    io::{stdout, Write},
-    path::PathBuf,
+    collections::{HashMap, HashSet},
+    fs,
+    io::stdout,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
 };
 
 use anyhow::anyhow;
-use arboard::Clipboard;
 use crossterm::{
-    cursor::{Hide, MoveTo, SetCursorStyle, Show},
+    cursor::{Hide, MoveTo, RestorePosition, SavePosition, SetCursorStyle, Show},
     queue,
     terminal::{Clear, ClearType},
 };
+use regex::Regex;
+use serde_json::{json, Value};
 
 use crate::{
     action::AppAction,
-    language_support::highlight::HighlightToken,
+    language_support::{
+        complete::CompletionItem,
+        lsp::{
+            apply_text_edits, parse_completion_items, parse_hover, parse_locations,
+            parse_workspace_edit, utf16_to_byte, LspEvent, LspLocation, LspManager,
+        },
+    },
+    quickfix::quickfix::QuickfixItem,
+    replace::replace::{changed_error, write_texts, LineEdit},
     utils::{
-        command::CommandManager,
+        command::{CommandManager, CommandRange, ExCommand},
         event::Event,
         key_binding::{Key, KeyConfig, KeyConfigType},
         rect::Rect,
+        shell::{is_command_not_found, run_filter, run_in_terminal, run_program},
         term::get_term_size,
-        vec2::IVec2,
+        vec2::{IVec2, UVec2},
     },
 };
 
 use super::{
-    action::{EditorAction, EditorBufferAction, EditorCursorAction, EditorEditAction},
+    action::{
+        EditorAction, EditorBufferAction, EditorCursorAction, EditorEditAction, EditorLspAction,
+        EditorOperator, EditorTabAction, EditorTextObject, EditorWindowAction,
+    },
     buffer::EditorBuffer,
+    change::{EditorChangeRecorder, VisualRegion},
+    command_line::{CommandLineEvent, CommandLineKind, EditorCommandLine},
+    completion::{is_completion_char, EditorCompletion},
     mode::EditorMode,
+    option::EditorOptions,
+    register::{EditorRegisters, RegisterKind},
     renderer::EditorRenderer,
+    search::{
+        build_regex, build_regex_with_case, split_at_delimiter, split_pattern,
+        translate_replacement, EditorSearch,
+    },
+    tab::EditorTab,
+    view::{BlockInsert, EditorView, OperatorRange},
+    window::{EditorWindow, SplitDirection},
 };
 
+// 最後の変更からリンターを実行するまでの時間
+const LINT_DELAY: Duration = Duration::from_millis(300);
+
+// 言語サーバーに整形を頼むときの字下げの幅
+const FORMAT_TAB_SIZE: usize = 4;
+
+// execute_command で処理するコマンド (補完用)
+const EDITOR_COMMANDS: &[&str] = &[
+    "substitute",
+    "delete",
+    "yank",
+    "edit",
+    "read",
+    "bnext",
+    "bprevious",
+    "buffers",
+    "files",
+    "ls",
+    "split",
+    "vsplit",
+    "tabnew",
+    "tabedit",
+    "tabclose",
+    "tabnext",
+    "tabprevious",
+    "close",
+    "bdelete",
+    "set",
+    "rename",
+    "problems",
+    "format",
+];
+
+// 存在するファイルはシンボリックリンクも解決し、まだないファイルは絶対パスにする
+fn normalize_path(path: &Path) -> Option<PathBuf> {
+    fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .ok()
+}
+
 pub struct Editor {
     rect: Rect,
-    buffers: Vec<EditorBuffer>,
-    current_buffer_index: Option<usize>,
+    sidebar_width: usize,
+    panel_height: usize,
+    buffers: Vec<Arc<RwLock<EditorBuffer>>>,
+    tabs: Vec<EditorTab>,
+    current_tab: usize,
+    next_window_id: usize,
     mode: EditorMode,
-    clipboard: Option<Clipboard>,
-    highlight_tokens: Vec<HighlightToken>,
-    command_input_buf: String,
+    registers: EditorRegisters,
+    operator_count: Option<usize>,
+    block_insert: Option<BlockInsert>,
+    pending_keys: String,
+    recording_macro: Option<char>,
+    change_recorder: EditorChangeRecorder,
+    search: EditorSearch,
+    options: EditorOptions,
+    message: Option<String>,
+    command_line: EditorCommandLine,
+    lsp: LspManager,
+    hover: Option<Vec<String>>,
+    completion: Option<EditorCompletion>,
+    locations: Option<(String, Vec<QuickfixItem>)>, // 一覧に表示する定義や参照
     renderer: EditorRenderer,
 }
 
 impl Editor {
-    pub fn new(path: Option<String>, rect: Rect) -> anyhow::Result<Self> {
+    pub fn new(paths: Vec<String>, rect: Rect) -> anyhow::Result<Self> {
+        let mut buffers = paths
+            .into_iter()
+            .map(|path| EditorBuffer::open(PathBuf::from(path)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if buffers.is_empty() {
+            buffers.push(EditorBuffer::new());
+        }
+
+        let buffers: Vec<_> = buffers
+            .into_iter()
+            .map(|buffer| Arc::new(RwLock::new(buffer)))
+            .collect();
+        let view = EditorView::new(Arc::clone(&buffers[0]));
+
         Ok(Self {
             rect,
-            buffers: match path {
-                None => vec![EditorBuffer::new()],
-                Some(path) => vec![EditorBuffer::open(PathBuf::from(path))?],
-            },
-            current_buffer_index: Some(0),
+            sidebar_width: 0,
+            panel_height: 0,
+            buffers,
+            tabs: vec![EditorTab::new(EditorWindow::new(0, view))],
+            current_tab: 0,
+            next_window_id: 1,
             mode: EditorMode::Normal,
-            clipboard: Clipboard::new().ok(),
-            highlight_tokens: vec![],
-            command_input_buf: String::new(),
+            registers: EditorRegisters::new(),
+            operator_count: None,
+            block_insert: None,
+            pending_keys: String::new(),
+            recording_macro: None,
+            change_recorder: EditorChangeRecorder::default(),
+            search: EditorSearch::default(),
+            options: EditorOptions::default(),
+            message: None,
+            command_line: EditorCommandLine::load(),
+            lsp: LspManager::default(),
+            hover: None,
+            completion: None,
+            locations: None,
             renderer: EditorRenderer::default(),
         })
     }
@@ -67,158 +178,2276 @@ impl Editor {
         match mode {
             EditorMode::Normal => self.set_normal_mode()?,
             EditorMode::Command => self.set_command_mode(),
+            EditorMode::Search { backward } => self.set_search_mode(backward)?,
             EditorMode::Insert { append } => self.set_insert_mode(append)?,
-            EditorMode::Visual => self.set_visual_mode()?,
+            EditorMode::Visual | EditorMode::VisualLine | EditorMode::VisualBlock => {
+                self.set_visual_mode(mode)?
+            }
+            EditorMode::OperatorPending(operator) => self.set_operator_pending_mode(operator),
         }
 
         Ok(())
     }
 
-    pub fn get_current_buffer(&self) -> Option<&EditorBuffer> {
-        self.current_buffer_index.map(|index| &self.buffers[index])
+    pub fn get_current_view(&self) -> Option<&EditorView> {
+        Some(self.tab().view())
+    }
+
+    pub fn get_current_view_mut(&mut self) -> Option<&mut EditorView> {
+        Some(self.tabs[self.current_tab].view_mut())
     }
 
-    pub fn get_current_buffer_mut(&mut self) -> Option<&mut EditorBuffer> {
-        self.current_buffer_index
-            .map(|index| &mut self.buffers[index])
+    fn tab(&self) -> &EditorTab {
+        &self.tabs[self.current_tab]
+    }
+
+    fn tab_mut(&mut self) -> &mut EditorTab {
+        &mut self.tabs[self.current_tab]
     }
 
     pub fn set_normal_mode(&mut self) -> anyhow::Result<()> {
         {
-            let Some(_) = self.get_current_buffer() else {
+            let Some(_) = self.get_current_view() else {
                 return Err(anyhow!("No buffer open"));
             };
         }
 
         if let EditorMode::Insert { append } = &self.mode {
+            let window_size = self.get_window_size();
+            let current = self.tabs[self.current_tab].view_mut();
+
+            if let Some(block) = self.block_insert.take() {
+                current.finish_block_insert(block);
+            }
+
             if *append {
-                let (_, window_size) = self.rect.clone().into();
-                self.buffers[self.current_buffer_index.unwrap()].move_by(
-                    IVec2::left(),
+                current.move_by(IVec2::left(), &self.mode, window_size);
+            }
+
+            current.end_change();
+        }
+
+        // 検索を取り消したときは元の位置に戻る
+        if let Some(origin) = self.search.finish() {
+            let window_size = self.get_window_size();
+            let current = self.tabs[self.current_tab].view_mut();
+            current.move_to(origin, &EditorMode::Normal, window_size);
+        }
+
+        self.mode = EditorMode::Normal;
+        self.operator_count = None;
+        Ok(())
+    }
+
+    // ビジュアルモード同士の切り替えでは選択の開始位置を保ち、同じモードなら終了する
+    pub fn set_visual_mode(&mut self, mode: EditorMode) -> anyhow::Result<()> {
+        if self.mode == mode {
+            return self.set_normal_mode();
+        }
+
+        let is_visual = self.mode.is_visual();
+        if let Some(current) = self.get_current_view_mut() {
+            if !is_visual {
+                current.start_visual_mode();
+            }
+            self.mode = mode;
+            Ok(())
+        } else {
+            Err(anyhow!("No buffer open"))
+        }
+    }
+
+    pub fn set_insert_mode(&mut self, append: bool) -> anyhow::Result<()> {
+        let mode = self.mode.clone();
+
+        {
+            let Some(_) = self.get_current_view() else {
+                return Err(anyhow!("No buffer open"));
+            };
+        }
+
+        self.block_insert = None;
+        self.get_current_view_mut().unwrap().sync(&mode);
+        self.get_current_view_mut().unwrap().begin_change();
+        self.mode = EditorMode::Insert { append };
+
+        let window_size = self.get_window_size();
+
+        let current = self.get_current_view_mut().unwrap();
+        if append {
+            current.move_by(IVec2::right(), &mode, window_size);
+        }
+
+        current.sync(&mode);
+        Ok(())
+    }
+
+    pub fn set_operator_pending_mode(&mut self, operator: EditorOperator) {
+        self.mode = EditorMode::OperatorPending(operator);
+    }
+
+    pub fn set_command_mode(&mut self) {
+        // ビジュアルモードからは選択範囲を対象にする
+        let text = if self.mode.is_visual() { "'<,'>" } else { "" };
+        self.command_line.start(CommandLineKind::Command, text);
+        self.mode = EditorMode::Command;
+    }
+
+    pub fn set_search_mode(&mut self, backward: bool) -> anyhow::Result<()> {
+        let Some(current) = self.get_current_view() else {
+            return Err(anyhow!("No buffer open"));
+        };
+
+        let origin = current.get_position(&self.mode);
+        self.search.start(backward, origin);
+        self.mode = EditorMode::Search { backward };
+        self.command_line.start(CommandLineKind::Search, "");
+        Ok(())
+    }
+
+    pub fn get_message(&self) -> Option<&String> {
+        self.message.as_ref()
+    }
+
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    // カーソルのある行の診断 (メッセージがないときにステータスラインに表示する)
+    pub fn get_cursor_diagnostic(&self) -> Option<String> {
+        let view = self.get_current_view()?;
+        let buffer = view.buffer();
+        let diagnostic = buffer.get_line_diagnostic(view.get_position(&self.mode).y)?;
+        let message = diagnostic.message.lines().next().unwrap_or_default();
+
+        Some(format!("{}: {}", diagnostic.severity.get_sign(), message))
+    }
+
+    pub fn clear_message(&mut self) {
+        self.message = None;
+    }
+
+    // nohlsearch でも入力中のパターンは表示する
+    pub fn get_search_highlight(&self) -> Option<&Regex> {
+        if !self.options.hlsearch && !matches!(self.mode, EditorMode::Search { .. }) {
+            return None;
+        }
+
+        self.search.get_highlight_regex()
+    }
+
+    pub fn get_command_line(&self) -> &EditorCommandLine {
+        &self.command_line
+    }
+
+    // 補完に使うコマンド名 (エディタで処理するコマンドは追加する)
+    pub fn set_command_names(&mut self, mut names: Vec<String>) {
+        names.extend(EDITOR_COMMANDS.iter().map(|name| name.to_string()));
+        self.command_line.set_command_names(names);
+    }
+
+    // 入力途中のキー (回数指定など) をステータスラインに表示する
+    pub fn set_pending_keys(&mut self, keys: &[Key]) {
+        self.pending_keys = keys.iter().map(|key| key.to_string()).collect();
+    }
+
+    pub fn get_pending_keys(&self) -> String {
+        match self.operator_count {
+            Some(count) => format!("{}{}", count, self.pending_keys),
+            None => self.pending_keys.clone(),
+        }
+    }
+
+    pub fn get_recording_macro(&self) -> Option<char> {
+        self.recording_macro
+    }
+
+    pub fn set_recording_macro(&mut self, name: Option<char>) {
+        self.recording_macro = name;
+    }
+
+    pub fn get_registers_mut(&mut self) -> &mut EditorRegisters {
+        &mut self.registers
+    }
+
+    // 3d2w のように、オペレーターとモーションの回数は掛け合わせる
+    fn take_operator_count(&mut self, count: Option<usize>) -> Option<usize> {
+        match (self.operator_count.take(), count) {
+            (Some(operator_count), Some(count)) => Some(operator_count.saturating_mul(count)),
+            (operator_count, count) => operator_count.or(count),
+        }
+    }
+
+    pub fn on_action(&mut self, action: EditorAction, count: Option<usize>) -> anyhow::Result<()> {
+        self.hover = None;
+        self.completion = None;
+
+        if let EditorAction::RepeatLastChange = action {
+            return self.repeat_last_change(count);
+        }
+
+        if self.mode.is_visual() {
+            if let Some(current) = self.get_current_view_mut() {
+                current.save_visual_marks();
+            }
+        }
+
+        self.record_change_before(&action, count);
+        let result = self.handle_action(action.clone(), count);
+        self.record_change_after(&action);
+
+        self.update_windows();
+        result
+    }
+
+    fn get_revision(&self) -> Option<usize> {
+        self.get_current_view()
+            .map(|current| current.get_revision())
+    }
+
+    // Normal かビジュアルモードから始まった操作を記録し、バッファが変更されたら「.」の対象にする
+    fn record_change_before(&mut self, action: &EditorAction, count: Option<usize>) {
+        if self.change_recorder.is_replaying() {
+            return;
+        }
+
+        let Some(revision) = self.get_revision() else {
+            return;
+        };
+
+        if (self.mode == EditorMode::Normal || self.mode.is_visual())
+            && !self.change_recorder.is_recording()
+        {
+            self.change_recorder.start(revision);
+        }
+
+        if self.mode.is_visual()
+            && matches!(
+                action,
+                EditorAction::Operator(_) | EditorAction::BlockInsert { .. }
+            )
+        {
+            if let Some(region) = self.get_visual_region() {
+                self.change_recorder.set_visual_region(region);
+            }
+        }
+
+        let action = match count {
+            Some(count) => AppAction::Count(count, Box::new(action.clone().into_app())),
+            None => action.clone().into_app(),
+        };
+        self.change_recorder.push(Event::Action(action));
+    }
+
+    fn record_change_after(&mut self, action: &EditorAction) {
+        if self.change_recorder.is_replaying() || !self.change_recorder.is_recording() {
+            return;
+        }
+
+        if self.mode.is_visual()
+            || matches!(
+                self.mode,
+                EditorMode::OperatorPending(_) | EditorMode::Insert { .. }
+            )
+        {
+            return;
+        }
+
+        match action {
+            EditorAction::SelectRegister(_) => {}
+            EditorAction::Buffer(EditorBufferAction::Edit(
+                EditorEditAction::Undo | EditorEditAction::Redo,
+            )) => self.change_recorder.cancel(),
+            _ => match self.get_revision() {
+                Some(revision) => self.change_recorder.finish(revision),
+                None => self.change_recorder.cancel(),
+            },
+        }
+    }
+
+    fn get_visual_region(&self) -> Option<VisualRegion> {
+        let current = self.get_current_view()?;
+        let range = current.get_visual_range(&self.mode);
+
+        let (line_count, width) = match range.kind {
+            RegisterKind::Linewise => (range.end.y - range.start.y + 1, 0),
+            RegisterKind::Blockwise => {
+                (range.end.y - range.start.y + 1, range.end.x - range.start.x)
+            }
+            RegisterKind::Charwise => {
+                let last = current.index_to_pos(current.pos_to_index(range.end).saturating_sub(1));
+                let width = if last.y == range.start.y {
+                    last.x + 1 - range.start.x.min(last.x)
+                } else {
+                    last.x + 1
+                };
+                (last.y.max(range.start.y) - range.start.y + 1, width)
+            }
+        };
+
+        Some(VisualRegion {
+            mode: self.mode.clone(),
+            line_count,
+            width,
+        })
+    }
+
+    // 直前の変更を現在のカーソル位置で繰り返す (1回の undo で戻せるようにまとめる)
+    fn repeat_last_change(&mut self, count: Option<usize>) -> anyhow::Result<()> {
+        let events = self.change_recorder.get_last_change(count);
+        if events.is_empty() || self.get_current_view().is_none() {
+            return Ok(());
+        }
+
+        self.change_recorder.set_replaying(true);
+        self.tabs[self.current_tab].view_mut().begin_change();
+
+        let result = events
+            .into_iter()
+            .try_for_each(|event| self.replay_event(event));
+
+        if self.mode != EditorMode::Normal {
+            self.set_normal_mode()?;
+        }
+
+        self.tabs[self.current_tab].view_mut().end_change();
+        self.change_recorder.set_replaying(false);
+        self.update_windows();
+
+        result
+    }
+
+    fn replay_event(&mut self, event: Event) -> anyhow::Result<()> {
+        match event {
+            Event::Action(AppAction::EditorAction(action)) => self.on_action(action, None),
+            Event::Action(AppAction::Count(count, action)) => match *action {
+                AppAction::EditorAction(action) => self.on_action(action, Some(count)),
+                _ => Ok(()),
+            },
+            Event::Input(key) => self.on_event(Event::Input(key)).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    fn handle_action(&mut self, action: EditorAction, count: Option<usize>) -> anyhow::Result<()> {
+        match action {
+            EditorAction::RepeatLastChange => self.repeat_last_change(count)?,
+            EditorAction::SearchNext { reverse } => self.on_search_next(reverse, count)?,
+            EditorAction::ClearSearchHighlight => self.search.set_highlight(false),
+            EditorAction::DiagnosticNext { reverse } => self.on_diagnostic_next(reverse, count)?,
+            EditorAction::SetMode(mode) => self.set_mode(mode)?,
+            EditorAction::SelectRegister(name) => self.registers.select(name),
+            EditorAction::Operator(operator) => self.on_operator(operator, count)?,
+            EditorAction::BlockInsert { append } => self.on_block_insert(append)?,
+            EditorAction::Window(action) => self.on_window_action(action, count)?,
+            EditorAction::Tab(action) => self.on_tab_action(action, count)?,
+            EditorAction::TextObject { object, inner } => self.on_text_object(object, inner)?,
+            EditorAction::Lsp(action) => self.on_lsp_action(action)?,
+            EditorAction::Buffer(EditorBufferAction::Cursor(motion))
+                if matches!(self.mode, EditorMode::OperatorPending(_)) =>
+            {
+                let EditorMode::OperatorPending(operator) = self.mode else {
+                    unreachable!()
+                };
+
+                let window_size = self.get_window_size();
+                if self.get_current_view().is_none() {
+                    return Ok(());
+                }
+
+                let count = self.take_operator_count(count);
+                let current = self.tabs[self.current_tab].view_mut();
+
+                let range = current.get_motion_range(operator, motion, count, window_size);
+                self.apply_operator(operator, range)?;
+            }
+            EditorAction::Buffer(_) if matches!(self.mode, EditorMode::OperatorPending(_)) => {
+                self.set_normal_mode()?
+            }
+            EditorAction::Buffer(action) => {
+                {
+                    let Some(_) = self.get_current_view() else {
+                        return Ok(());
+                    };
+                }
+
+                // 整形に失敗しても保存はする
+                if matches!(action, EditorBufferAction::Save) && self.options.formatonsave {
+                    if let Err(err) = self.format_buffer() {
+                        self.message = Some(format!("Failed to format: {}", err));
+                    }
+                }
+
+                let window_size = self.get_window_size();
+                let mode = self.tabs[self.current_tab].view_mut().on_action(
+                    action,
+                    count,
                     &self.mode,
+                    &mut self.registers,
                     window_size,
-                );
+                )?;
+
+                if let Some(mode) = mode {
+                    self.set_mode(mode)?;
+                }
             }
+        };
+
+        Ok(())
+    }
+
+    // n, N (オペレーター待ちの場合は一致箇所までを範囲にする)
+    fn on_search_next(&mut self, reverse: bool, count: Option<usize>) -> anyhow::Result<()> {
+        let Some(target) = self.find_next_match(reverse, count)? else {
+            return Ok(());
+        };
+
+        let window_size = self.get_window_size();
+
+        if let EditorMode::OperatorPending(operator) = self.mode {
+            let cursor = self.tab().view().get_position(&self.mode);
+            self.operator_count = None;
+            self.apply_operator(operator, OperatorRange::charwise(cursor, target))?;
+        } else {
+            self.tabs[self.current_tab]
+                .view_mut()
+                .move_to(target, &self.mode, window_size);
         }
 
-        self.mode = EditorMode::Normal;
         Ok(())
     }
 
-    pub fn set_visual_mode(&mut self) -> anyhow::Result<()> {
-        if let Some(current) = self.get_current_buffer_mut() {
-            current.start_visual_mode();
-            self.mode = EditorMode::Visual;
+    fn find_next_match(
+        &mut self,
+        reverse: bool,
+        count: Option<usize>,
+    ) -> anyhow::Result<Option<UVec2>> {
+        let Some(current) = self.get_current_view() else {
+            return Ok(None);
+        };
+
+        let Some(regex) = self.search.get_regex() else {
+            return Err(anyhow!("No previous regular expression"));
+        };
+
+        let backward = self.search.is_backward() != reverse;
+        let mut position = current.get_position(&self.mode);
+        let mut wrapped = false;
+
+        for _ in 0..count.unwrap_or(1) {
+            let Some((next, next_wrapped)) = current.find_match(regex, position, backward) else {
+                return Err(anyhow!("Pattern not found: {}", regex.as_str()));
+            };
+            if next_wrapped && !self.options.wrapscan {
+                return Err(anyhow!(
+                    "search hit {} without match for: {}",
+                    if backward { "TOP" } else { "BOTTOM" },
+                    regex.as_str()
+                ));
+            }
+
+            position = next;
+            wrapped |= next_wrapped;
+        }
+
+        if wrapped {
+            self.message = Some(if backward {
+                "search hit TOP, continuing at BOTTOM".to_string()
+            } else {
+                "search hit BOTTOM, continuing at TOP".to_string()
+            });
+        }
+
+        self.search.set_highlight(true);
+        Ok(Some(position))
+    }
+
+    fn on_search_input(&mut self, key: Key) -> anyhow::Result<()> {
+        match self.command_line.on_key(key) {
+            CommandLineEvent::Changed => self.incremental_search(),
+            CommandLineEvent::Submit(pattern) => return self.commit_search(&pattern),
+            CommandLineEvent::Cancel => return self.set_normal_mode(),
+            CommandLineEvent::None => {}
+        }
+
+        Ok(())
+    }
+
+    // 入力中のパターンに一致する位置へ仮に移動する
+    fn incremental_search(&mut self) {
+        let Some(origin) = self.search.get_origin() else {
+            return;
+        };
+
+        let window_size = self.get_window_size();
+        let pattern = self.command_line.get_text();
+        let regex = if pattern.is_empty() {
+            None
+        } else {
+            build_regex(pattern).ok()
+        };
+
+        let target = regex
+            .as_ref()
+            .and_then(|regex| {
+                self.tab()
+                    .view()
+                    .find_match(regex, origin, self.search.is_backward())
+            })
+            .map(|(target, _)| target)
+            .unwrap_or(origin);
+
+        self.tabs[self.current_tab]
+            .view_mut()
+            .move_to(target, &EditorMode::Normal, window_size);
+        self.search.set_preview(regex);
+    }
+
+    // 空のパターンは直前の検索を繰り返す
+    fn commit_search(&mut self, pattern: &str) -> anyhow::Result<()> {
+        self.set_normal_mode()?;
+
+        if !pattern.is_empty() {
+            self.search.set_pattern(pattern)?;
+        }
+
+        self.on_search_next(false, None)
+    }
+
+    // Ex コマンドのうちエディタで処理するもの。処理しなかった場合は false を返す
+    pub fn execute_command(&mut self, command: &ExCommand) -> anyhow::Result<bool> {
+        if self.get_current_view().is_none() {
+            return Ok(false);
+        }
+
+        match command.name.as_str() {
+            "" if command.bang => self.execute_shell(command)?,
+            "" if command.range.is_some() => {
+                let (_, y) = self.resolve_range(command.range.as_ref(), false)?;
+                self.move_to_line(y);
+            }
+            "s" | "substitute" => self.substitute(command)?,
+            "d" | "delete" => self.apply_command_operator(EditorOperator::Delete, command)?,
+            "y" | "yank" => self.apply_command_operator(EditorOperator::Yank, command)?,
+            "e" | "edit" => self.edit_file(command.args.trim())?,
+            "r" | "read" => self.read_into_buffer(command)?,
+            "bn" | "bnext" => self.cycle_buffer(Self::parse_buffer_count(command)? as isize)?,
+            "bp" | "bprevious" | "bN" | "bNext" => {
+                self.cycle_buffer(-(Self::parse_buffer_count(command)? as isize))?
+            }
+            "ls" | "buffers" | "files" => self.list_buffers(),
+            "sp" | "split" => self.split_window(SplitDirection::Horizontal, command.args.trim())?,
+            "vs" | "vsplit" => self.split_window(SplitDirection::Vertical, command.args.trim())?,
+            "tabnew" | "tabe" | "tabedit" => self.new_tab(command.args.trim())?,
+            "tabc" | "tabclose" => self.close_tab()?,
+            "tabn" | "tabnext" => {
+                self.on_tab_action(EditorTabAction::Next, Self::parse_tab_count(command)?)?
+            }
+            "tabp" | "tabprevious" | "tabN" | "tabNext" => {
+                self.on_tab_action(EditorTabAction::Previous, Self::parse_tab_count(command)?)?
+            }
+            "clo" | "close" => self.close_window()?,
+            "bd" | "bdelete" => self.delete_buffer(command)?,
+            "problems" => self.show_problems()?,
+            "format" => {
+                if !self.format_buffer()? {
+                    return Err(anyhow!("Can't format this buffer"));
+                }
+            }
+            "rename" => {
+                let name = command.args.trim();
+                if name.is_empty() {
+                    return Err(anyhow!("Argument required"));
+                }
+                self.on_lsp_action(EditorLspAction::Rename(name.to_string()))?
+            }
+            "se" | "set" => {
+                if let Some(message) = self.options.set(&command.args)? {
+                    self.message = Some(message);
+                }
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn parse_buffer_count(command: &ExCommand) -> anyhow::Result<usize> {
+        match command.args.trim() {
+            "" => Ok(1),
+            args => args
+                .parse()
+                .map_err(|_| anyhow!("Invalid argument: {}", args)),
+        }
+    }
+
+    fn parse_tab_count(command: &ExCommand) -> anyhow::Result<Option<usize>> {
+        match command.args.trim() {
+            "" => Ok(None),
+            args => args
+                .parse()
+                .map(Some)
+                .map_err(|_| anyhow!("Invalid argument: {}", args)),
+        }
+    }
+
+    fn get_current_buffer_index(&self) -> usize {
+        let view = self.tab().view();
+        self.buffers
+            .iter()
+            .position(|buffer| view.is_showing(buffer))
+            .unwrap_or(0)
+    }
+
+    // 現在のウィンドウに表示するバッファを切り替える (カーソル位置はバッファごとに覚えておく)
+    fn switch_buffer(&mut self, index: usize) -> anyhow::Result<()> {
+        if self.mode != EditorMode::Normal {
+            self.set_normal_mode()?;
+        }
+
+        let window_size = self.get_window_size();
+        let buffer = Arc::clone(&self.buffers[index]);
+        let view = self.tabs[self.current_tab].view_mut();
+
+        let cursor = view.get_position(&EditorMode::Normal);
+        view.buffer_mut().set_last_cursor(cursor);
+
+        *view = EditorView::new(buffer);
+        view.sync(&EditorMode::Normal);
+        view.sync_scroll_y(&EditorMode::Normal, window_size);
+
+        self.update_windows();
+        Ok(())
+    }
+
+    // :e path (すでに開いているファイルならそのバッファに切り替える)
+    pub fn edit_file(&mut self, path: &str) -> anyhow::Result<()> {
+        if path.is_empty() {
+            return Err(anyhow!("No file name"));
+        }
+
+        let path = PathBuf::from(path);
+        if let Some(index) = self.find_buffer_index(&path) {
+            return self.switch_buffer(index);
+        }
+
+        let buffer = Arc::new(RwLock::new(EditorBuffer::open(path)?));
+        let index = self.get_current_buffer_index();
+
+        // 変更していない無名バッファは開いたファイルで置き換える
+        let is_empty = {
+            let current = self.buffers[index].read().unwrap();
+            current.get_path().is_none() && !current.is_modified()
+        };
+        let current_window = self.get_current_window_id();
+        let is_shown_elsewhere = self.tabs.iter().enumerate().any(|(i, tab)| {
+            tab.get_layout().get_windows().iter().any(|window| {
+                (i != self.current_tab || window.get_id() != current_window)
+                    && window.get_view().is_showing(&self.buffers[index])
+            })
+        });
+
+        if is_empty && !is_shown_elsewhere {
+            self.buffers[index] = buffer;
+            self.switch_buffer(index)
+        } else {
+            self.buffers.push(buffer);
+            self.switch_buffer(self.buffers.len() - 1)
+        }
+    }
+
+    // :bn, :bp (末尾と先頭はつながっている)
+    fn cycle_buffer(&mut self, offset: isize) -> anyhow::Result<()> {
+        let len = self.buffers.len() as isize;
+        let index = self.get_current_buffer_index() as isize;
+        self.switch_buffer((index + offset).rem_euclid(len) as usize)
+    }
+
+    // バッファ番号, 現在のバッファ (%), 未保存の変更 (+), 名前
+    fn list_buffers(&mut self) {
+        let current_index = self.get_current_buffer_index();
+        let list = self
+            .buffers
+            .iter()
+            .enumerate()
+            .map(|(index, buffer)| {
+                let buffer = buffer.read().unwrap();
+                format!(
+                    "{}{}{} \"{}\"",
+                    index + 1,
+                    if index == current_index { " %" } else { "" },
+                    if buffer.is_modified() { " +" } else { "" },
+                    buffer.get_name()
+                )
+            })
+            .collect::<Vec<_>>();
+
+        self.message = Some(list.join(" | "));
+    }
+
+    // ファイルやディレクトリを移動したとき、その中のファイルを開いているバッファのパスを付け替える
+    pub fn rename_buffer_paths(&mut self, from: &Path, to: &Path) -> anyhow::Result<()> {
+        let current_dir = std::env::current_dir()?;
+
+        for buffer in &self.buffers {
+            let mut buffer = buffer.write().unwrap();
+            let Some(path) = buffer.get_path() else {
+                continue;
+            };
+            let Ok(rest) = std::path::absolute(path)?
+                .strip_prefix(from)
+                .map(Path::to_path_buf)
+            else {
+                continue;
+            };
+
+            let path = to.join(rest);
+            let path = match path.strip_prefix(&current_dir) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => path,
+            };
+            buffer.set_path(path);
+        }
+
+        Ok(())
+    }
+
+    // ファイルやディレクトリを削除したとき、その中のファイルを開いているバッファを未保存にする
+    pub fn mark_buffers_deleted(&mut self, path: &Path) -> anyhow::Result<()> {
+        let mut count = 0;
+
+        for buffer in &self.buffers {
+            let mut buffer = buffer.write().unwrap();
+            let Some(buffer_path) = buffer.get_path() else {
+                continue;
+            };
+            if std::path::absolute(buffer_path)?.starts_with(path) {
+                buffer.set_deleted();
+                count += 1;
+            }
+        }
+
+        if count > 0 {
+            self.set_message(format!(
+                "{} open buffer(s) no longer exist on disk (:w to write them again)",
+                count
+            ));
+        }
+        Ok(())
+    }
+
+    fn find_buffer(&self, path: &Path) -> Option<&Arc<RwLock<EditorBuffer>>> {
+        self.find_buffer_index(path)
+            .map(|index| &self.buffers[index])
+    }
+
+    // ./a.txt や dir/../a.txt のように書き方が違っても同じファイルなら同じバッファとみなす
+    fn find_buffer_index(&self, path: &Path) -> Option<usize> {
+        let path = normalize_path(path)?;
+        self.buffers.iter().position(|buffer| {
+            buffer
+                .read()
+                .unwrap()
+                .get_path()
+                .and_then(normalize_path)
+                .is_some_and(|buffer_path| buffer_path == path)
+        })
+    }
+
+    // 1つのバッファを didOpen するか、まだ送っていない変更を送る (言語サーバーがあればそのパスを返す)
+    fn sync_document(lsp: &mut LspManager, buffer: &mut EditorBuffer) -> Option<PathBuf> {
+        let (Some(path), Some(command)) = (
+            buffer
+                .get_path()
+                .and_then(|path| std::path::absolute(path).ok()),
+            buffer.get_language_server_cmd(),
+        ) else {
+            return None;
+        };
+
+        // 起動できなかった (または終了した) サーバーのために全体の文字列を作らない
+        if lsp.get_client(&command).is_none() {
+            buffer.close_document();
+            return None;
+        }
+
+        let result = if buffer.get_document_path() != Some(path.as_path()) || !lsp.is_open(&path) {
+            buffer.open_document(path.clone());
+            lsp.open_document(
+                &command,
+                &path,
+                &buffer.get_language_id(),
+                buffer.get_revision(),
+                &buffer.to_string(),
+            )
+        } else {
+            let (changes, is_saved) = buffer.take_document_changes();
+            let Some(client) = lsp.get_document_client(&path) else {
+                return Some(path);
+            };
+
+            let mut result = Ok(());
+            if !changes.is_empty() {
+                result =
+                    client.did_change(&path, buffer.get_revision(), changes, || buffer.to_string());
+            }
+            if is_saved {
+                result = result.and_then(|_| client.did_save(&path));
+            }
+            result
+        };
+
+        if let Err(err) = result {
+            log::warn!("Failed to sync {}: {}", path.display(), err);
+        }
+        Some(path)
+    }
+
+    // バッファの変更を言語サーバーに送り、届いた診断を反映する (再描画が必要なら true)
+    pub fn sync_language_servers(&mut self) -> bool {
+        let mut paths = HashSet::new();
+
+        for buffer in &self.buffers {
+            if let Some(path) = Self::sync_document(&mut self.lsp, &mut buffer.write().unwrap()) {
+                paths.insert(path);
+            }
+        }
+
+        if let Err(err) = self.lsp.close_documents_except(&paths) {
+            log::warn!("Failed to close documents: {}", err);
+        }
+
+        let mut is_changed = false;
+        for event in self.lsp.poll() {
+            match event {
+                LspEvent::Diagnostics { path, diagnostics } => {
+                    if let Some(buffer) = self.find_buffer(&path) {
+                        buffer.write().unwrap().set_diagnostics(diagnostics);
+                        is_changed = true;
+                    }
+                }
+                // 自動で補完を始めたときのエラーは表示しない
+                LspEvent::Response {
+                    method,
+                    result: Err(_),
+                } if method == "textDocument/completion" => {}
+                LspEvent::Response { method, result } => {
+                    let result = result
+                        .map_err(|err| anyhow!(err))
+                        .and_then(|result| self.on_lsp_response(&method, &result));
+                    if let Err(err) = result {
+                        self.message = Some(err.to_string());
+                    }
+                    is_changed = true;
+                }
+            }
+        }
+
+        is_changed
+    }
+
+    // 変更が落ち着いたバッファのリンターを実行する (再描画が必要なら true)
+    pub fn update_lint(&mut self) -> bool {
+        let mut is_changed = false;
+        for buffer in &self.buffers {
+            is_changed |= buffer.write().unwrap().update_lint(LINT_DELAY);
+        }
+        is_changed
+    }
+
+    // ]d, [d
+    fn on_diagnostic_next(&mut self, reverse: bool, count: Option<usize>) -> anyhow::Result<()> {
+        let Some(view) = self.get_current_view() else {
+            return Ok(());
+        };
+        let mut pos = view.get_position(&self.mode);
+        {
+            let buffer = view.buffer();
+            for _ in 0..count.unwrap_or(1) {
+                pos = buffer
+                    .find_diagnostic(pos, reverse)
+                    .ok_or_else(|| anyhow!("No diagnostics"))?
+                    .start;
+            }
+        }
+
+        self.move_to(pos);
+        Ok(())
+    }
+
+    // :problems (開いているバッファの診断を一覧にする)
+    fn show_problems(&mut self) -> anyhow::Result<()> {
+        let mut items = vec![];
+        for buffer in &self.buffers {
+            let buffer = buffer.read().unwrap();
+            let Some(path) = buffer.get_path() else {
+                continue;
+            };
+
+            items.extend(buffer.get_diagnostics().iter().map(|diagnostic| {
+                let message = diagnostic.message.lines().next().unwrap_or_default();
+                QuickfixItem {
+                    path: path.to_string_lossy().to_string(),
+                    line: diagnostic.start.y,
+                    column: diagnostic.start.x,
+                    text: match &diagnostic.source {
+                        Some(source) => format!(
+                            "{}: {} [{}]",
+                            diagnostic.severity.get_sign(),
+                            message,
+                            source
+                        ),
+                        None => format!("{}: {}", diagnostic.severity.get_sign(), message),
+                    },
+                }
+            }));
+        }
+
+        if items.is_empty() {
+            return Err(anyhow!("No problems"));
+        }
+        self.locations = Some((format!("Problems ({})", items.len()), items));
+        Ok(())
+    }
+
+    pub fn shutdown_language_servers(&mut self) {
+        self.lsp.shutdown();
+    }
+
+    // gd, gr, K, :rename (結果は sync_language_servers で受け取る)
+    fn on_lsp_action(&mut self, action: EditorLspAction) -> anyhow::Result<()> {
+        // 直前の変更を送ってから問い合わせる
+        self.sync_language_servers();
+
+        let (path, position) = {
+            let Some(view) = self.get_current_view() else {
+                return Ok(());
+            };
+            let buffer = view.buffer();
+            let path = buffer
+                .get_document_path()
+                .map(Path::to_path_buf)
+                .ok_or_else(|| anyhow!("No language server for this buffer"))?;
+            (path, buffer.position_to_lsp(view.get_position(&self.mode)))
+        };
+        let client = self
+            .lsp
+            .get_document_client(&path)
+            .ok_or_else(|| anyhow!("No language server for this buffer"))?;
+
+        let (method, params) = match action {
+            EditorLspAction::GoToDefinition => ("textDocument/definition", json!({})),
+            EditorLspAction::FindReferences => (
+                "textDocument/references",
+                json!({ "context": { "includeDeclaration": true } }),
+            ),
+            EditorLspAction::Hover => ("textDocument/hover", json!({})),
+            EditorLspAction::Rename(name) => ("textDocument/rename", json!({ "newName": name })),
+        };
+        client.request_at(method, &path, position, params)
+    }
+
+    fn on_lsp_response(&mut self, method: &str, result: &Value) -> anyhow::Result<()> {
+        match method {
+            "textDocument/definition" => {
+                self.show_locations("Definitions", parse_locations(result))
+            }
+            "textDocument/references" => self.show_locations("References", parse_locations(result)),
+            "textDocument/hover" => {
+                let text =
+                    parse_hover(result).ok_or_else(|| anyhow!("No information available"))?;
+                self.hover = Some(
+                    text.replace('\t', "    ")
+                        .lines()
+                        .map(str::to_string)
+                        .collect(),
+                );
+                Ok(())
+            }
+            "textDocument/rename" => self.apply_workspace_edit(result),
+            "textDocument/completion" => {
+                if let Some(completion) = &mut self.completion {
+                    completion.add_candidates(parse_completion_items(result));
+                    if completion.get_items().is_empty() && !completion.is_manual() {
+                        self.completion = None;
+                    }
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // 1つだけならその位置に移動し、複数あれば一覧で選べるようにする
+    fn show_locations(&mut self, title: &str, locations: Vec<LspLocation>) -> anyhow::Result<()> {
+        let current_dir = std::env::current_dir()?;
+        let display_path = |path: &Path| match path.strip_prefix(&current_dir) {
+            Ok(relative) => relative.to_string_lossy().to_string(),
+            Err(_) => path.to_string_lossy().to_string(),
+        };
+
+        match locations.as_slice() {
+            [] => Err(anyhow!("No locations found")),
+            [location] => {
+                self.edit_file(&display_path(&location.path))?;
+                let pos = match self.get_current_view() {
+                    Some(view) => view.buffer().position_from_lsp(location.range.start),
+                    None => return Ok(()),
+                };
+                self.move_to(pos);
+                Ok(())
+            }
+            _ => {
+                let mut files: HashMap<PathBuf, Vec<String>> = HashMap::new();
+                let items = locations
+                    .iter()
+                    .map(|location| {
+                        let lines =
+                            files.entry(location.path.clone()).or_insert_with(|| {
+                                match self.find_buffer(&location.path) {
+                                    Some(buffer) => {
+                                        let buffer = buffer.read().unwrap();
+                                        buffer.get_lines(0, buffer.get_line_count())
+                                    }
+                                    None => fs::read_to_string(&location.path)
+                                        .unwrap_or_default()
+                                        .lines()
+                                        .map(str::to_string)
+                                        .collect(),
+                                }
+                            });
+                        let start = location.range.start;
+                        let text = lines.get(start.line).cloned().unwrap_or_default();
+                        let column = text[..utf16_to_byte(&text, start.character)]
+                            .chars()
+                            .count();
+
+                        QuickfixItem {
+                            path: display_path(&location.path),
+                            line: start.line,
+                            column,
+                            text,
+                        }
+                    })
+                    .collect();
+
+                self.locations = Some((format!("{} ({})", title, locations.len()), items));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn get_completion(&self) -> Option<&EditorCompletion> {
+        self.completion.as_ref()
+    }
+
+    // カーソルの前にある入力中の単語の先頭と、その単語
+    fn get_completion_word(&self) -> Option<(UVec2, String)> {
+        let view = self.get_current_view()?;
+        let cursor = view.get_position(&self.mode);
+        let line: Vec<char> = view.buffer().get_line(cursor.y).chars().collect();
+        let end = cursor.x.min(line.len());
+        let len = line[..end]
+            .iter()
+            .rev()
+            .take_while(|c| is_completion_char(**c))
+            .count();
+
+        Some((
+            UVec2::new(end - len, cursor.y),
+            line[end - len..end].iter().collect(),
+        ))
+    }
+
+    // 言語ごとの候補とバッファ内の単語から補完を始め、言語サーバーの候補は届いたら追加する
+    fn start_completion(&mut self, is_manual: bool) {
+        let Some((start, prefix)) = self.get_completion_word() else {
+            return;
+        };
+        let Some(view) = self.get_current_view() else {
+            return;
+        };
+
+        let mut completion = EditorCompletion::new(start, prefix.clone(), is_manual);
+        let (items, document) = {
+            let buffer = view.buffer();
+            let cursor = view.get_position(&self.mode);
+            let document = buffer
+                .get_document_path()
+                .map(|path| (path.to_path_buf(), buffer.position_to_lsp(cursor)));
+            (buffer.get_completions(cursor), document)
+        };
+        completion.add_candidates(items);
+
+        // 今のバッファの単語を優先する (単語の一覧はバッファが変更されたときだけ作り直す)
+        let current = self.get_current_buffer_index();
+        let mut words = vec![];
+        let mut seen = HashSet::new();
+        for index in
+            std::iter::once(current).chain((0..self.buffers.len()).filter(|i| *i != current))
+        {
+            let mut buffer = self.buffers[index].write().unwrap();
+            buffer.update_word_index();
+            for word in buffer.get_word_index() {
+                if *word != prefix && seen.insert(word.clone()) {
+                    words.push(CompletionItem {
+                        label: word.clone(),
+                        detail: None,
+                        insert_text: word.clone(),
+                    });
+                }
+            }
+        }
+        completion.add_candidates(words);
+
+        self.completion = (is_manual || !completion.get_items().is_empty() || document.is_some())
+            .then_some(completion);
+
+        // 補完を要求する前に今のバッファの変更だけを送る
+        if let Some((path, position)) = document {
+            Self::sync_document(
+                &mut self.lsp,
+                &mut self.tabs[self.current_tab].view().buffer_mut(),
+            );
+            if let Some(client) = self.lsp.get_document_client(&path) {
+                let params = json!({ "context": { "triggerKind": 1 } });
+                if let Err(err) =
+                    client.request_at("textDocument/completion", &path, position, params)
+                {
+                    log::warn!("Failed to request completion: {}", err);
+                }
+            }
+        }
+    }
+
+    // 入力に合わせて候補を絞り込む (単語の外に出たら閉じる)
+    fn update_completion(&mut self) {
+        let Some((start, prefix)) = self.get_completion_word() else {
+            self.completion = None;
+            return;
+        };
+
+        match &mut self.completion {
+            Some(completion) if completion.get_start() == start => {
+                completion.set_prefix(prefix.clone());
+                if !completion.is_manual()
+                    && (completion.get_items().is_empty()
+                        || !EditorCompletion::should_start(&prefix))
+                {
+                    self.completion = None;
+                }
+            }
+            _ if EditorCompletion::should_start(&prefix) => self.start_completion(false),
+            _ => self.completion = None,
+        }
+    }
+
+    // 補完メニューの操作 (処理したキーなら true)
+    fn on_completion_key(&mut self, key: &Key) -> anyhow::Result<bool> {
+        let Some(completion) = &mut self.completion else {
+            if matches!(key, Key::Ctrl('n') | Key::Ctrl('p') | Key::Ctrl(' ')) {
+                self.start_completion(true);
+                return Ok(true);
+            }
+            return Ok(false);
+        };
+
+        match key {
+            Key::Ctrl('n') | Key::Down => completion.select_by(1),
+            Key::Ctrl('p') | Key::Up => completion.select_by(-1),
+            Key::Ctrl(' ') => {}
+            Key::Ctrl('e') => self.completion = None,
+            // 何も選んでいなければ <Tab> は先頭の候補を使う
+            Key::Char('\t') if !completion.get_items().is_empty() => {
+                if completion.get_selected().is_none() {
+                    completion.select_by(1);
+                }
+                self.accept_completion()?;
+            }
+            Key::Char('\n') | Key::Ctrl('y') if completion.get_selected().is_some() => {
+                self.accept_completion()?
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    // 入力中の単語を選んだ候補で置き換える (. で繰り返せるようキー入力として処理する)
+    fn accept_completion(&mut self) -> anyhow::Result<()> {
+        let Some(completion) = self.completion.take() else {
+            return Ok(());
+        };
+        let Some(item) = completion.get_selected_item() else {
+            return Ok(());
+        };
+
+        let keys = std::iter::repeat_n(Key::Backspace, completion.get_prefix().chars().count())
+            .chain(item.insert_text.chars().map(Key::Char));
+        let window_size = self.get_window_size();
+        for key in keys {
+            self.change_recorder.push(Event::Input(key.clone()));
+            self.tabs[self.current_tab].view_mut().on_event(
+                Event::Input(key),
+                &self.mode,
+                window_size,
+            )?;
+        }
+
+        self.update_windows();
+        Ok(())
+    }
+
+    // 一覧に表示する定義や参照、診断があれば受け取る
+    pub fn take_locations(&mut self) -> Option<(String, Vec<QuickfixItem>)> {
+        self.locations.take()
+    }
+
+    pub fn get_hover(&self) -> Option<&[String]> {
+        self.hover.as_deref()
+    }
+
+    // :rename の結果を適用する (開いていないファイルは直接書き換え、開いているバッファは1回の undo で戻せる)
+    fn apply_workspace_edit(&mut self, result: &Value) -> anyhow::Result<()> {
+        let files = parse_workspace_edit(result)?;
+        if files.is_empty() {
+            return Err(anyhow!("Nothing to rename"));
+        }
+
+        let (opened, closed): (Vec<_>, Vec<_>) = files
+            .iter()
+            .partition(|(path, _)| self.find_buffer(path).is_some());
+
+        let texts = closed
+            .iter()
+            .map(|(path, edits)| {
+                Ok((
+                    path.clone(),
+                    apply_text_edits(&fs::read_to_string(path)?, edits),
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        write_texts(&texts)?;
+
+        for (path, edits) in &opened {
+            let buffer = Arc::clone(self.find_buffer(path).unwrap());
+            buffer.write().unwrap().apply_text_edits(edits);
+        }
+
+        let count: usize = files.iter().map(|(_, edits)| edits.len()).sum();
+        self.message = Some(format!(
+            "Renamed {} occurrences in {} files",
+            count,
+            files.len()
+        ));
+
+        self.sync_window_scroll();
+        self.update_windows();
+        Ok(())
+    }
+
+    // :format (外部コマンド、言語サーバー、組み込みのフォーマッターの順に使う)
+    // 整形できるものがなければ false を返す
+    fn format_buffer(&mut self) -> anyhow::Result<bool> {
+        // 直前の変更を送ってから頼む
+        self.sync_language_servers();
+
+        let (text, command, path) = {
+            let Some(view) = self.get_current_view() else {
+                return Ok(true);
+            };
+            let buffer = view.buffer();
+            (
+                buffer.to_string(),
+                buffer.get_format_cmd(),
+                buffer.get_document_path().map(Path::to_path_buf),
+            )
+        };
+
+        let formatted = match command.map(|command| run_program(command, &text)) {
+            Some(Ok(formatted)) => Some(formatted),
+            // 整形コマンドがインストールされていなければ言語サーバーか組み込みのフォーマッターを使う
+            Some(Err(err)) if is_command_not_found(&err) => {
+                Some(self.format_with_lsp(&text, path)?.ok_or(err)?)
+            }
+            Some(Err(err)) => return Err(err),
+            None => self.format_with_lsp(&text, path)?,
+        };
+        let Some(formatted) = formatted else {
+            return Ok(false);
+        };
+
+        let cursor = {
+            let view = self.get_current_view().unwrap();
+            let cursor = view.get_position(&self.mode);
+            view.buffer_mut().replace_formatted(&formatted, cursor)
+        };
+        if let Some(cursor) = cursor {
+            self.move_to(cursor);
+            self.sync_window_scroll();
+            self.update_windows();
+        }
+        Ok(true)
+    }
+
+    // 言語サーバー (なければ組み込みのフォーマッター) で整形した内容
+    fn format_with_lsp(
+        &mut self,
+        text: &str,
+        path: Option<PathBuf>,
+    ) -> anyhow::Result<Option<String>> {
+        let Some(path) = path else {
+            return Ok(self.format_text());
+        };
+        let Some(client) = self.lsp.get_document_client(&path) else {
+            return Ok(self.format_text());
+        };
+
+        match client.formatting(&path, FORMAT_TAB_SIZE) {
+            Ok(edits) => Ok(Some(apply_text_edits(text, &edits))),
+            // 言語サーバーが整形に対応していなければ組み込みのフォーマッターを使う
+            Err(err) => Ok(Some(self.format_text().ok_or(err)?)),
+        }
+    }
+
+    fn format_text(&self) -> Option<String> {
+        self.get_current_view()?.buffer().format_text()
+    }
+
+    pub fn has_buffer(&self, path: &Path) -> bool {
+        self.find_buffer(path).is_some()
+    }
+
+    // ファイルを開いているバッファの内容 (絶対パスごと)
+    pub fn get_buffer_lines(&self) -> HashMap<PathBuf, Vec<String>> {
+        self.buffers
+            .iter()
+            .filter_map(|buffer| {
+                let buffer = buffer.read().unwrap();
+                let path = std::path::absolute(buffer.get_path()?).ok()?;
+                Some((path, buffer.get_lines(0, buffer.get_line_count())))
+            })
+            .collect()
+    }
+
+    // 置換する行がすべて検索したときの内容のままか
+    pub fn check_line_edits(&self, path: &Path, edits: &[LineEdit]) -> anyhow::Result<()> {
+        let buffer = self
+            .find_buffer(path)
+            .ok_or_else(|| anyhow!("No matching buffer for {}", path.display()))?
+            .read()
+            .unwrap();
+
+        for edit in edits {
+            if edit.y >= buffer.get_line_count() || buffer.get_line(edit.y) != edit.before {
+                return Err(changed_error(path));
+            }
+        }
+
+        Ok(())
+    }
+
+    // バッファの行を置き換える (1回の undo で戻せる)
+    pub fn apply_line_edits(&mut self, path: &Path, edits: &[LineEdit]) -> anyhow::Result<()> {
+        self.check_line_edits(path, edits)?;
+
+        let buffer = Arc::clone(self.find_buffer(path).unwrap());
+        {
+            let mut buffer = buffer.write().unwrap();
+            let cursor = UVec2::new(0, edits.first().map(|edit| edit.y).unwrap_or(0));
+
+            // 置換後の文字列に改行が含まれていても影響しないよう下の行から処理する
+            buffer.begin_change(cursor);
+            for edit in edits.iter().rev() {
+                buffer.delete_text(0, edit.y, edit.before.chars().count());
+                buffer.insert_text(0, edit.y, &edit.after);
+            }
+            buffer.end_change(cursor);
+        }
+
+        self.sync_window_scroll();
+        self.update_windows();
+        Ok(())
+    }
+
+    // :bd[!] [N] (未保存の変更があるバッファは ! を付けないと閉じない)
+    fn delete_buffer(&mut self, command: &ExCommand) -> anyhow::Result<()> {
+        let index = match command.args.trim() {
+            "" => self.get_current_buffer_index(),
+            args => match args.parse::<usize>() {
+                Ok(number) if (1..=self.buffers.len()).contains(&number) => number - 1,
+                _ => return Err(anyhow!("No matching buffer for {}", args)),
+            },
+        };
+
+        if self.buffers[index].read().unwrap().is_modified() && !command.bang {
+            return Err(anyhow!(
+                "No write since last change for buffer {} (add ! to override)",
+                index + 1
+            ));
+        }
+
+        if self.mode != EditorMode::Normal {
+            self.set_normal_mode()?;
+        }
+
+        let buffer = self.buffers.remove(index);
+        if self.buffers.is_empty() {
+            self.buffers
+                .push(Arc::new(RwLock::new(EditorBuffer::new())));
+        }
+
+        // 削除したバッファを表示していたウィンドウは次のバッファ (末尾なら前のバッファ) を表示する
+        let replacement = &self.buffers[index.min(self.buffers.len() - 1)];
+        let windows = self
+            .tabs
+            .iter_mut()
+            .flat_map(|tab| tab.get_layout_mut().get_windows_mut());
+        for window in windows {
+            if window.get_view().is_showing(&buffer) {
+                window.set_view(EditorView::new(Arc::clone(replacement)));
+            }
+        }
+
+        self.update_windows();
+        Ok(())
+    }
+
+    // 範囲を省略した場合は現在行 (whole_by_default ならファイル全体)
+    pub fn resolve_range(
+        &self,
+        range: Option<&CommandRange>,
+        whole_by_default: bool,
+    ) -> anyhow::Result<(usize, usize)> {
+        let Some(current) = self.get_current_view() else {
+            return Err(anyhow!("No buffer open"));
+        };
+
+        let y = current.get_position(&self.mode).y;
+        let last = current.get_line_count() - 1;
+
+        match range {
+            Some(range) => range.resolve(y, last, current.get_visual_marks()),
+            None if whole_by_default => Ok((0, last)),
+            None => Ok((y, y)),
+        }
+    }
+
+    pub fn move_to_line(&mut self, y: usize) {
+        let window_size = self.get_window_size();
+        if let Some(current) = self.get_current_view_mut() {
+            current.move_to(UVec2::new(0, y), &EditorMode::Normal, window_size);
+        }
+    }
+
+    // x は文字単位 (行の長さに収まるように直す)
+    pub fn move_to(&mut self, pos: UVec2) {
+        let window_size = self.get_window_size();
+        if let Some(current) = self.get_current_view_mut() {
+            current.move_to(pos, &EditorMode::Normal, window_size);
+            current.sync(&EditorMode::Normal);
+        }
+    }
+
+    pub fn get_line_count(&self) -> usize {
+        self.get_current_view()
+            .map(|current| current.get_line_count())
+            .unwrap_or(0)
+    }
+
+    pub fn begin_change(&mut self) {
+        if let Some(current) = self.get_current_view_mut() {
+            current.begin_change();
+        }
+    }
+
+    pub fn end_change(&mut self) {
+        if let Some(current) = self.get_current_view_mut() {
+            current.end_change();
+        }
+        self.update_windows();
+    }
+
+    pub fn set_line_marks(&mut self, lines: Vec<usize>) {
+        if let Some(current) = self.get_current_view() {
+            current.buffer_mut().set_line_marks(lines);
+        }
+    }
+
+    pub fn take_line_mark(&mut self) -> Option<usize> {
+        self.get_current_view()
+            .and_then(|current| current.buffer_mut().take_line_mark())
+    }
+
+    pub fn clear_line_marks(&mut self) {
+        if let Some(current) = self.get_current_view() {
+            current.buffer_mut().clear_line_marks();
+        }
+    }
+
+    // パターンを省略した場合は直前の検索パターンを使う
+    fn get_command_regex(
+        &mut self,
+        pattern: &str,
+        ignore_case: Option<bool>,
+    ) -> anyhow::Result<Regex> {
+        if pattern.is_empty() {
+            return self
+                .search
+                .get_regex()
+                .cloned()
+                .ok_or_else(|| anyhow!("No previous regular expression"));
+        }
+
+        let regex = build_regex_with_case(pattern, ignore_case)?;
+        self.search.set_regex(regex.clone());
+        Ok(regex)
+    }
+
+    // :g, :v の対象行
+    pub fn get_global_lines(
+        &mut self,
+        pattern: &str,
+        range: Option<&CommandRange>,
+        matching: bool,
+    ) -> anyhow::Result<Vec<usize>> {
+        let (start_y, end_y) = self.resolve_range(range, true)?;
+        let regex = self.get_command_regex(pattern, None)?;
+
+        Ok(self
+            .get_current_view()
+            .map(|current| current.get_global_lines(&regex, start_y, end_y, matching))
+            .unwrap_or_default())
+    }
+
+    // :s/pat/rep/flags (flags は g: 行内のすべて, i: 大文字小文字を無視, I: 区別する)
+    fn substitute(&mut self, command: &ExCommand) -> anyhow::Result<()> {
+        let (delimiter, pattern, rest) = split_pattern(&command.args)?;
+        let (replacement, flags) = split_at_delimiter(&rest, delimiter);
+
+        let mut global = false;
+        let mut ignore_case = None;
+
+        for flag in flags.trim().chars() {
+            match flag {
+                'g' => global = true,
+                'i' => ignore_case = Some(true),
+                'I' => ignore_case = Some(false),
+                flag => return Err(anyhow!("Invalid flag: {}", flag)),
+            }
+        }
+
+        let (start_y, end_y) = self.resolve_range(command.range.as_ref(), false)?;
+        let regex = self.get_command_regex(&pattern, ignore_case)?;
+        let replacement = translate_replacement(&replacement);
+
+        let window_size = self.get_window_size();
+        let current = self.tabs[self.current_tab].view_mut();
+
+        current.begin_change();
+        let (count, lines) = current.substitute(&regex, &replacement, global, start_y, end_y);
+        current.sync(&EditorMode::Normal);
+        current.sync_scroll_y(&EditorMode::Normal, window_size);
+        current.end_change();
+        self.update_windows();
+
+        match (count, lines) {
+            (0, _) => Err(anyhow!("Pattern not found: {}", regex.as_str())),
+            (count, lines) if lines > 1 => {
+                self.message = Some(format!("{} substitutions on {} lines", count, lines));
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // :!cmd (範囲を指定した場合はその行をコマンドに通した結果で置き換える)
+    fn execute_shell(&mut self, command: &ExCommand) -> anyhow::Result<()> {
+        let shell_command = command.args.trim();
+        if shell_command.is_empty() {
+            return Err(anyhow!("Argument required"));
+        }
+
+        if command.range.is_none() {
+            return run_in_terminal(shell_command);
+        }
+
+        let (start_y, end_y) = self.resolve_range(command.range.as_ref(), false)?;
+        let current = self.get_current_view().unwrap();
+        let mut input = current.get_lines(start_y, end_y + 1).join("\n");
+        input.push('\n');
+
+        let output = run_filter(shell_command, &input)?;
+        let lines: Vec<&str> = output.lines().collect();
+
+        self.edit_lines(start_y, |current| {
+            current.replace_lines(start_y, end_y, &lines)
+        });
+        self.message = Some(format!("{} lines filtered", end_y - start_y + 1));
+        Ok(())
+    }
+
+    // :r file, :r !cmd (現在行の下に挿入する)
+    fn read_into_buffer(&mut self, command: &ExCommand) -> anyhow::Result<()> {
+        let args = command.args.trim();
+        let text = match args.strip_prefix('!') {
+            _ if command.bang => run_filter(args, "")?,
+            Some(shell_command) => run_filter(shell_command.trim(), "")?,
+            None if args.is_empty() => return Err(anyhow!("No file name")),
+            None => fs::read_to_string(args).map_err(|_| anyhow!("Can't open file {}", args))?,
+        };
+
+        let lines: Vec<&str> = text.lines().collect();
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let (_, y) = self.resolve_range(command.range.as_ref(), false)?;
+        self.edit_lines(y + 1, |current| {
+            current.insert_line(y + 1, &lines.join("\n"))
+        });
+        Ok(())
+    }
+
+    // 変更後は y 行目 (行が減った場合は最後の行) に移動する
+    fn edit_lines(&mut self, y: usize, f: impl FnOnce(&mut EditorView)) {
+        let window_size = self.get_window_size();
+        let current = self.tabs[self.current_tab].view_mut();
+
+        current.begin_change();
+        f(current);
+        let y = y.min(current.get_line_count() - 1);
+        current.move_to(UVec2::new(0, y), &EditorMode::Normal, window_size);
+        current.sync(&EditorMode::Normal);
+        current.sync_scroll_y(&EditorMode::Normal, window_size);
+        current.end_change();
+        self.update_windows();
+    }
+
+    // :d, :y (引数でレジスタを指定できる)
+    fn apply_command_operator(
+        &mut self,
+        operator: EditorOperator,
+        command: &ExCommand,
+    ) -> anyhow::Result<()> {
+        let (start_y, end_y) = self.resolve_range(command.range.as_ref(), false)?;
+
+        let mut args = command.args.trim().chars();
+        if let (Some(name), None) = (args.next(), args.next()) {
+            if !EditorRegisters::is_valid_name(name) {
+                return Err(anyhow!("Invalid register: {}", name));
+            }
+            self.registers.select(name);
+        }
+
+        self.mode = EditorMode::Normal;
+        self.apply_operator(operator, OperatorRange::linewise(start_y, end_y))
+    }
+
+    fn on_operator(
+        &mut self,
+        operator: EditorOperator,
+        count: Option<usize>,
+    ) -> anyhow::Result<()> {
+        if self.get_current_view().is_none() {
+            return Ok(());
+        }
+
+        match self.mode {
+            EditorMode::Normal => {
+                self.set_operator_pending_mode(operator);
+                self.operator_count = count;
+            }
+            EditorMode::OperatorPending(pending) if pending == operator => {
+                let count = self.take_operator_count(count).unwrap_or(1);
+                let range = self.get_current_view().unwrap().get_line_range(count);
+                self.apply_operator(operator, range)?;
+            }
+            ref mode if mode.is_visual() => {
+                let range = self.get_current_view().unwrap().get_visual_range(mode);
+                self.apply_operator(operator, range)?;
+
+                // 矩形の c は削除した列に各行まとめて挿入する
+                if operator == EditorOperator::Change && range.kind == RegisterKind::Blockwise {
+                    self.block_insert = Some(
+                        self.tabs[self.current_tab]
+                            .view_mut()
+                            .start_block_insert(&range, false),
+                    );
+                }
+            }
+            _ => self.set_normal_mode()?,
+        }
+
+        Ok(())
+    }
+
+    // ビジュアルモードの I, A (矩形選択以外では選択範囲の先頭か末尾から挿入する)
+    fn on_block_insert(&mut self, append: bool) -> anyhow::Result<()> {
+        let mode = self.mode.clone();
+        let Some(current) = self.get_current_view() else {
+            return Ok(());
+        };
+        if !mode.is_visual() {
+            return Ok(());
+        }
+
+        let range = current.get_visual_range(&mode);
+        let window_size = self.get_window_size();
+
+        self.set_normal_mode()?;
+        self.set_insert_mode(false)?;
+
+        let current = self.tabs[self.current_tab].view_mut();
+        match range.kind {
+            RegisterKind::Blockwise => {
+                self.block_insert = Some(current.start_block_insert(&range, append));
+            }
+            RegisterKind::Linewise => {
+                let y = if append { range.end.y } else { range.start.y };
+                let x = if append {
+                    current.get_line_length(y)
+                } else {
+                    0
+                };
+                current.move_to(UVec2::new(x, y), &self.mode, window_size);
+            }
+            RegisterKind::Charwise => {
+                let target = if append { range.end } else { range.start };
+                current.move_to(target, &self.mode, window_size);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_text_object(&mut self, object: EditorTextObject, inner: bool) -> anyhow::Result<()> {
+        let Some(current) = self.get_current_view() else {
+            return Ok(());
+        };
+
+        let range = current.get_text_object_range(object, inner, &self.mode);
+
+        match (self.mode.clone(), range) {
+            (EditorMode::OperatorPending(operator), Some(range)) => {
+                self.apply_operator(operator, range)?
+            }
+            (EditorMode::OperatorPending(_), None) => self.set_normal_mode()?,
+            (mode, Some(range)) if mode.is_visual() => {
+                // 行単位のテキストオブジェクトは行選択に切り替える
+                if mode == EditorMode::Visual && range.kind == RegisterKind::Linewise {
+                    self.mode = EditorMode::VisualLine;
+                }
+                if let Some(current) = self.get_current_view_mut() {
+                    current.select_range(range);
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn apply_operator(
+        &mut self,
+        operator: EditorOperator,
+        range: OperatorRange,
+    ) -> anyhow::Result<()> {
+        let window_size = self.get_window_size();
+
+        self.tabs[self.current_tab].view_mut().begin_change();
+        let mode = self.tabs[self.current_tab].view_mut().apply_operator(
+            operator,
+            range,
+            &mut self.registers,
+            window_size,
+        );
+
+        self.mode = EditorMode::Normal;
+        let result = self.set_mode(mode);
+        self.tabs[self.current_tab].view_mut().end_change();
+
+        result
+    }
+
+    pub fn on_event(&mut self, evt: Event) -> anyhow::Result<Vec<Event>> {
+        let mut events = vec![];
+        self.hover = None;
+        let term_size = get_term_size()?;
+
+        self.rect.size = term_size;
+
+        // 補完メニューの操作は記録しない (選んだ候補は accept_completion で入力として記録する)
+        if let (EditorMode::Insert { .. }, Event::Input(key)) = (&self.mode, &evt) {
+            if !self.change_recorder.is_replaying() && self.on_completion_key(key)? {
+                return Ok(events);
+            }
+        }
+
+        if let (EditorMode::Insert { .. }, Event::Input(_)) = (&self.mode, &evt) {
+            if !self.change_recorder.is_replaying() {
+                self.change_recorder.push(evt.clone());
+            }
+        }
+
+        if let (EditorMode::Search { .. }, Event::Input(key)) = (&self.mode, &evt) {
+            self.on_search_input(key.clone())?;
+            return Ok(events);
+        }
+
+        if let EditorMode::Command = self.mode {
+            if let Event::Input(key) = evt.clone() {
+                match self.command_line.on_key(key) {
+                    CommandLineEvent::Submit(command) => {
+                        self.set_normal_mode()?;
+                        events.push(Event::Command(command));
+                    }
+                    CommandLineEvent::Cancel => self.set_normal_mode()?,
+                    CommandLineEvent::Changed | CommandLineEvent::None => {}
+                }
+            }
+        }
+
+        {
+            let Some(_) = self.get_current_view() else {
+                return Ok(events);
+            };
+        }
+
+        // クリックした位置のウィンドウに移動し、座標をウィンドウ内の位置に変換する
+        let evt = match evt {
+            Event::Click(pos) => {
+                let Some((id, rect)) = self
+                    .get_window_rects()
+                    .into_iter()
+                    .find(|(_, rect)| rect.contains(pos))
+                else {
+                    return Ok(events);
+                };
+
+                // 挿入中の変更 (undo のまとまりや矩形挿入) は元のウィンドウで終える
+                if id != self.get_current_window_id() && self.mode != EditorMode::Normal {
+                    self.set_normal_mode()?;
+                    if let Some(revision) = self.get_revision() {
+                        self.change_recorder.finish(revision);
+                    }
+                }
+
+                self.tab_mut().set_current_window(id);
+                Event::Click(UVec2::new(pos.x - rect.pos.x, pos.y - rect.pos.y))
+            }
+            evt => evt,
+        };
+
+        let input = match (&self.mode, &evt) {
+            (EditorMode::Insert { .. }, Event::Input(key)) => Some(key.clone()),
+            _ => None,
+        };
+
+        let window_size = self.get_window_size();
+        self.tabs[self.current_tab]
+            .view_mut()
+            .on_event(evt, &self.mode, window_size)?;
+
+        match input {
+            Some(Key::Char(_) | Key::Backspace | Key::Delete)
+                if !self.change_recorder.is_replaying() =>
+            {
+                self.update_completion()
+            }
+            _ => self.completion = None,
+        }
+
+        self.update_windows();
+
+        Ok(events)
+    }
+
+    // 左側のサイドバーの幅 (この分だけウィンドウを右にずらす)
+    pub fn set_sidebar_width(&mut self, width: usize) {
+        self.sidebar_width = width;
+        self.sync_window_scroll();
+    }
+
+    pub fn get_sidebar_width(&self) -> usize {
+        self.sidebar_width
+    }
+
+    // 下のパネルの高さ (この分だけウィンドウを並べる領域を縮める)
+    pub fn set_panel_height(&mut self, height: usize) {
+        self.panel_height = height;
+        self.sync_window_scroll();
+        self.update_windows();
+    }
+
+    // ウィンドウ用に少なくとも 2 行 (とコマンドライン) は残す
+    fn get_clamped_panel_height(&self) -> usize {
+        let tab_bar_height = usize::from(self.tabs.len() > 1);
+        self.panel_height
+            .min(self.rect.size.y.saturating_sub(tab_bar_height + 3))
+    }
+
+    // 下のパネルの領域 (コマンドラインの上)
+    pub fn get_panel_rect(&self) -> Option<Rect> {
+        let height = self.get_clamped_panel_height();
+        if height == 0 {
+            return None;
+        }
+
+        let layout_rect = self.get_layout_rect(self.tab());
+        Some(Rect::new(
+            UVec2::new(layout_rect.pos.x, layout_rect.pos.y + layout_rect.size.y),
+            UVec2::new(layout_rect.size.x, height),
+        ))
+    }
+
+    // ウィンドウごとにステータスラインを表示するか (ウィンドウが複数のときか下のパネルがあるとき)
+    pub fn has_window_status(&self) -> bool {
+        self.get_window_count() > 1 || self.get_clamped_panel_height() > 0
+    }
+
+    // ウィンドウを並べる領域 (タブが複数のときは最上行をタブバー、ウィンドウごとのステータスラインがあるときは最下行をコマンドライン用に空ける)
+    fn get_layout_rect(&self, tab: &EditorTab) -> Rect {
+        let mut rect = self.rect.clone();
+        let sidebar_width = self.sidebar_width.min(rect.size.x.saturating_sub(1));
+        rect.pos.x += sidebar_width;
+        rect.size.x -= sidebar_width;
+        if self.tabs.len() > 1 {
+            rect.pos.y += 1;
+            rect.size.y = rect.size.y.saturating_sub(1);
+        }
+
+        let panel_height = self.get_clamped_panel_height();
+        if tab.get_layout().get_windows().len() > 1 || panel_height > 0 {
+            rect.size.y = rect.size.y.saturating_sub(panel_height + 1);
+        }
+        rect
+    }
+
+    fn get_tab_rects(&self, tab: &EditorTab) -> Vec<(usize, Rect)> {
+        tab.get_layout().get_rects(self.get_layout_rect(tab))
+    }
+
+    // 各ウィンドウの領域 (最下行はそのウィンドウのステータスライン)
+    pub fn get_window_rects(&self) -> Vec<(usize, Rect)> {
+        self.get_tab_rects(self.tab())
+    }
+
+    fn get_current_window_rect(&self) -> Rect {
+        let current_window = self.get_current_window_id();
+        self.get_window_rects()
+            .into_iter()
+            .find(|(id, _)| *id == current_window)
+            .map(|(_, rect)| rect)
+            .unwrap_or_else(|| self.get_layout_rect(self.tab()))
+    }
+
+    pub fn get_window_size(&self) -> UVec2 {
+        self.get_current_window_rect().size
+    }
+
+    pub fn get_window_count(&self) -> usize {
+        self.tab().get_layout().get_windows().len()
+    }
+
+    pub fn get_current_window_id(&self) -> usize {
+        self.tab().get_current_window()
+    }
+
+    // 左端のウィンドウか (Ctrl-w h でサイドバーに移動する)
+    pub fn is_leftmost_window(&self) -> bool {
+        self.get_current_window_rect().pos.x == self.get_layout_rect(self.tab()).pos.x
+    }
+
+    // 下端のウィンドウか (Ctrl-w j で下のパネルに移動する)
+    pub fn is_bottommost_window(&self) -> bool {
+        let rect = self.get_current_window_rect();
+        let layout_rect = self.get_layout_rect(self.tab());
+        rect.pos.y + rect.size.y == layout_rect.pos.y + layout_rect.size.y
+    }
+
+    pub fn get_window(&self, id: usize) -> Option<&EditorWindow> {
+        self.tab().get_layout().get_window(id)
+    }
+
+    pub fn get_tabs(&self) -> &[EditorTab] {
+        &self.tabs
+    }
+
+    pub fn get_tab_count(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn get_current_tab_index(&self) -> usize {
+        self.current_tab
+    }
+
+    fn on_window_action(
+        &mut self,
+        action: EditorWindowAction,
+        count: Option<usize>,
+    ) -> anyhow::Result<()> {
+        let n = count.unwrap_or(1);
+
+        match action {
+            EditorWindowAction::Split(direction) => self.split_window(direction, "")?,
+            EditorWindowAction::Close => self.close_window()?,
+            EditorWindowAction::FocusLeft => self.focus_window(IVec2::left()),
+            EditorWindowAction::FocusDown => self.focus_window(IVec2::down()),
+            EditorWindowAction::FocusUp => self.focus_window(IVec2::up()),
+            EditorWindowAction::FocusRight => self.focus_window(IVec2::right()),
+            EditorWindowAction::FocusNext => {
+                let tab = self.tab_mut();
+                let windows = tab.get_layout().get_windows();
+                let index = windows
+                    .iter()
+                    .position(|window| window.get_id() == tab.get_current_window())
+                    .unwrap_or(0);
+                let id = windows[(index + n) % windows.len()].get_id();
+                tab.set_current_window(id);
+            }
+            EditorWindowAction::Resize { direction, delta } => {
+                let tab = self.tab_mut();
+                let id = tab.get_current_window();
+                tab.get_layout_mut()
+                    .resize(id, direction, delta * n as isize);
+            }
+            EditorWindowAction::Equalize => self.tab_mut().get_layout_mut().equalize(),
+        }
+
+        self.sync_window_scroll();
+        Ok(())
+    }
+
+    // ウィンドウの大きさが変わったときにカーソルが見える位置までスクロールする
+    fn sync_window_scroll(&mut self) {
+        let rects = self.get_window_rects();
+        for window in self.tab_mut().get_layout_mut().get_windows_mut() {
+            if let Some((_, rect)) = rects.iter().find(|(id, _)| *id == window.get_id()) {
+                let view = window.get_view_mut();
+                view.sync(&EditorMode::Normal);
+                view.sync_scroll_y(&EditorMode::Normal, rect.size);
+            }
+        }
+    }
+
+    // :sp, :vs (ファイル名を指定した場合は新しいウィンドウで開く)
+    pub fn split_window(&mut self, direction: SplitDirection, path: &str) -> anyhow::Result<()> {
+        if self.mode != EditorMode::Normal {
+            self.set_normal_mode()?;
+        }
+
+        // 単独のウィンドウを分割するときはコマンドライン用の行も差し引く
+        let rect = self.get_current_window_rect();
+        let reserved = usize::from(!self.has_window_status());
+        let (size, min_size) = match direction {
+            SplitDirection::Horizontal => (rect.size.y - reserved, 2),
+            SplitDirection::Vertical => (rect.size.x, 1),
+        };
+        if size < min_size * 2 + 1 {
+            return Err(anyhow!("Not enough room"));
+        }
+
+        let id = self.next_window_id;
+        self.next_window_id += 1;
+
+        let tab = self.tab_mut();
+        let view = tab.view().clone();
+        let current_window = tab.get_current_window();
+        tab.get_layout_mut()
+            .split(current_window, direction, EditorWindow::new(id, view), size);
+        tab.set_current_window(id);
+        self.sync_window_scroll();
+
+        if !path.is_empty() {
+            self.edit_file(path)?;
+        }
+
+        Ok(())
+    }
+
+    // 閉じたウィンドウの領域を引き継いだウィンドウに移動する (タブ内の最後のウィンドウならタブを閉じる)
+    pub fn close_window(&mut self) -> anyhow::Result<()> {
+        if self.get_window_count() == 1 {
+            return self.close_tab();
+        }
+
+        if self.mode != EditorMode::Normal {
+            self.set_normal_mode()?;
+        }
+
+        let rect = self.get_current_window_rect();
+        let current_window = self.get_current_window_id();
+        self.tab_mut().get_layout_mut().close(current_window);
+
+        if let Some((id, _)) = self
+            .get_window_rects()
+            .into_iter()
+            .find(|(_, new_rect)| new_rect.contains(rect.pos))
+        {
+            self.tab_mut().set_current_window(id);
+        }
+
+        self.sync_window_scroll();
+        Ok(())
+    }
+
+    // Ctrl-w h/j/k/l (カーソルのある行または列で隣り合うウィンドウ)
+    fn focus_window(&mut self, direction: IVec2) {
+        let rects = self.get_window_rects();
+        let rect = self.get_current_window_rect();
+
+        let view = self.tab().view();
+        let cursor = view.get_position(&self.mode);
+        let row = rect.pos.y
+            + cursor
+                .y
+                .saturating_sub(view.get_offset().y)
+                .min(rect.size.y - 1);
+        let column = rect.pos.x;
+
+        let target = rects.into_iter().find(|(_, other)| {
+            let is_adjacent = match (direction.x, direction.y) {
+                (x, _) if x < 0 => other.pos.x + other.size.x + 1 == rect.pos.x,
+                (x, _) if x > 0 => rect.pos.x + rect.size.x + 1 == other.pos.x,
+                (_, y) if y < 0 => other.pos.y + other.size.y == rect.pos.y,
+                _ => rect.pos.y + rect.size.y == other.pos.y,
+            };
+            let is_aligned = if direction.x != 0 {
+                (other.pos.y..other.pos.y + other.size.y).contains(&row)
+            } else {
+                (other.pos.x..other.pos.x + other.size.x + 1).contains(&column)
+            };
+
+            is_adjacent && is_aligned
+        });
+
+        if let Some((id, _)) = target {
+            self.tab_mut().set_current_window(id);
+        }
+    }
+
+    // :tabnew (ファイル名を指定した場合は新しいタブで開く)
+    pub fn new_tab(&mut self, path: &str) -> anyhow::Result<()> {
+        if self.mode != EditorMode::Normal {
+            self.set_normal_mode()?;
+        }
+
+        let id = self.next_window_id;
+        self.next_window_id += 1;
+
+        // 新しいタブは空のバッファで開く (ファイル名を指定した場合はそのまま置き換わる)
+        let buffer = Arc::new(RwLock::new(EditorBuffer::new()));
+        self.buffers.push(Arc::clone(&buffer));
+        let view = EditorView::new(buffer);
+        self.tabs.insert(
+            self.current_tab + 1,
+            EditorTab::new(EditorWindow::new(id, view)),
+        );
+        self.current_tab += 1;
+
+        if path.is_empty() {
+            self.sync_window_scroll();
+            self.update_windows();
             Ok(())
         } else {
-            Err(anyhow!("No buffer open"))
+            self.edit_file(path)
         }
     }
 
-    pub fn set_insert_mode(&mut self, append: bool) -> anyhow::Result<()> {
-        let mode = self.mode.clone();
-
-        {
-            let Some(_) = self.get_current_buffer() else {
-                return Err(anyhow!("No buffer open"));
-            };
+    // :tabclose (最後のタブは閉じない)
+    pub fn close_tab(&mut self) -> anyhow::Result<()> {
+        if self.tabs.len() == 1 {
+            return Err(anyhow!("Cannot close last tab page"));
         }
 
-        self.get_current_buffer_mut().unwrap().sync(&mode);
-        self.mode = EditorMode::Insert { append };
-
-        let (_, window_size) = self.rect.clone().into();
-
-        let current = self.get_current_buffer_mut().unwrap();
-        if append {
-            current.move_by(IVec2::right(), &mode, window_size);
+        if self.mode != EditorMode::Normal {
+            self.set_normal_mode()?;
         }
 
-        current.sync(&mode);
+        self.tabs.remove(self.current_tab);
+        self.current_tab = self.current_tab.min(self.tabs.len() - 1);
+
+        self.sync_window_scroll();
+        self.update_windows();
         Ok(())
     }
 
-    pub fn set_command_mode(&mut self) {
-        self.mode = EditorMode::Command;
-        self.command_input_buf = String::new();
-    }
+    // gt, gT (count を指定した gt はそのタブに移動する)
+    fn on_tab_action(
+        &mut self,
+        action: EditorTabAction,
+        count: Option<usize>,
+    ) -> anyhow::Result<()> {
+        if self.mode != EditorMode::Normal {
+            self.set_normal_mode()?;
+        }
 
-    pub fn on_action(&mut self, action: EditorAction) -> anyhow::Result<()> {
-        match action {
-            EditorAction::SetMode(mode) => self.set_mode(mode)?,
-            EditorAction::Buffer(action) => {
-                {
-                    let Some(_) = self.get_current_buffer() else {
-                        return Ok(());
-                    };
+        let len = self.tabs.len();
+        self.current_tab = match (action, count) {
+            (EditorTabAction::Next, Some(count)) => {
+                if count == 0 || count > len {
+                    return Err(anyhow!("Invalid tab page number: {}", count));
                 }
-
-                let (_, window_size) = self.rect.clone().into();
-                self.buffers[self.current_buffer_index.unwrap()].on_action(
-                    action,
-                    &self.mode,
-                    &mut self.clipboard,
-                    window_size,
-                )?;
+                count - 1
+            }
+            (EditorTabAction::Next, None) => (self.current_tab + 1) % len,
+            (EditorTabAction::Previous, count) => {
+                (self.current_tab + len - count.unwrap_or(1) % len) % len
             }
         };
 
+        self.sync_window_scroll();
         Ok(())
     }
 
-    pub fn on_event(&mut self, evt: Event) -> anyhow::Result<Vec<Event>> {
-        let mut events = vec![];
-        let term_size = get_term_size()?;
+    // 変更後に同じバッファを表示している他のウィンドウのカーソルを範囲内に戻す
+    fn update_windows(&mut self) {
+        let rects: Vec<_> = self
+            .tabs
+            .iter()
+            .map(|tab| self.get_tab_rects(tab))
+            .collect();
+        let current_tab = self.current_tab;
 
-        self.rect.size = term_size;
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            let current_window = tab.get_current_window();
 
-        if let EditorMode::Command = self.mode {
-            if let Event::Input(key) = evt.clone() {
-                match key {
-                    Key::Backspace => {
-                        if self.command_input_buf.is_empty() {
-                            self.set_normal_mode()?;
-                        } else {
-                            self.command_input_buf.pop();
-                        }
-                    }
-                    Key::Char('\n') => {
-                        self.set_normal_mode()?;
-                        events.push(Event::Command(self.command_input_buf.clone()));
-                    }
-                    Key::Char(c) => self.command_input_buf.push(c),
-                    _ => {}
+            for window in tab.get_layout_mut().get_windows_mut() {
+                let id = window.get_id();
+                let view = window.get_view_mut();
+
+                if i == current_tab && id == current_window {
+                    continue;
+                }
+                if let Some((_, rect)) = rects[i].iter().find(|(rect_id, _)| *rect_id == id) {
+                    view.sync(&EditorMode::Normal);
+                    view.sync_scroll_y(&EditorMode::Normal, rect.size);
                 }
             }
         }
+    }
 
-        {
-            let Some(_) = self.get_current_buffer() else {
-                return Ok(events);
-            };
+    // パネルやエクスプローラーに隠れないように最後に描画する
+    pub fn draw_wildmenu(&self) -> anyhow::Result<()> {
+        if self.mode != EditorMode::Command {
+            return Ok(());
         }
 
-        let (_, window_size) = self.rect.clone().into();
-        self.buffers[self.current_buffer_index.unwrap()].on_event(evt, &self.mode, window_size)?;
-
-        if let Some(current) = self.get_current_buffer() {
-            if let Some(tokens) = current.highlight() {
-                self.highlight_tokens = tokens;
-            }
+        if let Some((candidates, selected)) = self.command_line.get_completion() {
+            queue!(stdout(), SavePosition)?;
+            self.renderer
+                .render_wildmenu(self.rect.size, &candidates, selected);
+            queue!(stdout(), RestorePosition)?;
         }
 
-        Ok(events)
+        Ok(())
     }
 
     pub fn draw(&self) -> anyhow::Result<()> {
         queue!(stdout(), Clear(ClearType::All))?;
 
-        let cursor_pos = self.renderer.render(
-            self.rect.size,
-            self,
-            &self.highlight_tokens,
-            &self.command_input_buf,
-        )?;
+        let cursor_pos = self.renderer.render(self.rect.size, self)?;
 
         if let Some(cursor_pos) = cursor_pos {
             queue!(
@@ -229,15 +2458,21 @@ impl Editor {
 
             match self.mode {
                 EditorMode::Normal => queue!(stdout(), SetCursorStyle::SteadyBlock)?,
-                EditorMode::Visual => queue!(stdout(), SetCursorStyle::SteadyBlock)?,
+                EditorMode::Visual | EditorMode::VisualLine | EditorMode::VisualBlock => {
+                    queue!(stdout(), SetCursorStyle::SteadyBlock)?
+                }
+                EditorMode::OperatorPending(_) => {
+                    queue!(stdout(), SetCursorStyle::SteadyUnderScore)?
+                }
                 EditorMode::Insert { append: _ } => queue!(stdout(), SetCursorStyle::SteadyBar)?,
-                EditorMode::Command => queue!(stdout(), SetCursorStyle::SteadyBar)?,
+                EditorMode::Command | EditorMode::Search { .. } => {
+                    queue!(stdout(), SetCursorStyle::SteadyBar)?
+                }
             }
         } else {
             queue!(stdout(), Hide)?;
         }
 
-        stdout().flush()?;
         Ok(())
     }
 
@@ -258,6 +2493,21 @@ impl Editor {
             vec![Key::Char(':')],
             AppAction::EditorAction(EditorAction::SetMode(EditorMode::Command)),
         );
+        key_config.register(
+            KeyConfigType::Visual,
+            vec![Key::Char(':')],
+            EditorAction::SetMode(EditorMode::Command).into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Char('/')],
+            EditorAction::SetMode(EditorMode::Search { backward: false }).into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Char('?')],
+            EditorAction::SetMode(EditorMode::Search { backward: true }).into_app(),
+        );
         key_config.register(
             KeyConfigType::Normal,
             vec![Key::Char('i')],
@@ -269,10 +2519,156 @@ impl Editor {
             AppAction::EditorAction(EditorAction::SetMode(EditorMode::Insert { append: true })),
         );
         key_config.register(
-            KeyConfigType::Normal,
+            KeyConfigType::NormalAndVisual,
             vec![Key::Char('v')],
             AppAction::EditorAction(EditorAction::SetMode(EditorMode::Visual)),
         );
+        key_config.register(
+            KeyConfigType::NormalAndVisual,
+            vec![Key::Char('V')],
+            EditorAction::SetMode(EditorMode::VisualLine).into_app(),
+        );
+        key_config.register(
+            KeyConfigType::NormalAndVisual,
+            vec![Key::Ctrl('v')],
+            EditorAction::SetMode(EditorMode::VisualBlock).into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('s')],
+            EditorWindowAction::Split(SplitDirection::Horizontal).into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('v')],
+            EditorWindowAction::Split(SplitDirection::Vertical).into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('c')],
+            EditorWindowAction::Close.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('q')],
+            AppAction::Quit,
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('h')],
+            EditorWindowAction::FocusLeft.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('j')],
+            EditorWindowAction::FocusDown.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('k')],
+            EditorWindowAction::FocusUp.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('l')],
+            EditorWindowAction::FocusRight.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('w')],
+            EditorWindowAction::FocusNext.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Ctrl('w')],
+            EditorWindowAction::FocusNext.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('+')],
+            EditorWindowAction::Resize {
+                direction: SplitDirection::Horizontal,
+                delta: 1,
+            }
+            .into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('-')],
+            EditorWindowAction::Resize {
+                direction: SplitDirection::Horizontal,
+                delta: -1,
+            }
+            .into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('>')],
+            EditorWindowAction::Resize {
+                direction: SplitDirection::Vertical,
+                delta: 1,
+            }
+            .into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('<')],
+            EditorWindowAction::Resize {
+                direction: SplitDirection::Vertical,
+                delta: -1,
+            }
+            .into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('w'), Key::Char('=')],
+            EditorWindowAction::Equalize.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Char('g'), Key::Char('t')],
+            EditorTabAction::Next.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Char('g'), Key::Char('T')],
+            EditorTabAction::Previous.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Char('g'), Key::Char('d')],
+            EditorLspAction::GoToDefinition.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Char('g'), Key::Char('r')],
+            EditorLspAction::FindReferences.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Char('K')],
+            EditorLspAction::Hover.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Char(']'), Key::Char('d')],
+            EditorAction::DiagnosticNext { reverse: false }.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Char('['), Key::Char('d')],
+            EditorAction::DiagnosticNext { reverse: true }.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Visual,
+            vec![Key::Char('I')],
+            EditorAction::BlockInsert { append: false }.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Visual,
+            vec![Key::Char('A')],
+            EditorAction::BlockInsert { append: true }.into_app(),
+        );
 
         // Cursor Movement
         key_config.register(
@@ -349,44 +2745,168 @@ impl Editor {
         // Edit
         key_config.register(
             KeyConfigType::Normal,
-            vec![Key::Char('d'), Key::Char('d')],
+            vec![Key::Char('Y')],
             AppAction::EditorAction(EditorAction::Buffer(EditorBufferAction::Edit(
-                EditorEditAction::DeleteLine,
+                EditorEditAction::YankLine,
             ))),
         );
         key_config.register(
             KeyConfigType::Normal,
-            vec![Key::Char('y'), Key::Char('y')],
+            vec![Key::Char('p')],
             AppAction::EditorAction(EditorAction::Buffer(EditorBufferAction::Edit(
-                EditorEditAction::YankLine,
+                EditorEditAction::Paste,
             ))),
         );
         key_config.register(
             KeyConfigType::Normal,
-            vec![Key::Char('p')],
+            vec![Key::Char('P')],
             AppAction::EditorAction(EditorAction::Buffer(EditorBufferAction::Edit(
-                EditorEditAction::Paste,
+                EditorEditAction::PasteBefore,
             ))),
         );
-
         key_config.register(
-            KeyConfigType::Visual,
-            vec![Key::Char('d')],
+            KeyConfigType::Normal,
+            vec![Key::Char('u')],
             AppAction::EditorAction(EditorAction::Buffer(EditorBufferAction::Edit(
-                EditorEditAction::DeleteSelection,
+                EditorEditAction::Undo,
             ))),
         );
         key_config.register(
-            KeyConfigType::Visual,
-            vec![Key::Char('y')],
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('r')],
             AppAction::EditorAction(EditorAction::Buffer(EditorBufferAction::Edit(
-                EditorEditAction::YankSelection,
+                EditorEditAction::Redo,
             ))),
         );
+
+        // Operator
+        let operators = [
+            (vec![Key::Char('d')], EditorOperator::Delete),
+            (vec![Key::Char('c')], EditorOperator::Change),
+            (vec![Key::Char('y')], EditorOperator::Yank),
+            (vec![Key::Char('>')], EditorOperator::IndentRight),
+            (vec![Key::Char('<')], EditorOperator::IndentLeft),
+            (
+                vec![Key::Char('g'), Key::Char('U')],
+                EditorOperator::Uppercase,
+            ),
+            (
+                vec![Key::Char('g'), Key::Char('u')],
+                EditorOperator::Lowercase,
+            ),
+        ];
+
+        for (sequence, operator) in operators {
+            key_config.register(
+                KeyConfigType::Normal,
+                sequence.clone(),
+                EditorAction::Operator(operator).into_app(),
+            );
+            key_config.register(
+                KeyConfigType::OperatorPending,
+                sequence,
+                EditorAction::Operator(operator).into_app(),
+            );
+        }
+
+        // gUU, guu
+        key_config.register(
+            KeyConfigType::OperatorPending,
+            vec![Key::Char('U')],
+            EditorAction::Operator(EditorOperator::Uppercase).into_app(),
+        );
+        key_config.register(
+            KeyConfigType::OperatorPending,
+            vec![Key::Char('u')],
+            EditorAction::Operator(EditorOperator::Lowercase).into_app(),
+        );
+
+        for (key, operator) in [
+            (Key::Char('d'), EditorOperator::Delete),
+            (Key::Char('y'), EditorOperator::Yank),
+            (Key::Char('c'), EditorOperator::Change),
+            (Key::Char('>'), EditorOperator::IndentRight),
+            (Key::Char('<'), EditorOperator::IndentLeft),
+            (Key::Char('U'), EditorOperator::Uppercase),
+            (Key::Char('u'), EditorOperator::Lowercase),
+        ] {
+            key_config.register(
+                KeyConfigType::Visual,
+                vec![key],
+                EditorAction::Operator(operator).into_app(),
+            );
+        }
+
+        // Text Object
+        let text_objects = [
+            ('w', EditorTextObject::Word),
+            ('"', EditorTextObject::Quote('"')),
+            ('\'', EditorTextObject::Quote('\'')),
+            ('`', EditorTextObject::Quote('`')),
+            ('(', EditorTextObject::Bracket('(', ')')),
+            (')', EditorTextObject::Bracket('(', ')')),
+            ('b', EditorTextObject::Bracket('(', ')')),
+            ('{', EditorTextObject::Bracket('{', '}')),
+            ('}', EditorTextObject::Bracket('{', '}')),
+            ('B', EditorTextObject::Bracket('{', '}')),
+            ('[', EditorTextObject::Bracket('[', ']')),
+            (']', EditorTextObject::Bracket('[', ']')),
+            ('<', EditorTextObject::Bracket('<', '>')),
+            ('>', EditorTextObject::Bracket('<', '>')),
+            ('t', EditorTextObject::Tag),
+            ('p', EditorTextObject::Paragraph),
+        ];
+
+        for (c, object) in text_objects {
+            for (prefix, inner) in [('i', true), ('a', false)] {
+                for config_type in [KeyConfigType::OperatorPending, KeyConfigType::Visual] {
+                    key_config.register(
+                        config_type,
+                        vec![Key::Char(prefix), Key::Char(c)],
+                        EditorAction::TextObject { object, inner }.into_app(),
+                    );
+                }
+            }
+        }
+
+        // Search
+        key_config.register(
+            KeyConfigType::NormalAndVisual,
+            vec![Key::Char('n')],
+            EditorAction::SearchNext { reverse: false }.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::NormalAndVisual,
+            vec![Key::Char('N')],
+            EditorAction::SearchNext { reverse: true }.into_app(),
+        );
+
+        // Repeat
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Char('.')],
+            EditorAction::RepeatLastChange.into_app(),
+        );
+
+        // Register
+        for name in (0..=u8::MAX).map(char::from) {
+            if EditorRegisters::is_valid_name(name) {
+                key_config.register(
+                    KeyConfigType::NormalAndVisual,
+                    vec![Key::Char('"'), Key::Char(name)],
+                    EditorAction::SelectRegister(name).into_app(),
+                );
+            }
+        }
     }
 
     pub fn register_commands(&self, cmd_manager: &mut CommandManager) {
         cmd_manager.register("q", vec![AppAction::Quit]);
+        cmd_manager.register("noh", vec![EditorAction::ClearSearchHighlight.into_app()]);
+        cmd_manager.register(
+            "nohlsearch",
+            vec![EditorAction::ClearSearchHighlight.into_app()],
+        );
         cmd_manager.register(
             "w",
             vec![AppAction::EditorAction(EditorAction::Buffer(
@@ -409,3 +2929,233 @@ impl Editor {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use crate::{
+        editor::{
+            action::{
+                EditorAction, EditorBufferAction, EditorCursorAction, EditorOperator,
+                EditorWindowAction,
+            },
+            buffer::EditorBuffer,
+            mode::EditorMode,
+            window::SplitDirection,
+        },
+        utils::{event::Event, key_binding::Key, rect::Rect, vec2::UVec2},
+    };
+
+    use super::Editor;
+
+    fn editor(text: &str) -> Editor {
+        let editor = Editor::new(vec![], Rect::new(UVec2::default(), UVec2::new(80, 24))).unwrap();
+        editor.buffers[0].write().unwrap().insert_text(0, 0, text);
+        editor
+    }
+
+    fn text(editor: &Editor) -> String {
+        editor.buffers[0].read().unwrap().to_string()
+    }
+
+    fn cursor(motion: EditorCursorAction) -> EditorAction {
+        EditorAction::Buffer(EditorBufferAction::Cursor(motion))
+    }
+
+    fn run(editor: &mut Editor, actions: Vec<EditorAction>) {
+        for action in actions {
+            editor.on_action(action, None).unwrap();
+        }
+    }
+
+    #[test]
+    fn edit_reuses_buffer_for_the_same_file() {
+        let dir = std::env::temp_dir().join(format!("tui-studio-edit-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+
+        let mut editor = editor("");
+        let path = dir.join("a.txt");
+        editor.edit_file(path.to_str().unwrap()).unwrap();
+        editor
+            .edit_file(dir.join("sub/../a.txt").to_str().unwrap())
+            .unwrap();
+        editor
+            .edit_file(&format!("{}/./a.txt", dir.display()))
+            .unwrap();
+
+        assert_eq!(editor.buffers.len(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn deleting_a_directory_marks_its_buffers_modified() {
+        let dir = std::env::temp_dir().join(format!("tui-studio-delete-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/a.txt"), "a").unwrap();
+        std::fs::write(dir.join("b.txt"), "b").unwrap();
+
+        let mut editor = editor("");
+        editor
+            .edit_file(dir.join("sub/a.txt").to_str().unwrap())
+            .unwrap();
+        editor
+            .edit_file(dir.join("b.txt").to_str().unwrap())
+            .unwrap();
+        std::fs::remove_dir_all(dir.join("sub")).unwrap();
+        editor.mark_buffers_deleted(&dir.join("sub")).unwrap();
+
+        let modified: Vec<bool> = editor
+            .buffers
+            .iter()
+            .map(|buffer| buffer.read().unwrap().is_modified())
+            .collect();
+        assert_eq!(modified, vec![true, false]);
+        assert!(editor.get_message().is_some());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_language_server_does_not_open_documents() {
+        let dir = std::env::temp_dir().join(format!("tui-studio-lsp-sync-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("index.html"), "<p>").unwrap();
+
+        let mut editor = editor("");
+        editor
+            .edit_file(dir.join("index.html").to_str().unwrap())
+            .unwrap();
+        let command = editor.buffers[0]
+            .read()
+            .unwrap()
+            .get_language_server_cmd()
+            .unwrap();
+
+        // サーバーがインストールされている環境では確かめられない
+        if editor.lsp.get_client(&command).is_none() {
+            for _ in 0..2 {
+                editor.sync_language_servers();
+                let buffer = editor.buffers[0].read().unwrap();
+                assert!(buffer.get_document_path().is_none());
+                assert!(!editor.lsp.is_open(&dir.join("index.html")));
+            }
+        }
+
+        editor.lsp.shutdown();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn click_on_another_window_ends_insert_in_the_old_buffer() {
+        let mut editor = editor("abc");
+        run(
+            &mut editor,
+            vec![EditorAction::Window(EditorWindowAction::Split(
+                SplitDirection::Vertical,
+            ))],
+        );
+        editor
+            .buffers
+            .push(Arc::new(RwLock::new(EditorBuffer::new())));
+        editor.switch_buffer(1).unwrap();
+
+        let other = editor
+            .get_window_rects()
+            .into_iter()
+            .find(|(id, _)| *id != editor.get_current_window_id())
+            .map(|(_, rect)| rect)
+            .unwrap();
+
+        run(
+            &mut editor,
+            vec![EditorAction::SetMode(EditorMode::Insert { append: false })],
+        );
+        editor.on_event(Event::Input(Key::Char('x'))).unwrap();
+        editor.on_event(Event::Click(other.pos)).unwrap();
+
+        assert_eq!(editor.get_mode(), EditorMode::Normal);
+        let mut buffer = editor.buffers[1].write().unwrap();
+        assert_eq!(buffer.to_string(), "x");
+        buffer.undo();
+        assert_eq!(buffer.to_string(), "");
+    }
+
+    #[test]
+    fn repeats_visual_line_operator() {
+        let mut editor = editor("1\n2\n3\n4\n5\n6");
+
+        run(
+            &mut editor,
+            vec![
+                EditorAction::SetMode(EditorMode::VisualLine),
+                cursor(EditorCursorAction::Down),
+                EditorAction::Operator(EditorOperator::Delete),
+            ],
+        );
+        assert_eq!(text(&editor), "3\n4\n5\n6");
+
+        run(&mut editor, vec![EditorAction::RepeatLastChange]);
+        assert_eq!(text(&editor), "5\n6");
+    }
+
+    #[test]
+    fn repeats_visual_operator_with_the_same_size() {
+        let mut editor = editor("abcdefgh");
+
+        run(
+            &mut editor,
+            vec![
+                EditorAction::SetMode(EditorMode::Visual),
+                cursor(EditorCursorAction::Right),
+                cursor(EditorCursorAction::Right),
+                EditorAction::Operator(EditorOperator::Uppercase),
+            ],
+        );
+        assert_eq!(text(&editor), "ABCdefgh");
+
+        editor.get_current_view_mut().unwrap().move_to_x(4);
+        run(&mut editor, vec![EditorAction::RepeatLastChange]);
+        assert_eq!(text(&editor), "ABCdEFGh");
+        assert_eq!(editor.get_mode(), EditorMode::Normal);
+    }
+
+    #[test]
+    fn repeats_multi_line_visual_operator() {
+        let mut editor = editor("abcd\nefgh\nijkl\nmnop\nqrst");
+
+        run(
+            &mut editor,
+            vec![
+                EditorAction::SetMode(EditorMode::Visual),
+                cursor(EditorCursorAction::Down),
+                EditorAction::Operator(EditorOperator::Delete),
+            ],
+        );
+        assert_eq!(text(&editor), "fgh\nijkl\nmnop\nqrst");
+
+        editor.get_current_view_mut().unwrap().move_to_x(2);
+        run(&mut editor, vec![EditorAction::RepeatLastChange]);
+        assert_eq!(text(&editor), "fgjkl\nmnop\nqrst");
+    }
+
+    #[test]
+    fn cancelled_visual_mode_keeps_the_last_change() {
+        let mut editor = editor("abc\ndef\nghi");
+
+        run(
+            &mut editor,
+            vec![
+                EditorAction::SetMode(EditorMode::VisualLine),
+                EditorAction::Operator(EditorOperator::Delete),
+                EditorAction::SetMode(EditorMode::Visual),
+                cursor(EditorCursorAction::Right),
+                EditorAction::SetMode(EditorMode::Normal),
+            ],
+        );
+        assert_eq!(text(&editor), "def\nghi");
+
+        run(&mut editor, vec![EditorAction::RepeatLastChange]);
+        assert_eq!(text(&editor), "ghi");
+    }
+}
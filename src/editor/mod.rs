@@ -1,7 +1,16 @@
 pub mod action;
 pub mod buffer;
+pub mod change;
+pub mod command_line;
+pub mod completion;
 #[allow(clippy::module_inception)]
 pub mod editor;
 pub mod mode;
+pub mod option;
+pub mod register;
 pub mod renderer;
+pub mod search;
+pub mod tab;
 pub mod utils;
+pub mod view;
+pub mod window;
@@ -1,16 +1,33 @@
 use std::fmt::Display;
 
+use super::action::EditorOperator;
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
 pub enum EditorMode {
     #[default]
     Normal,
     Visual,
+    VisualLine,
+    VisualBlock,
+    OperatorPending(EditorOperator),
     Command,
+    Search {
+        backward: bool,
+    },
     Insert {
         append: bool,
     },
 }
 
+impl EditorMode {
+    pub fn is_visual(&self) -> bool {
+        matches!(
+            self,
+            EditorMode::Visual | EditorMode::VisualLine | EditorMode::VisualBlock
+        )
+    }
+}
+
 impl Display for EditorMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -19,7 +36,11 @@ impl Display for EditorMode {
             match self {
                 EditorMode::Normal => "NORMAL",
                 EditorMode::Visual => "VISUAL",
+                EditorMode::VisualLine => "V-LINE",
+                EditorMode::VisualBlock => "V-BLOCK",
+                EditorMode::OperatorPending(_) => "O-PENDING",
                 EditorMode::Command => "COMMAND",
+                EditorMode::Search { .. } => "SEARCH",
                 EditorMode::Insert { append } =>
                     if *append {
                         "APPEND"
@@ -0,0 +1,115 @@
+use anyhow::anyhow;
+
+// (名前, 短い名前)
+pub const OPTION_NAMES: &[(&str, &str)] = &[
+    ("hlsearch", "hls"),
+    ("wrapscan", "ws"),
+    ("formatonsave", "fos"),
+];
+
+// :set で変更できる設定
+pub struct EditorOptions {
+    pub hlsearch: bool,     // 検索した文字列をハイライトする
+    pub wrapscan: bool,     // 検索でファイルの端まで行ったら反対側から続ける
+    pub formatonsave: bool, // 保存する前に :format する
+}
+
+impl Default for EditorOptions {
+    fn default() -> Self {
+        Self {
+            hlsearch: true,
+            wrapscan: true,
+            formatonsave: false,
+        }
+    }
+}
+
+impl EditorOptions {
+    fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "hlsearch" | "hls" => Some(self.hlsearch),
+            "wrapscan" | "ws" => Some(self.wrapscan),
+            "formatonsave" | "fos" => Some(self.formatonsave),
+            _ => None,
+        }
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "hlsearch" | "hls" => Some(&mut self.hlsearch),
+            "wrapscan" | "ws" => Some(&mut self.wrapscan),
+            "formatonsave" | "fos" => Some(&mut self.formatonsave),
+            _ => None,
+        }
+    }
+
+    fn format(&self, name: &str) -> Option<String> {
+        let value = self.get(name)?;
+        let (long, _) = OPTION_NAMES
+            .iter()
+            .find(|(long, short)| *long == name || *short == name)?;
+
+        Some(if value {
+            long.to_string()
+        } else {
+            format!("no{}", long)
+        })
+    }
+
+    // :set name / noname / invname / name! / name? (表示するメッセージを返す)
+    pub fn set(&mut self, args: &str) -> anyhow::Result<Option<String>> {
+        let args: Vec<&str> = args.split_whitespace().collect();
+        if args.is_empty() || args == ["all"] {
+            let values: Vec<String> = OPTION_NAMES
+                .iter()
+                .filter_map(|(name, _)| self.format(name))
+                .collect();
+            return Ok(Some(values.join("  ")));
+        }
+
+        let mut messages = vec![];
+        for arg in args {
+            if let Some(name) = arg.strip_suffix('?') {
+                let value = self
+                    .format(name)
+                    .ok_or_else(|| anyhow!("Unknown option: {}", name))?;
+                messages.push(value);
+                continue;
+            }
+
+            let (name, value) = if let Some(name) = arg.strip_suffix('!') {
+                (name, None)
+            } else if let Some(name) = arg.strip_prefix("inv") {
+                (name, None)
+            } else if let Some(name) = arg.strip_prefix("no") {
+                (name, Some(false))
+            } else {
+                (arg, Some(true))
+            };
+
+            let option = self
+                .get_mut(name)
+                .ok_or_else(|| anyhow!("Unknown option: {}", arg))?;
+            *option = value.unwrap_or(!*option);
+        }
+
+        Ok((!messages.is_empty()).then(|| messages.join("  ")))
+    }
+}
+
+// :set の引数の補完候補
+pub fn complete_option(word: &str) -> Vec<String> {
+    let (prefix, name) = if let Some(name) = word.strip_prefix("no") {
+        ("no", name)
+    } else if let Some(name) = word.strip_prefix("inv") {
+        ("inv", name)
+    } else {
+        ("", word)
+    };
+
+    OPTION_NAMES
+        .iter()
+        .filter(|(long, _)| long.starts_with(name))
+        .map(|(long, _)| format!("{}{}", prefix, long))
+        .collect()
+}
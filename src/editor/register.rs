@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use arboard::Clipboard;
+
+pub const UNNAMED_REGISTER: char = '"';
+pub const YANK_REGISTER: char = '0';
+pub const SMALL_DELETE_REGISTER: char = '-';
+pub const CLIPBOARD_REGISTER: char = '+';
+pub const BLACK_HOLE_REGISTER: char = '_';
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum RegisterKind {
+    #[default]
+    Charwise,
+    Linewise,
+    Blockwise,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Register {
+    pub text: String,
+    pub kind: RegisterKind,
+}
+
+impl Register {
+    pub fn new(text: String, kind: RegisterKind) -> Self {
+        Self { text, kind }
+    }
+}
+
+pub struct EditorRegisters {
+    registers: HashMap<char, Register>,
+    clipboard: Option<Clipboard>,
+    selected: Option<char>,
+}
+
+impl EditorRegisters {
+    pub fn new() -> Self {
+        Self {
+            registers: HashMap::new(),
+            clipboard: Clipboard::new().ok(),
+            selected: None,
+        }
+    }
+
+    pub fn is_valid_name(name: char) -> bool {
+        name.is_ascii_alphanumeric()
+            || [
+                UNNAMED_REGISTER,
+                SMALL_DELETE_REGISTER,
+                CLIPBOARD_REGISTER,
+                BLACK_HOLE_REGISTER,
+            ]
+            .contains(&name)
+    }
+
+    pub fn select(&mut self, name: char) {
+        self.selected = Some(name);
+    }
+
+    pub fn get(&mut self, name: char) -> anyhow::Result<Option<Register>> {
+        if name == CLIPBOARD_REGISTER {
+            return self.get_clipboard().map(Some);
+        }
+
+        Ok(self.registers.get(&name.to_ascii_lowercase()).cloned())
+    }
+
+    pub fn set(&mut self, name: char, register: Register) {
+        match name {
+            BLACK_HOLE_REGISTER => {}
+            CLIPBOARD_REGISTER => {
+                self.set_clipboard(&register);
+                self.registers.insert(UNNAMED_REGISTER, register);
+            }
+            name if name.is_ascii_uppercase() => {
+                let name = name.to_ascii_lowercase();
+                let register = match self.registers.get(&name) {
+                    None => register,
+                    Some(current) => {
+                        let kind = if current.kind == RegisterKind::Linewise {
+                            RegisterKind::Linewise
+                        } else {
+                            register.kind
+                        };
+                        let separator = if kind == RegisterKind::Charwise {
+                            ""
+                        } else {
+                            "\n"
+                        };
+
+                        Register::new(
+                            format!("{}{}{}", current.text, separator, register.text),
+                            kind,
+                        )
+                    }
+                };
+
+                self.registers.insert(name, register.clone());
+                self.registers.insert(UNNAMED_REGISTER, register);
+            }
+            name => {
+                self.registers.insert(name, register.clone());
+                if name != UNNAMED_REGISTER {
+                    self.registers.insert(UNNAMED_REGISTER, register);
+                }
+            }
+        }
+    }
+
+    pub fn yank(&mut self, register: Register) {
+        match self.selected.take() {
+            Some(name) => self.set(name, register),
+            None => self.set(YANK_REGISTER, register),
+        }
+    }
+
+    pub fn delete(&mut self, register: Register) {
+        match self.selected.take() {
+            Some(name) => self.set(name, register),
+            None => {
+                if register.kind == RegisterKind::Charwise && !register.text.contains('\n') {
+                    self.set(SMALL_DELETE_REGISTER, register);
+                } else {
+                    for n in (1..9).rev() {
+                        let from = char::from_digit(n, 10).unwrap();
+                        let to = char::from_digit(n + 1, 10).unwrap();
+                        if let Some(shifted) = self.registers.remove(&from) {
+                            self.registers.insert(to, shifted);
+                        }
+                    }
+                    self.set('1', register);
+                }
+            }
+        }
+    }
+
+    // マクロの記録 (無名レジスタは更新しない)
+    pub fn set_macro(&mut self, name: char, text: String) {
+        match name {
+            BLACK_HOLE_REGISTER => {}
+            CLIPBOARD_REGISTER => self.set_clipboard(&Register::new(text, RegisterKind::Charwise)),
+            name if name.is_ascii_uppercase() => self
+                .registers
+                .entry(name.to_ascii_lowercase())
+                .or_default()
+                .text
+                .push_str(&text),
+            name => {
+                self.registers
+                    .insert(name, Register::new(text, RegisterKind::Charwise));
+            }
+        }
+    }
+
+    pub fn paste(&mut self) -> anyhow::Result<Option<Register>> {
+        let name = self.selected.take().unwrap_or(UNNAMED_REGISTER);
+        self.get(name)
+    }
+
+    fn set_clipboard(&mut self, register: &Register) {
+        let Some(clipboard) = &mut self.clipboard else {
+            log::warn!("Clipboard is not available");
+            return;
+        };
+
+        let text = match register.kind {
+            RegisterKind::Charwise | RegisterKind::Blockwise => register.text.clone(),
+            RegisterKind::Linewise => format!("{}\n", register.text),
+        };
+
+        if let Err(err) = clipboard.set_text(text) {
+            log::warn!("Failed to write to clipboard: {}", err);
+        }
+    }
+
+    fn get_clipboard(&mut self) -> anyhow::Result<Register> {
+        let Some(clipboard) = &mut self.clipboard else {
+            return Err(anyhow!("Clipboard is not available"));
+        };
+
+        let text = clipboard.get_text()?.replace("\r\n", "\n");
+        Ok(match text.strip_suffix('\n') {
+            Some(text) => Register::new(text.to_string(), RegisterKind::Linewise),
+            None => Register::new(text, RegisterKind::Charwise),
+        })
+    }
+}
+
+impl Default for EditorRegisters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
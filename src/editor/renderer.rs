@@ -1,272 +1,796 @@
-use std::{cmp::Ordering, io::stdout};
+use std::io::stdout;
 
 use crossterm::{
     cursor::MoveTo,
     queue,
-    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    style::{
+        Attribute, Color as CrosstermColor, Print, ResetColor, SetAttribute, SetBackgroundColor,
+        SetForegroundColor, SetUnderlineColor,
+    },
     terminal::{Clear, ClearType},
+    Command,
 };
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    editor::{editor::Editor, mode::EditorMode},
-    language_support::highlight::HighlightToken,
-    utils::vec2::{IVec2, UVec2},
+    editor::{
+        buffer::BufferDiagnostic,
+        command_line::EditorCommandLine,
+        completion::{EditorCompletion, COMPLETION_MENU_HEIGHT},
+        editor::Editor,
+        mode::EditorMode,
+        register::RegisterKind,
+        tab::EditorTab,
+        view::{char_width, EditorView, OperatorRange},
+    },
+    language_support::{highlight::HighlightToken, lsp::DiagnosticSeverity},
+    utils::{
+        rect::Rect,
+        vec2::{IVec2, UVec2},
+    },
 };
 
+// 補完メニューのラベルと説明の最大幅
+const COMPLETION_LABEL_WIDTH: usize = 30;
+const COMPLETION_DETAIL_WIDTH: usize = 30;
+
+// K で表示する説明の大きさ (枠を除く)
+const HOVER_MAX_WIDTH: usize = 60;
+const HOVER_MAX_HEIGHT: usize = 12;
+
 #[derive(Default)]
 pub struct EditorRenderer {}
 
+// 表示幅に収まるように切り詰める
+fn truncate_to_width(text: &str, width: usize) -> String {
+    let mut col = 0;
+    text.chars()
+        .take_while(|c| {
+            col += char_width(*c);
+            col <= width
+        })
+        .collect()
+}
+
+// 表示幅で折り返す
+fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    let mut col = 0;
+
+    for c in line.chars() {
+        let w = char_width(c);
+        if col + w > width && col > 0 {
+            lines.push(String::new());
+            col = 0;
+        }
+        lines.last_mut().unwrap().push(c);
+        col += w;
+    }
+
+    lines
+}
+
+fn get_diagnostic_color(severity: DiagnosticSeverity) -> CrosstermColor {
+    match severity {
+        DiagnosticSeverity::Error => CrosstermColor::Red,
+        DiagnosticSeverity::Warning => CrosstermColor::Yellow,
+        DiagnosticSeverity::Information => CrosstermColor::Blue,
+        DiagnosticSeverity::Hint => CrosstermColor::Cyan,
+    }
+}
+
 impl EditorRenderer {
+    // signs が空でなければ行番号の左に診断の記号を表示する
     fn render_numbers(
         &self,
-        window_size: UVec2,
-        lines: &[String],
+        rect: &Rect,
+        line_count: usize,
         scroll_y: usize,
         offset_x: usize,
+        signs: &[Option<DiagnosticSeverity>],
     ) {
-        (0..lines.len())
+        let num_width = if signs.is_empty() {
+            offset_x
+        } else {
+            offset_x - 2
+        };
+
+        (0..line_count)
             .skip(scroll_y)
-            .take(window_size.y)
+            .take(rect.size.y)
             .enumerate()
             .for_each(|(draw_y, y)| {
                 queue!(
                     stdout(),
-                    MoveTo(0, draw_y as u16),
-                    Print(format!("{}{:<offset_x$}", ResetColor, y + 1))
+                    MoveTo(rect.pos.x as u16, (rect.pos.y + draw_y) as u16),
+                    ResetColor
                 )
                 .unwrap();
+
+                if !signs.is_empty() {
+                    let sign = match signs.get(draw_y).copied().flatten() {
+                        Some(severity) => format!(
+                            "{}{} ",
+                            SetForegroundColor(get_diagnostic_color(severity)),
+                            severity.get_sign()
+                        ),
+                        None => "  ".to_string(),
+                    };
+                    queue!(stdout(), Print(sign), ResetColor).unwrap();
+                }
+
+                queue!(stdout(), Print(format!("{:<num_width$}", y + 1))).unwrap();
             });
     }
 
-    fn render_code_string(
+    // pos にかかっている診断のうち最も重要なもの
+    fn get_diagnostic_severity(
         &self,
-        x: usize,
-        y: usize,
-        is_select: bool,
-        tokens: &[HighlightToken],
-        code: &str,
-    ) {
-        let mut code = code.to_string();
-
-        for highlight_token in tokens.iter().rev() {
-            if highlight_token.end.y == y {
-                if let Some(x) = highlight_token.end.x.checked_sub(x) {
-                    code.insert_str(x, format!("{}", ResetColor).as_str());
-                }
-            }
-
-            if highlight_token.start.y == y {
-                if let Some(x) = highlight_token.start.x.checked_sub(x) {
-                    code.insert_str(
-                        x,
-                        format!(
-                            "{}",
-                            SetForegroundColor(highlight_token.clone().color.into()),
-                        )
-                        .as_str(),
-                    );
-                }
-            }
-        }
+        diagnostics: &[BufferDiagnostic],
+        pos: UVec2,
+    ) -> Option<DiagnosticSeverity> {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| {
+                // 範囲が空なら1文字だけ下線を引く
+                diagnostic.start == pos || (diagnostic.start <= pos && pos < diagnostic.end)
+            })
+            .map(|diagnostic| diagnostic.severity)
+            .min()
+    }
 
-        if is_select {
-            code.insert_str(0, format!("{}", SetBackgroundColor(Color::White)).as_str());
+    fn get_token_color(
+        &self,
+        tokens: &[HighlightToken],
+        byte_pos: UVec2,
+    ) -> Option<CrosstermColor> {
+        // トークンは開始位置でソート済みかつ重なりがない
+        let index = tokens.partition_point(|token| token.start <= byte_pos);
+        let token = tokens.get(index.checked_sub(1)?)?;
+
+        if byte_pos < token.end {
+            Some(token.color.clone().into())
         } else {
-            code.insert_str(0, format!("{}", SetBackgroundColor(Color::Reset)).as_str());
+            None
         }
+    }
 
-        queue!(stdout(), Print(code.to_string())).unwrap();
+    // 矩形選択は表示列で判定する (全角文字は一部でもかかっていれば選択する)
+    fn is_selected(&self, selection: &OperatorRange, pos: UVec2, col: usize, width: usize) -> bool {
+        match selection.kind {
+            RegisterKind::Charwise => selection.start <= pos && pos < selection.end,
+            RegisterKind::Linewise => selection.start.y <= pos.y && pos.y <= selection.end.y,
+            RegisterKind::Blockwise => {
+                selection.start.y <= pos.y
+                    && pos.y <= selection.end.y
+                    && col < selection.end.x
+                    && col + width.max(1) > selection.start.x
+            }
+        }
     }
 
-    fn render_code_visual_mode(
+    // 1文字ずつ色を決め、同じ色が続く間はまとめて出力する (width を超える部分は描画しない)
+    #[allow(clippy::too_many_arguments)]
+    fn render_code_line(
         &self,
         y: usize,
-        line: &String,
-        cursor_pos: UVec2,
-        start_pos: UVec2,
+        width: usize,
+        line: &str,
         tokens: &[HighlightToken],
+        selection: Option<&OperatorRange>,
+        search_matches: &[(UVec2, UVec2)],
+        diagnostics: &[BufferDiagnostic],
     ) {
-        let line = line.to_string();
-        let (cursor_x, cursor_y) = cursor_pos.into();
-        let (start_x, start_y) = start_pos.into();
-
-        let (min_y, max_y) = (start_y.min(cursor_y), start_y.max(cursor_y));
-        if min_y <= y && max_y >= y {
-            let start = match start_y.cmp(&y) {
-                Ordering::Greater => line.len(),
-                Ordering::Less => 0,
-                Ordering::Equal => {
-                    if start_pos < cursor_pos || line.is_empty() {
-                        start_x
-                    } else {
-                        start_x + 1
-                    }
-                }
+        let mut code = String::new();
+        let mut current_style = None;
+        let mut col = 0;
+
+        for (x, (byte_x, c)) in line.char_indices().enumerate() {
+            let pos = UVec2::new(x, y);
+            let foreground = self
+                .get_token_color(tokens, UVec2::new(byte_x, y))
+                .unwrap_or(CrosstermColor::Reset);
+
+            let width_of_char = char_width(c);
+            if col + width_of_char > width {
+                break;
+            }
+
+            let is_selected = selection
+                .is_some_and(|selection| self.is_selected(selection, pos, col, width_of_char));
+            col += width_of_char;
+            let is_matched = search_matches
+                .iter()
+                .any(|(start, end)| *start <= pos && pos < *end);
+
+            let background = if is_selected {
+                CrosstermColor::White
+            } else if is_matched {
+                CrosstermColor::DarkYellow
+            } else {
+                CrosstermColor::Reset
             };
 
-            let end = match cursor_y.cmp(&y) {
-                Ordering::Greater => line.len(),
-                Ordering::Less => 0,
-                Ordering::Equal => {
-                    if start_pos < cursor_pos || line.is_empty() {
-                        cursor_x
-                    } else {
-                        cursor_x + 1
+            let underline = self
+                .get_diagnostic_severity(diagnostics, pos)
+                .map(get_diagnostic_color);
+
+            if current_style != Some((foreground, background, underline)) {
+                code.push_str(&format!(
+                    "{}{}",
+                    SetForegroundColor(foreground),
+                    SetBackgroundColor(background)
+                ));
+                match underline {
+                    Some(color) => {
+                        code.push_str(&SetAttribute(Attribute::Underlined).to_string());
+                        SetUnderlineColor(color).write_ansi(&mut code).unwrap();
                     }
+                    None => code.push_str(&SetAttribute(Attribute::NoUnderline).to_string()),
                 }
-            };
-
-            let (front_index, front_text) = if start <= end {
-                (0, &line[0..start])
-            } else {
-                (0, &line[0..end])
-            };
+                current_style = Some((foreground, background, underline));
+            }
 
-            let (select_index, select_text) = if start <= end {
-                (start, &line[start..end])
-            } else {
-                (end, &line[end..start])
-            };
+            code.push(c);
+        }
 
-            let (back_index, back_text) = if start <= end {
-                (end, &line[end..line.len()])
-            } else {
-                (start, &line[start..line.len()])
-            };
+        queue!(
+            stdout(),
+            Print(code),
+            SetAttribute(Attribute::NoUnderline),
+            ResetColor
+        )
+        .unwrap();
 
-            self.render_code_string(front_index, y, false, tokens, front_text);
-            self.render_code_string(select_index, y, true, tokens, select_text);
-            self.render_code_string(back_index, y, false, tokens, back_text);
-        } else {
-            self.render_code_string(0, y, false, tokens, &line);
-        }
+        self.render_virtual_text(y, width.saturating_sub(col), diagnostics);
     }
 
-    fn render_code_line(
-        &self,
-        mode: &EditorMode,
-        cursor_pos: UVec2,
-        y: usize,
-        line: &String,
-        tokens: &[HighlightToken],
-        visual_start: UVec2,
-    ) -> anyhow::Result<()> {
-        if let EditorMode::Visual = mode.clone() {
-            self.render_code_visual_mode(y, line, cursor_pos, visual_start, tokens);
-        } else {
-            self.render_code_string(0, y, false, tokens, &line.to_string());
-        }
-
-        Ok(())
+    // 行末の後ろに、その行から始まる診断のうち最も重要なもののメッセージを表示する
+    fn render_virtual_text(&self, y: usize, width: usize, diagnostics: &[BufferDiagnostic]) {
+        let Some(diagnostic) = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.start.y == y)
+            .min_by_key(|diagnostic| diagnostic.severity)
+        else {
+            return;
+        };
+
+        let message = diagnostic.message.lines().next().unwrap_or_default();
+        let text = truncate_to_width(&format!("  ■ {}", message), width);
+        queue!(
+            stdout(),
+            SetForegroundColor(get_diagnostic_color(diagnostic.severity)),
+            Print(text),
+            ResetColor
+        )
+        .unwrap();
     }
 
     #[allow(clippy::too_many_arguments)]
     fn render_code(
         &self,
-        window_size: UVec2,
-        mode: &EditorMode,
+        rect: &Rect,
         scroll_y: usize,
-        cursor_pos: UVec2,
         offset_x: usize,
         lines: &[String],
         tokens: &[HighlightToken],
-        visual_start: UVec2,
+        selection: Option<&OperatorRange>,
+        search_matches: &[(UVec2, UVec2)],
+        diagnostics: &[BufferDiagnostic],
     ) -> anyhow::Result<()> {
-        for (draw_y, line) in lines.iter().skip(scroll_y).take(window_size.y).enumerate() {
-            queue!(stdout(), MoveTo(offset_x as u16, draw_y as u16)).unwrap();
+        let width = rect.size.x.saturating_sub(offset_x);
+
+        for (draw_y, line) in lines.iter().take(rect.size.y).enumerate() {
+            queue!(
+                stdout(),
+                MoveTo((rect.pos.x + offset_x) as u16, (rect.pos.y + draw_y) as u16)
+            )
+            .unwrap();
             self.render_code_line(
-                mode,
-                cursor_pos,
                 draw_y + scroll_y,
+                width,
                 line,
                 tokens,
-                visual_start,
-            )?;
+                selection,
+                search_matches,
+                diagnostics,
+            );
         }
 
         Ok(())
     }
 
-    fn render_command_box(&self, window_size: UVec2, command_input_buf: &str) -> UVec2 {
+    // 分割したウィンドウごとのステータスライン (ファイル名と未保存の変更)
+    fn render_window_status(&self, rect: &Rect, view: &EditorView, is_current: bool) {
+        let buffer = view.buffer();
+        let name = format!(
+            " {}{} ",
+            buffer.get_name(),
+            if buffer.is_modified() { " [+]" } else { "" }
+        );
+        let name: String = name
+            .chars()
+            .scan(0, |width, c| {
+                *width += char_width(c);
+                (*width <= rect.size.x).then_some(c)
+            })
+            .collect();
+        let space = " ".repeat(rect.size.x.saturating_sub(name.width()));
+
+        let background = if is_current {
+            CrosstermColor::White
+        } else {
+            CrosstermColor::DarkGrey
+        };
+
+        queue!(
+            stdout(),
+            MoveTo(rect.pos.x as u16, (rect.pos.y + rect.size.y - 1) as u16),
+            SetBackgroundColor(background),
+            SetForegroundColor(CrosstermColor::Black),
+            Print(name),
+            Print(space),
+            ResetColor
+        )
+        .unwrap();
+    }
+
+    // 最上行のタブバー (タブが複数のときだけ表示する)
+    fn render_tab_bar(&self, rect: &Rect, tabs: &[EditorTab], current: usize) {
+        queue!(stdout(), MoveTo(rect.pos.x as u16, rect.pos.y as u16)).unwrap();
+
+        let mut width = 0;
+        for (i, tab) in tabs.iter().enumerate() {
+            let label: String = format!(" {} {} ", i + 1, tab.get_label())
+                .chars()
+                .take_while(|c| {
+                    width += char_width(*c);
+                    width <= rect.size.x
+                })
+                .collect();
+
+            let background = if i == current {
+                CrosstermColor::White
+            } else {
+                CrosstermColor::DarkGrey
+            };
+
+            queue!(
+                stdout(),
+                SetBackgroundColor(background),
+                SetForegroundColor(CrosstermColor::Black),
+                Print(label),
+                ResetColor
+            )
+            .unwrap();
+
+            if width > rect.size.x {
+                return;
+            }
+        }
+
+        queue!(
+            stdout(),
+            SetBackgroundColor(CrosstermColor::DarkGrey),
+            Print(" ".repeat(rect.size.x - width)),
+            ResetColor
+        )
+        .unwrap();
+    }
+
+    // 左右に並んだウィンドウの間の区切り線
+    fn render_separator(&self, rect: &Rect) {
+        for y in rect.pos.y..rect.pos.y + rect.size.y {
+            queue!(
+                stdout(),
+                MoveTo((rect.pos.x + rect.size.x) as u16, y as u16),
+                Print('│')
+            )
+            .unwrap();
+        }
+    }
+
+    // rect の中にビューを描画し、カーソルの位置を返す (最下行はステータスライン用)
+    fn render_window(
+        &self,
+        rect: &Rect,
+        view: &EditorView,
+        mode: &EditorMode,
+        search_regex: Option<&Regex>,
+    ) -> anyhow::Result<Option<UVec2>> {
+        let code_rect = Rect::new(
+            rect.pos,
+            UVec2::new(rect.size.x, rect.size.y.saturating_sub(1)),
+        );
+
+        let line_count = view.get_line_count();
+        let num_len = (line_count - 1).to_string().len();
+        let scroll_y = view.get_offset().y;
+
+        view.buffer_mut()
+            .update_highlight(scroll_y, scroll_y + code_rect.size.y);
+
+        // 診断があるときは行番号の左に記号の列を追加する
+        let buffer = view.buffer();
+        let diagnostics = buffer.get_diagnostics();
+        let signs: Vec<Option<DiagnosticSeverity>> = if diagnostics.is_empty() {
+            vec![]
+        } else {
+            (scroll_y..scroll_y + code_rect.size.y)
+                .map(|y| {
+                    buffer
+                        .get_line_diagnostic(y)
+                        .map(|diagnostic| diagnostic.severity)
+                })
+                .collect()
+        };
+        let offset_x = num_len + 1 + if signs.is_empty() { 0 } else { 2 };
+
+        let lines = view.get_lines(scroll_y, scroll_y + code_rect.size.y);
+
+        let selection = if mode.is_visual() {
+            Some(view.get_visual_range(mode))
+        } else {
+            None
+        };
+        let search_matches = search_regex
+            .map(|regex| view.get_search_matches(regex, scroll_y, scroll_y + code_rect.size.y))
+            .unwrap_or_default();
+
+        self.render_numbers(&code_rect, line_count, scroll_y, offset_x, &signs);
+        self.render_code(
+            &code_rect,
+            scroll_y,
+            offset_x,
+            &lines,
+            buffer.get_highlight_tokens(),
+            selection.as_ref(),
+            &search_matches,
+            diagnostics,
+        )?;
+
+        Ok(view.get_draw_position(mode).checked_add(IVec2::new(
+            (rect.pos.x + offset_x) as isize,
+            rect.pos.y as isize - scroll_y as isize,
+        )))
+    }
+
+    fn render_command_box(
+        &self,
+        window_size: UVec2,
+        prompt: char,
+        command_line: &EditorCommandLine,
+    ) -> UVec2 {
         let y = window_size.y - 1;
-        let len = command_input_buf.len() + 1;
 
         queue!(
             stdout(),
             MoveTo(0, y as u16),
             Clear(ClearType::CurrentLine),
-            Print(":"),
-            Print(command_input_buf),
+            Print(prompt),
+            Print(command_line.get_text()),
         )
         .unwrap();
 
-        UVec2::new(len, y)
+        UVec2::new(command_line.get_cursor_column() + 1, y)
     }
 
-    fn render_status_line(&self, window_size: UVec2, mode: &EditorMode) {
-        let y = window_size.y - 1;
-        let mode = format!(" {} ", mode);
-        let space = " ".repeat(window_size.x - mode.len());
+    // コマンドラインの上に補完候補を並べる (入りきらない場合は選択中の候補を含む部分を表示する)
+    pub fn render_wildmenu(
+        &self,
+        window_size: UVec2,
+        candidates: &[&str],
+        selected: Option<usize>,
+    ) {
+        let Some(y) = window_size.y.checked_sub(2) else {
+            return;
+        };
+
+        let mut start = 0;
+        let mut col = 0;
+        for (i, candidate) in candidates.iter().enumerate() {
+            let width = candidate.width() + 2;
+            if col + width > window_size.x && col > 0 {
+                if selected.is_some_and(|selected| selected < i) {
+                    break;
+                }
+                start = i;
+                col = 0;
+            }
+            col += width;
+        }
 
         queue!(
             stdout(),
             MoveTo(0, y as u16),
-            Clear(ClearType::CurrentLine),
-            SetBackgroundColor(Color::White),
-            SetForegroundColor(Color::Black),
-            Print(mode),
-            Print(space),
+            SetBackgroundColor(CrosstermColor::DarkGrey),
+            SetForegroundColor(CrosstermColor::White),
+        )
+        .unwrap();
+
+        let mut col = 0;
+        for (i, candidate) in candidates.iter().enumerate().skip(start) {
+            let width = candidate.width() + 2;
+            if col + width > window_size.x && col > 0 {
+                break;
+            }
+
+            if Some(i) == selected {
+                queue!(
+                    stdout(),
+                    SetBackgroundColor(CrosstermColor::White),
+                    SetForegroundColor(CrosstermColor::Black),
+                    Print(format!(" {} ", candidate)),
+                    SetBackgroundColor(CrosstermColor::DarkGrey),
+                    SetForegroundColor(CrosstermColor::White),
+                )
+                .unwrap();
+            } else {
+                queue!(stdout(), Print(format!(" {} ", candidate))).unwrap();
+            }
+            col += width;
+        }
+
+        queue!(
+            stdout(),
+            Print(" ".repeat(window_size.x.saturating_sub(col))),
             ResetColor
         )
         .unwrap();
     }
 
-    pub fn render(
+    // 入力中の単語の下 (入らなければ上) に候補を表示する
+    fn render_completion(
         &self,
+        bottom: usize,
         window_size: UVec2,
-        editor: &Editor,
-        tokens: &[HighlightToken],
-        command_input_buf: &str,
-    ) -> anyhow::Result<Option<UVec2>> {
-        if let Some(current) = editor.get_current_buffer() {
-            let mode = editor.get_mode();
+        cursor: UVec2,
+        completion: &EditorCompletion,
+    ) {
+        let items = completion.get_items();
+        if items.is_empty() {
+            return;
+        }
 
-            let lines = current.get_lines();
+        let label_width = items
+            .iter()
+            .map(|item| item.label.width())
+            .max()
+            .unwrap_or(0)
+            .min(COMPLETION_LABEL_WIDTH);
+        let detail_width = items
+            .iter()
+            .filter_map(|item| item.detail.as_ref().map(|detail| detail.width()))
+            .max()
+            .map_or(0, |width| width.min(COMPLETION_DETAIL_WIDTH) + 1);
+        let width = (label_width + detail_width + 2).min(window_size.x);
+
+        let height = items.len().min(COMPLETION_MENU_HEIGHT);
+        let below = bottom.saturating_sub(cursor.y + 1);
+        let top = if height <= below || below >= cursor.y {
+            cursor.y + 1
+        } else {
+            cursor.y - height.min(cursor.y)
+        };
+        let height = height.min(if top > cursor.y { below } else { cursor.y });
+
+        let x = cursor
+            .x
+            .saturating_sub(completion.get_prefix().width())
+            .min(window_size.x.saturating_sub(width));
+
+        let scroll = completion.get_scroll();
+        for (i, item) in items.iter().skip(scroll).take(height).enumerate() {
+            let (fg, bg) = if completion.get_selected() == Some(scroll + i) {
+                (CrosstermColor::Black, CrosstermColor::White)
+            } else {
+                (CrosstermColor::White, CrosstermColor::DarkGrey)
+            };
 
-            let num_len = (lines.len() - 1).to_string().len();
-            let offset_x = num_len + 1;
+            let label = truncate_to_width(&item.label, label_width);
+            let detail = item
+                .detail
+                .as_deref()
+                .map(|detail| {
+                    truncate_to_width(
+                        detail.lines().next().unwrap_or_default(),
+                        COMPLETION_DETAIL_WIDTH,
+                    )
+                })
+                .unwrap_or_default();
+            let text = format!(
+                " {}{} {}",
+                label,
+                " ".repeat(label_width - label.width()),
+                detail
+            );
+            let text = truncate_to_width(&text, width);
+
+            queue!(
+                stdout(),
+                MoveTo(x as u16, (top + i) as u16),
+                SetForegroundColor(fg),
+                SetBackgroundColor(bg),
+                Print(&text),
+                Print(" ".repeat(width.saturating_sub(text.width()))),
+            )
+            .unwrap();
+        }
 
-            let scroll_y = current.get_offset().y;
+        queue!(stdout(), ResetColor).unwrap();
+    }
 
-            let cursor_pos = current.get_position(&mode);
-            let draw_cursor_pos = current.get_draw_position(&mode);
+    // カーソルの下 (入らなければ上) に枠で囲んで表示する
+    fn render_hover(&self, bottom: usize, window_size: UVec2, cursor: UVec2, lines: &[String]) {
+        let max_width = HOVER_MAX_WIDTH.min(window_size.x.saturating_sub(2));
+        if max_width == 0 {
+            return;
+        }
 
-            let mut draw_cursor_pos =
-                draw_cursor_pos.checked_add(IVec2::new(offset_x as isize, -(scroll_y as isize)));
+        let lines: Vec<String> = lines
+            .iter()
+            .flat_map(|line| wrap_line(line, max_width))
+            .collect();
+        let width = lines
+            .iter()
+            .map(|line| line.width())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let below = bottom.saturating_sub(cursor.y + 1);
+        let above = cursor.y;
+        let wanted = lines.len().min(HOVER_MAX_HEIGHT) + 2;
+        let (top, height) = if wanted <= below || below >= above {
+            (cursor.y + 1, wanted.min(below))
+        } else {
+            (cursor.y - wanted.min(above), wanted.min(above))
+        };
+        if height < 3 {
+            return;
+        }
 
-            let visual_start = current.get_visual_start();
+        let x = cursor.x.min(window_size.x.saturating_sub(width + 2));
+        let border = "─".repeat(width);
 
-            self.render_numbers(window_size, &lines, scroll_y, offset_x);
-            self.render_code(
-                window_size,
-                &mode,
-                scroll_y,
-                cursor_pos,
-                offset_x,
-                &lines,
-                tokens,
-                visual_start,
-            )?;
+        queue!(
+            stdout(),
+            SetForegroundColor(CrosstermColor::White),
+            MoveTo(x as u16, top as u16),
+            Print(format!("┌{}┐", border)),
+            MoveTo(x as u16, (top + height - 1) as u16),
+            Print(format!("└{}┘", border)),
+        )
+        .unwrap();
+
+        for (i, line) in lines.iter().take(height - 2).enumerate() {
+            queue!(
+                stdout(),
+                MoveTo(x as u16, (top + 1 + i) as u16),
+                Print(format!(
+                    "│{}{}│",
+                    line,
+                    " ".repeat(width.saturating_sub(line.width()))
+                )),
+            )
+            .unwrap();
+        }
+
+        queue!(stdout(), ResetColor).unwrap();
+    }
+
+    fn render_status_line(
+        &self,
+        window_size: UVec2,
+        mode: &EditorMode,
+        recording_macro: Option<char>,
+        message: Option<&String>,
+        pending_keys: &str,
+    ) {
+        let y = window_size.y - 1;
+        let mode = match recording_macro {
+            Some(name) => format!(" {} recording @{} ", mode, name),
+            None => format!(" {} ", mode),
+        };
+        let message = match message {
+            Some(message) => format!("{} ", message),
+            None => String::new(),
+        };
+        let pending_keys = format!("{} ", pending_keys);
+        let space = " ".repeat(
+            window_size
+                .x
+                .saturating_sub(mode.len() + message.width() + pending_keys.width()),
+        );
+
+        queue!(
+            stdout(),
+            MoveTo(0, y as u16),
+            Clear(ClearType::CurrentLine),
+            SetBackgroundColor(CrosstermColor::White),
+            SetForegroundColor(CrosstermColor::Black),
+            Print(mode),
+            Print(message),
+            Print(space),
+            Print(pending_keys),
+            ResetColor
+        )
+        .unwrap();
+    }
+
+    pub fn render(&self, window_size: UVec2, editor: &Editor) -> anyhow::Result<Option<UVec2>> {
+        let mode = editor.get_mode();
+        let rects = editor.get_window_rects();
+        let mut draw_cursor_pos = None;
+
+        if editor.get_tab_count() > 1 {
+            let sidebar_width = editor.get_sidebar_width().min(window_size.x);
+            let rect = Rect::new(
+                UVec2::new(sidebar_width, 0),
+                UVec2::new(window_size.x - sidebar_width, 1),
+            );
+            self.render_tab_bar(&rect, editor.get_tabs(), editor.get_current_tab_index());
+        }
+
+        for (id, rect) in &rects {
+            let Some(window) = editor.get_window(*id) else {
+                continue;
+            };
+
+            let is_current = *id == editor.get_current_window_id();
+            let view = window.get_view();
 
-            if let EditorMode::Command = mode {
-                draw_cursor_pos = Some(self.render_command_box(window_size, command_input_buf));
+            // 選択範囲や検索中の表示は操作中のウィンドウだけに出す
+            let window_mode = if is_current {
+                mode.clone()
             } else {
-                self.render_status_line(window_size, &mode);
+                EditorMode::Normal
+            };
+            let cursor_pos =
+                self.render_window(rect, view, &window_mode, editor.get_search_highlight())?;
+
+            if is_current {
+                draw_cursor_pos = cursor_pos;
+            }
+            if editor.has_window_status() {
+                self.render_window_status(rect, view, is_current);
+            }
+            if rect.pos.x + rect.size.x < window_size.x {
+                self.render_separator(rect);
+            }
+        }
+
+        if let Some(cursor) = draw_cursor_pos {
+            let bottom = editor
+                .get_panel_rect()
+                .map_or(window_size.y.saturating_sub(1), |rect| rect.pos.y);
+            if let Some(lines) = editor.get_hover() {
+                self.render_hover(bottom, window_size, cursor, lines);
+            }
+            if let (EditorMode::Insert { .. }, Some(completion)) = (&mode, editor.get_completion())
+            {
+                self.render_completion(bottom, window_size, cursor, completion);
             }
+        }
 
-            Ok(draw_cursor_pos)
+        let command_line = editor.get_command_line();
+        if let EditorMode::Command = mode {
+            draw_cursor_pos = Some(self.render_command_box(window_size, ':', command_line));
+        } else if let EditorMode::Search { backward } = mode {
+            let prompt = if backward { '?' } else { '/' };
+            draw_cursor_pos = Some(self.render_command_box(window_size, prompt, command_line));
         } else {
-            Ok(None)
+            let diagnostic = editor.get_cursor_diagnostic();
+            self.render_status_line(
+                window_size,
+                &mode,
+                editor.get_recording_macro(),
+                editor.get_message().or(diagnostic.as_ref()),
+                &editor.get_pending_keys(),
+            );
         }
+
+        Ok(draw_cursor_pos)
     }
 }
@@ -0,0 +1,193 @@
+use anyhow::anyhow;
+use regex::{Regex, RegexBuilder};
+
+use crate::utils::vec2::UVec2;
+
+// smartcase: 大文字を含まないパターンは大文字小文字を区別しない
+fn has_uppercase(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c.is_uppercase() {
+            return true;
+        }
+    }
+
+    false
+}
+
+pub fn build_regex(pattern: &str) -> anyhow::Result<Regex> {
+    build_regex_with_case(pattern, None)
+}
+
+// ignore_case を指定しない場合は smartcase で判定する
+pub fn build_regex_with_case(pattern: &str, ignore_case: Option<bool>) -> anyhow::Result<Regex> {
+    Ok(RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case.unwrap_or(!has_uppercase(pattern)))
+        .build()?)
+}
+
+#[derive(Default)]
+pub struct EditorSearch {
+    regex: Option<Regex>,
+    preview: Option<Regex>,
+    backward: bool,
+    highlight: bool,
+    origin: Option<UVec2>,
+}
+
+impl EditorSearch {
+    pub fn get_regex(&self) -> Option<&Regex> {
+        self.regex.as_ref()
+    }
+
+    pub fn is_backward(&self) -> bool {
+        self.backward
+    }
+
+    pub fn get_origin(&self) -> Option<UVec2> {
+        self.origin
+    }
+
+    // 検索プロンプトを開いたときのカーソル位置を覚えておく
+    pub fn start(&mut self, backward: bool, origin: UVec2) {
+        self.backward = backward;
+        self.origin = Some(origin);
+    }
+
+    pub fn finish(&mut self) -> Option<UVec2> {
+        self.preview = None;
+        self.origin.take()
+    }
+
+    // 入力途中のパターン (インクリメンタルサーチ用)
+    pub fn set_preview(&mut self, regex: Option<Regex>) {
+        self.preview = regex;
+    }
+
+    pub fn set_pattern(&mut self, pattern: &str) -> anyhow::Result<()> {
+        self.set_regex(build_regex(pattern)?);
+        Ok(())
+    }
+
+    pub fn set_regex(&mut self, regex: Regex) {
+        self.regex = Some(regex);
+        self.highlight = true;
+    }
+
+    // :noh で消したハイライトは次の検索で再び表示する
+    pub fn get_highlight_regex(&self) -> Option<&Regex> {
+        if self.origin.is_some() {
+            self.preview.as_ref()
+        } else if self.highlight {
+            self.regex.as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn set_highlight(&mut self, highlight: bool) {
+        self.highlight = highlight;
+    }
+}
+
+// "/pat/rest" をパターンと残りに分ける (区切り文字はエスケープできる)
+pub fn split_pattern(args: &str) -> anyhow::Result<(char, String, String)> {
+    let mut chars = args.chars();
+    let delimiter = chars
+        .next()
+        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && !['\\', '"', '|'].contains(c))
+        .ok_or_else(|| anyhow!("Regular expressions can't be delimited by letters"))?;
+
+    let (pattern, rest) = split_at_delimiter(chars.as_str(), delimiter);
+    Ok((delimiter, pattern, rest))
+}
+
+pub fn split_at_delimiter(text: &str, delimiter: char) -> (String, String) {
+    let mut part = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c) if c == delimiter => part.push(c),
+                Some(c) => {
+                    part.push('\\');
+                    part.push(c);
+                }
+                None => part.push('\\'),
+            },
+            c if c == delimiter => return (part, chars.as_str().to_string()),
+            c => part.push(c),
+        }
+    }
+
+    (part, String::new())
+}
+
+// Vim 形式の置換文字列 (\1, & など) を regex クレートの形式 (${1}, ${0}) に変換する
+pub fn translate_replacement(replacement: &str) -> String {
+    let mut result = String::new();
+    let mut chars = replacement.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => result.push_str(&format!("${{{}}}", digit)),
+                Some('n' | 'r') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some('$') => result.push_str("$$"),
+                Some(c) => result.push(c),
+                None => result.push('\\'),
+            },
+            '&' => result.push_str("${0}"),
+            '$' => result.push_str("$$"),
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_pattern, translate_replacement};
+
+    #[test]
+    fn translates_vim_replacement() {
+        assert_eq!(translate_replacement(r"\1-\2"), "${1}-${2}");
+        assert_eq!(translate_replacement("[&]"), "[${0}]");
+        assert_eq!(translate_replacement(r"a\nb\tc"), "a\nb\tc");
+    }
+
+    #[test]
+    fn escapes_literal_characters_in_replacement() {
+        assert_eq!(translate_replacement("$1"), "$$1");
+        assert_eq!(translate_replacement(r"\$"), "$$");
+        assert_eq!(translate_replacement(r"\&\\"), r"&\");
+        assert_eq!(translate_replacement(r"end\"), r"end\");
+    }
+
+    #[test]
+    fn replacement_is_usable_by_regex() {
+        let regex = regex::Regex::new(r"(\w+)=(\w+)").unwrap();
+        let replaced = regex.replace_all("a=b c=d", translate_replacement(r"\2=\1 & $"));
+
+        assert_eq!(replaced, "b=a a=b $ d=c c=d $");
+    }
+
+    #[test]
+    fn splits_pattern_at_unescaped_delimiter() {
+        assert_eq!(
+            split_pattern(r"/a\/b/c/g").unwrap(),
+            ('/', "a/b".to_string(), "c/g".to_string())
+        );
+        assert_eq!(
+            split_pattern(r"#a\.b#x").unwrap(),
+            ('#', r"a\.b".to_string(), "x".to_string())
+        );
+        assert!(split_pattern("abc").is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use super::{
+    view::EditorView,
+    window::{EditorLayout, EditorWindow},
+};
+
+// タブページごとにウィンドウの分割とフォーカスを持つ (バッファは全タブで共有する)
+pub struct EditorTab {
+    layout: EditorLayout,
+    current_window: usize,
+}
+
+impl EditorTab {
+    pub fn new(window: EditorWindow) -> Self {
+        Self {
+            current_window: window.get_id(),
+            layout: EditorLayout::Window(window),
+        }
+    }
+
+    pub fn get_layout(&self) -> &EditorLayout {
+        &self.layout
+    }
+
+    pub fn get_layout_mut(&mut self) -> &mut EditorLayout {
+        &mut self.layout
+    }
+
+    pub fn get_current_window(&self) -> usize {
+        self.current_window
+    }
+
+    pub fn set_current_window(&mut self, id: usize) {
+        self.current_window = id;
+    }
+
+    pub fn view(&self) -> &EditorView {
+        self.layout.view(self.current_window)
+    }
+
+    pub fn view_mut(&mut self) -> &mut EditorView {
+        self.layout.view_mut(self.current_window)
+    }
+
+    // タブバーに表示する名前 (フォーカスしているウィンドウのバッファ名)
+    pub fn get_label(&self) -> String {
+        let name = self.view().buffer().get_name();
+        let modified = self
+            .layout
+            .get_windows()
+            .iter()
+            .any(|window| window.get_view().buffer().is_modified());
+
+        format!("{}{}", name, if modified { " [+]" } else { "" })
+    }
+}
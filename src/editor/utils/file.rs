@@ -26,10 +26,19 @@ impl EditorFile {
         })
     }
 
-    pub fn _set_file_path(&mut self, path: PathBuf) {
+    pub fn get_path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
         self.path = Some(path);
     }
 
+    // 削除されたファイルのハンドルを捨て、次の書き込みで作り直す
+    pub fn close(&mut self) {
+        self.file = None;
+    }
+
     pub fn read(&mut self) -> anyhow::Result<String> {
         let Some(_) = &self.path else {
             return Err(anyhow!("File name is missing."));
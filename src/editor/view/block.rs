@@ -0,0 +1,236 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    editor::{mode::EditorMode, register::RegisterKind},
+    utils::vec2::UVec2,
+};
+
+use super::{operator::OperatorRange, EditorView};
+
+// 矩形選択の I, A で挿入を始めたときの状態
+#[derive(Debug, Clone, Copy)]
+pub struct BlockInsert {
+    start_y: usize,
+    end_y: usize,
+    col: usize,
+    x: usize,
+    line_len: usize,
+    line_count: usize,
+    pad: bool,
+}
+
+pub fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+impl EditorView {
+    // 行頭から x 文字目までの表示幅
+    pub fn get_display_col(&self, x: usize, y: usize) -> usize {
+        self.get_line(y).chars().take(x).map(char_width).sum()
+    }
+
+    pub fn get_line_width(&self, y: usize) -> usize {
+        self.get_line(y).width()
+    }
+
+    // 表示列 col 以降で最初の文字の位置
+    pub fn get_x_at_display_col(&self, y: usize, col: usize) -> usize {
+        let mut width = 0;
+
+        for (x, c) in self.get_line(y).chars().enumerate() {
+            if width >= col {
+                return x;
+            }
+            width += char_width(c);
+        }
+
+        self.get_line_length(y)
+    }
+
+    // 表示列 left..right に一部でもかかる文字の範囲 (文字単位の半開区間)
+    pub fn get_block_span(&self, y: usize, left: usize, right: usize) -> (usize, usize) {
+        let mut col = 0;
+        let mut start = None;
+
+        for (x, c) in self.get_line(y).chars().enumerate() {
+            if col >= right {
+                return (start.unwrap_or(x), x);
+            }
+            if start.is_none() && col + char_width(c).max(1) > left {
+                start = Some(x);
+            }
+            col += char_width(c);
+        }
+
+        let len = self.get_line_length(y);
+        (start.unwrap_or(len), len)
+    }
+
+    // 文字の表示列と幅 (空行でも 1 列は選択する)
+    fn get_char_cols(&self, pos: UVec2) -> (usize, usize) {
+        let width = self
+            .get_line(pos.y)
+            .chars()
+            .nth(pos.x)
+            .map(char_width)
+            .unwrap_or(1)
+            .max(1);
+
+        (self.get_display_col(pos.x, pos.y), width)
+    }
+
+    pub fn get_visual_range(&self, mode: &EditorMode) -> OperatorRange {
+        match mode {
+            EditorMode::VisualLine => OperatorRange::linewise(self.visual_start.y, self.cursor.y),
+            EditorMode::VisualBlock => {
+                let (start_col, start_width) =
+                    self.get_char_cols(self.clamp_pos(self.visual_start));
+                let (cursor_col, cursor_width) =
+                    self.get_char_cols(self.clamp_pos(self.get_position(mode)));
+
+                OperatorRange::blockwise(
+                    self.visual_start.y,
+                    self.cursor.y,
+                    start_col.min(cursor_col),
+                    (start_col + start_width).max(cursor_col + cursor_width),
+                )
+            }
+            _ => {
+                let (start, end) = self.get_selection_range(mode);
+                OperatorRange::charwise(self.index_to_pos(start), self.index_to_pos(end))
+            }
+        }
+    }
+
+    // 範囲の先頭の文字位置 (矩形の場合は表示列から変換する)
+    pub fn get_range_start(&self, range: &OperatorRange) -> UVec2 {
+        match range.kind {
+            RegisterKind::Blockwise => {
+                let (x, _) = self.get_block_span(range.start.y, range.start.x, range.end.x);
+                UVec2::new(x, range.start.y)
+            }
+            _ => range.start,
+        }
+    }
+
+    pub(super) fn get_block_text(&self, range: &OperatorRange) -> String {
+        (range.start.y..=range.end.y)
+            .map(|y| {
+                let (start, end) = self.get_block_span(y, range.start.x, range.end.x);
+                self.get_line(y)
+                    .chars()
+                    .skip(start)
+                    .take(end - start)
+                    .collect()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // 矩形の各行を text の対応する行で置き換える
+    pub(super) fn replace_block_text(&mut self, range: &OperatorRange, text: &str) {
+        let mut pieces = text.split('\n');
+
+        for y in range.start.y..=range.end.y {
+            let piece = pieces.next().unwrap_or("");
+            let (start, end) = self.get_block_span(y, range.start.x, range.end.x);
+
+            self.delete_text(start, y, end - start);
+            self.insert_text(start, y, piece);
+        }
+    }
+
+    // 表示列 col に揃えて挿入する (短い行は pad が true なら空白で埋め、false なら飛ばす)
+    pub fn insert_at_display_col(&mut self, y: usize, col: usize, text: &str, pad: bool) {
+        let width = self.get_line_width(y);
+
+        if width < col {
+            if !pad {
+                return;
+            }
+            self.insert_text(self.get_line_length(y), y, &" ".repeat(col - width));
+        }
+
+        let x = self.get_x_at_display_col(y, col);
+        self.insert_text(x, y, text);
+    }
+
+    // 矩形のレジスタは各行を同じ列に貼り付ける
+    pub(super) fn paste_block(&mut self, text: &str, before: bool, count: usize, cursor: UVec2) {
+        let (col, width) = self.get_char_cols(cursor);
+        let col = if before || self.get_line_length(cursor.y) == 0 {
+            col
+        } else {
+            col + width
+        };
+
+        let lines: Vec<&str> = text.split('\n').collect();
+        let block_width = lines.iter().map(|line| line.width()).max().unwrap_or(0);
+
+        for (i, line) in lines.into_iter().enumerate() {
+            let y = cursor.y + i;
+            if y >= self.get_line_count() {
+                self.insert_line(y, "");
+            }
+
+            // 行の途中に貼り付ける場合は後ろの文字がずれないよう矩形の幅まで埋める
+            let piece = if self.get_line_width(y) > col {
+                format!("{}{}", line, " ".repeat(block_width - line.width()))
+            } else {
+                line.to_string()
+            };
+
+            self.insert_at_display_col(y, col, &piece.repeat(count.max(1)), true);
+        }
+
+        self.cursor = UVec2::new(self.get_x_at_display_col(cursor.y, col), cursor.y);
+    }
+
+    // 先頭行の表示列 col から挿入を始める (append なら短い行を空白で埋める)
+    pub fn start_block_insert(&mut self, range: &OperatorRange, append: bool) -> BlockInsert {
+        let y = range.start.y;
+        let col = if append {
+            range.end.x
+        } else {
+            self.get_display_col(self.get_range_start(range).x, y)
+        };
+
+        if append {
+            self.insert_at_display_col(y, col, "", true);
+        }
+        self.cursor = UVec2::new(self.get_x_at_display_col(y, col), y);
+
+        BlockInsert {
+            start_y: y,
+            end_y: range.end.y,
+            col,
+            x: self.cursor.x,
+            line_len: self.get_line_length(y),
+            line_count: self.get_line_count(),
+            pad: append,
+        }
+    }
+
+    // 先頭行に挿入されたテキストを残りの行にも挿入する (改行を含む場合は何もしない)
+    pub fn finish_block_insert(&mut self, block: BlockInsert) {
+        if self.get_line_count() != block.line_count || self.cursor.y != block.start_y {
+            return;
+        }
+
+        let line_len = self.get_line_length(block.start_y);
+        if line_len <= block.line_len || self.cursor.x < block.x {
+            return;
+        }
+
+        let text: String = self
+            .get_line(block.start_y)
+            .chars()
+            .skip(block.x)
+            .take(line_len - block.line_len)
+            .collect();
+
+        for y in block.start_y + 1..=block.end_y {
+            self.insert_at_display_col(y, block.col, &text, block.pad);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    fmt::Display,
+    sync::{RwLockReadGuard, RwLockWriteGuard},
+};
+
+use crate::{editor::buffer::EditorBuffer, utils::vec2::UVec2};
+
+use super::EditorView;
+
+// バッファは他のウィンドウと共有しているので、参照はその都度ロックする
+impl EditorView {
+    pub fn buffer(&self) -> RwLockReadGuard<'_, EditorBuffer> {
+        self.buffer.read().unwrap()
+    }
+
+    pub fn buffer_mut(&self) -> RwLockWriteGuard<'_, EditorBuffer> {
+        self.buffer.write().unwrap()
+    }
+
+    // 変更はこのビューのカーソル位置とともに履歴に残す
+    fn edit<R>(&mut self, f: impl FnOnce(&mut EditorBuffer) -> R) -> R {
+        let cursor = self.cursor;
+        let mut buffer = self.buffer_mut();
+
+        buffer.begin_change(cursor);
+        let result = f(&mut buffer);
+        buffer.end_change(cursor);
+
+        result
+    }
+
+    pub fn get_line_count(&self) -> usize {
+        self.buffer().get_line_count()
+    }
+
+    pub fn get_line_length(&self, y: usize) -> usize {
+        self.buffer().get_line_length(y)
+    }
+
+    pub fn get_lines(&self, start: usize, end: usize) -> Vec<String> {
+        self.buffer().get_lines(start, end)
+    }
+
+    pub fn get_line(&self, y: usize) -> String {
+        self.buffer().get_line(y)
+    }
+
+    pub fn get_char_count(&self) -> usize {
+        self.buffer().get_char_count()
+    }
+
+    pub fn pos_to_index(&self, pos: UVec2) -> usize {
+        self.buffer().pos_to_index(pos)
+    }
+
+    pub fn index_to_pos(&self, index: usize) -> UVec2 {
+        self.buffer().index_to_pos(index)
+    }
+
+    pub fn get_text(&self, start: usize, end: usize) -> String {
+        self.buffer().get_text(start, end)
+    }
+
+    pub fn get_revision(&self) -> usize {
+        self.buffer().get_revision()
+    }
+
+    pub fn insert_text(&mut self, x: usize, y: usize, text: &str) {
+        self.edit(|buffer| buffer.insert_text(x, y, text));
+    }
+
+    pub fn delete_text(&mut self, x: usize, y: usize, len: usize) {
+        self.edit(|buffer| buffer.delete_text(x, y, len));
+    }
+
+    pub fn insert_line(&mut self, y: usize, line: &str) {
+        self.edit(|buffer| buffer.insert_line(y, line));
+    }
+
+    pub fn delete_range(&mut self, start: usize, end: usize) {
+        self.edit(|buffer| buffer.delete_range(start, end));
+    }
+
+    pub fn delete_line(&mut self, y: usize) {
+        self.edit(|buffer| buffer.delete_line(y));
+    }
+
+    pub fn split_line(&mut self, x: usize, y: usize) {
+        self.edit(|buffer| buffer.split_line(x, y));
+    }
+
+    pub fn join_lines(&mut self, y: usize) {
+        self.edit(|buffer| buffer.join_lines(y));
+    }
+
+    // start_y..=end_y の行を lines で置き換える (空なら行ごと削除する)
+    pub fn replace_lines(&mut self, start_y: usize, end_y: usize, lines: &[&str]) {
+        self.edit(|buffer| {
+            if lines.is_empty() {
+                for y in (start_y..=end_y).rev() {
+                    buffer.delete_line(y);
+                }
+                return;
+            }
+
+            let start = buffer.pos_to_index(UVec2::new(0, start_y));
+            let end = buffer.pos_to_index(UVec2::new(buffer.get_line_length(end_y), end_y));
+            buffer.delete_range(start, end);
+            buffer.insert_text(0, start_y, &lines.join("\n"));
+        });
+    }
+
+    pub fn insert_char(&mut self, x: usize, y: usize, ch: char) {
+        self.edit(|buffer| buffer.insert_char(x, y, ch));
+    }
+
+    pub fn delete_char(&mut self, x: usize, y: usize) {
+        self.edit(|buffer| buffer.delete_char(x, y));
+    }
+}
+
+impl Display for EditorView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.buffer())
+    }
+}
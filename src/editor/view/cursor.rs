@@ -5,22 +5,22 @@ use crate::{
     utils::vec2::{IVec2, UVec2},
 };
 
-use super::EditorBuffer;
+use super::EditorView;
 
-impl EditorBuffer {
+impl EditorView {
     pub fn get_position(&self, mode: &EditorMode) -> UVec2 {
         UVec2::new(self.clamp_x(self.cursor.x, mode), self.cursor.y)
     }
 
-    pub fn get_visual_start(&self) -> UVec2 {
-        self.visual_start
-    }
-
     pub(super) fn clamp_x(&self, x: usize, mode: &EditorMode) -> usize {
         let line_len = self.get_line_length(self.cursor.y);
 
         match mode {
-            EditorMode::Normal | EditorMode::Visual => {
+            EditorMode::Normal
+            | EditorMode::Visual
+            | EditorMode::VisualLine
+            | EditorMode::VisualBlock
+            | EditorMode::OperatorPending(_) => {
                 if line_len == 0 {
                     0
                 } else if x > line_len - 1 {
@@ -59,7 +59,6 @@ impl EditorBuffer {
         self.sync_scroll_y(mode, window_size);
     }
 
-    #[allow(unused)]
     pub fn move_to(&mut self, target: UVec2, mode: &EditorMode, window_size: UVec2) {
         self.move_to_y(target.y, mode, window_size);
         self.move_to_x(target.x);
@@ -178,11 +177,21 @@ impl EditorBuffer {
     }
 
     pub fn sync(&mut self, mode: &EditorMode) {
-        self.sync_x(mode);
         self.sync_y();
+        self.sync_x(mode);
     }
 
     pub fn start_visual_mode(&mut self) {
         self.visual_start = self.cursor;
     }
+
+    // '<, '> で参照する直前の選択範囲の行
+    pub fn save_visual_marks(&mut self) {
+        let (start, end) = (self.visual_start.y, self.cursor.y);
+        self.buffer_mut().set_visual_marks(start, end);
+    }
+
+    pub fn get_visual_marks(&self) -> Option<(usize, usize)> {
+        self.buffer().get_visual_marks()
+    }
 }
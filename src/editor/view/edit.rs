@@ -0,0 +1,104 @@
+use crate::{
+    editor::{
+        mode::EditorMode,
+        register::{EditorRegisters, Register, RegisterKind},
+    },
+    utils::vec2::UVec2,
+};
+
+use super::{operator::OperatorRange, EditorView};
+
+impl EditorView {
+    pub(super) fn clamp_pos(&self, pos: UVec2) -> UVec2 {
+        let y = self.clamp_y(pos.y);
+        let x = pos.x.min(self.get_line_length(y).saturating_sub(1));
+        UVec2::new(x, y)
+    }
+
+    // 選択範囲 (文字インデックスの半開区間)
+    pub fn get_selection_range(&self, mode: &EditorMode) -> (usize, usize) {
+        let cursor = self.clamp_pos(self.get_position(mode));
+        let visual_start = self.clamp_pos(self.visual_start);
+
+        let (start, end) = if visual_start <= cursor {
+            (visual_start, cursor)
+        } else {
+            (cursor, visual_start)
+        };
+
+        (
+            self.pos_to_index(start),
+            (self.pos_to_index(end) + 1).min(self.get_char_count()),
+        )
+    }
+
+    pub fn select_range(&mut self, range: OperatorRange) {
+        match range.kind {
+            RegisterKind::Linewise => {
+                self.visual_start = UVec2::new(0, range.start.y);
+                self.cursor = UVec2::new(self.get_line_length(range.end.y), range.end.y);
+            }
+            RegisterKind::Charwise | RegisterKind::Blockwise => {
+                let end = self
+                    .pos_to_index(range.end)
+                    .max(self.pos_to_index(range.start) + 1);
+                self.visual_start = range.start;
+                self.cursor = self.index_to_pos(end - 1);
+            }
+        }
+    }
+
+    pub fn yank_line(&mut self, count: usize, registers: &mut EditorRegisters) {
+        let text = self.get_range_text(&self.get_line_range(count));
+        registers.yank(Register::new(text, RegisterKind::Linewise));
+    }
+
+    pub fn paste(
+        &mut self,
+        before: bool,
+        count: usize,
+        mode: &EditorMode,
+        registers: &mut EditorRegisters,
+        window_size: UVec2,
+    ) -> anyhow::Result<()> {
+        let Some(register) = registers.paste()? else {
+            return Ok(());
+        };
+
+        let separator = match register.kind {
+            RegisterKind::Linewise => "\n",
+            _ => "",
+        };
+        let text = vec![register.text.as_str(); count.max(1)].join(separator);
+
+        let cursor = self.get_position(mode);
+
+        match register.kind {
+            RegisterKind::Blockwise => self.paste_block(&register.text, before, count, cursor),
+            RegisterKind::Linewise => {
+                let y = if before { cursor.y } else { cursor.y + 1 };
+                self.insert_line(y, &text);
+                self.cursor = UVec2::new(0, y);
+            }
+            RegisterKind::Charwise => {
+                if text.is_empty() {
+                    return Ok(());
+                }
+
+                let x = if before || self.get_line_length(cursor.y) == 0 {
+                    cursor.x
+                } else {
+                    cursor.x + 1
+                };
+                self.insert_text(x, cursor.y, &text);
+
+                let end = self.pos_to_index(UVec2::new(x, cursor.y)) + text.chars().count();
+                self.cursor = self.index_to_pos(end - 1);
+            }
+        }
+
+        self.sync(mode);
+        self.sync_scroll_y(mode, window_size);
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+use crate::{editor::mode::EditorMode, utils::vec2::UVec2};
+
+use super::EditorView;
+
+impl EditorView {
+    /// 編集のまとまりを開始する (入れ子にできる)
+    pub fn begin_change(&mut self) {
+        let cursor = self.cursor;
+        self.buffer_mut().begin_change(cursor);
+    }
+
+    /// 編集のまとまりを終了し、最も外側であれば1回の undo 単位として確定する
+    pub fn end_change(&mut self) {
+        let cursor = self.cursor;
+        self.buffer_mut().end_change(cursor);
+    }
+
+    pub fn undo(&mut self, mode: &EditorMode, window_size: UVec2) -> bool {
+        let Some(cursor) = self.buffer_mut().undo() else {
+            return false;
+        };
+
+        self.cursor = cursor;
+        self.sync(mode);
+        self.sync_scroll_y(mode, window_size);
+        true
+    }
+
+    pub fn redo(&mut self, mode: &EditorMode, window_size: UVec2) -> bool {
+        let Some(cursor) = self.buffer_mut().redo() else {
+            return false;
+        };
+
+        self.cursor = cursor;
+        self.sync(mode);
+        self.sync_scroll_y(mode, window_size);
+        true
+    }
+}
@@ -0,0 +1,236 @@
+mod block;
+mod content;
+mod cursor;
+mod edit;
+mod history;
+mod operator;
+mod scroll;
+mod search;
+mod text_object;
+
+use std::sync::{Arc, RwLock};
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::utils::{event::Event, key_binding::Key, vec2::UVec2};
+
+use super::{
+    action::{EditorBufferAction, EditorCursorAction, EditorEditAction},
+    buffer::EditorBuffer,
+    mode::EditorMode,
+    register::EditorRegisters,
+};
+
+pub use block::{char_width, BlockInsert};
+pub use operator::OperatorRange;
+
+// ウィンドウごとのカーソルとスクロール位置 (バッファは同じものを複数のビューで共有する)
+#[derive(Clone)]
+pub struct EditorView {
+    buffer: Arc<RwLock<EditorBuffer>>,
+    cursor: UVec2,
+    visual_start: UVec2,
+    scroll: UVec2,
+}
+
+impl EditorView {
+    pub fn new(buffer: Arc<RwLock<EditorBuffer>>) -> Self {
+        let cursor = buffer.read().unwrap().get_last_cursor();
+
+        Self {
+            buffer,
+            cursor,
+            visual_start: UVec2::default(),
+            scroll: UVec2::default(),
+        }
+    }
+
+    pub fn is_showing(&self, buffer: &Arc<RwLock<EditorBuffer>>) -> bool {
+        Arc::ptr_eq(&self.buffer, buffer)
+    }
+
+    // TODO この関数はuiに移動予定
+    pub fn delete_key(&mut self, mode: &EditorMode) {
+        let cursor = self.get_position(mode);
+
+        if cursor.x == self.get_line_length(cursor.y) {
+            self.join_lines(cursor.y);
+        } else {
+            self.delete_char(cursor.x, cursor.y);
+        }
+    }
+
+    // TODO この関数はuiに移動予定
+    pub fn backspace_key(&mut self, mode: &EditorMode, window_size: UVec2) -> anyhow::Result<()> {
+        let cursor = self.get_position(mode);
+
+        if cursor.x == 0 {
+            if cursor.y == 0 {
+                return Ok(());
+            }
+
+            let line_length = self.get_line_length(cursor.y - 1);
+            self.move_by_y(-1, mode, window_size);
+
+            // line_length - 1 するのが本来は良いが usize が 0 以下になるのを防ぐため、- 1 はしない
+            self.move_to_x(line_length);
+            self.join_lines(cursor.y - 1);
+        } else {
+            let remove_x = cursor.x - 1;
+
+            self.move_by_x(-1, mode);
+            self.delete_char(remove_x, cursor.y);
+        }
+
+        Ok(())
+    }
+
+    // TODO この関数はuiに移動予定
+    pub fn get_draw_position(&self, mode: &EditorMode) -> UVec2 {
+        let line = self.get_line(self.cursor.y);
+        let x = self.clamp_x(self.cursor.x, mode);
+
+        UVec2::new(
+            line.to_string()
+                .chars()
+                .take(x)
+                .filter_map(|c| c.width())
+                .sum(),
+            self.cursor.y,
+        )
+    }
+
+    pub fn on_cursor_action(
+        &mut self,
+        action: EditorCursorAction,
+        count: Option<usize>,
+        mode: &EditorMode,
+        window_size: UVec2,
+    ) {
+        let n = count.unwrap_or(1);
+
+        match action {
+            EditorCursorAction::Left => self.move_by_x(-(n as isize), mode),
+            EditorCursorAction::Down => self.move_by_y(n as isize, mode, window_size),
+            EditorCursorAction::Up => self.move_by_y(-(n as isize), mode, window_size),
+            EditorCursorAction::Right => self.move_by_x(n as isize, mode),
+            EditorCursorAction::LineStart => self.move_to_x(0),
+            EditorCursorAction::LineEnd => {
+                self.move_by_y(n as isize - 1, mode, window_size);
+                self.move_to_x(usize::MAX);
+            }
+            // 5gg, 5G は 5行目へ移動する
+            EditorCursorAction::Top | EditorCursorAction::Bottom if count.is_some() => {
+                self.move_to_y(n - 1, mode, window_size)
+            }
+            EditorCursorAction::Top => self.move_to_top(mode, window_size),
+            EditorCursorAction::Bottom => self.move_to_bottom(mode, window_size),
+            EditorCursorAction::NextWord => (0..n).for_each(|_| self.move_to_next_word()),
+            EditorCursorAction::BackWord => (0..n).for_each(|_| self.move_to_back_word()),
+        }
+    }
+
+    pub fn on_action(
+        &mut self,
+        action: EditorBufferAction,
+        count: Option<usize>,
+        mode: &EditorMode,
+        registers: &mut EditorRegisters,
+        window_size: UVec2,
+    ) -> anyhow::Result<Option<EditorMode>> {
+        let n = count.unwrap_or(1);
+
+        match action {
+            EditorBufferAction::Save => self.buffer_mut().save()?,
+            EditorBufferAction::Cursor(action) => {
+                self.on_cursor_action(action, count, mode, window_size)
+            }
+            EditorBufferAction::Edit(EditorEditAction::Undo) => {
+                for _ in 0..n {
+                    if !self.undo(mode, window_size) {
+                        break;
+                    }
+                }
+            }
+            EditorBufferAction::Edit(EditorEditAction::Redo) => {
+                for _ in 0..n {
+                    if !self.redo(mode, window_size) {
+                        break;
+                    }
+                }
+            }
+            EditorBufferAction::Edit(EditorEditAction::YankLine) => self.yank_line(n, registers),
+            EditorBufferAction::Edit(action) => {
+                self.begin_change();
+                let result = match action {
+                    EditorEditAction::Paste => self
+                        .paste(false, n, mode, registers, window_size)
+                        .map(|_| None),
+                    EditorEditAction::PasteBefore => self
+                        .paste(true, n, mode, registers, window_size)
+                        .map(|_| None),
+                    _ => Ok(None),
+                };
+                self.end_change();
+                return result;
+            }
+        };
+
+        Ok(None)
+    }
+
+    pub fn on_event(
+        &mut self,
+        evt: Event,
+        mode: &EditorMode,
+        window_size: UVec2,
+    ) -> anyhow::Result<Option<EditorMode>> {
+        let cursor_pos = self.get_position(mode);
+        let cursor_x = cursor_pos.x;
+        let cursor_y = cursor_pos.y;
+
+        match mode {
+            EditorMode::Normal => match evt {
+                Event::Click(pos) => {
+                    // 診断があるときは行番号の左に記号の列がある
+                    let num_len = (self.get_line_count() - 1).to_string().len();
+                    let sign_len = if self.buffer().get_diagnostics().is_empty() {
+                        0
+                    } else {
+                        2
+                    };
+                    let offset_x = num_len + 1 + sign_len;
+                    let scroll_y = self.get_offset().y;
+
+                    let x = pos.x.saturating_sub(offset_x);
+
+                    self.move_to_y(pos.y + scroll_y, mode, window_size);
+                    self.move_to_x(x);
+                }
+                Event::Scroll(scroll) => self.scroll_by(scroll),
+                _ => {}
+            },
+            EditorMode::Insert { append: _ } => {
+                if let Event::Input(key) = evt {
+                    match key {
+                        Key::Delete => self.delete_key(mode),
+                        Key::Backspace => self.backspace_key(mode, window_size)?,
+                        Key::Char('\n') => {
+                            self.split_line(cursor_x, cursor_y);
+                            self.move_by_y(1, mode, window_size);
+                            self.move_to_x(0);
+                        }
+                        Key::Char(c) => {
+                            self.insert_char(cursor_x, cursor_y, c);
+                            self.move_by_x(1, mode);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(None)
+    }
+}
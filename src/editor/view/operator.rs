@@ -0,0 +1,297 @@
+use crate::{
+    editor::{
+        action::{EditorCursorAction, EditorOperator},
+        mode::EditorMode,
+        register::{EditorRegisters, Register, RegisterKind},
+    },
+    utils::vec2::UVec2,
+};
+
+use super::EditorView;
+
+const INDENT: &str = "    ";
+
+// オペレーターの適用範囲 (Charwise は end を含まない、Linewise は start.y..=end.y、
+// Blockwise の x は表示列で end.x を含まない)
+#[derive(Debug, Clone, Copy)]
+pub struct OperatorRange {
+    pub start: UVec2,
+    pub end: UVec2,
+    pub kind: RegisterKind,
+}
+
+impl OperatorRange {
+    pub fn charwise(start: UVec2, end: UVec2) -> Self {
+        Self {
+            start: start.min(end),
+            end: start.max(end),
+            kind: RegisterKind::Charwise,
+        }
+    }
+
+    pub fn linewise(start_y: usize, end_y: usize) -> Self {
+        Self {
+            start: UVec2::new(0, start_y.min(end_y)),
+            end: UVec2::new(0, start_y.max(end_y)),
+            kind: RegisterKind::Linewise,
+        }
+    }
+
+    pub fn blockwise(start_y: usize, end_y: usize, left: usize, right: usize) -> Self {
+        Self {
+            start: UVec2::new(left, start_y.min(end_y)),
+            end: UVec2::new(right, start_y.max(end_y)),
+            kind: RegisterKind::Blockwise,
+        }
+    }
+}
+
+impl EditorView {
+    pub fn get_motion_range(
+        &mut self,
+        operator: EditorOperator,
+        motion: EditorCursorAction,
+        count: Option<usize>,
+        window_size: UVec2,
+    ) -> OperatorRange {
+        let mode = EditorMode::Normal;
+        let start = self.get_position(&mode);
+
+        // Vim と同じく、空白以外の文字の上での cw は ce として扱う (後ろの空白は残す)
+        if operator == EditorOperator::Change
+            && matches!(motion, EditorCursorAction::NextWord)
+            && self
+                .get_line(start.y)
+                .chars()
+                .nth(start.x)
+                .is_some_and(|c| !c.is_whitespace())
+        {
+            let end = self.get_word_end(start, count.unwrap_or(1));
+            return OperatorRange::charwise(start, end);
+        }
+
+        self.on_cursor_action(motion.clone(), count, &mode, window_size);
+        let end = self.get_position(&mode);
+        self.cursor = start;
+
+        match motion {
+            EditorCursorAction::Up
+            | EditorCursorAction::Down
+            | EditorCursorAction::Top
+            | EditorCursorAction::Bottom => OperatorRange::linewise(start.y, end.y),
+            EditorCursorAction::LineEnd => {
+                let end_x = (end.x + 1).min(self.get_line_length(end.y));
+                OperatorRange::charwise(start, UVec2::new(end_x, end.y))
+            }
+            EditorCursorAction::NextWord if end.y > start.y => {
+                let end_x = self.get_line_length(start.y);
+                OperatorRange::charwise(start, UVec2::new(end_x, start.y))
+            }
+            _ => OperatorRange::charwise(start, end),
+        }
+    }
+
+    // start から count 個目の単語の直後の位置 (2個目以降の単語の前の空白と改行は飛ばす)
+    fn get_word_end(&self, start: UVec2, count: usize) -> UVec2 {
+        let mut pos = start;
+
+        for i in 0..count {
+            let mut line: Vec<char> = self.get_line(pos.y).chars().collect();
+
+            if i > 0 {
+                loop {
+                    while pos.x < line.len() && line[pos.x].is_whitespace() {
+                        pos.x += 1;
+                    }
+                    if pos.x < line.len() || pos.y + 1 >= self.get_line_count() {
+                        break;
+                    }
+
+                    pos = UVec2::new(0, pos.y + 1);
+                    line = self.get_line(pos.y).chars().collect();
+                }
+            }
+
+            while pos.x < line.len() && !line[pos.x].is_whitespace() {
+                pos.x += 1;
+            }
+        }
+
+        pos
+    }
+
+    pub fn get_line_range(&self, count: usize) -> OperatorRange {
+        let end_y = self.clamp_y(self.cursor.y + count.max(1) - 1);
+        OperatorRange::linewise(self.cursor.y, end_y)
+    }
+
+    pub fn get_range_text(&self, range: &OperatorRange) -> String {
+        match range.kind {
+            RegisterKind::Linewise => self.get_lines(range.start.y, range.end.y + 1).join("\n"),
+            RegisterKind::Charwise => {
+                self.get_text(self.pos_to_index(range.start), self.pos_to_index(range.end))
+            }
+            RegisterKind::Blockwise => self.get_block_text(range),
+        }
+    }
+
+    fn delete_range_text(&mut self, range: &OperatorRange) {
+        match range.kind {
+            RegisterKind::Linewise => {
+                for y in (range.start.y..=range.end.y).rev() {
+                    self.delete_line(y);
+                }
+            }
+            RegisterKind::Charwise => {
+                self.delete_range(self.pos_to_index(range.start), self.pos_to_index(range.end))
+            }
+            RegisterKind::Blockwise => self.replace_block_text(range, ""),
+        }
+    }
+
+    fn replace_range_text(&mut self, range: &OperatorRange, text: &str) {
+        let (start, end) = match range.kind {
+            RegisterKind::Linewise => (
+                UVec2::new(0, range.start.y),
+                UVec2::new(self.get_line_length(range.end.y), range.end.y),
+            ),
+            RegisterKind::Charwise => (range.start, range.end),
+            RegisterKind::Blockwise => return self.replace_block_text(range, text),
+        };
+
+        self.delete_range(self.pos_to_index(start), self.pos_to_index(end));
+        self.insert_text(start.x, start.y, text);
+    }
+
+    fn indent_lines(&mut self, range: &OperatorRange, right: bool) {
+        for y in range.start.y..=range.end.y {
+            let line = self.get_line(y);
+
+            if right {
+                if !line.is_empty() {
+                    self.insert_text(0, y, INDENT);
+                }
+            } else {
+                let width = if line.starts_with('\t') {
+                    1
+                } else {
+                    line.chars()
+                        .take(INDENT.len())
+                        .take_while(|c| *c == ' ')
+                        .count()
+                };
+                self.delete_text(0, y, width);
+            }
+        }
+    }
+
+    // オペレーターを適用し、次に遷移するモードを返す
+    pub fn apply_operator(
+        &mut self,
+        operator: EditorOperator,
+        range: OperatorRange,
+        registers: &mut EditorRegisters,
+        window_size: UVec2,
+    ) -> EditorMode {
+        let mode = EditorMode::Normal;
+        let text = self.get_range_text(&range);
+        let start = self.get_range_start(&range);
+
+        match operator {
+            EditorOperator::Yank => {
+                registers.yank(Register::new(text, range.kind));
+                self.cursor = match range.kind {
+                    RegisterKind::Linewise => UVec2::new(self.cursor.x, range.start.y),
+                    _ => start,
+                };
+            }
+            EditorOperator::Delete => {
+                registers.delete(Register::new(text, range.kind));
+                self.delete_range_text(&range);
+                self.cursor = start;
+            }
+            EditorOperator::Change => {
+                registers.delete(Register::new(text, range.kind));
+                self.replace_range_text(&range, "");
+                self.cursor = start;
+                self.sync_y();
+                self.sync_scroll_y(&mode, window_size);
+                return EditorMode::Insert { append: false };
+            }
+            EditorOperator::IndentRight | EditorOperator::IndentLeft => {
+                self.indent_lines(&range, operator == EditorOperator::IndentRight);
+                self.cursor = UVec2::new(0, range.start.y);
+            }
+            EditorOperator::Uppercase => {
+                self.replace_range_text(&range, &text.to_uppercase());
+                self.cursor = start;
+            }
+            EditorOperator::Lowercase => {
+                self.replace_range_text(&range, &text.to_lowercase());
+                self.cursor = start;
+            }
+        }
+
+        self.sync(&mode);
+        self.sync_scroll_y(&mode, window_size);
+        mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use crate::{
+        editor::{
+            action::{EditorCursorAction, EditorOperator},
+            buffer::EditorBuffer,
+            view::EditorView,
+        },
+        utils::vec2::UVec2,
+    };
+
+    fn view(text: &str, cursor: UVec2) -> EditorView {
+        let mut buffer = EditorBuffer::new();
+        buffer.insert_text(0, 0, text);
+        buffer.set_last_cursor(cursor);
+        EditorView::new(Arc::new(RwLock::new(buffer)))
+    }
+
+    fn word_range(operator: EditorOperator, text: &str, x: usize, count: usize) -> String {
+        let mut view = view(text, UVec2::new(x, 0));
+        let range = view.get_motion_range(
+            operator,
+            EditorCursorAction::NextWord,
+            Some(count),
+            UVec2::new(80, 24),
+        );
+        view.get_range_text(&range)
+    }
+
+    #[test]
+    fn change_word_keeps_trailing_whitespace() {
+        assert_eq!(word_range(EditorOperator::Change, "foo   bar", 0, 1), "foo");
+        assert_eq!(word_range(EditorOperator::Change, "foo   bar", 1, 1), "oo");
+        assert_eq!(
+            word_range(EditorOperator::Change, "foo   bar baz", 0, 2),
+            "foo   bar"
+        );
+    }
+
+    #[test]
+    fn change_word_with_count_crosses_lines() {
+        assert_eq!(
+            word_range(EditorOperator::Change, "foo\n  bar", 0, 2),
+            "foo\n  bar"
+        );
+    }
+
+    #[test]
+    fn change_word_on_whitespace_uses_the_motion() {
+        assert_eq!(
+            word_range(EditorOperator::Change, "foo   bar", 3, 1),
+            word_range(EditorOperator::Delete, "foo   bar", 3, 1)
+        );
+    }
+}
@@ -5,9 +5,9 @@ use crate::{
     utils::vec2::{IVec2, UVec2},
 };
 
-use super::EditorBuffer;
+use super::EditorView;
 
-impl EditorBuffer {
+impl EditorView {
     pub fn get_offset(&self) -> UVec2 {
         self.scroll
     }
@@ -60,9 +60,11 @@ impl EditorBuffer {
 
     pub fn sync_scroll_y(&mut self, mode: &EditorMode, window_size: UVec2) {
         let cursor = self.get_position(mode);
+        // 最下行はステータスラインなので除く (小さく分割したウィンドウでも1行は表示する)
+        let height = window_size.y.saturating_sub(1).max(1);
 
-        if cursor.y >= self.scroll.y + window_size.y - 1 {
-            self.scroll_to_y(cursor.y - (window_size.y - 1));
+        if cursor.y >= self.scroll.y + height {
+            self.scroll_to_y(cursor.y + 1 - height);
         } else if cursor.y < self.scroll.y {
             self.scroll_to_y(cursor.y);
         }
@@ -0,0 +1,120 @@
+use regex::Regex;
+
+use crate::utils::vec2::UVec2;
+
+use super::EditorView;
+
+impl EditorView {
+    // 行内の一致箇所 (文字単位の半開区間)
+    fn find_in_line(&self, regex: &Regex, y: usize) -> Vec<(usize, usize)> {
+        let line = self.get_line(y);
+        let offsets: Vec<usize> = line.char_indices().map(|(byte, _)| byte).collect();
+        let to_x = |byte: usize| offsets.partition_point(|offset| *offset < byte);
+
+        regex
+            .find_iter(&line)
+            .map(|m| (to_x(m.start()), to_x(m.end())))
+            .collect()
+    }
+
+    pub fn get_search_matches(
+        &self,
+        regex: &Regex,
+        start_y: usize,
+        end_y: usize,
+    ) -> Vec<(UVec2, UVec2)> {
+        (start_y..end_y.min(self.get_line_count()))
+            .flat_map(|y| {
+                self.find_in_line(regex, y)
+                    .into_iter()
+                    .map(move |(start, end)| (UVec2::new(start, y), UVec2::new(end, y)))
+            })
+            .collect()
+    }
+
+    // from の次 (backward なら前) の一致を探す。ファイルの端で折り返した場合は true も返す
+    pub fn find_match(&self, regex: &Regex, from: UVec2, backward: bool) -> Option<(UVec2, bool)> {
+        let line_count = self.get_line_count();
+
+        for i in 0..=line_count {
+            let (y, wrapped) = if backward {
+                ((from.y + line_count - i) % line_count, i > from.y)
+            } else {
+                ((from.y + i) % line_count, from.y + i >= line_count)
+            };
+
+            let mut matches = self.find_in_line(regex, y).into_iter().map(|(x, _)| x);
+            let x = match (backward, i) {
+                (false, 0) => matches.find(|x| *x > from.x),
+                (false, i) if i == line_count => matches.find(|x| *x <= from.x),
+                (false, _) => matches.next(),
+                (true, 0) => matches.rfind(|x| *x < from.x),
+                (true, i) if i == line_count => matches.rfind(|x| *x >= from.x),
+                (true, _) => matches.next_back(),
+            };
+
+            if let Some(x) = x {
+                return Some((UVec2::new(x, y), wrapped));
+            }
+        }
+
+        None
+    }
+
+    // start_y..=end_y の各行を置換し、(置換した数, 置換した行数) を返す
+    pub fn substitute(
+        &mut self,
+        regex: &Regex,
+        replacement: &str,
+        global: bool,
+        start_y: usize,
+        end_y: usize,
+    ) -> (usize, usize) {
+        let mut count = 0;
+        let mut lines = 0;
+        let mut last_y = None;
+
+        // 置換で行が増減しても影響しないよう下の行から処理する
+        for y in (start_y..=end_y).rev() {
+            let line = self.get_line(y);
+            let matches = regex.find_iter(&line).count();
+            if matches == 0 {
+                continue;
+            }
+
+            let replaced = if global {
+                regex.replace_all(&line, replacement)
+            } else {
+                regex.replace(&line, replacement)
+            };
+
+            if replaced != line {
+                self.delete_text(0, y, line.chars().count());
+                self.insert_text(0, y, &replaced);
+            }
+
+            count += if global { matches } else { 1 };
+            lines += 1;
+            last_y.get_or_insert(y);
+        }
+
+        if let Some(y) = last_y {
+            self.cursor = UVec2::new(0, y);
+        }
+
+        (count, lines)
+    }
+
+    // :g で対象にする行 (matching が false なら一致しない行)
+    pub fn get_global_lines(
+        &self,
+        regex: &Regex,
+        start_y: usize,
+        end_y: usize,
+        matching: bool,
+    ) -> Vec<usize> {
+        (start_y..=end_y)
+            .filter(|y| regex.is_match(&self.get_line(*y)) == matching)
+            .collect()
+    }
+}
@@ -0,0 +1,267 @@
+use regex::Regex;
+
+use crate::{
+    editor::{action::EditorTextObject, mode::EditorMode},
+    utils::vec2::UVec2,
+};
+
+use super::{operator::OperatorRange, EditorView};
+
+#[derive(PartialEq)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Whitespace
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+impl EditorView {
+    pub fn get_text_object_range(
+        &self,
+        object: EditorTextObject,
+        inner: bool,
+        mode: &EditorMode,
+    ) -> Option<OperatorRange> {
+        let cursor = self.get_position(mode);
+
+        match object {
+            EditorTextObject::Word => self.get_word_range(cursor, inner),
+            EditorTextObject::Quote(quote) => self.get_quote_range(cursor, quote, inner),
+            EditorTextObject::Bracket(open, close) => {
+                self.get_bracket_range(cursor, open, close, inner)
+            }
+            EditorTextObject::Tag => self.get_tag_range(cursor, inner),
+            EditorTextObject::Paragraph => Some(self.get_paragraph_range(cursor, inner)),
+        }
+    }
+
+    fn get_word_range(&self, cursor: UVec2, inner: bool) -> Option<OperatorRange> {
+        let line: Vec<char> = self.get_line(cursor.y).chars().collect();
+        if line.is_empty() {
+            return None;
+        }
+
+        let x = cursor.x.min(line.len() - 1);
+        let class = char_class(line[x]);
+
+        let mut start = x;
+        while start > 0 && char_class(line[start - 1]) == class {
+            start -= 1;
+        }
+
+        let mut end = x + 1;
+        while end < line.len() && char_class(line[end]) == class {
+            end += 1;
+        }
+
+        if !inner {
+            if class == CharClass::Whitespace {
+                if end < line.len() {
+                    let next = char_class(line[end]);
+                    while end < line.len() && char_class(line[end]) == next {
+                        end += 1;
+                    }
+                }
+            } else {
+                let word_end = end;
+                while end < line.len() && char_class(line[end]) == CharClass::Whitespace {
+                    end += 1;
+                }
+
+                if end == word_end {
+                    while start > 0 && char_class(line[start - 1]) == CharClass::Whitespace {
+                        start -= 1;
+                    }
+                }
+            }
+        }
+
+        Some(OperatorRange::charwise(
+            UVec2::new(start, cursor.y),
+            UVec2::new(end, cursor.y),
+        ))
+    }
+
+    fn get_quote_range(&self, cursor: UVec2, quote: char, inner: bool) -> Option<OperatorRange> {
+        let line: Vec<char> = self.get_line(cursor.y).chars().collect();
+        let quotes: Vec<usize> = line
+            .iter()
+            .enumerate()
+            .filter(|(x, c)| **c == quote && (*x == 0 || line[x - 1] != '\\'))
+            .map(|(x, _)| x)
+            .collect();
+
+        let (open, close) = quotes
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .find(|(open, close)| *open <= cursor.x && cursor.x <= *close)
+            .or_else(|| {
+                quotes
+                    .chunks_exact(2)
+                    .map(|pair| (pair[0], pair[1]))
+                    .find(|(open, _)| *open > cursor.x)
+            })?;
+
+        let (start, end) = if inner {
+            (open + 1, close)
+        } else {
+            (open, close + 1)
+        };
+
+        Some(OperatorRange::charwise(
+            UVec2::new(start, cursor.y),
+            UVec2::new(end, cursor.y),
+        ))
+    }
+
+    fn get_bracket_range(
+        &self,
+        cursor: UVec2,
+        open: char,
+        close: char,
+        inner: bool,
+    ) -> Option<OperatorRange> {
+        let text: Vec<char> = self.to_string().chars().collect();
+        let index = self.pos_to_index(cursor).min(text.len().checked_sub(1)?);
+
+        let mut depth = 0;
+        let mut open_index = None;
+        let mut i = if text[index] == close {
+            index.checked_sub(1)
+        } else {
+            Some(index)
+        };
+
+        while let Some(j) = i {
+            if text[j] == close {
+                depth += 1;
+            } else if text[j] == open {
+                if depth == 0 {
+                    open_index = Some(j);
+                    break;
+                }
+                depth -= 1;
+            }
+            i = j.checked_sub(1);
+        }
+
+        let open_index = open_index?;
+        let mut depth = 0;
+        let mut close_index = None;
+
+        for (j, c) in text.iter().enumerate().skip(open_index + 1) {
+            if *c == open {
+                depth += 1;
+            } else if *c == close {
+                if depth == 0 {
+                    close_index = Some(j);
+                    break;
+                }
+                depth -= 1;
+            }
+        }
+
+        let close_index = close_index?;
+        let (start, end) = if inner {
+            (open_index + 1, close_index)
+        } else {
+            (open_index, close_index + 1)
+        };
+
+        Some(OperatorRange::charwise(
+            self.index_to_pos(start),
+            self.index_to_pos(end),
+        ))
+    }
+
+    fn get_tag_range(&self, cursor: UVec2, inner: bool) -> Option<OperatorRange> {
+        let text = self.to_string();
+        let index = self.pos_to_index(cursor);
+        let tag_regex = Regex::new(r"<(/)?([a-zA-Z][^\s/>]*)[^>]*?(/)?>").unwrap();
+
+        let offsets: Vec<usize> = text.char_indices().map(|(byte, _)| byte).collect();
+        let to_index = |byte: usize| offsets.partition_point(|offset| *offset < byte);
+
+        // (開始タグの開始, 開始タグの終了, 終了タグの開始, 終了タグの終了)
+        let mut pairs: Vec<(usize, usize, usize, usize)> = vec![];
+        let mut stack: Vec<(String, usize, usize)> = vec![];
+
+        for cap in tag_regex.captures_iter(&text) {
+            let whole = cap.get(0).unwrap();
+            let name = cap[2].to_string();
+
+            if cap.get(3).is_some() {
+                continue;
+            }
+
+            if cap.get(1).is_none() {
+                stack.push((name, to_index(whole.start()), to_index(whole.end())));
+            } else if let Some(position) = stack.iter().rposition(|(open, ..)| *open == name) {
+                let (_, open_start, open_end) = stack[position].clone();
+                stack.truncate(position);
+                pairs.push((
+                    open_start,
+                    open_end,
+                    to_index(whole.start()),
+                    to_index(whole.end()),
+                ));
+            }
+        }
+
+        let (open_start, open_end, close_start, close_end) = pairs
+            .into_iter()
+            .filter(|(open_start, _, _, close_end)| *open_start <= index && index < *close_end)
+            .min_by_key(|(open_start, _, _, close_end)| close_end - open_start)?;
+
+        let (start, end) = if inner {
+            (open_end, close_start)
+        } else {
+            (open_start, close_end)
+        };
+
+        Some(OperatorRange::charwise(
+            self.index_to_pos(start),
+            self.index_to_pos(end),
+        ))
+    }
+
+    fn get_paragraph_range(&self, cursor: UVec2, inner: bool) -> OperatorRange {
+        let is_blank = |y: usize| self.get_line(y).trim().is_empty();
+        let blank = is_blank(cursor.y);
+        let last = self.get_line_count() - 1;
+
+        let mut start = cursor.y;
+        while start > 0 && is_blank(start - 1) == blank {
+            start -= 1;
+        }
+
+        let mut end = cursor.y;
+        while end < last && is_blank(end + 1) == blank {
+            end += 1;
+        }
+
+        if !inner {
+            let paragraph_end = end;
+            while end < last && is_blank(end + 1) != blank {
+                end += 1;
+            }
+
+            if !blank && end == paragraph_end {
+                while start > 0 && is_blank(start - 1) {
+                    start -= 1;
+                }
+            }
+        }
+
+        OperatorRange::linewise(start, end)
+    }
+}
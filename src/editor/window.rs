@@ -0,0 +1,298 @@
+use crate::utils::{rect::Rect, vec2::UVec2};
+
+use super::view::EditorView;
+
+// Horizontal は上下 (:sp)、Vertical は左右 (:vs) に並べる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+pub struct EditorWindow {
+    id: usize,
+    view: EditorView,
+}
+
+impl EditorWindow {
+    pub fn new(id: usize, view: EditorView) -> Self {
+        Self { id, view }
+    }
+
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+
+    pub fn get_view(&self) -> &EditorView {
+        &self.view
+    }
+
+    pub fn get_view_mut(&mut self) -> &mut EditorView {
+        &mut self.view
+    }
+
+    pub fn set_view(&mut self, view: EditorView) {
+        self.view = view;
+    }
+}
+
+// ウィンドウの分割 (sizes は各子の行数または列数で、画面サイズが変わったときは比率として扱う)
+pub enum EditorLayout {
+    Window(EditorWindow),
+    Split {
+        direction: SplitDirection,
+        children: Vec<EditorLayout>,
+        sizes: Vec<usize>,
+    },
+}
+
+impl EditorLayout {
+    fn is_window(&self, id: usize) -> bool {
+        matches!(self, EditorLayout::Window(window) if window.id == id)
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        match self {
+            EditorLayout::Window(window) => window.id == id,
+            EditorLayout::Split { children, .. } => children.iter().any(|child| child.contains(id)),
+        }
+    }
+
+    pub fn get_window(&self, id: usize) -> Option<&EditorWindow> {
+        match self {
+            EditorLayout::Window(window) if window.id == id => Some(window),
+            EditorLayout::Window(_) => None,
+            EditorLayout::Split { children, .. } => {
+                children.iter().find_map(|child| child.get_window(id))
+            }
+        }
+    }
+
+    pub fn get_window_mut(&mut self, id: usize) -> Option<&mut EditorWindow> {
+        match self {
+            EditorLayout::Window(window) if window.id == id => Some(window),
+            EditorLayout::Window(_) => None,
+            EditorLayout::Split { children, .. } => children
+                .iter_mut()
+                .find_map(|child| child.get_window_mut(id)),
+        }
+    }
+
+    pub fn view(&self, id: usize) -> &EditorView {
+        self.get_window(id).expect("window not found").get_view()
+    }
+
+    pub fn view_mut(&mut self, id: usize) -> &mut EditorView {
+        self.get_window_mut(id)
+            .expect("window not found")
+            .get_view_mut()
+    }
+
+    pub fn get_windows(&self) -> Vec<&EditorWindow> {
+        match self {
+            EditorLayout::Window(window) => vec![window],
+            EditorLayout::Split { children, .. } => children
+                .iter()
+                .flat_map(|child| child.get_windows())
+                .collect(),
+        }
+    }
+
+    pub fn get_windows_mut(&mut self) -> Vec<&mut EditorWindow> {
+        match self {
+            EditorLayout::Window(window) => vec![window],
+            EditorLayout::Split { children, .. } => children
+                .iter_mut()
+                .flat_map(|child| child.get_windows_mut())
+                .collect(),
+        }
+    }
+
+    // id のウィンドウを分割し、新しいウィンドウを上 (左) に置く。size は分割前のウィンドウの大きさ
+    pub fn split(
+        &mut self,
+        id: usize,
+        direction: SplitDirection,
+        window: EditorWindow,
+        size: usize,
+    ) {
+        self.split_window(id, direction, &mut Some(window), size);
+    }
+
+    fn split_window(
+        &mut self,
+        id: usize,
+        direction: SplitDirection,
+        window: &mut Option<EditorWindow>,
+        size: usize,
+    ) -> bool {
+        match self {
+            EditorLayout::Window(target) if target.id == id => {
+                let half = size / 2;
+                let old = std::mem::replace(
+                    self,
+                    EditorLayout::Split {
+                        direction,
+                        children: vec![],
+                        sizes: vec![],
+                    },
+                );
+
+                *self = EditorLayout::Split {
+                    direction,
+                    children: vec![EditorLayout::Window(window.take().unwrap()), old],
+                    sizes: vec![half.max(1), (size - half).max(1)],
+                };
+                true
+            }
+            EditorLayout::Window(_) => false,
+            EditorLayout::Split {
+                direction: split_direction,
+                children,
+                sizes,
+            } => {
+                // 同じ向きの分割の中ならその場に並べる
+                if *split_direction == direction {
+                    if let Some(i) = children.iter().position(|child| child.is_window(id)) {
+                        let half = (sizes[i] / 2).max(1);
+                        sizes[i] = sizes[i].saturating_sub(half).max(1);
+                        sizes.insert(i, half);
+                        children.insert(i, EditorLayout::Window(window.take().unwrap()));
+                        return true;
+                    }
+                }
+
+                children
+                    .iter_mut()
+                    .any(|child| child.split_window(id, direction, window, size))
+            }
+        }
+    }
+
+    // 閉じたウィンドウの領域は隣のウィンドウに渡す。最後の1つは閉じない
+    pub fn close(&mut self, id: usize) -> Option<EditorWindow> {
+        let EditorLayout::Split {
+            children, sizes, ..
+        } = self
+        else {
+            return None;
+        };
+
+        let Some(i) = children.iter().position(|child| child.is_window(id)) else {
+            return children.iter_mut().find_map(|child| child.close(id));
+        };
+
+        let EditorLayout::Window(window) = children.remove(i) else {
+            unreachable!()
+        };
+        let size = sizes.remove(i);
+        let neighbor = i.min(sizes.len() - 1);
+        sizes[neighbor] += size;
+
+        if children.len() == 1 {
+            *self = children.pop().unwrap();
+        }
+
+        Some(window)
+    }
+
+    // id を含む direction 向きの分割のうち最も内側で大きさを変える (隣のウィンドウと増減を相殺する)
+    pub fn resize(&mut self, id: usize, direction: SplitDirection, delta: isize) -> bool {
+        let EditorLayout::Split {
+            direction: split_direction,
+            children,
+            sizes,
+        } = self
+        else {
+            return false;
+        };
+
+        let Some(i) = children.iter().position(|child| child.contains(id)) else {
+            return false;
+        };
+
+        if children[i].resize(id, direction, delta) {
+            return true;
+        }
+        if *split_direction != direction {
+            return false;
+        }
+
+        let neighbor = if i + 1 < children.len() { i + 1 } else { i - 1 };
+        if delta >= 0 {
+            let amount = (delta as usize).min(sizes[neighbor].saturating_sub(1));
+            sizes[i] += amount;
+            sizes[neighbor] -= amount;
+        } else {
+            let amount = ((-delta) as usize).min(sizes[i].saturating_sub(1));
+            sizes[i] -= amount;
+            sizes[neighbor] += amount;
+        }
+
+        true
+    }
+
+    // すべてのウィンドウを同じ大きさにする
+    pub fn equalize(&mut self) {
+        if let EditorLayout::Split {
+            children, sizes, ..
+        } = self
+        {
+            let total: usize = sizes.iter().sum();
+            let len = sizes.len();
+
+            for (i, size) in sizes.iter_mut().enumerate() {
+                *size = total / len + usize::from(i < total % len);
+            }
+            children.iter_mut().for_each(|child| child.equalize());
+        }
+    }
+
+    // 各ウィンドウの領域 (左右の分割では間に1列の区切りを入れる)
+    pub fn get_rects(&self, rect: Rect) -> Vec<(usize, Rect)> {
+        let (direction, children, sizes) = match self {
+            EditorLayout::Window(window) => return vec![(window.id, rect)],
+            EditorLayout::Split {
+                direction,
+                children,
+                sizes,
+            } => (direction, children, sizes),
+        };
+
+        let total = sizes.iter().sum::<usize>().max(1);
+        let (length, separator) = match direction {
+            SplitDirection::Horizontal => (rect.size.y, 0),
+            SplitDirection::Vertical => (rect.size.x, 1),
+        };
+        let available = length.saturating_sub(separator * (children.len() - 1));
+
+        let mut rects = vec![];
+        let mut offset = 0;
+        let mut used = 0;
+
+        for (i, child) in children.iter().enumerate() {
+            let size = if i + 1 == children.len() {
+                available.saturating_sub(used)
+            } else {
+                available * sizes[i] / total
+            };
+
+            let child_rect = match direction {
+                SplitDirection::Horizontal => Rect::new(
+                    UVec2::new(rect.pos.x, rect.pos.y + offset),
+                    UVec2::new(rect.size.x, size),
+                ),
+                SplitDirection::Vertical => Rect::new(
+                    UVec2::new(rect.pos.x + offset, rect.pos.y),
+                    UVec2::new(size, rect.size.y),
+                ),
+            };
+
+            rects.extend(child.get_rects(child_rect));
+            offset += size + separator;
+            used += size;
+        }
+
+        rects
+    }
+}
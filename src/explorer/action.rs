@@ -0,0 +1,26 @@
+use crate::action::AppAction;
+
+#[derive(Debug, Clone, Hash)]
+pub enum ExplorerAction {
+    Toggle,  // サイドバーの表示・非表示
+    Focus,   // サイドバーに移動する (非表示なら開く)
+    Unfocus, // エディタに戻る
+    MoveUp,
+    MoveDown,
+    MoveToTop,
+    MoveToBottom,
+    Open,     // ファイルを開く、またはディレクトリを開閉する
+    Expand,   // ディレクトリを開く (ファイルなら開く)
+    Collapse, // ディレクトリを閉じる (閉じていれば親ディレクトリに移動する)
+    Create,
+    Rename,
+    Move,
+    Delete,
+    Refresh,
+}
+
+impl ExplorerAction {
+    pub fn into_app(self) -> AppAction {
+        AppAction::ExplorerAction(self)
+    }
+}
@@ -0,0 +1,813 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+
+use crate::utils::{
+    command::CommandManager,
+    key_binding::{Key, KeyConfig, KeyConfigType},
+    vec2::IVec2,
+};
+
+use super::{
+    action::ExplorerAction,
+    tree::{read_tree, ExplorerEntry},
+};
+
+const DEFAULT_WIDTH: usize = 30;
+
+pub enum ExplorerPromptKind {
+    Create { dir: PathBuf },
+    Rename { path: PathBuf },
+    Move { path: PathBuf },
+    Delete { path: PathBuf },
+}
+
+// 下のコマンドラインに表示する入力欄 (削除は y で確定する)
+pub struct ExplorerPrompt {
+    pub kind: ExplorerPromptKind,
+    pub input: String,
+}
+
+impl ExplorerPrompt {
+    pub fn get_message(&self, root: &Path) -> String {
+        match &self.kind {
+            ExplorerPromptKind::Create { .. } => "New file (end with / for a directory): ".into(),
+            ExplorerPromptKind::Rename { .. } => "Rename to: ".into(),
+            ExplorerPromptKind::Move { .. } => "Move to: ".into(),
+            ExplorerPromptKind::Delete { path } => format!(
+                "Delete {}? (y/n): ",
+                path.strip_prefix(root).unwrap_or(path).display()
+            ),
+        }
+    }
+}
+
+// エクスプローラーでの操作の結果、エディタに反映するもの
+pub enum ExplorerEvent {
+    Open(PathBuf),
+    Moved { from: PathBuf, to: PathBuf },
+    Deleted(PathBuf),
+}
+
+pub struct FileExplorer {
+    root: PathBuf,
+    entries: Vec<ExplorerEntry>,
+    expanded: HashSet<PathBuf>,
+    selected: usize,
+    scroll: usize,
+    width: usize,
+    is_visible: bool,
+    is_focused: bool,
+    prompt: Option<ExplorerPrompt>,
+}
+
+impl FileExplorer {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            entries: vec![],
+            expanded: HashSet::new(),
+            selected: 0,
+            scroll: 0,
+            width: DEFAULT_WIDTH,
+            is_visible: false,
+            is_focused: false,
+            prompt: None,
+        }
+    }
+
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn get_entries(&self) -> &[ExplorerEntry] {
+        &self.entries
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn get_scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn get_prompt(&self) -> Option<&ExplorerPrompt> {
+        self.prompt.as_ref()
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.is_visible
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.is_visible && self.is_focused
+    }
+
+    pub fn set_focused(&mut self, is_focused: bool) {
+        self.is_focused = is_focused;
+    }
+
+    // 区切り線を含めた幅 (非表示なら 0)
+    pub fn get_width(&self) -> usize {
+        if self.is_visible {
+            self.width + 1
+        } else {
+            0
+        }
+    }
+
+    pub fn get_panel_width(&self) -> usize {
+        self.width
+    }
+
+    fn get_selected_entry(&self) -> Option<&ExplorerEntry> {
+        self.entries.get(self.selected)
+    }
+
+    // ツリーを読み直し、選択していたパスを選択し直す
+    pub fn refresh(&mut self) -> anyhow::Result<()> {
+        let selected = self.get_selected_entry().map(|entry| entry.path.clone());
+        self.expanded.retain(|path| path.is_dir());
+        self.entries = read_tree(&self.root, &self.expanded)?;
+
+        match selected {
+            Some(path) => self.select_path(&path),
+            None => self.selected = 0,
+        }
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        Ok(())
+    }
+
+    fn select_path(&mut self, path: &Path) {
+        if let Some(index) = self.entries.iter().position(|entry| entry.path == path) {
+            self.selected = index;
+        }
+    }
+
+    // 選択している項目が見える位置までスクロールする (height はツリーを表示する行数)
+    fn sync_scroll(&mut self, height: usize) {
+        let height = height.max(1);
+
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+
+    fn move_by(&mut self, offset: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(offset).min(last);
+    }
+
+    pub fn on_action(
+        &mut self,
+        action: ExplorerAction,
+        count: Option<usize>,
+        height: usize,
+    ) -> anyhow::Result<Option<ExplorerEvent>> {
+        let n = count.unwrap_or(1) as isize;
+        let mut event = None;
+
+        match action {
+            ExplorerAction::Toggle => {
+                self.is_visible = !self.is_visible;
+                self.is_focused = self.is_visible;
+                if self.is_visible {
+                    self.refresh()?;
+                }
+            }
+            ExplorerAction::Focus => {
+                if !self.is_visible {
+                    self.is_visible = true;
+                    self.refresh()?;
+                }
+                self.is_focused = true;
+            }
+            ExplorerAction::Unfocus => self.is_focused = false,
+            ExplorerAction::MoveUp => self.move_by(-n),
+            ExplorerAction::MoveDown => self.move_by(n),
+            ExplorerAction::MoveToTop => self.selected = 0,
+            ExplorerAction::MoveToBottom => self.selected = self.entries.len().saturating_sub(1),
+            ExplorerAction::Open => event = self.open(true)?,
+            ExplorerAction::Expand => event = self.open(false)?,
+            ExplorerAction::Collapse => self.collapse()?,
+            ExplorerAction::Create => self.start_create(),
+            ExplorerAction::Rename => self.start_rename(),
+            ExplorerAction::Move => self.start_move(),
+            ExplorerAction::Delete => self.start_delete(),
+            ExplorerAction::Refresh => self.refresh()?,
+        }
+
+        self.sync_scroll(height);
+        Ok(event)
+    }
+
+    // ディレクトリは開閉し (toggle が false なら開くだけ)、ファイルはエディタで開く
+    fn open(&mut self, toggle: bool) -> anyhow::Result<Option<ExplorerEvent>> {
+        let Some(entry) = self.get_selected_entry() else {
+            return Ok(None);
+        };
+
+        let path = entry.path.clone();
+        if !entry.is_dir {
+            self.is_focused = false;
+            return Ok(Some(ExplorerEvent::Open(path)));
+        }
+
+        if entry.is_expanded && toggle {
+            self.expanded.remove(&path);
+        } else {
+            self.expanded.insert(path);
+        }
+
+        self.refresh()?;
+        Ok(None)
+    }
+
+    fn collapse(&mut self) -> anyhow::Result<()> {
+        let Some(entry) = self.get_selected_entry() else {
+            return Ok(());
+        };
+
+        if entry.is_expanded {
+            let path = entry.path.clone();
+            self.expanded.remove(&path);
+            return self.refresh();
+        }
+
+        if let Some(parent) = entry.path.parent().map(|parent| parent.to_path_buf()) {
+            self.select_path(&parent);
+        }
+
+        Ok(())
+    }
+
+    pub fn on_click(&mut self, y: usize) -> anyhow::Result<Option<ExplorerEvent>> {
+        self.is_focused = true;
+
+        // 最上行はルートのディレクトリ名
+        let Some(row) = y.checked_sub(1) else {
+            return Ok(None);
+        };
+        let index = self.scroll + row;
+        if index >= self.entries.len() {
+            return Ok(None);
+        }
+
+        self.selected = index;
+        self.open(true)
+    }
+
+    pub fn on_scroll(&mut self, offset: IVec2, height: usize) {
+        let max = self.entries.len().saturating_sub(height.max(1));
+        self.scroll = self.scroll.saturating_add_signed(offset.y).min(max);
+        self.selected = self
+            .selected
+            .clamp(self.scroll, self.scroll + height.max(1) - 1)
+            .min(self.entries.len().saturating_sub(1));
+    }
+
+    // 選択しているディレクトリの中 (ファイルなら同じディレクトリ) に作る
+    fn start_create(&mut self) {
+        let dir = match self.get_selected_entry() {
+            Some(entry) if entry.is_dir => entry.path.clone(),
+            Some(entry) => entry
+                .path
+                .parent()
+                .map(|parent| parent.to_path_buf())
+                .unwrap_or_else(|| self.root.clone()),
+            None => self.root.clone(),
+        };
+
+        self.prompt = Some(ExplorerPrompt {
+            kind: ExplorerPromptKind::Create { dir },
+            input: String::new(),
+        });
+    }
+
+    fn start_rename(&mut self) {
+        let Some(entry) = self.get_selected_entry() else {
+            return;
+        };
+
+        self.prompt = Some(ExplorerPrompt {
+            kind: ExplorerPromptKind::Rename {
+                path: entry.path.clone(),
+            },
+            input: entry.name.clone(),
+        });
+    }
+
+    fn start_move(&mut self) {
+        let Some(entry) = self.get_selected_entry() else {
+            return;
+        };
+
+        let input = entry
+            .path
+            .strip_prefix(&self.root)
+            .unwrap_or(&entry.path)
+            .to_string_lossy()
+            .to_string();
+
+        self.prompt = Some(ExplorerPrompt {
+            kind: ExplorerPromptKind::Move {
+                path: entry.path.clone(),
+            },
+            input,
+        });
+    }
+
+    fn start_delete(&mut self) {
+        let Some(entry) = self.get_selected_entry() else {
+            return;
+        };
+
+        self.prompt = Some(ExplorerPrompt {
+            kind: ExplorerPromptKind::Delete {
+                path: entry.path.clone(),
+            },
+            input: String::new(),
+        });
+    }
+
+    // 入力欄を表示している間のキー入力
+    pub fn on_prompt_key(&mut self, key: Key) -> anyhow::Result<Option<ExplorerEvent>> {
+        let Some(prompt) = &mut self.prompt else {
+            return Ok(None);
+        };
+
+        if let ExplorerPromptKind::Delete { .. } = prompt.kind {
+            let prompt = self.prompt.take().unwrap();
+            if let (Key::Char('y' | 'Y'), ExplorerPromptKind::Delete { path }) = (key, prompt.kind)
+            {
+                return self.delete(path);
+            }
+            return Ok(None);
+        }
+
+        match key {
+            Key::Char('\n') => {
+                let prompt = self.prompt.take().unwrap();
+                self.submit(prompt)
+            }
+            Key::Esc | Key::Ctrl('c') => {
+                self.prompt = None;
+                Ok(None)
+            }
+            Key::Backspace => {
+                prompt.input.pop();
+                Ok(None)
+            }
+            Key::Char(c) => {
+                prompt.input.push(c);
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn submit(&mut self, prompt: ExplorerPrompt) -> anyhow::Result<Option<ExplorerEvent>> {
+        let input = prompt.input.trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        match prompt.kind {
+            ExplorerPromptKind::Create { dir } => {
+                self.create(&dir, input)?;
+                Ok(None)
+            }
+            ExplorerPromptKind::Rename { path } => {
+                let to = path
+                    .parent()
+                    .map(|parent| parent.join(input))
+                    .unwrap_or_else(|| self.root.join(input));
+                self.rename(path, to)
+            }
+            ExplorerPromptKind::Move { path } => {
+                // 既存のディレクトリ (または / で終わるパス) を指定した場合はその中に移動する
+                let mut to = self.root.join(input);
+                if input.ends_with('/') || to.is_dir() {
+                    to = to.join(path.file_name().unwrap_or_default());
+                }
+                self.rename(path, to)
+            }
+            ExplorerPromptKind::Delete { .. } => Ok(None),
+        }
+    }
+
+    fn create(&mut self, dir: &Path, name: &str) -> anyhow::Result<()> {
+        let path = dir.join(name);
+        if path.exists() {
+            return Err(anyhow!("{} already exists", name));
+        }
+
+        if name.ends_with('/') {
+            fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            File::create_new(&path)?;
+        }
+
+        self.expand_to(&path);
+        self.refresh()?;
+        self.select_path(&path);
+        Ok(())
+    }
+
+    // 上書きはしない
+    fn rename(&mut self, from: PathBuf, to: PathBuf) -> anyhow::Result<Option<ExplorerEvent>> {
+        if from == to {
+            return Ok(None);
+        }
+        if to.exists() {
+            return Err(anyhow!("{} already exists", to.display()));
+        }
+        if to.starts_with(&from) {
+            return Err(anyhow!("Cannot move a directory into itself"));
+        }
+
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&from, &to)?;
+
+        // 開いていたディレクトリは移動先でも開いておく
+        let expanded: Vec<_> = self
+            .expanded
+            .iter()
+            .filter_map(|path| path.strip_prefix(&from).ok().map(|rest| to.join(rest)))
+            .collect();
+        self.expanded.extend(expanded);
+
+        self.expand_to(&to);
+        self.refresh()?;
+        self.select_path(&to);
+        Ok(Some(ExplorerEvent::Moved { from, to }))
+    }
+
+    fn delete(&mut self, path: PathBuf) -> anyhow::Result<Option<ExplorerEvent>> {
+        if path.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+
+        self.refresh()?;
+        Ok(Some(ExplorerEvent::Deleted(path)))
+    }
+
+    // path が見えるように親ディレクトリを開く
+    fn expand_to(&mut self, path: &Path) {
+        let mut parent = path.parent();
+        while let Some(dir) = parent {
+            if !dir.starts_with(&self.root) || dir == self.root {
+                break;
+            }
+            self.expanded.insert(dir.to_path_buf());
+            parent = dir.parent();
+        }
+    }
+
+    pub fn register_keybindings(&self, key_config: &mut KeyConfig) {
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('n')],
+            ExplorerAction::Toggle.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Ctrl('n')],
+            ExplorerAction::Toggle.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('q')],
+            ExplorerAction::Toggle.into_app(),
+        );
+
+        // Focus
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Ctrl('w'), Key::Char('l')],
+            ExplorerAction::Unfocus.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Ctrl('w'), Key::Char('w')],
+            ExplorerAction::Unfocus.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Ctrl('w'), Key::Ctrl('w')],
+            ExplorerAction::Unfocus.into_app(),
+        );
+
+        // Move
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('j')],
+            ExplorerAction::MoveDown.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Down],
+            ExplorerAction::MoveDown.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('k')],
+            ExplorerAction::MoveUp.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Up],
+            ExplorerAction::MoveUp.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('g'), Key::Char('g')],
+            ExplorerAction::MoveToTop.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('G')],
+            ExplorerAction::MoveToBottom.into_app(),
+        );
+
+        // Tree
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('\n')],
+            ExplorerAction::Open.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('o')],
+            ExplorerAction::Open.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('l')],
+            ExplorerAction::Expand.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Right],
+            ExplorerAction::Expand.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('h')],
+            ExplorerAction::Collapse.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Left],
+            ExplorerAction::Collapse.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('R')],
+            ExplorerAction::Refresh.into_app(),
+        );
+
+        // File
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('a')],
+            ExplorerAction::Create.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('r')],
+            ExplorerAction::Rename.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('m')],
+            ExplorerAction::Move.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Char('d')],
+            ExplorerAction::Delete.into_app(),
+        );
+    }
+
+    pub fn register_commands(&self, cmd_manager: &mut CommandManager) {
+        cmd_manager.register("Explore", vec![ExplorerAction::Focus.into_app()]);
+        cmd_manager.register("Lexplore", vec![ExplorerAction::Toggle.into_app()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process,
+    };
+
+    use crate::utils::key_binding::Key;
+
+    use super::{ExplorerEvent, ExplorerPrompt, ExplorerPromptKind, FileExplorer};
+
+    // テストごとに空のディレクトリを作る
+    fn explorer(name: &str) -> FileExplorer {
+        let root = env::temp_dir().join(format!("tui-studio-explorer-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        FileExplorer::new(root)
+    }
+
+    fn submit(
+        explorer: &mut FileExplorer,
+        kind: ExplorerPromptKind,
+        input: &str,
+    ) -> anyhow::Result<Option<ExplorerEvent>> {
+        explorer.submit(ExplorerPrompt {
+            kind,
+            input: input.to_string(),
+        })
+    }
+
+    fn moved(event: Option<ExplorerEvent>) -> (PathBuf, PathBuf) {
+        match event {
+            Some(ExplorerEvent::Moved { from, to }) => (from, to),
+            _ => panic!("expected a Moved event"),
+        }
+    }
+
+    fn cleanup(explorer: FileExplorer) {
+        fs::remove_dir_all(explorer.get_root()).unwrap();
+    }
+
+    #[test]
+    fn create_makes_files_and_directories() {
+        let mut explorer = explorer("create");
+        let root = explorer.get_root().to_path_buf();
+
+        explorer.create(&root, "src/main.rs").unwrap();
+        explorer.create(&root, "docs/").unwrap();
+
+        assert!(root.join("src/main.rs").is_file());
+        assert!(root.join("docs").is_dir());
+        assert!(explorer.expanded.contains(&root.join("src")));
+        assert_eq!(
+            explorer.get_selected_entry().unwrap().path,
+            root.join("docs")
+        );
+
+        let err = explorer.create(&root, "src/main.rs").unwrap_err();
+        assert_eq!(err.to_string(), "src/main.rs already exists");
+        cleanup(explorer);
+    }
+
+    #[test]
+    fn rename_stays_in_the_same_directory() {
+        let mut explorer = explorer("rename");
+        let root = explorer.get_root().to_path_buf();
+        fs::create_dir(root.join("dir")).unwrap();
+        fs::write(root.join("dir/a.txt"), "a").unwrap();
+
+        let path = root.join("dir/a.txt");
+        let event = submit(&mut explorer, ExplorerPromptKind::Rename { path }, "b.txt").unwrap();
+
+        assert_eq!(
+            moved(event),
+            (root.join("dir/a.txt"), root.join("dir/b.txt"))
+        );
+        assert_eq!(fs::read_to_string(root.join("dir/b.txt")).unwrap(), "a");
+        cleanup(explorer);
+    }
+
+    #[test]
+    fn move_into_an_existing_directory() {
+        let mut explorer = explorer("move-dir");
+        let root = explorer.get_root().to_path_buf();
+        fs::create_dir(root.join("dir")).unwrap();
+        fs::write(root.join("a.txt"), "a").unwrap();
+
+        let path = root.join("a.txt");
+        let event = submit(&mut explorer, ExplorerPromptKind::Move { path }, "dir").unwrap();
+
+        assert_eq!(moved(event), (root.join("a.txt"), root.join("dir/a.txt")));
+        assert!(root.join("dir/a.txt").is_file());
+        assert!(explorer.expanded.contains(&root.join("dir")));
+        cleanup(explorer);
+    }
+
+    #[test]
+    fn move_with_trailing_slash_creates_the_directory() {
+        let mut explorer = explorer("move-slash");
+        let root = explorer.get_root().to_path_buf();
+        fs::write(root.join("a.txt"), "a").unwrap();
+
+        let path = root.join("a.txt");
+        let event = submit(&mut explorer, ExplorerPromptKind::Move { path }, "new/").unwrap();
+
+        assert_eq!(moved(event), (root.join("a.txt"), root.join("new/a.txt")));
+        assert!(root.join("new/a.txt").is_file());
+        cleanup(explorer);
+    }
+
+    #[test]
+    fn move_into_itself_is_refused() {
+        let mut explorer = explorer("move-itself");
+        let root = explorer.get_root().to_path_buf();
+        fs::create_dir_all(root.join("dir/sub")).unwrap();
+
+        let path = root.join("dir");
+        let err = submit(&mut explorer, ExplorerPromptKind::Move { path }, "dir/sub/")
+            .err()
+            .unwrap();
+
+        assert_eq!(err.to_string(), "Cannot move a directory into itself");
+        assert!(root.join("dir/sub").is_dir());
+        cleanup(explorer);
+    }
+
+    #[test]
+    fn rename_does_not_overwrite() {
+        let mut explorer = explorer("overwrite");
+        let root = explorer.get_root().to_path_buf();
+        fs::write(root.join("a.txt"), "a").unwrap();
+        fs::write(root.join("b.txt"), "b").unwrap();
+
+        let path = root.join("a.txt");
+        let err = submit(&mut explorer, ExplorerPromptKind::Rename { path }, "b.txt")
+            .err()
+            .unwrap();
+
+        assert!(err.to_string().ends_with("b.txt already exists"));
+        assert_eq!(fs::read_to_string(root.join("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(root.join("b.txt")).unwrap(), "b");
+        cleanup(explorer);
+    }
+
+    #[test]
+    fn rename_keeps_expanded_directories() {
+        let mut explorer = explorer("expanded");
+        let root = explorer.get_root().to_path_buf();
+        fs::create_dir_all(root.join("dir/sub")).unwrap();
+        fs::write(root.join("dir/sub/a.txt"), "a").unwrap();
+        explorer.expanded.insert(root.join("dir"));
+        explorer.expanded.insert(root.join("dir/sub"));
+        explorer.refresh().unwrap();
+
+        let path = root.join("dir");
+        submit(&mut explorer, ExplorerPromptKind::Rename { path }, "other").unwrap();
+
+        assert!(explorer.expanded.contains(&root.join("other")));
+        assert!(explorer.expanded.contains(&root.join("other/sub")));
+        assert!(!explorer.expanded.contains(&root.join("dir")));
+        let paths: Vec<&Path> = explorer
+            .get_entries()
+            .iter()
+            .map(|entry| entry.path.as_path())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                root.join("other").as_path(),
+                root.join("other/sub").as_path(),
+                root.join("other/sub/a.txt").as_path(),
+            ]
+        );
+        cleanup(explorer);
+    }
+
+    #[test]
+    fn delete_sends_an_event() {
+        let mut explorer = explorer("delete");
+        let root = explorer.get_root().to_path_buf();
+        fs::create_dir(root.join("dir")).unwrap();
+        fs::write(root.join("dir/a.txt"), "a").unwrap();
+        explorer.refresh().unwrap();
+
+        explorer.start_delete();
+        let event = explorer.on_prompt_key(Key::Char('y')).unwrap();
+
+        assert!(matches!(event, Some(ExplorerEvent::Deleted(path)) if path == root.join("dir")));
+        assert!(!root.join("dir").exists());
+        assert!(explorer.get_entries().is_empty());
+
+        // y 以外では削除しない
+        fs::write(root.join("b.txt"), "b").unwrap();
+        explorer.refresh().unwrap();
+        explorer.start_delete();
+        assert!(explorer.on_prompt_key(Key::Char('n')).unwrap().is_none());
+        assert!(root.join("b.txt").exists());
+        cleanup(explorer);
+    }
+}
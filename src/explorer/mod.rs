@@ -0,0 +1,5 @@
+pub mod action;
+#[allow(clippy::module_inception)]
+pub mod explorer;
+pub mod renderer;
+mod tree;
@@ -0,0 +1,145 @@
+use std::io::stdout;
+
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{Color as CrosstermColor, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{Clear, ClearType},
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::utils::{file_type::FileType, vec2::UVec2};
+
+use super::explorer::FileExplorer;
+
+const DIRECTORY_ICON: char = '\u{f07b}';
+const OPEN_DIRECTORY_ICON: char = '\u{f07c}';
+
+#[derive(Default)]
+pub struct ExplorerRenderer {}
+
+impl ExplorerRenderer {
+    // width 列に収まるように切り詰め、残りを空白で埋める
+    fn fit(&self, text: &str, width: usize) -> String {
+        let mut fitted = String::new();
+        for c in text.chars() {
+            if fitted.width() + c.to_string().width() > width {
+                break;
+            }
+            fitted.push(c);
+        }
+
+        let space = " ".repeat(width.saturating_sub(fitted.width()));
+        fitted + &space
+    }
+
+    fn render_title(&self, explorer: &FileExplorer, width: usize) {
+        let root = explorer.get_root();
+        let name = root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| root.to_string_lossy().to_string());
+
+        queue!(
+            stdout(),
+            MoveTo(0, 0),
+            SetForegroundColor(CrosstermColor::Blue),
+            Print(self.fit(&format!(" {}/", name), width)),
+            ResetColor
+        )
+        .unwrap();
+    }
+
+    fn render_entries(&self, explorer: &FileExplorer, width: usize, height: usize) {
+        let entries = explorer.get_entries();
+
+        for (draw_y, (index, entry)) in entries
+            .iter()
+            .enumerate()
+            .skip(explorer.get_scroll())
+            .take(height)
+            .enumerate()
+        {
+            let (marker, icon) = if entry.is_dir {
+                if entry.is_expanded {
+                    ('▾', OPEN_DIRECTORY_ICON)
+                } else {
+                    ('▸', DIRECTORY_ICON)
+                }
+            } else {
+                (
+                    ' ',
+                    FileType::file_name_to_type(entry.name.clone()).get_icon(),
+                )
+            };
+
+            let line = format!(
+                " {}{} {} {}",
+                "  ".repeat(entry.depth),
+                marker,
+                icon,
+                entry.name
+            );
+
+            let foreground = if entry.is_dir {
+                CrosstermColor::Blue
+            } else {
+                CrosstermColor::Reset
+            };
+            let background = match (index == explorer.get_selected(), explorer.is_focused()) {
+                (true, true) => CrosstermColor::DarkBlue,
+                (true, false) => CrosstermColor::DarkGrey,
+                _ => CrosstermColor::Reset,
+            };
+
+            queue!(
+                stdout(),
+                MoveTo(0, (draw_y + 1) as u16),
+                SetForegroundColor(foreground),
+                SetBackgroundColor(background),
+                Print(self.fit(&line, width)),
+                ResetColor
+            )
+            .unwrap();
+        }
+    }
+
+    fn render_separator(&self, x: usize, height: usize) {
+        for y in 0..height {
+            queue!(stdout(), MoveTo(x as u16, y as u16), Print('│')).unwrap();
+        }
+    }
+
+    // 入力欄はコマンドラインの位置に表示し、カーソルの位置を返す
+    fn render_prompt(&self, explorer: &FileExplorer, window_size: UVec2) -> Option<UVec2> {
+        let prompt = explorer.get_prompt()?;
+        let y = window_size.y - 1;
+        let message = prompt.get_message(explorer.get_root());
+
+        queue!(
+            stdout(),
+            MoveTo(0, y as u16),
+            Clear(ClearType::CurrentLine),
+            Print(&message),
+            Print(&prompt.input)
+        )
+        .unwrap();
+
+        Some(UVec2::new(message.width() + prompt.input.width(), y))
+    }
+
+    // サイドバーを左端に描画する (最下行はコマンドライン)
+    pub fn render(&self, explorer: &FileExplorer, window_size: UVec2) -> Option<UVec2> {
+        let width = explorer.get_panel_width().min(window_size.x);
+        let height = window_size.y.saturating_sub(1);
+
+        for y in 0..height {
+            queue!(stdout(), MoveTo(0, y as u16), Print(" ".repeat(width))).unwrap();
+        }
+
+        self.render_title(explorer, width);
+        self.render_entries(explorer, width, height.saturating_sub(1));
+        self.render_separator(width, height);
+        self.render_prompt(explorer, window_size)
+    }
+}
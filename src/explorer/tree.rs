@@ -0,0 +1,63 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub struct ExplorerEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub depth: usize,
+    pub is_dir: bool,
+    pub is_expanded: bool,
+}
+
+// 開いているディレクトリの中身を含めて、表示する順に並べる
+pub fn read_tree(root: &Path, expanded: &HashSet<PathBuf>) -> anyhow::Result<Vec<ExplorerEntry>> {
+    let mut entries = vec![];
+    read_dir(root, 0, expanded, &mut entries)?;
+    Ok(entries)
+}
+
+fn read_dir(
+    dir: &Path,
+    depth: usize,
+    expanded: &HashSet<PathBuf>,
+    entries: &mut Vec<ExplorerEntry>,
+) -> anyhow::Result<()> {
+    let mut children: Vec<(String, PathBuf, bool)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            (name, path.clone(), path.is_dir())
+        })
+        .filter(|(name, _, _)| name != ".git")
+        .collect();
+
+    // ディレクトリを先に、名前は大文字小文字を区別せずに並べる
+    children.sort_by(|(a_name, _, a_dir), (b_name, _, b_dir)| {
+        b_dir
+            .cmp(a_dir)
+            .then_with(|| a_name.to_lowercase().cmp(&b_name.to_lowercase()))
+    });
+
+    for (name, path, is_dir) in children {
+        let is_expanded = is_dir && expanded.contains(&path);
+
+        entries.push(ExplorerEntry {
+            path: path.clone(),
+            name,
+            depth,
+            is_dir,
+            is_expanded,
+        });
+
+        // 読めないディレクトリは中身を表示しない
+        if is_expanded {
+            let _ = read_dir(&path, depth + 1, expanded, entries);
+        }
+    }
+
+    Ok(())
+}
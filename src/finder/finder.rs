@@ -0,0 +1,376 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ignore::WalkBuilder;
+
+use crate::{
+    action::AppAction,
+    utils::{
+        command::CommandManager,
+        key_binding::{Key, KeyConfig, KeyConfigType},
+    },
+};
+
+// 候補を並べ直すのは上位のこの件数まで
+const MAX_MATCHES: usize = 1000;
+
+#[derive(Clone)]
+pub struct FinderMatch {
+    pub path: String,
+    pub score: i64,
+    pub indices: Vec<usize>, // 一致した文字の位置 (文字単位)
+}
+
+// 選んだファイルをどこで開くか
+pub enum FinderTarget {
+    Buffer,
+    Split,
+    VerticalSplit,
+    Tab,
+}
+
+pub enum FinderEvent {
+    Open(String, FinderTarget),
+}
+
+// 別スレッドで集めたファイル一覧 (walking が false になったら完了)
+#[derive(Default)]
+struct FinderFiles {
+    paths: Mutex<Vec<String>>,
+    walking: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+// 入力と候補数が変わったときだけ並べ直す
+#[derive(Default)]
+struct FinderCache {
+    query: String,
+    file_count: usize,
+    matches: Vec<FinderMatch>,
+}
+
+pub struct FileFinder {
+    root: PathBuf,
+    is_open: bool,
+    query: String,
+    selected: usize,
+    files: Arc<FinderFiles>,
+    cache: Mutex<Option<FinderCache>>,
+    matcher: SkimMatcherV2,
+}
+
+impl FileFinder {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            is_open: false,
+            query: String::new(),
+            selected: 0,
+            files: Arc::default(),
+            cache: Mutex::new(None),
+            matcher: SkimMatcherV2::default(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    pub fn get_query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn get_file_count(&self) -> usize {
+        self.files.paths.lock().unwrap().len()
+    }
+
+    pub fn is_walking(&self) -> bool {
+        self.files.walking.load(Ordering::Relaxed)
+    }
+
+    // 開くたびにファイル一覧を作り直す (.gitignore などで除外されたパスは含めない)
+    pub fn open(&mut self) {
+        self.close();
+
+        self.is_open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.files = Arc::new(FinderFiles::default());
+        self.files.walking.store(true, Ordering::Relaxed);
+        *self.cache.lock().unwrap() = None;
+
+        let files = Arc::clone(&self.files);
+        let root = self.root.clone();
+        thread::spawn(move || Self::walk(&root, &files));
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.files.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    fn walk(root: &Path, files: &FinderFiles) {
+        let mut batch = vec![];
+
+        for entry in WalkBuilder::new(root).build().flatten() {
+            if files.cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+            if !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                continue;
+            }
+
+            let path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            batch.push(path.to_string_lossy().to_string());
+
+            // ロックする回数を減らすためにまとめて追加する
+            if batch.len() >= 256 {
+                files.paths.lock().unwrap().append(&mut batch);
+            }
+        }
+
+        files.paths.lock().unwrap().append(&mut batch);
+        files.walking.store(false, Ordering::Relaxed);
+    }
+
+    // 入力に一致するファイルをスコアの高い順に返す (入力が空なら見つかった順)
+    pub fn get_matches(&self) -> Vec<FinderMatch> {
+        let paths = self.files.paths.lock().unwrap();
+        let mut cache = self.cache.lock().unwrap();
+
+        if let Some(cache) = cache.as_ref() {
+            if cache.query == self.query && cache.file_count == paths.len() {
+                return cache.matches.clone();
+            }
+        }
+
+        let matches: Vec<FinderMatch> = if self.query.is_empty() {
+            paths
+                .iter()
+                .take(MAX_MATCHES)
+                .map(|path| FinderMatch {
+                    path: path.clone(),
+                    score: 0,
+                    indices: vec![],
+                })
+                .collect()
+        } else {
+            let mut matches: Vec<FinderMatch> = paths
+                .iter()
+                .filter_map(|path| {
+                    let (score, indices) = self.matcher.fuzzy_indices(path, &self.query)?;
+                    Some(FinderMatch {
+                        path: path.clone(),
+                        score,
+                        indices,
+                    })
+                })
+                .collect();
+
+            // 同じスコアなら短いパスを優先する
+            matches.sort_by(|a, b| {
+                b.score
+                    .cmp(&a.score)
+                    .then_with(|| a.path.len().cmp(&b.path.len()))
+            });
+            matches.truncate(MAX_MATCHES);
+            matches
+        };
+
+        *cache = Some(FinderCache {
+            query: self.query.clone(),
+            file_count: paths.len(),
+            matches: matches.clone(),
+        });
+
+        matches
+    }
+
+    pub fn on_key(&mut self, key: Key) -> Option<FinderEvent> {
+        let target = match key {
+            Key::Esc | Key::Ctrl('c') => {
+                self.close();
+                return None;
+            }
+            Key::Up | Key::Ctrl('p') | Key::Ctrl('k') => {
+                self.selected = self.selected.saturating_sub(1);
+                return None;
+            }
+            Key::Down | Key::Ctrl('n') | Key::Ctrl('j') => {
+                let len = self.get_matches().len();
+                self.selected = (self.selected + 1).min(len.saturating_sub(1));
+                return None;
+            }
+            Key::Backspace => {
+                self.query.pop();
+                self.selected = 0;
+                return None;
+            }
+            Key::Char('\n') => FinderTarget::Buffer,
+            Key::Ctrl('s') | Key::Ctrl('x') => FinderTarget::Split,
+            Key::Ctrl('v') => FinderTarget::VerticalSplit,
+            Key::Ctrl('t') => FinderTarget::Tab,
+            Key::Char(c) => {
+                self.query.push(c);
+                self.selected = 0;
+                return None;
+            }
+            _ => return None,
+        };
+
+        let selected = self.get_matches().into_iter().nth(self.selected)?;
+        self.close();
+        Some(FinderEvent::Open(selected.path, target))
+    }
+
+    pub fn register_keybindings(&self, key_config: &mut KeyConfig) {
+        key_config.register(
+            KeyConfigType::Normal,
+            vec![Key::Ctrl('p')],
+            AppAction::OpenFinder,
+        );
+        key_config.register(
+            KeyConfigType::Explorer,
+            vec![Key::Ctrl('p')],
+            AppAction::OpenFinder,
+        );
+    }
+
+    pub fn register_commands(&self, cmd_manager: &mut CommandManager) {
+        cmd_manager.register("Files", vec![AppAction::OpenFinder]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process};
+
+    use super::{FileFinder, FinderFiles, MAX_MATCHES};
+
+    fn finder(paths: &[&str], query: &str) -> FileFinder {
+        let mut finder = FileFinder::new(PathBuf::new());
+        *finder.files.paths.lock().unwrap() = paths.iter().map(|path| path.to_string()).collect();
+        finder.query = query.to_string();
+        finder
+    }
+
+    fn paths(finder: &FileFinder) -> Vec<String> {
+        finder
+            .get_matches()
+            .into_iter()
+            .map(|finder_match| finder_match.path)
+            .collect()
+    }
+
+    #[test]
+    fn matches_are_sorted_by_score_then_length() {
+        let finder = finder(
+            &[
+                "src/domain.rs",
+                "xmain.rs",
+                "README.md",
+                "src/lib/main.rs",
+                "docs/main.md",
+                "m_a_i_n.rs",
+                "main.rs",
+            ],
+            "main",
+        );
+
+        let matches = finder.get_matches();
+        assert!(matches
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        assert_eq!(
+            paths(&finder),
+            vec![
+                "main.rs",
+                "m_a_i_n.rs",
+                "docs/main.md",
+                "src/lib/main.rs",
+                "xmain.rs",
+                "src/domain.rs",
+            ]
+        );
+        assert_eq!(matches[1].indices, vec![0, 2, 4, 6]);
+    }
+
+    #[test]
+    fn empty_query_keeps_walk_order() {
+        let finder = finder(&["b/long/path.rs", "a.rs"], "");
+        assert_eq!(paths(&finder), vec!["b/long/path.rs", "a.rs"]);
+    }
+
+    #[test]
+    fn matches_are_truncated() {
+        let names: Vec<String> = (0..MAX_MATCHES + 10)
+            .map(|i| format!("file{}.rs", i))
+            .collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+        let mut finder = finder(&names, "");
+        assert_eq!(finder.get_matches().len(), MAX_MATCHES);
+        assert_eq!(finder.get_matches()[0].path, "file0.rs");
+
+        finder.query = "file".to_string();
+        assert_eq!(finder.get_matches().len(), MAX_MATCHES);
+    }
+
+    #[test]
+    fn cache_is_rebuilt_when_query_or_file_count_changes() {
+        let mut finder = finder(&["a.rs", "b.rs"], "a");
+        assert_eq!(paths(&finder), vec!["a.rs"]);
+
+        // 件数が変わらなければ前回の結果を使う
+        finder.files.paths.lock().unwrap()[1] = "ab.rs".to_string();
+        assert_eq!(paths(&finder), vec!["a.rs"]);
+
+        finder
+            .files
+            .paths
+            .lock()
+            .unwrap()
+            .push("abc.rs".to_string());
+        assert_eq!(paths(&finder), vec!["a.rs", "ab.rs", "abc.rs"]);
+
+        finder.query = "c".to_string();
+        assert_eq!(paths(&finder), vec!["abc.rs"]);
+    }
+
+    #[test]
+    fn walk_skips_ignored_files() {
+        let root = env::temp_dir().join(format!("tui-studio-finder-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        // .gitignore は git のリポジトリの中でだけ使われる
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("README.md"), "").unwrap();
+        fs::write(root.join("debug.log"), "").unwrap();
+        fs::write(root.join("target/out"), "").unwrap();
+
+        let files = FinderFiles::default();
+        FileFinder::walk(&root, &files);
+
+        let mut paths = files.paths.into_inner().unwrap();
+        paths.sort();
+        assert_eq!(paths, vec!["README.md", "src/main.rs"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
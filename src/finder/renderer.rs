@@ -0,0 +1,147 @@
+use std::io::stdout;
+
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{Color as CrosstermColor, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::utils::{rect::Rect, vec2::UVec2};
+
+use super::finder::{FileFinder, FinderMatch};
+
+#[derive(Default)]
+pub struct FinderRenderer {}
+
+impl FinderRenderer {
+    // 画面の中央に置くポップアップの領域 (枠を含む)
+    fn get_rect(&self, window_size: UVec2) -> Rect {
+        let width = (window_size.x * 3 / 5).max(40).min(window_size.x);
+        let height = (window_size.y * 3 / 5).max(8).min(window_size.y);
+
+        Rect::new(
+            UVec2::new((window_size.x - width) / 2, (window_size.y - height) / 2),
+            UVec2::new(width, height),
+        )
+    }
+
+    fn render_border(&self, rect: &Rect, title: &str) {
+        let inner = rect.size.x.saturating_sub(2);
+        let title: String = title.chars().take(inner).collect();
+        let top = format!(
+            "┌{}{}┐",
+            title,
+            "─".repeat(inner.saturating_sub(title.width()))
+        );
+        let bottom = format!("└{}┘", "─".repeat(inner));
+
+        queue!(
+            stdout(),
+            MoveTo(rect.pos.x as u16, rect.pos.y as u16),
+            Print(top)
+        )
+        .unwrap();
+
+        for y in 1..rect.size.y.saturating_sub(1) {
+            queue!(
+                stdout(),
+                MoveTo(rect.pos.x as u16, (rect.pos.y + y) as u16),
+                Print('│'),
+                Print(" ".repeat(inner)),
+                Print('│')
+            )
+            .unwrap();
+        }
+
+        queue!(
+            stdout(),
+            MoveTo(
+                rect.pos.x as u16,
+                (rect.pos.y + rect.size.y.saturating_sub(1)) as u16
+            ),
+            Print(bottom)
+        )
+        .unwrap();
+    }
+
+    // 一致した文字を強調して表示する
+    fn render_match(&self, item: &FinderMatch, width: usize, is_selected: bool) {
+        let background = if is_selected {
+            CrosstermColor::DarkGrey
+        } else {
+            CrosstermColor::Reset
+        };
+
+        queue!(stdout(), SetBackgroundColor(background)).unwrap();
+
+        let mut col = 0;
+        for (i, c) in item.path.chars().enumerate() {
+            let char_width = c.width().unwrap_or(0);
+            if col + char_width > width {
+                break;
+            }
+
+            let foreground = if item.indices.contains(&i) {
+                CrosstermColor::Yellow
+            } else {
+                CrosstermColor::Reset
+            };
+            queue!(stdout(), SetForegroundColor(foreground), Print(c)).unwrap();
+            col += char_width;
+        }
+
+        queue!(
+            stdout(),
+            Print(" ".repeat(width.saturating_sub(col))),
+            ResetColor
+        )
+        .unwrap();
+    }
+
+    // 入力欄にカーソルを置くので、その位置を返す
+    pub fn render(&self, finder: &FileFinder, window_size: UVec2) -> UVec2 {
+        let rect = self.get_rect(window_size);
+        let matches = finder.get_matches();
+
+        let status = format!(
+            "{}/{}{}",
+            matches.len(),
+            finder.get_file_count(),
+            if finder.is_walking() { " …" } else { "" }
+        );
+        self.render_border(&rect, &format!(" Files ({}) ", status));
+
+        let inner_x = rect.pos.x + 1;
+        let inner_width = rect.size.x.saturating_sub(2);
+        let prompt = format!("> {}", finder.get_query());
+
+        queue!(
+            stdout(),
+            MoveTo(inner_x as u16, (rect.pos.y + 1) as u16),
+            Print(&prompt)
+        )
+        .unwrap();
+
+        // 選択している候補が見えるようにずらす
+        let height = rect.size.y.saturating_sub(3);
+        let scroll = (finder.get_selected() + 1).saturating_sub(height);
+
+        for (draw_y, (i, item)) in matches
+            .iter()
+            .enumerate()
+            .skip(scroll)
+            .take(height)
+            .enumerate()
+        {
+            queue!(
+                stdout(),
+                MoveTo(inner_x as u16, (rect.pos.y + 2 + draw_y) as u16)
+            )
+            .unwrap();
+            self.render_match(item, inner_width, i == finder.get_selected());
+        }
+
+        UVec2::new(inner_x + prompt.width().min(inner_width), rect.pos.y + 1)
+    }
+}
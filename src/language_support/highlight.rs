@@ -62,6 +62,6 @@ pub fn regex_tokenize<T: ToColor + Clone>(
         }
     }
 
-    tokens.sort_by(|x, y| x.start.cmp(&y.start));
+    tokens.sort_by_key(|token| token.start);
     tokens
 }
@@ -1,6 +1,9 @@
 use crate::{
     language_support::{
+        complete::CompletionItem,
         highlight::{regex_tokenize, HighlightToken},
+        lint::LintError,
+        lsp::DiagnosticSeverity,
         LanguageSupport,
     },
     utils::color::{Color, ToColor},
@@ -15,6 +18,53 @@ const SYNTAX: [(&str, TokenKind); 6] = [
     (r"(?m)[a-zA-Z\-]+\s*:\s*(.*);$", TokenKind::Value),
 ];
 
+// 整形するときの字下げ
+const INDENT: &str = "  ";
+
+// { } の中で補完するプロパティ名
+const PROPERTIES: [&str; 40] = [
+    "align-items",
+    "background",
+    "background-color",
+    "border",
+    "border-radius",
+    "bottom",
+    "box-shadow",
+    "box-sizing",
+    "color",
+    "cursor",
+    "display",
+    "flex",
+    "flex-direction",
+    "font",
+    "font-family",
+    "font-size",
+    "font-weight",
+    "gap",
+    "grid-template-columns",
+    "height",
+    "justify-content",
+    "left",
+    "line-height",
+    "margin",
+    "max-width",
+    "min-height",
+    "opacity",
+    "overflow",
+    "padding",
+    "position",
+    "right",
+    "text-align",
+    "text-decoration",
+    "top",
+    "transform",
+    "transition",
+    "visibility",
+    "white-space",
+    "width",
+    "z-index",
+];
+
 #[derive(Clone)]
 enum TokenKind {
     TagSelector,
@@ -54,4 +104,254 @@ impl LanguageSupport for CSSLanguageSupport {
     fn highlight(&self, source_code: &str) -> Option<Vec<HighlightToken>> {
         Some(regex_tokenize(source_code, SYNTAX.to_vec()))
     }
+
+    // { の中で、: より前を入力しているときはプロパティ名
+    fn complete(&self, cursor_position: usize, source_code: &str) -> Option<Vec<CompletionItem>> {
+        let before: String = source_code.chars().take(cursor_position).collect();
+        let block = &before[before.rfind(['{', '}'])?..];
+        let declaration = block.rsplit([';', '{']).next().unwrap_or_default();
+        if !block.starts_with('{') || declaration.contains(':') {
+            return None;
+        }
+
+        Some(
+            PROPERTIES
+                .iter()
+                .map(|property| CompletionItem {
+                    label: property.to_string(),
+                    detail: Some("property".to_string()),
+                    insert_text: format!("{}: ", property),
+                })
+                .collect(),
+        )
+    }
+
+    // 対応の取れていない { } (コメントと文字列の中は数えない)
+    fn lint(&self, source_code: &str) -> Option<Vec<LintError>> {
+        let error = |index: usize, message: &str| {
+            LintError::new(
+                source_code,
+                index,
+                index + 1,
+                DiagnosticSeverity::Error,
+                message.to_string(),
+                "css",
+            )
+        };
+
+        let mut errors = vec![];
+        let mut stack = vec![];
+        let mut chars = source_code.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '/' if chars.peek().is_some_and(|(_, next)| *next == '*') => {
+                    chars.next();
+                    let mut prev = ' ';
+                    for (_, c) in chars.by_ref() {
+                        if prev == '*' && c == '/' {
+                            break;
+                        }
+                        prev = c;
+                    }
+                }
+                '"' | '\'' => {
+                    let mut is_escaped = false;
+                    for (_, next) in chars.by_ref() {
+                        if next == '\n' || (next == c && !is_escaped) {
+                            break;
+                        }
+                        is_escaped = next == '\\' && !is_escaped;
+                    }
+                }
+                '{' => stack.push(i),
+                '}' => match stack.pop() {
+                    Some(_) => {}
+                    None => errors.push(error(i, "Unexpected '}'")),
+                },
+                _ => {}
+            }
+        }
+        errors.extend(stack.into_iter().map(|i| error(i, "Unclosed '{'")));
+
+        Some(errors)
+    }
+
+    // 宣言を1行ずつに分けて { } の中を字下げする (コメントと文字列はそのまま残す)
+    fn format(&self, source_code: &str) -> Option<String> {
+        if !self.lint(source_code)?.is_empty() {
+            return None;
+        }
+
+        let mut output = String::new();
+        let mut depth = 0;
+        let mut parens: usize = 0; // url(...) の中の ; では改行しない
+        let mut is_line_start = true;
+        let mut has_space = false;
+        let mut is_after_comment = false; // 行末のコメントの後の改行は残す
+        let mut chars = source_code.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() {
+                if c == '\n' && is_after_comment && !is_line_start {
+                    output.push('\n');
+                    is_line_start = true;
+                }
+                has_space = true;
+                continue;
+            }
+
+            match c {
+                '{' => {
+                    output.truncate(output.trim_end().len());
+                    output.push_str(" {\n");
+                    depth += 1;
+                    is_line_start = true;
+                }
+                ';' if parens == 0 => {
+                    output.push_str(";\n");
+                    is_line_start = true;
+                }
+                '}' => {
+                    output.truncate(output.trim_end().len());
+                    output.push('\n');
+                    depth -= 1;
+                    output.push_str(&INDENT.repeat(depth));
+                    output.push_str("}\n");
+                    // トップレベルの規則の間は1行空ける
+                    if depth == 0 {
+                        output.push('\n');
+                    }
+                    is_line_start = true;
+                }
+                _ => {
+                    if is_line_start {
+                        output.push_str(&INDENT.repeat(depth));
+                    } else if has_space {
+                        output.push(' ');
+                    }
+                    is_line_start = false;
+                    output.push(c);
+
+                    match c {
+                        '/' if chars.peek() == Some(&'*') => {
+                            output.push(chars.next().unwrap());
+                            let mut prev = ' ';
+                            for next in chars.by_ref() {
+                                output.push(next);
+                                if prev == '*' && next == '/' {
+                                    break;
+                                }
+                                prev = next;
+                            }
+                        }
+                        '"' | '\'' => {
+                            let mut is_escaped = false;
+                            for next in chars.by_ref() {
+                                output.push(next);
+                                if next == '\n' || (next == c && !is_escaped) {
+                                    break;
+                                }
+                                is_escaped = next == '\\' && !is_escaped;
+                            }
+                        }
+                        '(' => parens += 1,
+                        ')' => parens = parens.saturating_sub(1),
+                        _ => {}
+                    }
+                }
+            }
+
+            is_after_comment = output.ends_with("*/");
+            has_space = false;
+        }
+
+        output.truncate(output.trim_end().len());
+        output.push('\n');
+        Some(output)
+    }
+
+    fn get_lsp_server_cmd(&self) -> Option<String> {
+        Some("vscode-css-language-server --stdio".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language_support::LanguageSupport;
+
+    use super::CSSLanguageSupport;
+
+    // (行, 列, メッセージ)
+    fn lint(source_code: &str) -> Vec<(usize, usize, String)> {
+        CSSLanguageSupport::new()
+            .lint(source_code)
+            .unwrap()
+            .into_iter()
+            .map(|error| (error.line, error.column, error.message))
+            .collect()
+    }
+
+    fn format(source_code: &str) -> Option<String> {
+        CSSLanguageSupport::new().format(source_code)
+    }
+
+    #[test]
+    fn reports_unbalanced_braces() {
+        assert!(lint("a { color: red; }").is_empty());
+        assert_eq!(
+            lint("a {\n  color: red;\n}\n}"),
+            vec![(3, 0, "Unexpected '}'".to_string())]
+        );
+        assert_eq!(
+            lint("a {\n  b { color: red; }"),
+            vec![(0, 2, "Unclosed '{'".to_string())]
+        );
+    }
+
+    #[test]
+    fn ignores_braces_in_comments_and_strings() {
+        assert!(lint("/* { */ a { content: \"}\"; b: '{'; }").is_empty());
+        assert_eq!(
+            lint("/* { */ } é"),
+            vec![(0, 8, "Unexpected '}'".to_string())]
+        );
+    }
+
+    #[test]
+    fn formats_declarations_one_per_line() {
+        assert_eq!(
+            format("a{color:red;margin:0}b , c{ top : 0 }").unwrap(),
+            "a {\n  color:red;\n  margin:0\n}\n\nb , c {\n  top : 0\n}\n"
+        );
+    }
+
+    #[test]
+    fn formats_nested_rules_and_keeps_comments() {
+        assert_eq!(
+            format("@media print{a{color:red} /* x; y */}").unwrap(),
+            "@media print {\n  a {\n    color:red\n  }\n  /* x; y */\n}\n"
+        );
+    }
+
+    #[test]
+    fn does_not_split_inside_parentheses() {
+        assert_eq!(
+            format("a{background:url(\"a;b\") no-repeat;}").unwrap(),
+            "a {\n  background:url(\"a;b\") no-repeat;\n}\n"
+        );
+    }
+
+    #[test]
+    fn does_not_format_unbalanced_braces() {
+        assert_eq!(format("a { color: red;"), None);
+    }
+
+    #[test]
+    fn completes_properties_inside_blocks() {
+        let css = CSSLanguageSupport::new();
+
+        assert!(css.complete(5, "a { c").is_some());
+        assert!(css.complete(12, "a { color: r").is_none());
+        assert!(css.complete(1, "a").is_none());
+    }
 }
@@ -1,6 +1,11 @@
+use regex::Regex;
+
 use crate::{
     language_support::{
+        complete::CompletionItem,
         highlight::{regex_tokenize, HighlightToken},
+        lint::{blank_out, LintError},
+        lsp::DiagnosticSeverity,
         LanguageSupport,
     },
     utils::color::{Color, ToColor},
@@ -16,6 +21,21 @@ const SYNTAX: [(&str, TokenKind); 7] = [
     (r"([^<]+)", TokenKind::Text),
 ];
 
+// < の後に補完するタグ名
+const TAGS: [&str; 48] = [
+    "a", "abbr", "article", "aside", "audio", "body", "br", "button", "canvas", "code", "dialog",
+    "div", "em", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "head", "header", "hr",
+    "html", "iframe", "img", "input", "label", "li", "link", "main", "meta", "nav", "ol", "option",
+    "p", "pre", "script", "section", "select", "span", "strong", "style", "table", "textarea",
+    "title", "ul",
+];
+
+// 終了タグのない要素
+const VOID_TAGS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
 #[derive(Clone)]
 enum TokenKind {
     Tag,
@@ -53,4 +73,150 @@ impl LanguageSupport for HTMLLanguageSupport {
     fn highlight(&self, source_code: &str) -> Option<Vec<HighlightToken>> {
         Some(regex_tokenize(source_code, SYNTAX.to_vec()))
     }
+
+    // 入力中の単語が < または </ の直後ならタグ名
+    fn complete(&self, cursor_position: usize, source_code: &str) -> Option<Vec<CompletionItem>> {
+        let before: String = source_code.chars().take(cursor_position).collect();
+        let before = before.trim_end_matches(|c: char| c.is_ascii_alphanumeric());
+        if !before.ends_with('<') && !before.ends_with("</") {
+            return None;
+        }
+
+        Some(
+            TAGS.iter()
+                .map(|tag| CompletionItem {
+                    label: tag.to_string(),
+                    detail: Some("tag".to_string()),
+                    insert_text: tag.to_string(),
+                })
+                .collect(),
+        )
+    }
+
+    // 閉じていないタグと、対応する開始タグのない終了タグ
+    fn lint(&self, source_code: &str) -> Option<Vec<LintError>> {
+        // コメントと script, style の中身はタグとして扱わない
+        let mut code = source_code.to_string();
+        let mut ranges: Vec<(usize, usize)> = Regex::new(r"(?s)<!--.*?(-->|$)")
+            .unwrap()
+            .find_iter(source_code)
+            .map(|m| (m.start(), m.end()))
+            .collect();
+        for name in ["script", "style"] {
+            let regex = Regex::new(&format!(r"(?is)<{0}\b[^>]*>(.*?)</{0}\s*>", name)).unwrap();
+            ranges.extend(regex.captures_iter(source_code).map(|caps| {
+                let content = caps.get(1).unwrap();
+                (content.start(), content.end())
+            }));
+        }
+        for (start, end) in ranges {
+            blank_out(&mut code, start, end);
+        }
+
+        let error = |start: usize, end: usize, message: String| {
+            LintError::new(
+                source_code,
+                start,
+                end,
+                DiagnosticSeverity::Error,
+                message,
+                "html",
+            )
+        };
+
+        let mut errors = vec![];
+        let mut stack: Vec<(String, usize, usize)> = vec![];
+        let tag = Regex::new(r"<(/?)([a-zA-Z][a-zA-Z0-9\-]*)[^<>]*?(/?)>").unwrap();
+        for caps in tag.captures_iter(&code) {
+            let m = caps.get(0).unwrap();
+            let name = caps[2].to_ascii_lowercase();
+
+            if caps[1].is_empty() {
+                if caps[3].is_empty() && !VOID_TAGS.contains(&name.as_str()) {
+                    stack.push((name, m.start(), m.end()));
+                }
+            } else if let Some(index) = stack.iter().rposition(|(open, _, _)| *open == name) {
+                // 間にある閉じていないタグはここで閉じたものとする
+                for (open, start, end) in stack.drain(index + 1..) {
+                    errors.push(error(start, end, format!("Unclosed tag <{}>", open)));
+                }
+                stack.pop();
+            } else {
+                errors.push(error(
+                    m.start(),
+                    m.end(),
+                    format!("Unexpected closing tag </{}>", name),
+                ));
+            }
+        }
+        errors.extend(
+            stack
+                .into_iter()
+                .map(|(open, start, end)| error(start, end, format!("Unclosed tag <{}>", open))),
+        );
+
+        Some(errors)
+    }
+
+    fn get_lsp_server_cmd(&self) -> Option<String> {
+        Some("vscode-html-language-server --stdio".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language_support::LanguageSupport;
+
+    use super::HTMLLanguageSupport;
+
+    // (行, 列, 終わりの列, メッセージ)
+    fn lint(source_code: &str) -> Vec<(usize, usize, usize, String)> {
+        HTMLLanguageSupport::new()
+            .lint(source_code)
+            .unwrap()
+            .into_iter()
+            .map(|error| (error.line, error.column, error.end_column, error.message))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_balanced_void_and_self_closing_tags() {
+        assert!(lint("<div><p>a<br>b</p><img src=x><x-y/></DIV>").is_empty());
+    }
+
+    #[test]
+    fn reports_unclosed_tags() {
+        assert_eq!(
+            lint("<html>\n  <p>\n</html>"),
+            vec![(1, 2, 5, "Unclosed tag <p>".to_string())]
+        );
+        assert_eq!(
+            lint("<div>"),
+            vec![(0, 0, 5, "Unclosed tag <div>".to_string())]
+        );
+    }
+
+    #[test]
+    fn reports_unexpected_closing_tags() {
+        assert_eq!(
+            lint("<p>é</p></span>"),
+            vec![(0, 8, 15, "Unexpected closing tag </span>".to_string())]
+        );
+    }
+
+    #[test]
+    fn ignores_comments_scripts_and_styles() {
+        assert!(
+            lint("<!-- <div> --><script>if (a<b) {}</p></script><style>a>b{}</style>").is_empty()
+        );
+    }
+
+    #[test]
+    fn completes_tag_names_after_angle_bracket() {
+        let html = HTMLLanguageSupport::new();
+
+        assert!(html.complete(3, "<di").is_some());
+        assert!(html.complete(4, "</di").is_some());
+        assert!(html.complete(2, "di").is_none());
+    }
 }
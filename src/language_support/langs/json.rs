@@ -0,0 +1,114 @@
+use serde::de::IgnoredAny;
+
+use crate::language_support::LanguageSupport;
+
+// 整形するときの字下げ
+const INDENT: &str = "  ";
+
+fn push_newline(output: &mut String, depth: usize) {
+    output.push('\n');
+    output.push_str(&INDENT.repeat(depth));
+}
+
+pub struct JSONLanguageSupport {}
+
+impl JSONLanguageSupport {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl LanguageSupport for JSONLanguageSupport {
+    // キーの順番を変えないよう、値を読み直さずに空白だけを入れ直す
+    fn format(&self, source_code: &str) -> Option<String> {
+        serde_json::from_str::<IgnoredAny>(source_code).ok()?;
+
+        let mut output = String::new();
+        let mut depth = 0;
+        let mut chars = source_code.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    output.push(c);
+                    let mut is_escaped = false;
+                    for next in chars.by_ref() {
+                        output.push(next);
+                        if next == '"' && !is_escaped {
+                            break;
+                        }
+                        is_escaped = next == '\\' && !is_escaped;
+                    }
+                }
+                '{' | '[' => {
+                    output.push(c);
+                    // 空のオブジェクトと配列は1行にする
+                    while chars.next_if(|next| next.is_whitespace()).is_some() {}
+                    match chars.next_if(|next| matches!(next, '}' | ']')) {
+                        Some(close) => output.push(close),
+                        None => {
+                            depth += 1;
+                            push_newline(&mut output, depth);
+                        }
+                    }
+                }
+                '}' | ']' => {
+                    depth -= 1;
+                    push_newline(&mut output, depth);
+                    output.push(c);
+                }
+                ',' => {
+                    output.push(c);
+                    push_newline(&mut output, depth);
+                }
+                ':' => output.push_str(": "),
+                c if c.is_whitespace() => {}
+                c => output.push(c),
+            }
+        }
+
+        output.push('\n');
+        Some(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language_support::LanguageSupport;
+
+    use super::JSONLanguageSupport;
+
+    fn format(source_code: &str) -> Option<String> {
+        JSONLanguageSupport::new().format(source_code)
+    }
+
+    #[test]
+    fn formats_nested_values_in_original_key_order() {
+        assert_eq!(
+            format(r#"{"b":1,"a":[true,null,{"c":"d"}]}"#).unwrap(),
+            "{\n  \"b\": 1,\n  \"a\": [\n    true,\n    null,\n    {\n      \"c\": \"d\"\n    }\n  ]\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_empty_containers_on_one_line() {
+        assert_eq!(
+            format("{ \"a\" : { }, \"b\": [\n] }").unwrap(),
+            "{\n  \"a\": {},\n  \"b\": []\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_strings_unchanged() {
+        assert_eq!(
+            format(r#"{"a b":"x, {y}: \"z\" \\"}"#).unwrap(),
+            "{\n  \"a b\": \"x, {y}: \\\"z\\\" \\\\\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn does_not_format_invalid_json() {
+        assert_eq!(format(r#"{"a": }"#), None);
+        assert_eq!(format("[1, 2"), None);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod commit_message;
 pub mod css;
 pub mod html;
+pub mod json;
 pub mod markdown;
@@ -1,5 +1,59 @@
+use super::lsp::DiagnosticSeverity;
+
+// 位置は 0 始まりの行と、行内の文字単位の列
 pub struct LintError {
     pub message: String,
     pub line: usize,
     pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub severity: DiagnosticSeverity,
+    pub source: String,
+}
+
+// バイト位置を行と列 (文字単位) にする
+fn to_line_column(source_code: &str, index: usize) -> (usize, usize) {
+    let before = &source_code[..index];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count(),
+        before[line_start..].chars().count(),
+    )
+}
+
+impl LintError {
+    // start, end はバイト位置
+    pub fn new(
+        source_code: &str,
+        start: usize,
+        end: usize,
+        severity: DiagnosticSeverity,
+        message: String,
+        source: &str,
+    ) -> Self {
+        let (line, column) = to_line_column(source_code, start);
+        let (end_line, end_column) = to_line_column(source_code, end);
+
+        Self {
+            message,
+            line,
+            column,
+            end_line,
+            end_column,
+            severity,
+            source: source.to_string(),
+        }
+    }
+}
+
+// コメントなどを空白に置き換える (バイト位置が変わらないよう同じバイト数にする)
+pub fn blank_out(source_code: &mut String, start: usize, end: usize) {
+    let blank: String = source_code[start..end]
+        .chars()
+        .map(|c| match c {
+            '\n' => "\n".to_string(),
+            c => " ".repeat(c.len_utf8()),
+        })
+        .collect();
+    source_code.replace_range(start..end, &blank);
 }
@@ -1,101 +1,860 @@
-// use std::process::Stdio;
-
-// use serde::{Deserialize, Serialize};
-// use serde_json::{json, Value};
-// use tokio::{
-//     io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
-//     process::Command,
-// };
-
-// // LSPメッセージの構造
-// #[derive(Serialize, Deserialize, Debug)]
-// struct LspRequest {
-//     jsonrpc: String,
-//     id: u64,
-//     method: String,
-//     params: Value,
-// }
-
-// #[derive(Serialize, Deserialize, Debug)]
-// struct LspResponse {
-//     jsonrpc: String,
-//     id: u64,
-//     result: Option<Value>,
-//     error: Option<LspError>,
-// }
-
-// #[derive(Serialize, Deserialize, Debug)]
-// struct LspError {
-//     code: i32,
-//     message: String,
-// }
-
-// pub struct LSPClient {
-//     server_cmd: String,
-// }
-
-// impl LSPClient {
-//     pub fn new(server_cmd: String) -> Self {
-//         Self { server_cmd }
-//     }
-
-//     pub async fn run(&self) -> Result<()> {
-//         let mut server = Command::new(self.server_cmd.clone())
-//             .stdin(Stdio::piped())
-//             .stdout(Stdio::piped())
-//             .spawn()?;
-
-//         let stdin = server.stdin.take().expect("Failed to open stdin");
-//         let stdout = server.stdout.take().expect("Failed to open stdout");
-
-//         let mut writer = BufWriter::new(stdin);
-//         let mut reader = BufReader::new(stdout).lines();
-
-//         // サーバーからのレスポンスを受信するタスク
-//         let response_task = tokio::spawn(async move {
-//             let mut response = String::new();
-//             while let Ok(line) = reader.next_line().await {
-//                 match line {
-//                     Some(line) => {
-//                         response.push_str(&line);
-//                         // LSPレスポンスの処理（JSONパースなど）
-//                         if let Ok(parsed_response) = serde_json::from_str::<LspResponse>(&response)
-//                         {
-//                             println!("Received response: {:?}", parsed_response);
-//                         }
-//                         response.clear();
-//                     }
-//                     None => break,
-//                 }
-//             }
-//         });
-
-//         // LSPリクエストを送信
-//         let request = LspRequest {
-//             jsonrpc: "2.0".to_string(),
-//             id: 1,
-//             method: "initialize".to_string(),
-//             params: json!({
-//                 "processId": std::process::id(),
-//                 "rootUri": "file:///path/to/your/project",
-//                 "capabilities": {},
-//             }),
-//         };
-
-//         let request_str = serde_json::to_string(&request).context("Failed to serialize request")?;
-
-//         // リクエストをサーバーに送信
-//         writer
-//             .write_all(request_str.as_bytes())
-//             .await
-//             .context("Failed to send request")?;
-//         writer.flush().await.context("Failed to flush writer")?;
-
-//         // レスポンスタスクが完了するのを待つ
-//         response_task
-//             .await
-//             .context("Response handling task failed")?;
-
-//         Ok(())
-//     }
-// }
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::complete::CompletionItem;
+
+// shutdown の応答やサーバーの終了を待つ時間
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+// 保存前の整形などで応答を待つ時間
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Content-Length ヘッダーを付けてメッセージを書き込む
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+// メッセージを1つ読み込む (サーバーが終了していれば None)
+// 本文が JSON として読めなくても本文は読み切るので、内側のエラーなら次のメッセージを読める
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<serde_json::Result<Value>>> {
+    let mut content_length = None;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        // Content-Type などほかのヘッダーは使わない
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let content_length = content_length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)))
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    uri
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(path.len());
+
+    let mut i = 0;
+    while i < path.len() {
+        if path[i] == b'%' {
+            let hex = std::str::from_utf8(path.get(i + 1..i + 3)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            bytes.push(path[i]);
+            i += 1;
+        }
+    }
+
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+// 位置 (character は UTF-16 の単位)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LspPosition {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl LspPosition {
+    pub fn new(line: usize, character: usize) -> Self {
+        Self { line, character }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LspRange {
+    pub start: LspPosition,
+    pub end: LspPosition,
+}
+
+// didChange で送る変更 (range を text で置き換える)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentChange {
+    pub range: LspRange,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiagnosticSeverity {
+    #[default]
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl DiagnosticSeverity {
+    fn from_lsp(severity: Option<u8>) -> Self {
+        match severity {
+            Some(2) => Self::Warning,
+            Some(3) => Self::Information,
+            Some(4) => Self::Hint,
+            _ => Self::Error,
+        }
+    }
+
+    // 行番号の左に表示する記号
+    pub fn get_sign(&self) -> char {
+        match self {
+            Self::Error => 'E',
+            Self::Warning => 'W',
+            Self::Information => 'I',
+            Self::Hint => 'H',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LspDiagnostic {
+    pub range: LspRange,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    pub source: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawDiagnostic {
+    range: LspRange,
+    severity: Option<u8>,
+    message: String,
+    source: Option<String>,
+}
+
+// ファイルの中の範囲 (定義や参照の位置)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LspLocation {
+    pub path: PathBuf,
+    pub range: LspRange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextEdit {
+    pub range: LspRange,
+    #[serde(rename = "newText")]
+    pub new_text: String,
+}
+
+pub enum LspEvent {
+    Diagnostics {
+        path: PathBuf,
+        diagnostics: Vec<LspDiagnostic>,
+    },
+    // エディタから送ったリクエストへの応答 (エラーならメッセージ)
+    Response {
+        method: String,
+        result: Result<Value, String>,
+    },
+}
+
+// Location / Location[] / LocationLink[] (見つからなければ null)
+pub fn parse_locations(value: &Value) -> Vec<LspLocation> {
+    let values = match value {
+        Value::Array(values) => values.iter().collect(),
+        Value::Object(_) => vec![value],
+        _ => vec![],
+    };
+
+    values
+        .into_iter()
+        .filter_map(|value| {
+            let (uri, range) = match value.get("targetUri") {
+                Some(uri) => (uri, &value["targetSelectionRange"]),
+                None => (&value["uri"], &value["range"]),
+            };
+
+            Some(LspLocation {
+                path: uri_to_path(uri.as_str()?)?,
+                range: serde_json::from_value(range.clone()).ok()?,
+            })
+        })
+        .collect()
+}
+
+// MarkupContent / MarkedString / MarkedString[] をそのまま表示できる文字列にする
+pub fn parse_hover(value: &Value) -> Option<String> {
+    fn to_text(contents: &Value) -> Option<String> {
+        match contents {
+            Value::String(text) => Some(text.clone()),
+            Value::Array(values) => {
+                let texts: Vec<String> = values.iter().filter_map(to_text).collect();
+                Some(texts.join("\n\n"))
+            }
+            Value::Object(_) => contents["value"].as_str().map(str::to_string),
+            _ => None,
+        }
+    }
+
+    // コードブロックの ``` の行は表示しない
+    let text = to_text(&value["contents"])?;
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect();
+    let text = lines.join("\n").trim().to_string();
+
+    (!text.is_empty()).then_some(text)
+}
+
+// CompletionItem[] / CompletionList (スニペットはプレースホルダーの文字列だけ残す)
+pub fn parse_completion_items(value: &Value) -> Vec<CompletionItem> {
+    let items = match value {
+        Value::Array(items) => items,
+        _ => match value["items"].as_array() {
+            Some(items) => items,
+            None => return vec![],
+        },
+    };
+    let placeholder = Regex::new(r"\$\{\d+:([^}]*)\}|\$\{?\d+\}?").unwrap();
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let label = item["label"].as_str()?.to_string();
+            let text = item["textEdit"]["newText"]
+                .as_str()
+                .or(item["insertText"].as_str())
+                .unwrap_or(&label);
+            let insert_text = if item["insertTextFormat"] == 2 {
+                placeholder.replace_all(text, "$1").to_string()
+            } else {
+                text.to_string()
+            };
+
+            Some(CompletionItem {
+                detail: item["detail"].as_str().map(str::to_string),
+                label,
+                insert_text,
+            })
+        })
+        .collect()
+}
+
+// WorkspaceEdit の changes と documentChanges (ファイルの作成などには対応しない)
+pub fn parse_workspace_edit(value: &Value) -> anyhow::Result<Vec<(PathBuf, Vec<TextEdit>)>> {
+    let mut files: Vec<(PathBuf, Vec<TextEdit>)> = vec![];
+    let mut add = |uri: &Value, edits: &Value| -> anyhow::Result<()> {
+        let uri = uri
+            .as_str()
+            .ok_or_else(|| anyhow!("Invalid workspace edit"))?;
+        let path = uri_to_path(uri).ok_or_else(|| anyhow!("Unsupported URI: {}", uri))?;
+        let edits: Vec<TextEdit> = serde_json::from_value(edits.clone())?;
+
+        match files.iter_mut().find(|(file, _)| *file == path) {
+            Some((_, file_edits)) => file_edits.extend(edits),
+            None => files.push((path, edits)),
+        }
+        Ok(())
+    };
+
+    if let Some(changes) = value["documentChanges"].as_array() {
+        for change in changes {
+            if let Some(kind) = change["kind"].as_str() {
+                return Err(anyhow!("Unsupported workspace edit: {}", kind));
+            }
+            add(&change["textDocument"]["uri"], &change["edits"])?;
+        }
+    } else if let Some(changes) = value["changes"].as_object() {
+        for (uri, edits) in changes {
+            add(&Value::String(uri.clone()), edits)?;
+        }
+    }
+
+    Ok(files)
+}
+
+// 行の中の UTF-16 での位置をバイト位置にする
+pub fn utf16_to_byte(line: &str, character: usize) -> usize {
+    let mut len = 0;
+    for (i, c) in line.char_indices() {
+        if len >= character {
+            return i;
+        }
+        len += c.len_utf16();
+    }
+    line.len()
+}
+
+// 後ろの編集から適用するので、前の編集の位置はずれない
+pub fn apply_text_edits(text: &str, edits: &[TextEdit]) -> String {
+    let mut line_starts = vec![0];
+    line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+
+    let to_byte = |pos: LspPosition| {
+        let Some(start) = line_starts.get(pos.line) else {
+            return text.len();
+        };
+        let end = line_starts
+            .get(pos.line + 1)
+            .map_or(text.len(), |end| end - 1);
+        start + utf16_to_byte(&text[*start..end], pos.character)
+    };
+
+    let mut edits: Vec<&TextEdit> = edits.iter().collect();
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+
+    let mut text = text.to_string();
+    for edit in edits {
+        let start = to_byte(edit.range.start);
+        let end = to_byte(edit.range.end).max(start);
+        text.replace_range(start..end, &edit.new_text);
+    }
+    text
+}
+
+// didChange の送り方 (initialize の応答で決まる)
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TextDocumentSync {
+    None,
+    Full,
+    Incremental,
+}
+
+impl TextDocumentSync {
+    fn from_capabilities(capabilities: &Value) -> Self {
+        let sync = &capabilities["textDocumentSync"];
+        let kind = sync.get("change").unwrap_or(sync).as_u64();
+
+        match kind {
+            Some(1) => Self::Full,
+            Some(2) => Self::Incremental,
+            _ => Self::None,
+        }
+    }
+}
+
+// 標準入出力で言語サーバーとやり取りする
+pub struct LspClient {
+    child: Child,
+    writer: Mutex<ChildStdin>, // サーバーからのリクエストへの応答と共有する
+    receiver: Mutex<Receiver<Value>>,
+    next_id: u64,
+    pending: HashMap<u64, String>, // 応答を待っているリクエストのメソッド
+    sync: TextDocumentSync,
+    can_format: bool,
+    is_initialized: bool,
+    queue: Vec<Value>,     // initialize の応答が来てから送る通知
+    events: Vec<LspEvent>, // 応答を待っている間に届いた診断など (次の poll で返す)
+}
+
+impl LspClient {
+    pub fn start(program: &str, args: &[&str], root: &Path) -> anyhow::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stdout"))?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stdin"))?;
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            loop {
+                match read_message(&mut reader) {
+                    Ok(Some(Ok(message))) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(Some(Err(err))) => {
+                        log::warn!("Invalid message from the language server: {}", err)
+                    }
+                    Ok(None) | Err(_) => break,
+                }
+            }
+        });
+
+        let mut client = Self {
+            child,
+            writer: Mutex::new(stdin),
+            receiver: Mutex::new(receiver),
+            next_id: 0,
+            pending: HashMap::new(),
+            sync: TextDocumentSync::None,
+            can_format: false,
+            is_initialized: false,
+            queue: vec![],
+            events: vec![],
+        };
+
+        client.request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": path_to_uri(root),
+                "capabilities": {
+                    "textDocument": {
+                        "synchronization": { "didSave": true },
+                        "publishDiagnostics": { "relatedInformation": false },
+                        "definition": { "linkSupport": true },
+                        "references": {},
+                        "hover": { "contentFormat": ["plaintext", "markdown"] },
+                        "rename": { "prepareSupport": false },
+                        "completion": { "completionItem": { "snippetSupport": false } },
+                        "formatting": {},
+                    },
+                },
+            }),
+        )?;
+
+        Ok(client)
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    fn send(&self, message: &Value) -> anyhow::Result<()> {
+        write_message(&mut *self.writer.lock().unwrap(), message)?;
+        Ok(())
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> anyhow::Result<u64> {
+        self.next_id += 1;
+        let id = self.next_id;
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+
+        self.pending.insert(id, method.to_string());
+        if self.is_initialized || method == "initialize" {
+            self.send(&message)?;
+        } else {
+            self.queue.push(message);
+        }
+        Ok(id)
+    }
+
+    // textDocument/definition など、カーソル位置についてのリクエスト (params に追加の引数を入れる)
+    pub fn request_at(
+        &mut self,
+        method: &str,
+        path: &Path,
+        position: LspPosition,
+        mut params: Value,
+    ) -> anyhow::Result<()> {
+        params["textDocument"] = json!({ "uri": path_to_uri(path) });
+        params["position"] = serde_json::to_value(position)?;
+        self.request(method, params)?;
+        Ok(())
+    }
+
+    // 応答が届くまで待つ (間に届いたメッセージも処理しておく)
+    fn request_wait(&mut self, method: &str, params: Value) -> anyhow::Result<Value> {
+        let id = self.request(method, params)?;
+        let deadline = Instant::now() + REQUEST_TIMEOUT;
+
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let message = self.receiver.lock().unwrap().recv_timeout(timeout);
+            match message {
+                Ok(message)
+                    if message["id"].as_u64() == Some(id) && message["method"].is_null() =>
+                {
+                    self.pending.remove(&id);
+                    return match message["error"]["message"].as_str() {
+                        Some(error) => Err(anyhow!("{}", error)),
+                        None => Ok(message["result"].clone()),
+                    };
+                }
+                Ok(message) => {
+                    let event = self.on_message(message)?;
+                    self.events.extend(event);
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    // 後から届いた応答は無視する
+                    self.pending.remove(&id);
+                    return Err(anyhow!("Language server timed out"));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(anyhow!("Language server exited"));
+                }
+            }
+        }
+    }
+
+    // textDocument/formatting (保存前にも使うので応答を待つ)
+    pub fn formatting(&mut self, path: &Path, tab_size: usize) -> anyhow::Result<Vec<TextEdit>> {
+        if self.is_initialized && !self.can_format {
+            return Err(anyhow!("Language server doesn't support formatting"));
+        }
+
+        let result = self.request_wait(
+            "textDocument/formatting",
+            json!({
+                "textDocument": { "uri": path_to_uri(path) },
+                "options": { "tabSize": tab_size, "insertSpaces": true },
+            }),
+        )?;
+        Ok(serde_json::from_value::<Option<Vec<TextEdit>>>(result)?.unwrap_or_default())
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> anyhow::Result<()> {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+
+        if self.is_initialized {
+            self.send(&message)
+        } else {
+            self.queue.push(message);
+            Ok(())
+        }
+    }
+
+    pub fn did_open(
+        &mut self,
+        path: &Path,
+        language_id: &str,
+        version: usize,
+        text: &str,
+    ) -> anyhow::Result<()> {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": path_to_uri(path),
+                    "languageId": language_id,
+                    "version": version,
+                    "text": text,
+                },
+            }),
+        )
+    }
+
+    // サーバーが差分に対応していなければ全体を送る
+    // (initialize の応答が来る前はどちらか分からないので全体を送る。全体の文字列は必要なときだけ作る)
+    pub fn did_change(
+        &mut self,
+        path: &Path,
+        version: usize,
+        changes: Vec<ContentChange>,
+        text: impl FnOnce() -> String,
+    ) -> anyhow::Result<()> {
+        let content_changes = match self.sync {
+            _ if !self.is_initialized => json!([{ "text": text() }]),
+            TextDocumentSync::None => return Ok(()),
+            TextDocumentSync::Full => json!([{ "text": text() }]),
+            TextDocumentSync::Incremental => serde_json::to_value(changes)?,
+        };
+
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": path_to_uri(path), "version": version },
+                "contentChanges": content_changes,
+            }),
+        )
+    }
+
+    pub fn did_save(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.notify(
+            "textDocument/didSave",
+            json!({ "textDocument": { "uri": path_to_uri(path) } }),
+        )
+    }
+
+    pub fn did_close(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.notify(
+            "textDocument/didClose",
+            json!({ "textDocument": { "uri": path_to_uri(path) } }),
+        )
+    }
+
+    fn on_response(&mut self, method: &str, result: &Value) -> anyhow::Result<()> {
+        if method == "initialize" {
+            self.sync = TextDocumentSync::from_capabilities(&result["capabilities"]);
+            self.can_format = matches!(
+                result["capabilities"]["documentFormattingProvider"],
+                Value::Bool(true) | Value::Object(_)
+            );
+            self.is_initialized = true;
+            self.send(&json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }))?;
+
+            for message in std::mem::take(&mut self.queue) {
+                self.send(&message)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // 使わないリクエストにも応答しないとサーバーが待ち続けることがある
+    fn on_server_request(&self, id: &Value, method: &str, params: &Value) -> anyhow::Result<()> {
+        let result = match method {
+            "workspace/configuration" => {
+                let count = params["items"].as_array().map_or(0, |items| items.len());
+                Value::Array(vec![Value::Null; count])
+            }
+            _ => Value::Null,
+        };
+
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+
+    fn on_notification(&self, method: &str, params: &Value) -> Option<LspEvent> {
+        if method != "textDocument/publishDiagnostics" {
+            return None;
+        }
+
+        let path = uri_to_path(params["uri"].as_str()?)?;
+        let diagnostics: Vec<RawDiagnostic> =
+            serde_json::from_value(params["diagnostics"].clone()).ok()?;
+
+        Some(LspEvent::Diagnostics {
+            path,
+            diagnostics: diagnostics
+                .into_iter()
+                .map(|diagnostic| LspDiagnostic {
+                    range: diagnostic.range,
+                    severity: DiagnosticSeverity::from_lsp(diagnostic.severity),
+                    message: diagnostic.message,
+                    source: diagnostic.source,
+                })
+                .collect(),
+        })
+    }
+
+    fn on_message(&mut self, message: Value) -> anyhow::Result<Option<LspEvent>> {
+        let method = message["method"].as_str();
+
+        match (message.get("id"), method) {
+            (Some(id), Some(method)) => self.on_server_request(id, method, &message["params"])?,
+            (Some(id), None) => {
+                let method = id.as_u64().and_then(|id| self.pending.remove(&id));
+                match method.as_deref() {
+                    Some("initialize" | "shutdown") => {
+                        self.on_response(method.as_deref().unwrap(), &message["result"])?
+                    }
+                    Some(method) => {
+                        let result = match message["error"]["message"].as_str() {
+                            Some(error) => Err(error.to_string()),
+                            None => Ok(message["result"].clone()),
+                        };
+                        return Ok(Some(LspEvent::Response {
+                            method: method.to_string(),
+                            result,
+                        }));
+                    }
+                    None => {}
+                }
+            }
+            (None, Some(method)) => return Ok(self.on_notification(method, &message["params"])),
+            (None, None) => {}
+        }
+
+        Ok(None)
+    }
+
+    // 届いているメッセージを処理する (待たない)
+    pub fn poll(&mut self) -> Vec<LspEvent> {
+        let messages: Vec<Value> = self.receiver.lock().unwrap().try_iter().collect();
+
+        // 1つのメッセージの処理に失敗しても残りは処理する
+        let mut events = std::mem::take(&mut self.events);
+        for message in messages {
+            match self.on_message(message) {
+                Ok(event) => events.extend(event),
+                Err(err) => log::warn!("Failed to handle a language server message: {}", err),
+            }
+        }
+        events
+    }
+
+    // shutdown の応答を待ってから exit を送る (応答がなければ終了させる)
+    pub fn shutdown(&mut self) {
+        if let Ok(id) = self.request("shutdown", Value::Null) {
+            let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+
+            loop {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                let message = self.receiver.lock().unwrap().recv_timeout(timeout);
+                match message {
+                    Ok(message) if message["id"].as_u64() == Some(id) => break,
+                    // 待っている間のリクエストにも応答する
+                    Ok(message) => {
+                        let _ = self.on_message(message);
+                    }
+                    Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        }
+
+        let _ = self.send(&json!({ "jsonrpc": "2.0", "method": "exit" }));
+
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        while self.is_running() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// 言語サーバーをコマンドごとに1つだけ起動して使い回す
+#[derive(Default)]
+pub struct LspManager {
+    clients: HashMap<String, LspClient>,
+    failed: HashSet<String>, // 起動できなかったコマンド (何度も試さない)
+    documents: HashMap<PathBuf, String>, // didOpen したファイルとサーバーのコマンド
+}
+
+impl LspManager {
+    pub fn get_client(&mut self, command: &str) -> Option<&mut LspClient> {
+        if self.failed.contains(command) {
+            return None;
+        }
+
+        if !self.clients.contains_key(command) {
+            let args: Vec<&str> = command.split_whitespace().collect();
+            let (program, args) = args.split_first()?;
+            let root = std::env::current_dir().ok()?;
+
+            match LspClient::start(program, args, &root) {
+                Ok(client) => {
+                    self.clients.insert(command.to_string(), client);
+                }
+                Err(err) => {
+                    log::warn!("Failed to start language server {}: {}", command, err);
+                    self.failed.insert(command.to_string());
+                    return None;
+                }
+            }
+        }
+
+        self.clients.get_mut(command)
+    }
+
+    pub fn is_open(&self, path: &Path) -> bool {
+        self.documents.contains_key(path)
+    }
+
+    pub fn open_document(
+        &mut self,
+        command: &str,
+        path: &Path,
+        language_id: &str,
+        version: usize,
+        text: &str,
+    ) -> anyhow::Result<()> {
+        let Some(client) = self.get_client(command) else {
+            return Ok(());
+        };
+
+        client.did_open(path, language_id, version, text)?;
+        self.documents
+            .insert(path.to_path_buf(), command.to_string());
+        Ok(())
+    }
+
+    pub fn get_document_client(&mut self, path: &Path) -> Option<&mut LspClient> {
+        let command = self.documents.get(path)?;
+        self.clients.get_mut(command)
+    }
+
+    // paths に含まれないファイル (バッファを閉じたもの) を didClose する
+    pub fn close_documents_except(&mut self, paths: &HashSet<PathBuf>) -> anyhow::Result<()> {
+        let closed: Vec<PathBuf> = self
+            .documents
+            .keys()
+            .filter(|path| !paths.contains(*path))
+            .cloned()
+            .collect();
+
+        for path in closed {
+            if let Some(client) = self.get_document_client(&path) {
+                client.did_close(&path)?;
+            }
+            self.documents.remove(&path);
+        }
+
+        Ok(())
+    }
+
+    // 終了したサーバーは取り除き、もう起動しない
+    pub fn poll(&mut self) -> Vec<LspEvent> {
+        let mut events = vec![];
+        let mut exited = vec![];
+
+        for (command, client) in &mut self.clients {
+            events.extend(client.poll());
+
+            if !client.is_running() {
+                exited.push(command.clone());
+            }
+        }
+
+        for command in exited {
+            log::warn!("Language server exited: {}", command);
+            self.clients.remove(&command);
+            self.documents.retain(|_, document| *document != command);
+            self.failed.insert(command);
+        }
+
+        events
+    }
+
+    pub fn shutdown(&mut self) {
+        for client in self.clients.values_mut() {
+            client.shutdown();
+        }
+        self.clients.clear();
+        self.documents.clear();
+    }
+}
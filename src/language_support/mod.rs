@@ -1,9 +1,14 @@
 use std::any::Any;
 
+use complete::CompletionItem;
 use highlight::HighlightToken;
+use lint::LintError;
 
+pub mod complete;
 pub mod highlight;
 pub mod langs;
+pub mod lint;
+pub mod lsp;
 
 pub trait LanguageSupport: Any + Send + Sync {
     // シンタックスハイライト
@@ -12,31 +17,31 @@ pub trait LanguageSupport: Any + Send + Sync {
         None
     }
 
-    // /// 補完候補を取得
-    // fn complete(&self, cursor_position: usize, source_code: &str) -> Option<Vec<CompletionItem>> {
-    //     let _ = (cursor_position, source_code);
-    //     None
-    // }
-    //
-    // /// エラーチェックを実行
-    // fn lint(&self, source_code: &str) -> Option<Vec<LintError>> {
-    //     let _ = source_code;
-    //     None
-    // }
-    //
-    // /// フォーマット済みコードを返す
-    // fn format(&self, source_code: &str) -> Option<String> {
-    //     let _ = source_code;
-    //     None
-    // }
-    //
+    // 補完候補 (source_code はカーソルまでの数行で、cursor_position はその中での文字単位の位置)
+    fn complete(&self, cursor_position: usize, source_code: &str) -> Option<Vec<CompletionItem>> {
+        let _ = (cursor_position, source_code);
+        None
+    }
+
+    // エラーチェック
+    fn lint(&self, source_code: &str) -> Option<Vec<LintError>> {
+        let _ = source_code;
+        None
+    }
+
+    // 整形したコード (整形できなければ None)
+    fn format(&self, source_code: &str) -> Option<String> {
+        let _ = source_code;
+        None
+    }
+
     // /// スニペットのリストを取得
     // fn snippets(&self) -> Option<Vec<Snippet>> {
     //     None
     // }
-    //
-    // /// LSPサーバーのコマンドを取得
-    // fn get_lsp_server_cmd(&self) -> Option<String> {
-    //     None
-    // }
+
+    // LSPサーバーのコマンド (引数は空白で区切る)
+    fn get_lsp_server_cmd(&self) -> Option<String> {
+        None
+    }
 }
@@ -1,7 +1,15 @@
 mod action;
 mod app;
 pub(crate) mod editor;
+pub(crate) mod explorer;
+pub(crate) mod finder;
 pub(crate) mod language_support;
+pub(crate) mod quickfix;
+pub(crate) mod replace;
+pub(crate) mod terminal;
 pub mod utils;
 
 pub use app::*;
+
+// tests/lsp.rs から使う
+pub use language_support::lsp;
@@ -1,7 +1,10 @@
 use clap::Parser;
 use log::error;
 use tui_studio::{
-    utils::term::{init_term, safe_exit},
+    utils::{
+        log::init_logger,
+        term::{init_term, safe_exit},
+    },
     App,
 };
 
@@ -9,14 +12,15 @@ use tui_studio::{
 #[command(version, about, long_about = None)]
 struct Args {
     #[arg()]
-    path: Option<String>,
+    paths: Vec<String>,
 }
 
 fn main() -> anyhow::Result<()> {
     init_term()?;
+    init_logger()?;
     let args = Args::parse();
 
-    if let Err(err) = App::run(args.path) {
+    if let Err(err) = App::run(args.paths) {
         error!("{}", err)
     };
 
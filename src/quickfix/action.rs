@@ -0,0 +1,21 @@
+use crate::action::AppAction;
+
+#[derive(Debug, Clone, Hash)]
+pub enum QuickfixAction {
+    Open,    // 一覧を表示して移動する (:copen)
+    Close,   // 一覧を閉じる (:cclose)
+    Unfocus, // エディタに戻る
+    MoveUp,
+    MoveDown,
+    MoveToTop,
+    MoveToBottom,
+    Jump,     // 選択している位置に移動する
+    Next,     // 次の位置に移動する (:cnext)
+    Previous, // 前の位置に移動する (:cprev)
+}
+
+impl QuickfixAction {
+    pub fn into_app(self) -> AppAction {
+        AppAction::QuickfixAction(self)
+    }
+}
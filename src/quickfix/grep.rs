@@ -0,0 +1,168 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use regex::Regex;
+
+use crate::utils::walk::{display_path, read_text_file, walk_files_parallel};
+
+use super::quickfix::QuickfixItem;
+
+// これ以上見つかったら検索を打ち切る
+const MAX_ITEMS: usize = 10000;
+
+// 別スレッドで集めた検索結果 (searching が false になったら完了)
+#[derive(Default)]
+pub(super) struct GrepResults {
+    pub items: Mutex<Vec<QuickfixItem>>,
+    pub searching: AtomicBool,
+    pub cancelled: AtomicBool,
+}
+
+pub(super) fn grep(regex: &Regex, path: &Path, results: &GrepResults) {
+    walk_files_parallel(path, |path| {
+        if results.cancelled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        let items = grep_file(regex, path);
+
+        // ファイルごとにまとめて追加するので、同じファイルの結果は並んだままになる
+        let mut all_items = results.items.lock().unwrap();
+        all_items.extend(items);
+        if all_items.len() >= MAX_ITEMS {
+            all_items.truncate(MAX_ITEMS);
+            results.cancelled.store(true, Ordering::Relaxed);
+            return false;
+        }
+
+        true
+    });
+
+    results.searching.store(false, Ordering::Relaxed);
+}
+
+fn grep_file(regex: &Regex, path: &Path) -> Vec<QuickfixItem> {
+    let Some(text) = read_text_file(path) else {
+        return vec![];
+    };
+    let path = display_path(path);
+
+    text.lines()
+        .enumerate()
+        .filter_map(|(y, line)| {
+            let m = regex.find(line)?;
+            Some(QuickfixItem {
+                path: path.clone(),
+                line: y,
+                column: line[..m.start()].chars().count(),
+                text: line.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::PathBuf,
+        process,
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    use regex::Regex;
+
+    use super::{grep, grep_file, GrepResults, MAX_ITEMS};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("tui-studio-grep-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn column_counts_chars_before_the_match() {
+        let dir = temp_dir("column");
+        let path = dir.join("a.txt");
+        fs::write(&path, "日本語 foo\nbar\n  ✓ foo foo\n").unwrap();
+
+        let items = grep_file(&Regex::new("foo").unwrap(), &path);
+
+        let found: Vec<_> = items
+            .iter()
+            .map(|item| (item.line, item.column, item.text.as_str()))
+            .collect();
+        assert_eq!(found, vec![(0, 4, "日本語 foo"), (2, 4, "✓ foo foo")]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn binary_files_are_skipped() {
+        let dir = temp_dir("binary");
+        let path = dir.join("a.bin");
+        fs::write(&path, "foo\0foo\n").unwrap();
+
+        assert!(grep_file(&Regex::new("foo").unwrap(), &path).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn results_are_truncated_at_max_items() {
+        let dir = temp_dir("max");
+        fs::write(dir.join("a.txt"), "foo\n".repeat(MAX_ITEMS + 10)).unwrap();
+
+        let results = GrepResults {
+            searching: AtomicBool::new(true),
+            ..Default::default()
+        };
+        grep(&Regex::new("foo").unwrap(), &dir, &results);
+
+        assert_eq!(results.items.lock().unwrap().len(), MAX_ITEMS);
+        assert!(results.cancelled.load(Ordering::Relaxed));
+        assert!(!results.searching.load(Ordering::Relaxed));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cancelled_search_adds_nothing() {
+        let dir = temp_dir("cancel");
+        fs::write(dir.join("a.txt"), "foo\n").unwrap();
+
+        let results = GrepResults {
+            searching: AtomicBool::new(true),
+            cancelled: AtomicBool::new(true),
+            ..Default::default()
+        };
+        grep(&Regex::new("foo").unwrap(), &dir, &results);
+
+        assert!(results.items.lock().unwrap().is_empty());
+        assert!(!results.searching.load(Ordering::Relaxed));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn results_of_each_file_stay_together() {
+        let dir = temp_dir("group");
+        for i in 0..20 {
+            fs::write(dir.join(format!("{}.txt", i)), "foo\nbar\n".repeat(50)).unwrap();
+        }
+
+        let results = GrepResults::default();
+        grep(&Regex::new("foo").unwrap(), &dir, &results);
+
+        let items = results.items.into_inner().unwrap();
+        assert_eq!(items.len(), 20 * 50);
+        for chunk in items.chunks(50) {
+            assert!(chunk.iter().all(|item| item.path == chunk[0].path));
+            let lines: Vec<usize> = chunk.iter().map(|item| item.line).collect();
+            assert_eq!(lines, (0..100).step_by(2).collect::<Vec<_>>());
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
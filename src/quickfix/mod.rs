@@ -0,0 +1,5 @@
+pub mod action;
+mod grep;
+#[allow(clippy::module_inception)]
+pub mod quickfix;
+pub mod renderer;
@@ -0,0 +1,355 @@
+use std::{
+    ops::Range,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use anyhow::anyhow;
+
+use crate::{
+    editor::{action::EditorAction, mode::EditorMode, search::build_regex},
+    utils::{
+        command::{split_first_arg, CommandManager},
+        key_binding::{Key, KeyConfig, KeyConfigType},
+        vec2::IVec2,
+    },
+};
+
+use super::{
+    action::QuickfixAction,
+    grep::{grep, GrepResults},
+};
+
+// タイトル行を含めた高さ
+const DEFAULT_HEIGHT: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct QuickfixItem {
+    pub path: String,
+    pub line: usize,   // 0始まりの行番号
+    pub column: usize, // 一致した位置 (文字単位)
+    pub text: String,
+}
+
+pub enum QuickfixEvent {
+    Jump(QuickfixItem),
+}
+
+// :grep の結果の一覧 (エディタの下に表示する)
+pub struct QuickfixList {
+    title: String,
+    results: Arc<GrepResults>,
+    selected: usize,
+    scroll: usize,
+    height: usize,
+    has_jumped: bool, // まだ一度も移動していなければ :cnext で最初の位置に移動する
+    is_visible: bool,
+    is_focused: bool,
+}
+
+impl Default for QuickfixList {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            results: Arc::default(),
+            selected: 0,
+            scroll: 0,
+            height: DEFAULT_HEIGHT,
+            has_jumped: false,
+            is_visible: false,
+            is_focused: false,
+        }
+    }
+}
+
+impl QuickfixList {
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn get_scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn get_item_count(&self) -> usize {
+        self.results.items.lock().unwrap().len()
+    }
+
+    pub fn get_items(&self, range: Range<usize>) -> Vec<QuickfixItem> {
+        let items = self.results.items.lock().unwrap();
+        let range = range.start.min(items.len())..range.end.min(items.len());
+        items[range].to_vec()
+    }
+
+    fn get_item(&self, index: usize) -> Option<QuickfixItem> {
+        self.results.items.lock().unwrap().get(index).cloned()
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.results.searching.load(Ordering::Relaxed)
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.is_visible
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.is_visible && self.is_focused
+    }
+
+    pub fn set_focused(&mut self, is_focused: bool) {
+        self.is_focused = is_focused;
+    }
+
+    // タイトル行を含めた高さ (非表示なら 0)
+    pub fn get_height(&self) -> usize {
+        if self.is_visible {
+            self.height
+        } else {
+            0
+        }
+    }
+
+    // 一覧を表示できる行数
+    fn get_list_height(&self) -> usize {
+        self.height.saturating_sub(1).max(1)
+    }
+
+    // :grep pattern [path] (結果は検索しながら一覧に追加していく)
+    pub fn grep(&mut self, args: &str) -> anyhow::Result<()> {
+        let (pattern, path) = split_first_arg(args)?;
+        if pattern.is_empty() {
+            return Err(anyhow!("Argument required"));
+        }
+        let regex = build_regex(&pattern)?;
+        let path = PathBuf::from(if path.is_empty() { "." } else { &path });
+        if !path.exists() {
+            return Err(anyhow!("Can't find file or directory: {}", path.display()));
+        }
+
+        // 前の検索がまだ続いていれば止める
+        self.results.cancelled.store(true, Ordering::Relaxed);
+        self.results = Arc::new(GrepResults {
+            searching: AtomicBool::new(true),
+            ..Default::default()
+        });
+
+        self.title = format!(":grep {}", args.trim());
+        self.selected = 0;
+        self.scroll = 0;
+        self.has_jumped = false;
+        self.is_visible = true;
+        self.is_focused = true;
+
+        let results = Arc::clone(&self.results);
+        thread::spawn(move || grep(&regex, &path, &results));
+        Ok(())
+    }
+
+    // 言語サーバーから受け取った参照などを一覧にする
+    pub fn set_items(&mut self, title: String, items: Vec<QuickfixItem>) {
+        self.results.cancelled.store(true, Ordering::Relaxed);
+        self.results = Arc::new(GrepResults {
+            items: Mutex::new(items),
+            ..Default::default()
+        });
+
+        self.title = title;
+        self.selected = 0;
+        self.scroll = 0;
+        self.has_jumped = false;
+        self.is_visible = true;
+        self.is_focused = true;
+    }
+
+    pub fn close(&mut self) {
+        self.is_visible = false;
+        self.is_focused = false;
+    }
+
+    fn sync_scroll(&mut self) {
+        let height = self.get_list_height();
+
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+
+    fn move_by(&mut self, offset: isize) {
+        let last = self.get_item_count().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(offset).min(last);
+    }
+
+    // :cnext, :cprev (一覧の端を越える場合はエラーにする)
+    fn jump_by(&mut self, offset: isize) -> anyhow::Result<Option<QuickfixEvent>> {
+        let len = self.get_item_count();
+        if len == 0 {
+            return Err(anyhow!("No matches"));
+        }
+
+        let offset = if !self.has_jumped && offset > 0 {
+            offset - 1
+        } else {
+            offset
+        };
+        self.selected = self
+            .selected
+            .checked_add_signed(offset)
+            .filter(|index| *index < len)
+            .ok_or_else(|| anyhow!("No more items"))?;
+        Ok(self.jump())
+    }
+
+    fn jump(&mut self) -> Option<QuickfixEvent> {
+        let item = self.get_item(self.selected)?;
+        self.has_jumped = true;
+        self.is_focused = false;
+        Some(QuickfixEvent::Jump(item))
+    }
+
+    pub fn on_action(
+        &mut self,
+        action: QuickfixAction,
+        count: Option<usize>,
+    ) -> anyhow::Result<Option<QuickfixEvent>> {
+        let n = count.unwrap_or(1) as isize;
+        let mut event = None;
+
+        match action {
+            QuickfixAction::Open => {
+                self.is_visible = true;
+                self.is_focused = true;
+            }
+            QuickfixAction::Close => self.close(),
+            QuickfixAction::Unfocus => self.is_focused = false,
+            QuickfixAction::MoveUp => self.move_by(-n),
+            QuickfixAction::MoveDown => self.move_by(n),
+            QuickfixAction::MoveToTop => self.selected = 0,
+            QuickfixAction::MoveToBottom => self.selected = self.get_item_count().saturating_sub(1),
+            QuickfixAction::Jump => event = self.jump(),
+            QuickfixAction::Next => event = self.jump_by(n)?,
+            QuickfixAction::Previous => event = self.jump_by(-n)?,
+        }
+
+        self.sync_scroll();
+        Ok(event)
+    }
+
+    // y は一覧の最上行 (タイトル行) からの位置
+    pub fn on_click(&mut self, y: usize) -> Option<QuickfixEvent> {
+        self.is_focused = true;
+
+        let row = y.checked_sub(1)?;
+        let index = self.scroll + row;
+        if index >= self.get_item_count() {
+            return None;
+        }
+
+        self.selected = index;
+        self.jump()
+    }
+
+    pub fn on_scroll(&mut self, offset: IVec2) {
+        let height = self.get_list_height();
+        let len = self.get_item_count();
+        let max = len.saturating_sub(height);
+        self.scroll = self.scroll.saturating_add_signed(offset.y).min(max);
+        self.selected = self
+            .selected
+            .clamp(self.scroll, self.scroll + height - 1)
+            .min(len.saturating_sub(1));
+    }
+
+    pub fn register_keybindings(&self, key_config: &mut KeyConfig) {
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Char('q')],
+            QuickfixAction::Close.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Char(':')],
+            EditorAction::SetMode(EditorMode::Command).into_app(),
+        );
+
+        // Focus
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Ctrl('w'), Key::Char('k')],
+            QuickfixAction::Unfocus.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Ctrl('w'), Key::Char('w')],
+            QuickfixAction::Unfocus.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Ctrl('w'), Key::Ctrl('w')],
+            QuickfixAction::Unfocus.into_app(),
+        );
+
+        // Move
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Char('j')],
+            QuickfixAction::MoveDown.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Down],
+            QuickfixAction::MoveDown.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Char('k')],
+            QuickfixAction::MoveUp.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Up],
+            QuickfixAction::MoveUp.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Char('g'), Key::Char('g')],
+            QuickfixAction::MoveToTop.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Char('G')],
+            QuickfixAction::MoveToBottom.into_app(),
+        );
+
+        // Jump
+        key_config.register(
+            KeyConfigType::Quickfix,
+            vec![Key::Char('\n')],
+            QuickfixAction::Jump.into_app(),
+        );
+    }
+
+    pub fn register_commands(&self, cmd_manager: &mut CommandManager) {
+        cmd_manager.register("copen", vec![QuickfixAction::Open.into_app()]);
+        cmd_manager.register("cope", vec![QuickfixAction::Open.into_app()]);
+        cmd_manager.register("cclose", vec![QuickfixAction::Close.into_app()]);
+        cmd_manager.register("ccl", vec![QuickfixAction::Close.into_app()]);
+        cmd_manager.register("cnext", vec![QuickfixAction::Next.into_app()]);
+        cmd_manager.register("cn", vec![QuickfixAction::Next.into_app()]);
+        cmd_manager.register("cprevious", vec![QuickfixAction::Previous.into_app()]);
+        cmd_manager.register("cprev", vec![QuickfixAction::Previous.into_app()]);
+        cmd_manager.register("cp", vec![QuickfixAction::Previous.into_app()]);
+        cmd_manager.register("cNext", vec![QuickfixAction::Previous.into_app()]);
+        cmd_manager.register("cN", vec![QuickfixAction::Previous.into_app()]);
+    }
+}
@@ -0,0 +1,107 @@
+use std::io::stdout;
+
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{Color as CrosstermColor, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+};
+use unicode_width::UnicodeWidthChar;
+
+use crate::utils::rect::Rect;
+
+use super::quickfix::{QuickfixItem, QuickfixList};
+
+#[derive(Default)]
+pub struct QuickfixRenderer {}
+
+impl QuickfixRenderer {
+    // 色ごとに分けた文字列を width 列に収まるように表示し、残りを空白で埋める
+    fn render_parts(&self, parts: &[(&str, CrosstermColor)], width: usize) {
+        let mut col = 0;
+
+        'parts: for (text, color) in parts {
+            queue!(stdout(), SetForegroundColor(*color)).unwrap();
+            for c in text.chars() {
+                let char_width = c.width().unwrap_or(0);
+                if col + char_width > width {
+                    break 'parts;
+                }
+                queue!(stdout(), Print(c)).unwrap();
+                col += char_width;
+            }
+        }
+
+        queue!(stdout(), Print(" ".repeat(width.saturating_sub(col)))).unwrap();
+    }
+
+    fn render_title(&self, list: &QuickfixList, rect: &Rect) {
+        let status = format!(
+            "{}{}",
+            list.get_item_count(),
+            if list.is_searching() { " …" } else { "" }
+        );
+        let title = format!(" [Quickfix List] {} ({}) ", list.get_title(), status);
+
+        let background = if list.is_focused() {
+            CrosstermColor::White
+        } else {
+            CrosstermColor::DarkGrey
+        };
+
+        queue!(
+            stdout(),
+            MoveTo(rect.pos.x as u16, rect.pos.y as u16),
+            SetBackgroundColor(background)
+        )
+        .unwrap();
+        self.render_parts(&[(&title, CrosstermColor::Black)], rect.size.x);
+        queue!(stdout(), ResetColor).unwrap();
+    }
+
+    // path|line col column| text
+    fn render_item(&self, item: &QuickfixItem, width: usize, background: CrosstermColor) {
+        let position = format!("{} col {}", item.line + 1, item.column + 1);
+
+        queue!(stdout(), SetBackgroundColor(background)).unwrap();
+        self.render_parts(
+            &[
+                (&item.path, CrosstermColor::Blue),
+                ("|", CrosstermColor::Reset),
+                (&position, CrosstermColor::Yellow),
+                ("| ", CrosstermColor::Reset),
+                (&item.text, CrosstermColor::Reset),
+            ],
+            width,
+        );
+        queue!(stdout(), ResetColor).unwrap();
+    }
+
+    // エディタの下 (コマンドラインの上) に一覧を描画する
+    pub fn render(&self, list: &QuickfixList, rect: &Rect) {
+        self.render_title(list, rect);
+
+        let height = rect.size.y.saturating_sub(1);
+        let scroll = list.get_scroll();
+        let items = list.get_items(scroll..scroll + height);
+
+        for draw_y in 0..height {
+            queue!(
+                stdout(),
+                MoveTo(rect.pos.x as u16, (rect.pos.y + 1 + draw_y) as u16)
+            )
+            .unwrap();
+
+            let Some(item) = items.get(draw_y) else {
+                queue!(stdout(), Print(" ".repeat(rect.size.x))).unwrap();
+                continue;
+            };
+
+            let background = match (scroll + draw_y == list.get_selected(), list.is_focused()) {
+                (true, true) => CrosstermColor::DarkBlue,
+                (true, false) => CrosstermColor::DarkGrey,
+                _ => CrosstermColor::Reset,
+            };
+            self.render_item(item, rect.size.x, background);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use crate::action::AppAction;
+
+#[derive(Debug, Clone, Hash)]
+pub enum ReplaceAction {
+    Close,   // 置換せずに閉じる
+    Unfocus, // エディタに戻る
+    MoveUp,
+    MoveDown,
+    MoveToTop,
+    MoveToBottom,
+    Toggle, // 一致 (ファイルの行ならファイル内のすべての一致) を置換するかどうか切り替える
+    Jump,   // 選択している一致の位置に移動する
+    Apply,  // 選択した一致をすべて置換する
+}
+
+impl ReplaceAction {
+    pub fn into_app(self) -> AppAction {
+        AppAction::ReplaceAction(self)
+    }
+}
@@ -0,0 +1,4 @@
+pub mod action;
+pub mod renderer;
+#[allow(clippy::module_inception)]
+pub mod replace;
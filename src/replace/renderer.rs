@@ -0,0 +1,149 @@
+use std::io::stdout;
+
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{Color as CrosstermColor, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+};
+use unicode_width::UnicodeWidthChar;
+
+use crate::utils::rect::Rect;
+
+use super::replace::{ReplaceFile, ReplaceRow, WorkspaceReplace};
+
+#[derive(Default)]
+pub struct ReplaceRenderer {}
+
+impl ReplaceRenderer {
+    // 色ごとに分けた文字列を width 列に収まるように表示し、残りを空白で埋める
+    fn render_parts(&self, parts: &[(&str, CrosstermColor)], width: usize) {
+        let mut col = 0;
+
+        'parts: for (text, color) in parts {
+            queue!(stdout(), SetForegroundColor(*color)).unwrap();
+            for c in text.chars() {
+                // 置換後の文字列に含まれる改行やタブも1文字で表示する
+                let c = match c {
+                    '\n' => '⏎',
+                    '\t' => ' ',
+                    c => c,
+                };
+                let char_width = c.width().unwrap_or(0);
+                if col + char_width > width {
+                    break 'parts;
+                }
+                queue!(stdout(), Print(c)).unwrap();
+                col += char_width;
+            }
+        }
+
+        queue!(stdout(), Print(" ".repeat(width.saturating_sub(col)))).unwrap();
+    }
+
+    fn render_title(&self, replace: &WorkspaceReplace, rect: &Rect) {
+        let (enabled, total) = replace.get_counts();
+        let title = format!(
+            " [Replace] {} ({}/{} in {} files)  <Space> toggle  a apply  q cancel ",
+            replace.get_title(),
+            enabled,
+            total,
+            replace.get_files().len()
+        );
+
+        let background = if replace.is_focused() {
+            CrosstermColor::White
+        } else {
+            CrosstermColor::DarkGrey
+        };
+
+        queue!(
+            stdout(),
+            MoveTo(rect.pos.x as u16, rect.pos.y as u16),
+            SetBackgroundColor(background)
+        )
+        .unwrap();
+        self.render_parts(&[(&title, CrosstermColor::Black)], rect.size.x);
+        queue!(stdout(), ResetColor).unwrap();
+    }
+
+    // [x] path (enabled/total)
+    fn render_file(&self, file: &ReplaceFile, width: usize) {
+        let enabled = file.matches.iter().filter(|m| m.is_enabled).count();
+        let check = match enabled {
+            0 => "[ ] ",
+            n if n == file.matches.len() => "[x] ",
+            _ => "[-] ",
+        };
+        let count = format!(" ({}/{})", enabled, file.matches.len());
+
+        self.render_parts(
+            &[
+                (check, CrosstermColor::Reset),
+                (&file.path, CrosstermColor::Blue),
+                (&count, CrosstermColor::DarkGrey),
+            ],
+            width,
+        );
+    }
+
+    // 一致した部分を置換前 (赤) と置換後 (緑) で並べて表示する
+    fn render_match(&self, file: &ReplaceFile, index: usize, width: usize) {
+        let m = &file.matches[index];
+        let line = &file.lines[&m.line];
+        let check = if m.is_enabled { "    [x] " } else { "    [ ] " };
+        let number = format!("{}: ", m.line + 1);
+        let before = line[..m.start].trim_start();
+
+        let mut parts = vec![
+            (check, CrosstermColor::Reset),
+            (number.as_str(), CrosstermColor::Yellow),
+            (before, CrosstermColor::Reset),
+        ];
+        if m.is_enabled {
+            parts.push((&line[m.start..m.end], CrosstermColor::Red));
+            parts.push((&m.replacement, CrosstermColor::Green));
+        } else {
+            parts.push((&line[m.start..m.end], CrosstermColor::Reset));
+        }
+        parts.push((&line[m.end..], CrosstermColor::Reset));
+
+        self.render_parts(&parts, width);
+    }
+
+    // エディタの下 (コマンドラインの上) に置換候補を描画する
+    pub fn render(&self, replace: &WorkspaceReplace, rect: &Rect) {
+        self.render_title(replace, rect);
+
+        let height = rect.size.y.saturating_sub(1);
+        let scroll = replace.get_scroll();
+        let rows = replace.get_rows();
+        let files = replace.get_files();
+
+        for draw_y in 0..height {
+            queue!(
+                stdout(),
+                MoveTo(rect.pos.x as u16, (rect.pos.y + 1 + draw_y) as u16)
+            )
+            .unwrap();
+
+            let index = scroll + draw_y;
+            let Some(row) = rows.get(index) else {
+                queue!(stdout(), Print(" ".repeat(rect.size.x))).unwrap();
+                continue;
+            };
+
+            let background = match (index == replace.get_selected(), replace.is_focused()) {
+                (true, true) => CrosstermColor::DarkBlue,
+                (true, false) => CrosstermColor::DarkGrey,
+                _ => CrosstermColor::Reset,
+            };
+            queue!(stdout(), SetBackgroundColor(background)).unwrap();
+
+            match row {
+                ReplaceRow::File(i) => self.render_file(&files[*i], rect.size.x),
+                ReplaceRow::Match(i, j) => self.render_match(&files[*i], *j, rect.size.x),
+            }
+            queue!(stdout(), ResetColor).unwrap();
+        }
+    }
+}
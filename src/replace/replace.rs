@@ -0,0 +1,611 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::anyhow;
+use regex::Regex;
+
+use crate::{
+    editor::{
+        action::EditorAction,
+        mode::EditorMode,
+        search::{build_regex, translate_replacement},
+    },
+    utils::{
+        command::split_first_arg,
+        key_binding::{Key, KeyConfig, KeyConfigType},
+        vec2::IVec2,
+        walk::{display_path, read_text_file, walk_files_parallel},
+    },
+};
+
+use super::action::ReplaceAction;
+
+// タイトル行を含めた高さ
+const DEFAULT_HEIGHT: usize = 12;
+
+// これ以上見つかったら検索を打ち切る
+const MAX_MATCHES: usize = 10000;
+
+#[derive(Debug, Clone)]
+pub struct ReplaceMatch {
+    pub line: usize,  // 0始まりの行番号
+    pub start: usize, // 行内のバイト位置
+    pub end: usize,
+    pub replacement: String, // キャプチャを展開した置換後の文字列
+    pub is_enabled: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplaceFile {
+    pub path: String,
+    pub lines: BTreeMap<usize, String>, // 一致した行の置換前の内容
+    pub matches: Vec<ReplaceMatch>,
+}
+
+impl ReplaceFile {
+    fn get_enabled_count(&self) -> usize {
+        self.matches.iter().filter(|m| m.is_enabled).count()
+    }
+
+    // 有効な一致だけを置換した行
+    fn get_edits(&self) -> Vec<LineEdit> {
+        self.lines
+            .iter()
+            .filter_map(|(y, line)| {
+                let mut after = String::new();
+                let mut last = 0;
+                for m in self.matches.iter().filter(|m| m.line == *y && m.is_enabled) {
+                    after.push_str(&line[last..m.start]);
+                    after.push_str(&m.replacement);
+                    last = m.end;
+                }
+                if last == 0 {
+                    return None;
+                }
+                after.push_str(&line[last..]);
+
+                Some(LineEdit {
+                    y: *y,
+                    before: line.clone(),
+                    after,
+                })
+            })
+            .collect()
+    }
+}
+
+// 1行の置き換え (before が現在の内容と一致しない場合は適用しない)
+#[derive(Debug, Clone)]
+pub struct LineEdit {
+    pub y: usize,
+    pub before: String,
+    pub after: String,
+}
+
+pub struct FileEdit {
+    pub path: String,
+    pub edits: Vec<LineEdit>,
+}
+
+// 一覧の行 (ファイル名の行とその下に並ぶ一致の行)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplaceRow {
+    File(usize),
+    Match(usize, usize),
+}
+
+pub enum ReplaceEvent {
+    Jump {
+        path: String,
+        line: usize,
+        column: usize,
+    },
+    Apply(Vec<FileEdit>),
+}
+
+// :replace の置換候補の一覧 (エディタの下に表示する)
+pub struct WorkspaceReplace {
+    title: String,
+    files: Vec<ReplaceFile>,
+    selected: usize,
+    scroll: usize,
+    height: usize,
+    is_visible: bool,
+    is_focused: bool,
+}
+
+impl Default for WorkspaceReplace {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            files: vec![],
+            selected: 0,
+            scroll: 0,
+            height: DEFAULT_HEIGHT,
+            is_visible: false,
+            is_focused: false,
+        }
+    }
+}
+
+impl WorkspaceReplace {
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn get_files(&self) -> &[ReplaceFile] {
+        &self.files
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn get_scroll(&self) -> usize {
+        self.scroll
+    }
+
+    // (有効な一致の数, 一致の数)
+    pub fn get_counts(&self) -> (usize, usize) {
+        self.files.iter().fold((0, 0), |(enabled, total), file| {
+            (
+                enabled + file.get_enabled_count(),
+                total + file.matches.len(),
+            )
+        })
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.is_visible
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.is_visible && self.is_focused
+    }
+
+    pub fn set_focused(&mut self, is_focused: bool) {
+        self.is_focused = is_focused;
+    }
+
+    // タイトル行を含めた高さ (非表示なら 0)
+    pub fn get_height(&self) -> usize {
+        if self.is_visible {
+            self.height
+        } else {
+            0
+        }
+    }
+
+    fn get_list_height(&self) -> usize {
+        self.height.saturating_sub(1).max(1)
+    }
+
+    pub fn get_rows(&self) -> Vec<ReplaceRow> {
+        self.files
+            .iter()
+            .enumerate()
+            .flat_map(|(i, file)| {
+                std::iter::once(ReplaceRow::File(i))
+                    .chain((0..file.matches.len()).map(move |j| ReplaceRow::Match(i, j)))
+            })
+            .collect()
+    }
+
+    // :replace pattern replacement (開いているバッファは保存していない内容も対象にする)
+    pub fn search(
+        &mut self,
+        args: &str,
+        buffer_lines: HashMap<PathBuf, Vec<String>>,
+    ) -> anyhow::Result<()> {
+        let (pattern, rest) = split_first_arg(args)?;
+        let (replacement_arg, _) = split_first_arg(&rest)?;
+        if pattern.is_empty() {
+            return Err(anyhow!("Argument required"));
+        }
+
+        let regex = build_regex(&pattern)?;
+        let replacement = translate_replacement(&replacement_arg);
+        let files = Mutex::new(vec![]);
+        let count = Mutex::new(0);
+
+        walk_files_parallel(Path::new("."), |path| {
+            let lines = match std::path::absolute(path)
+                .ok()
+                .and_then(|path| buffer_lines.get(&path))
+            {
+                Some(lines) => lines.clone(),
+                None => match read_text_file(path) {
+                    Some(text) => text.lines().map(String::from).collect(),
+                    None => return true,
+                },
+            };
+
+            let Some(file) = Self::search_file(&regex, &replacement, display_path(path), lines)
+            else {
+                return true;
+            };
+
+            let mut count = count.lock().unwrap();
+            *count += file.matches.len();
+            files.lock().unwrap().push(file);
+            *count < MAX_MATCHES
+        });
+
+        // 並列に探すので順番が決まらない
+        let mut files = files.into_inner().unwrap();
+        if files.is_empty() {
+            return Err(anyhow!("Pattern not found: {}", pattern));
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        self.title = format!("{} → {}", pattern, replacement_arg);
+        self.files = files;
+        self.selected = 0;
+        self.scroll = 0;
+        self.is_visible = true;
+        self.is_focused = true;
+        Ok(())
+    }
+
+    fn search_file(
+        regex: &Regex,
+        replacement: &str,
+        path: String,
+        lines: Vec<String>,
+    ) -> Option<ReplaceFile> {
+        let mut file = ReplaceFile {
+            path,
+            lines: BTreeMap::new(),
+            matches: vec![],
+        };
+
+        for (y, line) in lines.into_iter().enumerate() {
+            let mut found = false;
+            for captures in regex.captures_iter(&line) {
+                let m = captures.get(0).unwrap();
+                let mut expanded = String::new();
+                captures.expand(replacement, &mut expanded);
+                file.matches.push(ReplaceMatch {
+                    line: y,
+                    start: m.start(),
+                    end: m.end(),
+                    replacement: expanded,
+                    is_enabled: true,
+                });
+                found = true;
+            }
+            if found {
+                file.lines.insert(y, line);
+            }
+        }
+
+        (!file.matches.is_empty()).then_some(file)
+    }
+
+    pub fn close(&mut self) {
+        self.is_visible = false;
+        self.is_focused = false;
+        self.files.clear();
+    }
+
+    fn sync_scroll(&mut self) {
+        let height = self.get_list_height();
+
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+
+    fn move_by(&mut self, offset: isize) {
+        let last = self.get_rows().len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(offset).min(last);
+    }
+
+    // ファイルの行ではファイル内の一致をまとめて切り替える
+    fn toggle(&mut self) {
+        match self.get_rows().get(self.selected) {
+            Some(ReplaceRow::File(i)) => {
+                let file = &mut self.files[*i];
+                let is_enabled = file.get_enabled_count() == 0;
+                for m in &mut file.matches {
+                    m.is_enabled = is_enabled;
+                }
+            }
+            Some(ReplaceRow::Match(i, j)) => {
+                let m = &mut self.files[*i].matches[*j];
+                m.is_enabled = !m.is_enabled;
+            }
+            None => {}
+        }
+    }
+
+    fn jump(&mut self) -> Option<ReplaceEvent> {
+        let (i, j) = match self.get_rows().get(self.selected)? {
+            ReplaceRow::File(i) => (*i, 0),
+            ReplaceRow::Match(i, j) => (*i, *j),
+        };
+
+        let file = &self.files[i];
+        let m = &file.matches[j];
+        let line = &file.lines[&m.line];
+        self.is_focused = false;
+
+        Some(ReplaceEvent::Jump {
+            path: file.path.clone(),
+            line: m.line,
+            column: line[..m.start].chars().count(),
+        })
+    }
+
+    fn apply(&mut self) -> anyhow::Result<Option<ReplaceEvent>> {
+        let edits: Vec<FileEdit> = self
+            .files
+            .iter()
+            .map(|file| FileEdit {
+                path: file.path.clone(),
+                edits: file.get_edits(),
+            })
+            .filter(|file| !file.edits.is_empty())
+            .collect();
+
+        if edits.is_empty() {
+            return Err(anyhow!("No matches selected"));
+        }
+
+        Ok(Some(ReplaceEvent::Apply(edits)))
+    }
+
+    pub fn on_action(
+        &mut self,
+        action: ReplaceAction,
+        count: Option<usize>,
+    ) -> anyhow::Result<Option<ReplaceEvent>> {
+        let n = count.unwrap_or(1) as isize;
+        let mut event = None;
+
+        match action {
+            ReplaceAction::Close => self.close(),
+            ReplaceAction::Unfocus => self.is_focused = false,
+            ReplaceAction::MoveUp => self.move_by(-n),
+            ReplaceAction::MoveDown => self.move_by(n),
+            ReplaceAction::MoveToTop => self.selected = 0,
+            ReplaceAction::MoveToBottom => self.selected = self.get_rows().len().saturating_sub(1),
+            ReplaceAction::Toggle => self.toggle(),
+            ReplaceAction::Jump => event = self.jump(),
+            ReplaceAction::Apply => event = self.apply()?,
+        }
+
+        self.sync_scroll();
+        Ok(event)
+    }
+
+    // y は一覧の最上行 (タイトル行) からの位置
+    pub fn on_click(&mut self, y: usize) -> Option<ReplaceEvent> {
+        self.is_focused = true;
+
+        let row = y.checked_sub(1)?;
+        let index = self.scroll + row;
+        if index >= self.get_rows().len() {
+            return None;
+        }
+
+        self.selected = index;
+        self.jump()
+    }
+
+    pub fn on_scroll(&mut self, offset: IVec2) {
+        let height = self.get_list_height();
+        let len = self.get_rows().len();
+        let max = len.saturating_sub(height);
+        self.scroll = self.scroll.saturating_add_signed(offset.y).min(max);
+        self.selected = self
+            .selected
+            .clamp(self.scroll, self.scroll + height - 1)
+            .min(len.saturating_sub(1));
+    }
+
+    pub fn register_keybindings(&self, key_config: &mut KeyConfig) {
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Char('q')],
+            ReplaceAction::Close.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Char(':')],
+            EditorAction::SetMode(EditorMode::Command).into_app(),
+        );
+
+        // Focus
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Ctrl('w'), Key::Char('k')],
+            ReplaceAction::Unfocus.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Ctrl('w'), Key::Char('w')],
+            ReplaceAction::Unfocus.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Ctrl('w'), Key::Ctrl('w')],
+            ReplaceAction::Unfocus.into_app(),
+        );
+
+        // Move
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Char('j')],
+            ReplaceAction::MoveDown.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Down],
+            ReplaceAction::MoveDown.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Char('k')],
+            ReplaceAction::MoveUp.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Up],
+            ReplaceAction::MoveUp.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Char('g'), Key::Char('g')],
+            ReplaceAction::MoveToTop.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Char('G')],
+            ReplaceAction::MoveToBottom.into_app(),
+        );
+
+        // Replace
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Char(' ')],
+            ReplaceAction::Toggle.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Char('\n')],
+            ReplaceAction::Jump.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Replace,
+            vec![Key::Char('a')],
+            ReplaceAction::Apply.into_app(),
+        );
+    }
+}
+
+// 開いていないファイルへの書き込み。すべて一時ファイルに書いてから置き換えるので、
+// 読み込みや一時ファイルの書き込みで失敗した場合はどのファイルも変更しない
+pub fn write_files(files: &[FileEdit]) -> anyhow::Result<()> {
+    let texts = files
+        .iter()
+        .map(|file| {
+            let path = PathBuf::from(&file.path);
+            let text = apply_to_text(&path, &fs::read_to_string(&path)?, &file.edits)?;
+            Ok((path, text))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    write_texts(&texts)
+}
+
+// ファイルの内容をまとめて置き換える (一時ファイルの書き込みで失敗したらどれも変更しない。
+// 置き換えの途中で失敗した場合は残りの一時ファイルを消し、置き換え済みのファイルをエラーに含める)
+pub fn write_texts(files: &[(PathBuf, String)]) -> anyhow::Result<()> {
+    let mut written: Vec<(PathBuf, &PathBuf)> = vec![];
+
+    let result = files.iter().try_for_each(|(path, text)| {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let temp = path.with_file_name(format!(".{}.replace", name));
+        fs::write(&temp, text)?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        written.push((temp, path));
+        anyhow::Ok(())
+    });
+
+    if let Err(err) = result {
+        for (temp, _) in written {
+            let _ = fs::remove_file(temp);
+        }
+        return Err(err);
+    }
+
+    for (i, (temp, path)) in written.iter().enumerate() {
+        if let Err(err) = fs::rename(temp, path) {
+            for (temp, _) in &written[i..] {
+                let _ = fs::remove_file(temp);
+            }
+
+            let replaced: Vec<String> = written[..i]
+                .iter()
+                .map(|(_, path)| path.display().to_string())
+                .collect();
+            return Err(if replaced.is_empty() {
+                anyhow!("Failed to replace {}: {}", path.display(), err)
+            } else {
+                anyhow!(
+                    "Failed to replace {}: {} (already written: {})",
+                    path.display(),
+                    err,
+                    replaced.join(", ")
+                )
+            });
+        }
+    }
+    Ok(())
+}
+
+// 改行コードはそのまま残す
+fn apply_to_text(path: &Path, text: &str, edits: &[LineEdit]) -> anyhow::Result<String> {
+    let mut lines: Vec<String> = text.split_inclusive('\n').map(String::from).collect();
+
+    for edit in edits {
+        let line = lines.get_mut(edit.y).ok_or_else(|| changed_error(path))?;
+        let content = line.trim_end_matches(['\n', '\r']);
+        if content != edit.before {
+            return Err(changed_error(path));
+        }
+
+        let ending = line[content.len()..].to_string();
+        *line = format!("{}{}", edit.after, ending);
+    }
+
+    Ok(lines.concat())
+}
+
+pub fn changed_error(path: &Path) -> anyhow::Error {
+    anyhow!(
+        "{} has changed since the search (run :replace again)",
+        path.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::write_texts;
+
+    #[test]
+    fn failed_rename_removes_remaining_temp_files() {
+        let dir = env::temp_dir().join(format!("tui-studio-replace-{}", process::id()));
+        fs::create_dir_all(dir.join("b")).unwrap();
+        fs::write(dir.join("a"), "a").unwrap();
+        fs::write(dir.join("c"), "c").unwrap();
+
+        // ディレクトリは一時ファイルで置き換えられない
+        let err = write_texts(&[
+            (dir.join("a"), "A".to_string()),
+            (dir.join("b"), "B".to_string()),
+            (dir.join("c"), "C".to_string()),
+        ])
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.contains(&format!("already written: {}", dir.join("a").display())));
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "A");
+        assert_eq!(fs::read_to_string(dir.join("c")).unwrap(), "c");
+        assert!(!dir.join(".b.replace").exists());
+        assert!(!dir.join(".c.replace").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
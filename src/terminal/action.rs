@@ -0,0 +1,28 @@
+use crate::action::AppAction;
+
+#[derive(Debug, Clone, Hash)]
+pub enum TerminalAction {
+    Open,          // 端末を表示してシェルに入力する (:terminal)
+    Close,         // 端末を隠す (シェルは終了しない)
+    Unfocus,       // エディタに戻る
+    EnterTerminal, // ノーマルモードからシェルへの入力に戻る
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    MoveToLineStart,
+    MoveToLineEnd,
+    MoveToTop,
+    MoveToBottom,
+    ScrollHalfPageUp,
+    ScrollHalfPageDown,
+    ToggleVisual,
+    ToggleVisualLine,
+    Yank, // 選択範囲 (選択していなければカーソル行) をレジスタに入れる
+}
+
+impl TerminalAction {
+    pub fn into_app(self) -> AppAction {
+        AppAction::TerminalAction(self)
+    }
+}
@@ -0,0 +1,652 @@
+use std::collections::VecDeque;
+
+use unicode_width::UnicodeWidthChar;
+use vte::{Params, Perform};
+
+use crate::utils::vec2::UVec2;
+
+// 画面の上に流れた行をいくつまで残すか
+const MAX_SCROLLBACK: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TerminalColor {
+    #[default]
+    Default,
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CellStyle {
+    pub fg: TerminalColor,
+    pub bg: TerminalColor,
+    pub bold: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub c: char, // 全角文字の右半分は '\0'
+    pub style: CellStyle,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            c: ' ',
+            style: CellStyle::default(),
+        }
+    }
+}
+
+// 代替画面 (vim や less が使う) に切り替える前の画面
+struct SavedScreen {
+    lines: Vec<Vec<Cell>>,
+    cursor: UVec2,
+}
+
+// シェルの出力 (VT100/xterm のエスケープシーケンス) を解釈した画面の内容
+pub struct TerminalGrid {
+    size: UVec2,
+    lines: Vec<Vec<Cell>>,
+    scrollback: VecDeque<Vec<Cell>>,
+    main_screen: Option<SavedScreen>, // 代替画面を表示している間の元の画面
+    cursor: UVec2,
+    saved_cursor: UVec2,
+    style: CellStyle,
+    scroll_top: usize,    // スクロール領域の最初の行
+    scroll_bottom: usize, // スクロール領域の最後の行
+    wrap_pending: bool,   // 右端に書いた直後 (次の文字で折り返す)
+    is_cursor_visible: bool,
+    is_application_cursor: bool, // 矢印キーを ESC O A の形式で送る
+    title: String,
+    responses: Vec<u8>, // カーソル位置の問い合わせなどへの返答
+}
+
+fn get_param(params: &Params, index: usize, default: usize) -> usize {
+    match params.iter().nth(index).and_then(|param| param.first()) {
+        Some(0) | None => default,
+        Some(n) => *n as usize,
+    }
+}
+
+impl TerminalGrid {
+    pub fn new(size: UVec2) -> Self {
+        let size = UVec2::new(size.x.max(1), size.y.max(1));
+
+        Self {
+            size,
+            lines: vec![vec![Cell::default(); size.x]; size.y],
+            scrollback: VecDeque::new(),
+            main_screen: None,
+            cursor: UVec2::default(),
+            saved_cursor: UVec2::default(),
+            style: CellStyle::default(),
+            scroll_top: 0,
+            scroll_bottom: size.y - 1,
+            wrap_pending: false,
+            is_cursor_visible: true,
+            is_application_cursor: false,
+            title: String::new(),
+            responses: vec![],
+        }
+    }
+
+    pub fn get_cursor(&self) -> UVec2 {
+        self.cursor
+    }
+
+    pub fn is_cursor_visible(&self) -> bool {
+        self.is_cursor_visible
+    }
+
+    pub fn is_application_cursor(&self) -> bool {
+        self.is_application_cursor
+    }
+
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.responses)
+    }
+
+    pub fn get_scrollback_len(&self) -> usize {
+        self.scrollback.len()
+    }
+
+    // スクロールバックと画面を合わせた行数
+    pub fn get_line_count(&self) -> usize {
+        self.scrollback.len() + self.lines.len()
+    }
+
+    // y はスクロールバックの先頭からの行番号
+    pub fn get_line(&self, y: usize) -> Option<&[Cell]> {
+        match y.checked_sub(self.scrollback.len()) {
+            Some(y) => self.lines.get(y).map(|line| line.as_slice()),
+            None => self.scrollback.get(y).map(|line| line.as_slice()),
+        }
+    }
+
+    // start..end 列の文字列 (行末の空白は除く)
+    pub fn get_text(&self, y: usize, start: usize, end: usize) -> String {
+        let Some(line) = self.get_line(y) else {
+            return String::new();
+        };
+
+        let end = end.min(line.len());
+        let text: String = line[start.min(end)..end]
+            .iter()
+            .filter(|cell| cell.c != '\0')
+            .map(|cell| cell.c)
+            .collect();
+        text.trim_end().to_string()
+    }
+
+    pub fn resize(&mut self, size: UVec2) {
+        let size = UVec2::new(size.x.max(1), size.y.max(1));
+        if size == self.size {
+            return;
+        }
+
+        for line in &mut self.lines {
+            line.resize(size.x, Cell::default());
+        }
+
+        // 縮めるときはカーソルより上の行をスクロールバックに送る
+        while self.lines.len() > size.y {
+            if self.cursor.y > 0 {
+                let line = self.lines.remove(0);
+                self.push_scrollback(line);
+                self.cursor.y -= 1;
+            } else {
+                self.lines.pop();
+            }
+        }
+        while self.lines.len() < size.y {
+            self.lines.push(vec![Cell::default(); size.x]);
+        }
+
+        self.size = size;
+        self.scroll_top = 0;
+        self.scroll_bottom = size.y - 1;
+        self.cursor.x = self.cursor.x.min(size.x - 1);
+        self.cursor.y = self.cursor.y.min(size.y - 1);
+        self.wrap_pending = false;
+    }
+
+    fn blank_line(&self) -> Vec<Cell> {
+        vec![Cell::default(); self.size.x]
+    }
+
+    fn push_scrollback(&mut self, line: Vec<Cell>) {
+        // 代替画面の内容は残さない
+        if self.main_screen.is_some() {
+            return;
+        }
+
+        self.scrollback.push_back(line);
+        if self.scrollback.len() > MAX_SCROLLBACK {
+            self.scrollback.pop_front();
+        }
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        for _ in 0..n.min(self.scroll_bottom + 1 - self.scroll_top) {
+            let line = self.lines.remove(self.scroll_top);
+            if self.scroll_top == 0 {
+                self.push_scrollback(line);
+            }
+            self.lines.insert(self.scroll_bottom, self.blank_line());
+        }
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        for _ in 0..n.min(self.scroll_bottom + 1 - self.scroll_top) {
+            self.lines.remove(self.scroll_bottom);
+            self.lines.insert(self.scroll_top, self.blank_line());
+        }
+    }
+
+    fn line_feed(&mut self) {
+        if self.cursor.y == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.cursor.y + 1 < self.size.y {
+            self.cursor.y += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        if self.cursor.y == self.scroll_top {
+            self.scroll_down(1);
+        } else {
+            self.cursor.y = self.cursor.y.saturating_sub(1);
+        }
+    }
+
+    fn move_to(&mut self, x: usize, y: usize) {
+        self.cursor = UVec2::new(x.min(self.size.x - 1), y.min(self.size.y - 1));
+        self.wrap_pending = false;
+    }
+
+    fn clear_cells(&mut self, y: usize, start: usize, end: usize) {
+        let end = end.min(self.size.x);
+        if let Some(line) = self.lines.get_mut(y) {
+            line[start.min(end)..end].fill(Cell::default());
+        }
+    }
+
+    // ESC [ J (0: カーソルから後ろ, 1: カーソルまで, 2, 3: 全体)
+    fn erase_display(&mut self, mode: usize) {
+        let UVec2 { x, y } = self.cursor;
+        match mode {
+            0 => {
+                self.clear_cells(y, x, self.size.x);
+                (y + 1..self.size.y).for_each(|y| self.clear_cells(y, 0, self.size.x));
+            }
+            1 => {
+                (0..y).for_each(|y| self.clear_cells(y, 0, self.size.x));
+                self.clear_cells(y, 0, x + 1);
+            }
+            _ => {
+                (0..self.size.y).for_each(|y| self.clear_cells(y, 0, self.size.x));
+                if mode == 3 {
+                    self.scrollback.clear();
+                }
+            }
+        }
+    }
+
+    // ESC [ K (0: カーソルから後ろ, 1: カーソルまで, 2: 行全体)
+    fn erase_line(&mut self, mode: usize) {
+        let UVec2 { x, y } = self.cursor;
+        match mode {
+            0 => self.clear_cells(y, x, self.size.x),
+            1 => self.clear_cells(y, 0, x + 1),
+            _ => self.clear_cells(y, 0, self.size.x),
+        }
+    }
+
+    // スクロール領域内で、カーソル行に空行を挿入する (削除する場合は n が負)
+    fn insert_lines(&mut self, n: isize) {
+        let y = self.cursor.y;
+        if y < self.scroll_top || y > self.scroll_bottom {
+            return;
+        }
+
+        for _ in 0..n.unsigned_abs().min(self.scroll_bottom + 1 - y) {
+            if n > 0 {
+                self.lines.remove(self.scroll_bottom);
+                self.lines.insert(y, self.blank_line());
+            } else {
+                self.lines.remove(y);
+                self.lines.insert(self.scroll_bottom, self.blank_line());
+            }
+        }
+        self.cursor.x = 0;
+    }
+
+    // カーソル位置に空白を挿入する (削除する場合は n が負)
+    fn insert_cells(&mut self, n: isize) {
+        let UVec2 { x, y } = self.cursor;
+        let count = n.unsigned_abs().min(self.size.x - x);
+        let cells = &mut self.lines[y][x..];
+
+        if n > 0 {
+            cells.rotate_right(count);
+            cells[..count].fill(Cell::default());
+        } else {
+            cells.rotate_left(count);
+            let len = cells.len();
+            cells[len - count..].fill(Cell::default());
+        }
+    }
+
+    fn switch_screen(&mut self, alternate: bool) {
+        match (alternate, self.main_screen.take()) {
+            (true, None) => {
+                let lines = std::mem::replace(
+                    &mut self.lines,
+                    vec![vec![Cell::default(); self.size.x]; self.size.y],
+                );
+                self.main_screen = Some(SavedScreen {
+                    lines,
+                    cursor: self.cursor,
+                });
+            }
+            (false, Some(screen)) => {
+                self.lines = screen.lines;
+                self.lines.resize(self.size.y, self.blank_line());
+                for line in &mut self.lines {
+                    line.resize(self.size.x, Cell::default());
+                }
+                self.move_to(screen.cursor.x, screen.cursor.y);
+            }
+            (_, screen) => self.main_screen = screen,
+        }
+    }
+
+    fn set_private_mode(&mut self, params: &Params, enabled: bool) {
+        for param in params.iter() {
+            match param.first() {
+                Some(1) => self.is_application_cursor = enabled,
+                Some(25) => self.is_cursor_visible = enabled,
+                Some(47 | 1047 | 1049) => self.switch_screen(enabled),
+                _ => {}
+            }
+        }
+    }
+
+    // 38;5;n / 38;2;r;g;b (':' で区切った形式にも対応する)
+    fn parse_extended_color<'a>(
+        param: &[u16],
+        rest: &mut impl Iterator<Item = &'a [u16]>,
+    ) -> Option<TerminalColor> {
+        let values: Vec<u16> = if param.len() > 1 {
+            param[1..].to_vec()
+        } else {
+            let kind = rest.next()?.first().copied()?;
+            let count = if kind == 5 { 1 } else { 3 };
+            std::iter::once(kind)
+                .chain(rest.take(count).filter_map(|param| param.first().copied()))
+                .collect()
+        };
+
+        match values.as_slice() {
+            [5, n, ..] => Some(TerminalColor::Indexed(*n as u8)),
+            [2, r, g, b] | [2, _, r, g, b, ..] => {
+                Some(TerminalColor::Rgb(*r as u8, *g as u8, *b as u8))
+            }
+            _ => None,
+        }
+    }
+
+    // ESC [ m (文字の色や装飾)
+    fn set_graphics(&mut self, params: &Params) {
+        if params.is_empty() {
+            self.style = CellStyle::default();
+            return;
+        }
+
+        let mut iter = params.iter();
+        while let Some(param) = iter.next() {
+            let n = param.first().copied().unwrap_or(0);
+            match n {
+                0 => self.style = CellStyle::default(),
+                1 => self.style.bold = true,
+                4 => self.style.underline = true,
+                7 => self.style.reverse = true,
+                22 => self.style.bold = false,
+                24 => self.style.underline = false,
+                27 => self.style.reverse = false,
+                30..=37 => self.style.fg = TerminalColor::Indexed(n as u8 - 30),
+                38 => {
+                    if let Some(color) = Self::parse_extended_color(param, &mut iter) {
+                        self.style.fg = color;
+                    }
+                }
+                39 => self.style.fg = TerminalColor::Default,
+                40..=47 => self.style.bg = TerminalColor::Indexed(n as u8 - 40),
+                48 => {
+                    if let Some(color) = Self::parse_extended_color(param, &mut iter) {
+                        self.style.bg = color;
+                    }
+                }
+                49 => self.style.bg = TerminalColor::Default,
+                90..=97 => self.style.fg = TerminalColor::Indexed(n as u8 - 90 + 8),
+                100..=107 => self.style.bg = TerminalColor::Indexed(n as u8 - 100 + 8),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Perform for TerminalGrid {
+    fn print(&mut self, c: char) {
+        let width = c.width().unwrap_or(0);
+        if width == 0 || width > self.size.x {
+            return;
+        }
+
+        if self.wrap_pending || self.cursor.x + width > self.size.x {
+            self.cursor.x = 0;
+            self.line_feed();
+            self.wrap_pending = false;
+        }
+
+        let UVec2 { x, y } = self.cursor;
+        let line = &mut self.lines[y];
+        line[x] = Cell {
+            c,
+            style: self.style,
+        };
+        if width == 2 {
+            line[x + 1] = Cell {
+                c: '\0',
+                style: self.style,
+            };
+        }
+
+        if x + width >= self.size.x {
+            self.cursor.x = self.size.x - 1;
+            self.wrap_pending = true;
+        } else {
+            self.cursor.x = x + width;
+        }
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            0x08 => {
+                self.cursor.x = self.cursor.x.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            b'\t' => {
+                let x = (self.cursor.x / 8 + 1) * 8;
+                self.move_to(x, self.cursor.y);
+            }
+            b'\n' | 0x0b | 0x0c => {
+                self.line_feed();
+                self.wrap_pending = false;
+            }
+            b'\r' => self.move_to(0, self.cursor.y),
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
+        if ignore {
+            return;
+        }
+
+        if intermediates == b"?" {
+            match action {
+                'h' => self.set_private_mode(params, true),
+                'l' => self.set_private_mode(params, false),
+                _ => {}
+            }
+            return;
+        }
+        if !intermediates.is_empty() {
+            return;
+        }
+
+        let n = get_param(params, 0, 1);
+        let UVec2 { x, y } = self.cursor;
+
+        match action {
+            'A' => self.move_to(x, y.saturating_sub(n)),
+            'B' | 'e' => self.move_to(x, y + n),
+            'C' | 'a' => self.move_to(x + n, y),
+            'D' => self.move_to(x.saturating_sub(n), y),
+            'E' => self.move_to(0, y + n),
+            'F' => self.move_to(0, y.saturating_sub(n)),
+            'G' | '`' => self.move_to(n - 1, y),
+            'd' => self.move_to(x, n - 1),
+            'H' | 'f' => self.move_to(get_param(params, 1, 1) - 1, n - 1),
+            'J' => self.erase_display(get_param(params, 0, 0)),
+            'K' => self.erase_line(get_param(params, 0, 0)),
+            'L' => self.insert_lines(n as isize),
+            'M' => self.insert_lines(-(n as isize)),
+            '@' => self.insert_cells(n as isize),
+            'P' => self.insert_cells(-(n as isize)),
+            'X' => self.clear_cells(y, x, x + n),
+            'S' => self.scroll_up(n),
+            'T' => self.scroll_down(n),
+            'm' => self.set_graphics(params),
+            'r' => {
+                let top = n - 1;
+                let bottom = get_param(params, 1, self.size.y).min(self.size.y) - 1;
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.move_to(0, 0);
+                }
+            }
+            's' => self.saved_cursor = self.cursor,
+            'u' => self.move_to(self.saved_cursor.x, self.saved_cursor.y),
+            'n' => match get_param(params, 0, 0) {
+                5 => self.responses.extend_from_slice(b"\x1b[0n"),
+                6 => self
+                    .responses
+                    .extend(format!("\x1b[{};{}R", y + 1, x + 1).bytes()),
+                _ => {}
+            },
+            'c' => self.responses.extend_from_slice(b"\x1b[?1;2c"),
+            _ => {}
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        // 文字集合の指定 (ESC ( B など) は無視する
+        if !intermediates.is_empty() {
+            return;
+        }
+
+        match byte {
+            b'7' => self.saved_cursor = self.cursor,
+            b'8' => self.move_to(self.saved_cursor.x, self.saved_cursor.y),
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.line_feed();
+                self.move_to(0, self.cursor.y);
+            }
+            b'M' => self.reverse_index(),
+            b'c' => {
+                let scrollback = std::mem::take(&mut self.scrollback);
+                *self = Self::new(self.size);
+                self.scrollback = scrollback;
+            }
+            _ => {}
+        }
+    }
+
+    // ESC ] 0 ; title BEL
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        if let [b"0" | b"2", title, ..] = params {
+            self.title = String::from_utf8_lossy(title).to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vte::Parser;
+
+    use crate::utils::vec2::UVec2;
+
+    use super::TerminalGrid;
+
+    fn grid(width: usize, height: usize, output: &str) -> TerminalGrid {
+        let mut grid = TerminalGrid::new(UVec2::new(width, height));
+        feed(&mut grid, output);
+        grid
+    }
+
+    fn feed(grid: &mut TerminalGrid, output: &str) {
+        Parser::new().advance(grid, output.as_bytes());
+    }
+
+    // スクロールバックを含めた全行
+    fn lines(grid: &TerminalGrid) -> Vec<String> {
+        (0..grid.get_line_count())
+            .map(|y| grid.get_text(y, 0, usize::MAX))
+            .collect()
+    }
+
+    #[test]
+    fn wraps_after_the_last_column() {
+        let mut grid = grid(4, 3, "abcd");
+        assert_eq!(grid.get_cursor(), UVec2::new(3, 0));
+        assert_eq!(lines(&grid), vec!["abcd", "", ""]);
+
+        // 右端に書いた直後の改行では空行を挟まない
+        feed(&mut grid, "\r\nxy");
+        assert_eq!(lines(&grid), vec!["abcd", "xy", ""]);
+
+        feed(&mut grid, "zwv");
+        assert_eq!(lines(&grid), vec!["abcd", "xyzw", "v"]);
+        assert_eq!(grid.get_cursor(), UVec2::new(1, 2));
+    }
+
+    #[test]
+    fn scrolled_lines_go_to_scrollback() {
+        let grid = grid(4, 2, "1\r\n2\r\n3\r\n4");
+        assert_eq!(grid.get_scrollback_len(), 2);
+        assert_eq!(lines(&grid), vec!["1", "2", "3", "4"]);
+        assert_eq!(grid.get_cursor(), UVec2::new(1, 1));
+    }
+
+    #[test]
+    fn scroll_region_keeps_other_lines() {
+        // 2〜3行目だけをスクロールする
+        let mut grid = grid(4, 4, "top\r\na\r\nb\r\nbot");
+        feed(&mut grid, "\x1b[2;3r");
+        assert_eq!(grid.get_cursor(), UVec2::new(0, 0));
+
+        feed(&mut grid, "\x1b[3;1Hc\r\nd");
+        assert_eq!(lines(&grid), vec!["top", "c", "d", "bot"]);
+        assert_eq!(grid.get_scrollback_len(), 0);
+
+        feed(&mut grid, "\x1b[2;1H\x1bMe");
+        assert_eq!(lines(&grid), vec!["top", "e", "c", "bot"]);
+
+        // ESC [ r で画面全体に戻す
+        feed(&mut grid, "\x1b[r\x1b[4;1H\n");
+        assert_eq!(grid.get_scrollback_len(), 1);
+        assert_eq!(lines(&grid), vec!["top", "e", "c", "bot", ""]);
+    }
+
+    #[test]
+    fn alternate_screen_restores_the_main_screen() {
+        let mut grid = grid(4, 2, "ab\r\ncd");
+        feed(&mut grid, "\x1b[?1049h");
+        assert_eq!(lines(&grid), vec!["", ""]);
+
+        // 代替画面でのスクロールはスクロールバックに残らない
+        feed(&mut grid, "\x1b[H1\r\n2\r\n3");
+        assert_eq!(lines(&grid), vec!["2", "3"]);
+        assert_eq!(grid.get_scrollback_len(), 0);
+
+        feed(&mut grid, "\x1b[?1049l");
+        assert_eq!(lines(&grid), vec!["ab", "cd"]);
+        assert_eq!(grid.get_cursor(), UVec2::new(2, 1));
+    }
+
+    #[test]
+    fn wide_char_at_the_right_edge_wraps() {
+        let wrapped = grid(5, 2, "abcd日");
+        assert_eq!(lines(&wrapped), vec!["abcd", "日"]);
+
+        let line = wrapped.get_line(1).unwrap();
+        assert_eq!((line[0].c, line[1].c), ('日', '\0'));
+        assert_eq!(wrapped.get_cursor(), UVec2::new(2, 1));
+
+        // ちょうど右端に収まる場合は折り返さない
+        let fitted = grid(4, 2, "ab日");
+        assert_eq!(lines(&fitted), vec!["ab日", ""]);
+        assert_eq!(fitted.get_cursor(), UVec2::new(3, 0));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod action;
+pub mod grid;
+pub mod renderer;
+#[allow(clippy::module_inception)]
+pub mod terminal;
@@ -0,0 +1,159 @@
+use std::io::stdout;
+
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{
+        Attribute, Color as CrosstermColor, Print, ResetColor, SetAttribute, SetBackgroundColor,
+        SetForegroundColor,
+    },
+};
+use unicode_width::UnicodeWidthChar;
+
+use crate::utils::{rect::Rect, vec2::UVec2};
+
+use super::{
+    grid::{Cell, TerminalColor},
+    terminal::TerminalPane,
+};
+
+#[derive(Default)]
+pub struct TerminalRenderer {}
+
+fn to_crossterm_color(color: TerminalColor) -> CrosstermColor {
+    match color {
+        TerminalColor::Default => CrosstermColor::Reset,
+        TerminalColor::Indexed(i) => CrosstermColor::AnsiValue(i),
+        TerminalColor::Rgb(r, g, b) => CrosstermColor::Rgb { r, g, b },
+    }
+}
+
+impl TerminalRenderer {
+    fn render_title(&self, terminal: &TerminalPane, rect: &Rect) {
+        let hint = if terminal.is_focused() && !terminal.is_normal_mode() {
+            " <C-\\><C-n> normal mode"
+        } else {
+            ""
+        };
+        let title = format!(" [Terminal] {}{} ", terminal.get_title(), hint);
+
+        let background = if terminal.is_focused() {
+            CrosstermColor::White
+        } else {
+            CrosstermColor::DarkGrey
+        };
+
+        queue!(
+            stdout(),
+            MoveTo(rect.pos.x as u16, rect.pos.y as u16),
+            SetBackgroundColor(background),
+            SetForegroundColor(CrosstermColor::Black)
+        )
+        .unwrap();
+
+        let mut col = 0;
+        for c in title.chars() {
+            let char_width = c.width().unwrap_or(0);
+            if col + char_width > rect.size.x {
+                break;
+            }
+            queue!(stdout(), Print(c)).unwrap();
+            col += char_width;
+        }
+        queue!(
+            stdout(),
+            Print(" ".repeat(rect.size.x.saturating_sub(col))),
+            ResetColor
+        )
+        .unwrap();
+    }
+
+    fn render_row(&self, terminal: &TerminalPane, row: usize, cells: &[Cell], width: usize) {
+        let mut col = 0;
+
+        for (x, cell) in cells.iter().enumerate() {
+            // 全角文字の右半分は左半分と一緒に表示している
+            if cell.c == '\0' {
+                continue;
+            }
+
+            let char_width = cell.c.width().unwrap_or(1).max(1);
+            if col + char_width > width {
+                break;
+            }
+
+            let style = &cell.style;
+            let (mut fg, mut bg) = (to_crossterm_color(style.fg), to_crossterm_color(style.bg));
+            if style.reverse {
+                (fg, bg) = (
+                    if bg == CrosstermColor::Reset {
+                        CrosstermColor::Black
+                    } else {
+                        bg
+                    },
+                    if fg == CrosstermColor::Reset {
+                        CrosstermColor::White
+                    } else {
+                        fg
+                    },
+                );
+            }
+            if terminal.is_selected(x, row) {
+                bg = CrosstermColor::DarkGrey;
+            }
+
+            queue!(
+                stdout(),
+                SetForegroundColor(fg),
+                SetBackgroundColor(bg),
+                SetAttribute(if style.bold {
+                    Attribute::Bold
+                } else {
+                    Attribute::NormalIntensity
+                }),
+                SetAttribute(if style.underline {
+                    Attribute::Underlined
+                } else {
+                    Attribute::NoUnderline
+                }),
+                Print(cell.c)
+            )
+            .unwrap();
+            col += char_width;
+        }
+
+        queue!(
+            stdout(),
+            SetAttribute(Attribute::Reset),
+            ResetColor,
+            Print(" ".repeat(width.saturating_sub(col)))
+        )
+        .unwrap();
+    }
+
+    // エディタの下に端末を描画し、カーソルの位置を返す
+    pub fn render(&self, terminal: &TerminalPane, rect: &Rect) -> Option<UVec2> {
+        self.render_title(terminal, rect);
+
+        let height = rect.size.y.saturating_sub(1);
+        let rows = terminal.get_rows();
+
+        for draw_y in 0..height {
+            queue!(
+                stdout(),
+                MoveTo(rect.pos.x as u16, (rect.pos.y + 1 + draw_y) as u16)
+            )
+            .unwrap();
+
+            match rows.get(draw_y) {
+                Some(cells) => self.render_row(terminal, draw_y, cells, rect.size.x),
+                None => queue!(stdout(), Print(" ".repeat(rect.size.x))).unwrap(),
+            }
+        }
+
+        terminal
+            .get_cursor_position()
+            .filter(|cursor| cursor.y < height && cursor.x < rect.size.x)
+            .map(|cursor| UVec2::new(rect.pos.x + cursor.x, rect.pos.y + 1 + cursor.y))
+    }
+}
@@ -0,0 +1,655 @@
+use std::{
+    env,
+    io::{Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+};
+
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use vte::Parser;
+
+use crate::{
+    editor::{
+        action::EditorAction,
+        mode::EditorMode,
+        register::{Register, RegisterKind},
+    },
+    utils::{
+        command::CommandManager,
+        key_binding::{Key, KeyConfig, KeyConfigType},
+        vec2::{IVec2, UVec2},
+    },
+};
+
+use super::{
+    action::TerminalAction,
+    grid::{Cell, TerminalGrid},
+};
+
+// タイトル行を含めた高さ
+const DEFAULT_HEIGHT: usize = 15;
+
+pub enum TerminalEvent {
+    Yank(Register),
+}
+
+// 出力を読み込むスレッドと共有する
+struct TerminalShared {
+    grid: Mutex<TerminalGrid>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    is_running: AtomicBool,
+    is_dirty: AtomicBool, // 前回の描画から画面が変わったか
+}
+
+struct TerminalSession {
+    master: Mutex<Box<dyn MasterPty + Send>>, // 描画スレッドと共有するため Sync にする
+    child: Box<dyn Child + Send + Sync>,
+    shared: Arc<TerminalShared>,
+}
+
+// 選択を始めた位置
+#[derive(Clone, Copy)]
+enum TerminalSelection {
+    Charwise(UVec2),
+    Linewise(UVec2),
+}
+
+// シェルを PTY で動かし、エディタの下に表示する端末
+pub struct TerminalPane {
+    session: Option<TerminalSession>,
+    size: UVec2,   // 端末の列数と行数 (タイトル行を除く)
+    height: usize, // タイトル行を含めた高さ
+    is_visible: bool,
+    is_focused: bool,
+    is_normal_mode: bool,    // スクロールバックを見たりコピーしたりするモード
+    is_escape_pending: bool, // <C-\> の次のキーを待っている
+    cursor: UVec2,           // ノーマルモードのカーソル (y はスクロールバックの先頭から)
+    scroll: usize,           // ノーマルモードで表示している最初の行
+    selection: Option<TerminalSelection>,
+}
+
+impl Default for TerminalPane {
+    fn default() -> Self {
+        Self {
+            session: None,
+            size: UVec2::new(80, DEFAULT_HEIGHT - 1),
+            height: DEFAULT_HEIGHT,
+            is_visible: false,
+            is_focused: false,
+            is_normal_mode: false,
+            is_escape_pending: false,
+            cursor: UVec2::default(),
+            scroll: 0,
+            selection: None,
+        }
+    }
+}
+
+fn read_output(mut reader: Box<dyn Read + Send>, shared: &TerminalShared) {
+    let mut parser = Parser::new();
+    let mut buf = [0; 4096];
+
+    // シェルが終了すると読み込みに失敗する
+    while let Ok(n @ 1..) = reader.read(&mut buf) {
+        let responses = {
+            let mut grid = shared.grid.lock().unwrap();
+            parser.advance(&mut *grid, &buf[..n]);
+            grid.take_responses()
+        };
+        if !responses.is_empty() {
+            let _ = shared.writer.lock().unwrap().write_all(&responses);
+        }
+        shared.is_dirty.store(true, Ordering::Relaxed);
+    }
+
+    shared.is_running.store(false, Ordering::Relaxed);
+    shared.is_dirty.store(true, Ordering::Relaxed);
+}
+
+// キー入力を端末に送るバイト列に変換する
+fn key_to_bytes(key: &Key, is_application_cursor: bool) -> Vec<u8> {
+    let arrow = |c: char| {
+        let prefix = if is_application_cursor {
+            "\x1bO"
+        } else {
+            "\x1b["
+        };
+        format!("{}{}", prefix, c).into_bytes()
+    };
+
+    match key {
+        Key::Char('\n') | Key::Ctrl('\n') => vec![b'\r'],
+        Key::Char(c) => c.to_string().into_bytes(),
+        Key::Ctrl(c) if c.is_ascii_alphabetic() => vec![c.to_ascii_lowercase() as u8 - b'a' + 1],
+        Key::Ctrl('@' | ' ' | '2') => vec![0],
+        Key::Ctrl('[' | '3') => vec![0x1b],
+        Key::Ctrl('\\' | '4') => vec![0x1c],
+        Key::Ctrl(']' | '5') => vec![0x1d],
+        Key::Ctrl('^' | '6') => vec![0x1e],
+        Key::Ctrl('_' | '7') => vec![0x1f],
+        Key::Ctrl(c) => c.to_string().into_bytes(),
+        Key::Backspace => vec![0x7f],
+        Key::Delete => b"\x1b[3~".to_vec(),
+        Key::Up => arrow('A'),
+        Key::Down => arrow('B'),
+        Key::Right => arrow('C'),
+        Key::Left => arrow('D'),
+        Key::Esc => vec![0x1b],
+        Key::InvalidKey => vec![],
+    }
+}
+
+impl TerminalPane {
+    pub fn is_visible(&self) -> bool {
+        self.is_visible
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.is_visible && self.is_focused
+    }
+
+    pub fn set_focused(&mut self, is_focused: bool) {
+        self.is_focused = is_focused;
+    }
+
+    pub fn is_normal_mode(&self) -> bool {
+        self.is_normal_mode
+    }
+
+    // シェルへの入力中か (キーバインドを使わずにすべてのキーを送る)
+    pub fn is_terminal_mode(&self) -> bool {
+        self.is_focused() && !self.is_normal_mode
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.session
+            .as_ref()
+            .is_some_and(|session| session.shared.is_running.load(Ordering::Relaxed))
+    }
+
+    // タイトル行を含めた高さ (非表示なら 0)
+    pub fn get_height(&self) -> usize {
+        if self.is_visible {
+            self.height
+        } else {
+            0
+        }
+    }
+
+    // 描画が必要か (確認すると次に出力があるまで false になる)
+    pub fn take_dirty(&self) -> bool {
+        self.session.as_ref().is_some_and(|session| {
+            self.is_visible && session.shared.is_dirty.swap(false, Ordering::Relaxed)
+        })
+    }
+
+    fn grid(&self) -> Option<MutexGuard<'_, TerminalGrid>> {
+        self.session
+            .as_ref()
+            .map(|session| session.shared.grid.lock().unwrap())
+    }
+
+    pub fn get_title(&self) -> String {
+        let title = self
+            .grid()
+            .map(|grid| grid.get_title().to_string())
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| env::var("SHELL").unwrap_or_else(|_| "sh".to_string()));
+
+        if !self.is_running() {
+            format!("{} [Process exited]", title)
+        } else if self.is_normal_mode {
+            format!("{} -- NORMAL --", title)
+        } else {
+            title
+        }
+    }
+
+    // :terminal (シェルが終了していれば起動し直す)
+    pub fn open(&mut self, size: UVec2) -> anyhow::Result<()> {
+        self.is_visible = true;
+        self.is_focused = true;
+        self.is_normal_mode = false;
+        self.selection = None;
+
+        if self.is_running() {
+            return self.resize(size);
+        }
+
+        self.size = size;
+        self.spawn()
+    }
+
+    fn spawn(&mut self) -> anyhow::Result<()> {
+        let pair = native_pty_system().openpty(PtySize {
+            rows: self.size.y as u16,
+            cols: self.size.x as u16,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+
+        let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+        let mut command = CommandBuilder::new(shell);
+        command.env("TERM", "xterm-256color");
+        command.cwd(env::current_dir()?);
+
+        let child = pair.slave.spawn_command(command)?;
+        let reader = pair.master.try_clone_reader()?;
+        let writer = pair.master.take_writer()?;
+
+        let shared = Arc::new(TerminalShared {
+            grid: Mutex::new(TerminalGrid::new(self.size)),
+            writer: Mutex::new(writer),
+            is_running: AtomicBool::new(true),
+            is_dirty: AtomicBool::new(true),
+        });
+
+        let thread_shared = Arc::clone(&shared);
+        thread::spawn(move || read_output(reader, &thread_shared));
+
+        self.session = Some(TerminalSession {
+            master: Mutex::new(pair.master),
+            child,
+            shared,
+        });
+        Ok(())
+    }
+
+    // 非表示にするだけで、シェルはそのまま動かしておく
+    pub fn close(&mut self) {
+        self.is_visible = false;
+        self.is_focused = false;
+    }
+
+    pub fn resize(&mut self, size: UVec2) -> anyhow::Result<()> {
+        if size == self.size || size.x == 0 || size.y == 0 {
+            return Ok(());
+        }
+
+        self.size = size;
+        if let Some(session) = &self.session {
+            session.master.lock().unwrap().resize(PtySize {
+                rows: size.y as u16,
+                cols: size.x as u16,
+                pixel_width: 0,
+                pixel_height: 0,
+            })?;
+            session.shared.grid.lock().unwrap().resize(size);
+        }
+
+        self.sync_scroll();
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        if let Some(session) = &self.session {
+            let mut writer = session.shared.writer.lock().unwrap();
+            writer.write_all(bytes)?;
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    // シェルへの入力 (<C-\><C-n> でノーマルモードに切り替える)
+    pub fn on_key(&mut self, key: Key) -> anyhow::Result<()> {
+        if self.is_normal_mode {
+            return Ok(());
+        }
+
+        // 終了した後は何かキーを押したら閉じる
+        if !self.is_running() {
+            self.close();
+            return Ok(());
+        }
+
+        if self.is_escape_pending {
+            self.is_escape_pending = false;
+            if key == Key::Ctrl('n') {
+                self.enter_normal_mode();
+                return Ok(());
+            }
+            self.write(&[0x1c])?;
+        } else if let Key::Ctrl('\\' | '4') = key {
+            self.is_escape_pending = true;
+            return Ok(());
+        }
+
+        let is_application_cursor = self.grid().is_some_and(|grid| grid.is_application_cursor());
+        self.write(&key_to_bytes(&key, is_application_cursor))
+    }
+
+    fn enter_normal_mode(&mut self) {
+        let Some((cursor, scrollback)) = self
+            .grid()
+            .map(|grid| (grid.get_cursor(), grid.get_scrollback_len()))
+        else {
+            return;
+        };
+
+        self.is_normal_mode = true;
+        self.cursor = UVec2::new(cursor.x, cursor.y + scrollback);
+        self.scroll = scrollback;
+        self.selection = None;
+    }
+
+    fn get_line_count(&self) -> usize {
+        self.grid().map_or(0, |grid| grid.get_line_count())
+    }
+
+    fn get_line_width(&self, y: usize) -> usize {
+        self.grid()
+            .map_or(0, |grid| grid.get_text(y, 0, usize::MAX).chars().count())
+    }
+
+    fn sync_scroll(&mut self) {
+        let last = self.get_line_count().saturating_sub(1);
+        let height = self.size.y.max(1);
+
+        self.cursor.y = self.cursor.y.min(last);
+        self.cursor.x = self.cursor.x.min(self.size.x.saturating_sub(1));
+        if self.cursor.y < self.scroll {
+            self.scroll = self.cursor.y;
+        } else if self.cursor.y >= self.scroll + height {
+            self.scroll = self.cursor.y + 1 - height;
+        }
+        self.scroll = self.scroll.min((last + 1).saturating_sub(height));
+    }
+
+    fn get_selected_text(&self) -> Option<Register> {
+        let grid = self.grid()?;
+        let cursor = self.cursor;
+
+        let register = match self.selection {
+            None => Register::new(
+                grid.get_text(cursor.y, 0, usize::MAX),
+                RegisterKind::Linewise,
+            ),
+            Some(TerminalSelection::Linewise(start)) => {
+                let lines: Vec<String> = (start.y.min(cursor.y)..=start.y.max(cursor.y))
+                    .map(|y| grid.get_text(y, 0, usize::MAX))
+                    .collect();
+                Register::new(lines.join("\n"), RegisterKind::Linewise)
+            }
+            Some(TerminalSelection::Charwise(start)) => {
+                let (first, last) = if (start.y, start.x) <= (cursor.y, cursor.x) {
+                    (start, cursor)
+                } else {
+                    (cursor, start)
+                };
+                let lines: Vec<String> = (first.y..=last.y)
+                    .map(|y| {
+                        let start = if y == first.y { first.x } else { 0 };
+                        let end = if y == last.y { last.x + 1 } else { usize::MAX };
+                        grid.get_text(y, start, end)
+                    })
+                    .collect();
+                Register::new(lines.join("\n"), RegisterKind::Charwise)
+            }
+        };
+
+        Some(register)
+    }
+
+    // 表示している行 (ノーマルモードではスクロールバックも含めて表示する)
+    pub fn get_rows(&self) -> Vec<Vec<Cell>> {
+        let Some(grid) = self.grid() else {
+            return vec![];
+        };
+
+        let start = if self.is_normal_mode {
+            self.scroll
+        } else {
+            grid.get_scrollback_len()
+        };
+        (start..start + self.size.y)
+            .filter_map(|y| grid.get_line(y).map(|line| line.to_vec()))
+            .collect()
+    }
+
+    // 端末内のカーソルの表示位置 (隠している場合は None)
+    pub fn get_cursor_position(&self) -> Option<UVec2> {
+        if self.is_normal_mode {
+            return Some(UVec2::new(self.cursor.x, self.cursor.y - self.scroll));
+        }
+
+        let grid = self.grid()?;
+        (self.is_running() && grid.is_cursor_visible()).then(|| grid.get_cursor())
+    }
+
+    // row は表示している行の位置
+    pub fn is_selected(&self, x: usize, row: usize) -> bool {
+        let y = self.scroll + row;
+        let cursor = self.cursor;
+
+        match self.selection {
+            _ if !self.is_normal_mode => false,
+            None => false,
+            Some(TerminalSelection::Linewise(start)) => {
+                (start.y.min(cursor.y)..=start.y.max(cursor.y)).contains(&y)
+            }
+            Some(TerminalSelection::Charwise(start)) => {
+                let (first, last) = if (start.y, start.x) <= (cursor.y, cursor.x) {
+                    (start, cursor)
+                } else {
+                    (cursor, start)
+                };
+                (first.y, first.x) <= (y, x) && (y, x) <= (last.y, last.x)
+            }
+        }
+    }
+
+    pub fn on_action(
+        &mut self,
+        action: TerminalAction,
+        count: Option<usize>,
+    ) -> anyhow::Result<Option<TerminalEvent>> {
+        let n = count.unwrap_or(1);
+        let half_page = (self.size.y / 2).max(1);
+        let mut event = None;
+
+        match action {
+            TerminalAction::Open => {}
+            TerminalAction::Close => self.close(),
+            TerminalAction::Unfocus => self.is_focused = false,
+            TerminalAction::EnterTerminal => {
+                self.is_normal_mode = false;
+                self.selection = None;
+            }
+            TerminalAction::MoveUp => self.cursor.y = self.cursor.y.saturating_sub(n),
+            TerminalAction::MoveDown => self.cursor.y += n,
+            TerminalAction::MoveLeft => self.cursor.x = self.cursor.x.saturating_sub(n),
+            TerminalAction::MoveRight => self.cursor.x += n,
+            TerminalAction::MoveToLineStart => self.cursor.x = 0,
+            TerminalAction::MoveToLineEnd => {
+                self.cursor.x = self.get_line_width(self.cursor.y).saturating_sub(1)
+            }
+            TerminalAction::MoveToTop => self.cursor.y = 0,
+            TerminalAction::MoveToBottom => self.cursor.y = self.get_line_count(),
+            TerminalAction::ScrollHalfPageUp => {
+                self.cursor.y = self.cursor.y.saturating_sub(half_page);
+                self.scroll = self.scroll.saturating_sub(half_page);
+            }
+            TerminalAction::ScrollHalfPageDown => {
+                self.cursor.y += half_page;
+                self.scroll += half_page;
+            }
+            TerminalAction::ToggleVisual => {
+                self.selection = match self.selection {
+                    Some(TerminalSelection::Charwise(_)) => None,
+                    _ => Some(TerminalSelection::Charwise(self.cursor)),
+                }
+            }
+            TerminalAction::ToggleVisualLine => {
+                self.selection = match self.selection {
+                    Some(TerminalSelection::Linewise(_)) => None,
+                    _ => Some(TerminalSelection::Linewise(self.cursor)),
+                }
+            }
+            TerminalAction::Yank => {
+                event = self.get_selected_text().map(TerminalEvent::Yank);
+                self.selection = None;
+            }
+        }
+
+        self.sync_scroll();
+        Ok(event)
+    }
+
+    pub fn on_click(&mut self) {
+        self.is_focused = true;
+    }
+
+    // スクロールするとノーマルモードでスクロールバックを表示する
+    pub fn on_scroll(&mut self, offset: IVec2) {
+        if !self.is_normal_mode {
+            self.enter_normal_mode();
+        }
+
+        let height = self.size.y.max(1);
+        let max = self.get_line_count().saturating_sub(height);
+        self.scroll = self.scroll.saturating_add_signed(offset.y).min(max);
+        self.cursor.y = self.cursor.y.clamp(self.scroll, self.scroll + height - 1);
+    }
+
+    pub fn register_keybindings(&self, key_config: &mut KeyConfig) {
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('i')],
+            TerminalAction::EnterTerminal.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('a')],
+            TerminalAction::EnterTerminal.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char(':')],
+            EditorAction::SetMode(EditorMode::Command).into_app(),
+        );
+
+        // Focus
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Ctrl('w'), Key::Char('k')],
+            TerminalAction::Unfocus.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Ctrl('w'), Key::Char('w')],
+            TerminalAction::Unfocus.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Ctrl('w'), Key::Ctrl('w')],
+            TerminalAction::Unfocus.into_app(),
+        );
+
+        // Move
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('h')],
+            TerminalAction::MoveLeft.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Left],
+            TerminalAction::MoveLeft.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('j')],
+            TerminalAction::MoveDown.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Down],
+            TerminalAction::MoveDown.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('k')],
+            TerminalAction::MoveUp.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Up],
+            TerminalAction::MoveUp.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('l')],
+            TerminalAction::MoveRight.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Right],
+            TerminalAction::MoveRight.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('0')],
+            TerminalAction::MoveToLineStart.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('$')],
+            TerminalAction::MoveToLineEnd.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('g'), Key::Char('g')],
+            TerminalAction::MoveToTop.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('G')],
+            TerminalAction::MoveToBottom.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Ctrl('u')],
+            TerminalAction::ScrollHalfPageUp.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Ctrl('d')],
+            TerminalAction::ScrollHalfPageDown.into_app(),
+        );
+
+        // Copy
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('v')],
+            TerminalAction::ToggleVisual.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('V')],
+            TerminalAction::ToggleVisualLine.into_app(),
+        );
+        key_config.register(
+            KeyConfigType::Terminal,
+            vec![Key::Char('y')],
+            TerminalAction::Yank.into_app(),
+        );
+    }
+
+    pub fn register_commands(&self, cmd_manager: &mut CommandManager) {
+        cmd_manager.register("terminal", vec![TerminalAction::Open.into_app()]);
+        cmd_manager.register("term", vec![TerminalAction::Open.into_app()]);
+        cmd_manager.register("ter", vec![TerminalAction::Open.into_app()]);
+    }
+}
+
+impl Drop for TerminalPane {
+    fn drop(&mut self) {
+        if let Some(session) = &mut self.session {
+            let _ = session.child.kill();
+        }
+    }
+}
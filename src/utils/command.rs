@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use anyhow::anyhow;
+
 use crate::action::AppAction;
 
 #[derive(Debug, Clone, Default)]
@@ -15,4 +17,353 @@ impl CommandManager {
     pub fn get_command(&self, alias: &str) -> Option<&Vec<AppAction>> {
         self.commands.get(alias)
     }
+
+    pub fn get_names(&self) -> Vec<String> {
+        self.commands.keys().cloned().collect()
+    }
+}
+
+// 最初の引数と残りに分ける (空白を含む場合は " か ' で囲む)
+pub fn split_first_arg(args: &str) -> anyhow::Result<(String, String)> {
+    let args = args.trim_start();
+    let (first, rest) = match args.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let end = args[1..]
+                .find(quote)
+                .ok_or_else(|| anyhow!("Missing quote: {}", quote))?;
+            (&args[1..end + 1], &args[end + 2..])
+        }
+        _ => args.split_at(args.find(char::is_whitespace).unwrap_or(args.len())),
+    };
+
+    Ok((first.to_string(), rest.trim().to_string()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineAddressBase {
+    Current,
+    Last,
+    Number(usize), // 1始まりの行番号
+    Mark(char),    // '< または '>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineAddress {
+    pub base: LineAddressBase,
+    pub offset: isize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandRange {
+    Whole,
+    Lines {
+        start: LineAddress,
+        end: LineAddress,
+        relative: bool, // ';' で区切った場合は end を start からの相対で解決する
+    },
+}
+
+// 範囲付きの Ex コマンド (例: "%s/foo/bar/g", "'<,'>d", ".,+2y")
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExCommand {
+    pub range: Option<CommandRange>,
+    pub name: String,
+    pub bang: bool,
+    pub args: String,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn next_if(&mut self, f: impl Fn(char) -> bool) -> Option<char> {
+        let c = self.peek().filter(|c| f(*c))?;
+        self.index += 1;
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.next_if(char::is_whitespace).is_some() {}
+    }
+
+    fn parse_number(&mut self) -> Option<usize> {
+        let mut digits = String::new();
+        while let Some(c) = self.next_if(|c| c.is_ascii_digit()) {
+            digits.push(c);
+        }
+
+        digits.parse().ok()
+    }
+
+    fn parse_address(&mut self) -> anyhow::Result<Option<LineAddress>> {
+        let base = match self.peek() {
+            Some('.') => {
+                self.index += 1;
+                Some(LineAddressBase::Current)
+            }
+            Some('$') => {
+                self.index += 1;
+                Some(LineAddressBase::Last)
+            }
+            Some('\'') => {
+                self.index += 1;
+                match self.next_if(|c| c == '<' || c == '>') {
+                    Some(mark) => Some(LineAddressBase::Mark(mark)),
+                    None => return Err(anyhow!("Invalid mark")),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => self.parse_number().map(LineAddressBase::Number),
+            _ => None,
+        };
+
+        let mut offset = 0;
+        let mut has_offset = false;
+
+        while let Some(sign) = self.next_if(|c| c == '+' || c == '-') {
+            let n = self.parse_number().unwrap_or(1) as isize;
+            offset += if sign == '+' { n } else { -n };
+            has_offset = true;
+        }
+
+        Ok(match (base, has_offset) {
+            (Some(base), _) => Some(LineAddress { base, offset }),
+            (None, true) => Some(LineAddress {
+                base: LineAddressBase::Current,
+                offset,
+            }),
+            (None, false) => None,
+        })
+    }
+
+    fn parse_range(&mut self) -> anyhow::Result<Option<CommandRange>> {
+        if self.next_if(|c| c == '%').is_some() {
+            return Ok(Some(CommandRange::Whole));
+        }
+
+        let start = self.parse_address()?;
+        let Some(separator) = self.next_if(|c| c == ',' || c == ';') else {
+            return Ok(start.map(|start| CommandRange::Lines {
+                start: start.clone(),
+                end: start,
+                relative: false,
+            }));
+        };
+
+        let current = LineAddress {
+            base: LineAddressBase::Current,
+            offset: 0,
+        };
+        let start = start.unwrap_or(current.clone());
+        let end = self.parse_address()?.unwrap_or(current);
+
+        Ok(Some(CommandRange::Lines {
+            start,
+            end,
+            relative: separator == ';',
+        }))
+    }
+}
+
+impl ExCommand {
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        let mut parser = Parser {
+            chars: input.trim_start_matches([' ', ':']).chars().collect(),
+            index: 0,
+        };
+
+        let range = parser.parse_range()?;
+        parser.skip_whitespace();
+
+        let mut name = String::new();
+        while let Some(c) = parser.next_if(|c| c.is_ascii_alphabetic()) {
+            name.push(c);
+        }
+
+        let bang = parser.next_if(|c| c == '!').is_some();
+        let args: String = parser.chars[parser.index..].iter().collect();
+
+        Ok(Self {
+            range,
+            name,
+            bang,
+            args,
+        })
+    }
+}
+
+impl LineAddress {
+    fn resolve(
+        &self,
+        current: usize,
+        last: usize,
+        marks: Option<(usize, usize)>,
+    ) -> anyhow::Result<usize> {
+        let base = match self.base {
+            LineAddressBase::Current => current,
+            LineAddressBase::Last => last,
+            LineAddressBase::Number(n) => n.saturating_sub(1),
+            LineAddressBase::Mark(mark) => {
+                let (start, end) = marks.ok_or_else(|| anyhow!("Mark not set"))?;
+                if mark == '<' {
+                    start
+                } else {
+                    end
+                }
+            }
+        };
+
+        base.checked_add_signed(self.offset)
+            .filter(|y| *y <= last)
+            .ok_or_else(|| anyhow!("Invalid range"))
+    }
+}
+
+impl CommandRange {
+    // 0始まりの行番号 (start..=end) に解決する
+    pub fn resolve(
+        &self,
+        current: usize,
+        last: usize,
+        marks: Option<(usize, usize)>,
+    ) -> anyhow::Result<(usize, usize)> {
+        match self {
+            CommandRange::Whole => Ok((0, last)),
+            CommandRange::Lines {
+                start,
+                end,
+                relative,
+            } => {
+                let start = start.resolve(current, last, marks)?;
+                let current = if *relative { start } else { current };
+                let end = end.resolve(current, last, marks)?;
+
+                Ok((start.min(end), start.max(end)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandRange, ExCommand, LineAddress, LineAddressBase};
+
+    fn address(base: LineAddressBase, offset: isize) -> LineAddress {
+        LineAddress { base, offset }
+    }
+
+    fn range(input: &str) -> Option<CommandRange> {
+        ExCommand::parse(input).unwrap().range
+    }
+
+    #[test]
+    fn parses_name_bang_and_args() {
+        let command = ExCommand::parse(":  w! foo.txt").unwrap();
+
+        assert_eq!(command.range, None);
+        assert_eq!(command.name, "w");
+        assert!(command.bang);
+        assert_eq!(command.args, " foo.txt");
+    }
+
+    #[test]
+    fn parses_args_without_separator() {
+        let command = ExCommand::parse("%s/a/b/g").unwrap();
+
+        assert_eq!(command.range, Some(CommandRange::Whole));
+        assert_eq!(command.name, "s");
+        assert_eq!(command.args, "/a/b/g");
+    }
+
+    #[test]
+    fn parses_line_addresses() {
+        assert_eq!(
+            range("3d"),
+            Some(CommandRange::Lines {
+                start: address(LineAddressBase::Number(3), 0),
+                end: address(LineAddressBase::Number(3), 0),
+                relative: false,
+            })
+        );
+        assert_eq!(
+            range(".,$-1y"),
+            Some(CommandRange::Lines {
+                start: address(LineAddressBase::Current, 0),
+                end: address(LineAddressBase::Last, -1),
+                relative: false,
+            })
+        );
+        assert_eq!(
+            range("'<,'>d"),
+            Some(CommandRange::Lines {
+                start: address(LineAddressBase::Mark('<'), 0),
+                end: address(LineAddressBase::Mark('>'), 0),
+                relative: false,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_offsets_without_base_and_omitted_addresses() {
+        assert_eq!(
+            range("-,+2d"),
+            Some(CommandRange::Lines {
+                start: address(LineAddressBase::Current, -1),
+                end: address(LineAddressBase::Current, 2),
+                relative: false,
+            })
+        );
+        assert_eq!(
+            range("5;+-3d"),
+            Some(CommandRange::Lines {
+                start: address(LineAddressBase::Number(5), 0),
+                end: address(LineAddressBase::Current, -2),
+                relative: true,
+            })
+        );
+        assert_eq!(
+            range(",3d"),
+            Some(CommandRange::Lines {
+                start: address(LineAddressBase::Current, 0),
+                end: address(LineAddressBase::Number(3), 0),
+                relative: false,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_mark() {
+        assert!(ExCommand::parse("'a,'bd").is_err());
+    }
+
+    #[test]
+    fn resolves_ranges_to_zero_based_lines() {
+        let resolve = |input: &str| range(input).unwrap().resolve(4, 9, Some((2, 6)));
+
+        assert_eq!(resolve("%d").unwrap(), (0, 9));
+        assert_eq!(resolve("3d").unwrap(), (2, 2));
+        assert_eq!(resolve(".,+2d").unwrap(), (4, 6));
+        assert_eq!(resolve("$-1,$d").unwrap(), (8, 9));
+        assert_eq!(resolve("'<,'>d").unwrap(), (2, 6));
+        // 逆順の範囲は入れ替える
+        assert_eq!(resolve("8,2d").unwrap(), (1, 7));
+        // ';' では end を start からの相対で解決する
+        assert_eq!(resolve("2;+1d").unwrap(), (1, 2));
+        assert_eq!(resolve("2,+1d").unwrap(), (1, 5));
+    }
+
+    #[test]
+    fn rejects_ranges_outside_the_buffer() {
+        let resolve = |input: &str, marks| range(input).unwrap().resolve(0, 9, marks);
+
+        assert!(resolve("11d", None).is_err());
+        assert!(resolve("-1d", None).is_err());
+        assert!(resolve("$+1d", None).is_err());
+        assert!(resolve("'<d", None).is_err());
+    }
 }
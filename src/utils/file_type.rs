@@ -64,6 +64,72 @@ impl FileType {
         self.name.clone()
     }
 
+    // 言語サーバーに送る languageId
+    pub fn get_language_id(&self) -> String {
+        match self.name.as_str() {
+            C_HEADER => C.to_string(),
+            CPP_HEADER => CPP.to_string(),
+            COMMIT_MESSAGE => "git-commit".to_string(),
+            JAVASCRIPT_REACT => "javascriptreact".to_string(),
+            TYPESCRIPT_REACT => "typescriptreact".to_string(),
+            PLANETEXT => "plaintext".to_string(),
+            SHELL => "shellscript".to_string(),
+            name => name.to_string(),
+        }
+    }
+
+    // :format で使う外部コマンド (標準入力から受け取り、整形した結果を標準出力に書き出す)
+    pub fn get_format_cmd(&self) -> Option<&'static str> {
+        match self.name.as_str() {
+            C | C_HEADER | CPP | CPP_HEADER => Some("clang-format"),
+            GO => Some("gofmt"),
+            JAVASCRIPT | JAVASCRIPT_REACT => Some("prettier --parser babel"),
+            TYPESCRIPT | TYPESCRIPT_REACT => Some("prettier --parser typescript"),
+            LUA => Some("stylua -"),
+            PYTHON => Some("black --quiet -"),
+            RUST => Some("rustfmt --edition 2021"),
+            SHELL => Some("shfmt"),
+            YAML => Some("prettier --parser yaml"),
+            _ => None,
+        }
+    }
+
+    // エクスプローラーなどで表示するアイコン (Nerd Fonts)
+    pub fn get_icon(&self) -> char {
+        match self.name.as_str() {
+            C | C_HEADER => '\u{e61e}',
+            CPP | CPP_HEADER => '\u{e61d}',
+            CSHARP => '\u{f031b}',
+            CSS => '\u{e749}',
+            DART => '\u{e798}',
+            DOCKERFILE => '\u{f308}',
+            ELIXIR => '\u{e62d}',
+            ERLANG => '\u{e7b1}',
+            GIT_IGNORE | COMMIT_MESSAGE => '\u{e702}',
+            GO => '\u{e627}',
+            HASKELL => '\u{e777}',
+            HTML => '\u{e736}',
+            JAVA => '\u{e738}',
+            JAVASCRIPT | JAVASCRIPT_REACT => '\u{e74e}',
+            JSON => '\u{e60b}',
+            KOTLIN => '\u{e634}',
+            LUA => '\u{e620}',
+            MAKEFILE => '\u{e779}',
+            MARKDOWN => '\u{e73e}',
+            PHP => '\u{e73d}',
+            PYTHON => '\u{e73c}',
+            RUBY => '\u{e739}',
+            RUST => '\u{e7a8}',
+            SCALA => '\u{e737}',
+            SHELL | POWERSHELL => '\u{e795}',
+            SWIFT => '\u{e755}',
+            TOML => '\u{e6b2}',
+            TYPESCRIPT | TYPESCRIPT_REACT => '\u{e628}',
+            YAML => '\u{e6a8}',
+            _ => '\u{f15b}',
+        }
+    }
+
     pub fn file_name_to_type(file_name: String) -> Self {
         let extension = file_name.split('.').next_back();
 
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, hash::Hash};
+use std::{collections::HashMap, fmt::Display, hash::Hash};
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
@@ -18,14 +18,93 @@ pub enum Key {
     InvalidKey,
 }
 
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Key::Char('\n') => write!(f, "<CR>"),
+            Key::Char('\t') => write!(f, "<Tab>"),
+            Key::Char('<') => write!(f, "<lt>"),
+            Key::Char(c) => write!(f, "{}", c),
+            Key::Ctrl('\n') => write!(f, "<C-CR>"),
+            Key::Ctrl('\t') => write!(f, "<C-Tab>"),
+            Key::Ctrl(c) => write!(f, "<C-{}>", c),
+            Key::Backspace => write!(f, "<BS>"),
+            Key::Delete => write!(f, "<Del>"),
+            Key::Left => write!(f, "<Left>"),
+            Key::Right => write!(f, "<Right>"),
+            Key::Up => write!(f, "<Up>"),
+            Key::Down => write!(f, "<Down>"),
+            Key::Esc => write!(f, "<Esc>"),
+            Key::InvalidKey => Ok(()),
+        }
+    }
+}
+
+impl Key {
+    // Display で書き出したキー列 (例: "dw<Esc>") を読み込む
+    pub fn parse_sequence(text: &str) -> Vec<Key> {
+        let mut keys = vec![];
+        let mut rest = text;
+
+        while let Some(c) = rest.chars().next() {
+            if c == '<' {
+                if let Some(end) = rest.find('>') {
+                    if let Some(key) = Self::parse_special(&rest[1..end]) {
+                        keys.push(key);
+                        rest = &rest[end + 1..];
+                        continue;
+                    }
+                }
+            }
+
+            keys.push(Key::Char(c));
+            rest = &rest[c.len_utf8()..];
+        }
+
+        keys
+    }
+
+    fn parse_special(name: &str) -> Option<Key> {
+        let key = match name {
+            "CR" => Key::Char('\n'),
+            "Tab" => Key::Char('\t'),
+            "lt" => Key::Char('<'),
+            "C-CR" => Key::Ctrl('\n'),
+            "C-Tab" => Key::Ctrl('\t'),
+            "BS" => Key::Backspace,
+            "Del" => Key::Delete,
+            "Left" => Key::Left,
+            "Right" => Key::Right,
+            "Up" => Key::Up,
+            "Down" => Key::Down,
+            "Esc" => Key::Esc,
+            name => {
+                let c = name.strip_prefix("C-")?;
+                let mut chars = c.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Key::Ctrl(c),
+                    _ => return None,
+                }
+            }
+        };
+
+        Some(key)
+    }
+}
+
 #[derive(Hash, PartialEq, Eq, Clone)]
 pub enum KeyConfigType {
     All,
     Normal,
     Visual,
     NormalAndVisual,
+    OperatorPending,
     Insert,
     Command,
+    Explorer,
+    Quickfix,
+    Replace,
+    Terminal,
 }
 
 fn map_key_with_modifiers(modifiers: KeyModifiers, c: char) -> Key {
@@ -75,17 +154,48 @@ impl KeyConfig {
         self.bindings.get(&(config_type, sequence))
     }
 
-    pub fn get_action(&self, mode: EditorMode, sequence: KeySequence) -> Option<&AppAction> {
-        self.get_binding(KeyConfigType::All, sequence.clone())
-            .or(match mode {
-                EditorMode::Normal => self
-                    .get_binding(KeyConfigType::Normal, sequence.clone())
-                    .or(self.get_binding(KeyConfigType::NormalAndVisual, sequence)),
-                EditorMode::Visual => self
-                    .get_binding(KeyConfigType::Visual, sequence.clone())
-                    .or(self.get_binding(KeyConfigType::NormalAndVisual, sequence)),
-                EditorMode::Command => self.get_binding(KeyConfigType::Command, sequence),
-                EditorMode::Insert { .. } => self.get_binding(KeyConfigType::Insert, sequence),
-            })
+    // 優先度の高い順
+    pub fn get_config_types(mode: EditorMode) -> Vec<KeyConfigType> {
+        let mut config_types = vec![KeyConfigType::All];
+
+        match mode {
+            EditorMode::Normal => {
+                config_types.push(KeyConfigType::Normal);
+                config_types.push(KeyConfigType::NormalAndVisual);
+            }
+            EditorMode::Visual | EditorMode::VisualLine | EditorMode::VisualBlock => {
+                config_types.push(KeyConfigType::Visual);
+                config_types.push(KeyConfigType::NormalAndVisual);
+            }
+            EditorMode::OperatorPending(_) => {
+                config_types.push(KeyConfigType::OperatorPending);
+                config_types.push(KeyConfigType::NormalAndVisual);
+            }
+            EditorMode::Command | EditorMode::Search { .. } => {
+                config_types.push(KeyConfigType::Command)
+            }
+            EditorMode::Insert { .. } => config_types.push(KeyConfigType::Insert),
+        }
+
+        config_types
+    }
+
+    pub fn get_action(
+        &self,
+        config_types: &[KeyConfigType],
+        sequence: KeySequence,
+    ) -> Option<&AppAction> {
+        config_types
+            .iter()
+            .find_map(|config_type| self.get_binding(config_type.clone(), sequence.clone()))
+    }
+
+    // sequence の続きを入力すると一致するキーバインドがあるか
+    pub fn is_prefix(&self, config_types: &[KeyConfigType], sequence: &[Key]) -> bool {
+        self.bindings.keys().any(|(config_type, binding)| {
+            config_types.contains(config_type)
+                && binding.len() > sequence.len()
+                && binding.starts_with(sequence)
+        })
     }
 }
@@ -5,5 +5,8 @@ pub mod file_type;
 pub mod key_binding;
 pub mod log;
 pub mod rect;
+pub mod shell;
 pub mod term;
+pub mod text_storage;
 pub mod vec2;
+pub mod walk;
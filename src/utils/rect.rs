@@ -10,6 +10,11 @@ impl Rect {
     pub fn new(pos: UVec2, size: UVec2) -> Self {
         Self { pos, size }
     }
+
+    pub fn contains(&self, pos: UVec2) -> bool {
+        (self.pos.x..self.pos.x + self.size.x).contains(&pos.x)
+            && (self.pos.y..self.pos.y + self.size.y).contains(&pos.y)
+    }
 }
 
 impl From<Rect> for (UVec2, UVec2) {
@@ -0,0 +1,119 @@
+use std::{
+    env,
+    io::{self, stdout, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+use anyhow::anyhow;
+
+use super::term::{clean_term, init_term};
+
+fn shell_command(command: &str) -> Command {
+    let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+    let mut shell_command = Command::new(shell);
+    shell_command.arg("-c").arg(command);
+    shell_command
+}
+
+// :!cmd (端末を元に戻して実行し、Enter が押されるまで出力を表示しておく)
+pub fn run_in_terminal(command: &str) -> anyhow::Result<()> {
+    clean_term()?;
+
+    let status = shell_command(command).status();
+    if let Ok(status) = &status {
+        if !status.success() {
+            print!("\r\nshell returned {}\r\n", status.code().unwrap_or(-1));
+        }
+    }
+    print!("\r\nPress ENTER to continue");
+    stdout().flush()?;
+    let _ = io::stdin().read_line(&mut String::new());
+
+    init_term()?;
+    status?;
+    Ok(())
+}
+
+// input を標準入力に渡して標準出力を受け取る (:r !cmd, :{range}!cmd)
+pub fn run_filter(command: &str, input: &str) -> anyhow::Result<String> {
+    run_with_input(shell_command(command), input)
+}
+
+// シェルを通さずに実行する (インストールされていなければ is_command_not_found が true になる)
+pub fn run_program(command: &str, input: &str) -> anyhow::Result<String> {
+    let args: Vec<&str> = command.split_whitespace().collect();
+    let Some((program, args)) = args.split_first() else {
+        return Err(anyhow!("Empty command"));
+    };
+
+    let mut program_command = Command::new(program);
+    program_command.args(args);
+    run_with_input(program_command, input).map_err(|err| {
+        if is_command_not_found(&err) {
+            err.context(format!("{}: command not found", program))
+        } else {
+            err
+        }
+    })
+}
+
+pub fn is_command_not_found(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<io::Error>()
+        .is_some_and(|err| err.kind() == io::ErrorKind::NotFound)
+}
+
+fn run_with_input(mut command: Command, input: &str) -> anyhow::Result<String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // 出力を読まずに書き込むとパイプが詰まるので別スレッドで渡す
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_string();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child.wait_with_output()?;
+    // 入力を読まないコマンドでは書き込みに失敗するが、出力はそのまま使う
+    let _ = writer.join();
+
+    if !output.status.success() {
+        let code = output.status.code().unwrap_or(-1);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => anyhow!("shell returned {}", code),
+            stderr => anyhow!("shell returned {}: {}", code, stderr),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_command_not_found, run_filter, run_program};
+
+    #[test]
+    fn run_program_passes_input() {
+        assert_eq!(run_program("cat", "abc\n").unwrap(), "abc\n");
+        assert_eq!(run_program("tr a-z A-Z", "abc").unwrap(), "ABC");
+    }
+
+    #[test]
+    fn missing_program_is_not_found() {
+        let err = run_program("tui-studio-missing-formatter --stdin", "").unwrap_err();
+        assert!(is_command_not_found(&err));
+        assert_eq!(
+            err.to_string(),
+            "tui-studio-missing-formatter: command not found"
+        );
+
+        // 失敗しただけのコマンドとは区別する
+        let err = run_program("false", "").unwrap_err();
+        assert!(!is_command_not_found(&err));
+        let err = run_filter("tui-studio-missing-formatter", "").unwrap_err();
+        assert!(!is_command_not_found(&err));
+    }
+}
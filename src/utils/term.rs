@@ -13,12 +13,11 @@ use crossterm::{
     },
 };
 
-use super::{log::init_logger, vec2::UVec2};
+use super::vec2::UVec2;
 
 pub fn init_term() -> io::Result<()> {
     enable_raw_mode()?;
     execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
-    init_logger().unwrap();
     Ok(())
 }
 
@@ -0,0 +1,202 @@
+use std::fmt::Display;
+
+use ropey::Rope;
+
+/// ロープによるテキストの格納領域 (挿入・削除・行の参照はいずれも O(log n))
+#[derive(Default, Clone)]
+pub struct TextStorage {
+    rope: Rope,
+}
+
+impl TextStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    pub fn char_count(&self) -> usize {
+        self.rope.len_chars()
+    }
+
+    pub fn line_len(&self, y: usize) -> usize {
+        let line = self.rope.line(y);
+        let len = line.len_chars();
+
+        if len > 0 && line.char(len - 1) == '\n' {
+            len - 1
+        } else {
+            len
+        }
+    }
+
+    pub fn line(&self, y: usize) -> String {
+        let mut line = self.rope.line(y).to_string();
+        if line.ends_with('\n') {
+            line.pop();
+        }
+        line
+    }
+
+    pub fn lines(&self, start: usize, end: usize) -> Vec<String> {
+        (start..end.min(self.line_count()))
+            .map(|y| self.line(y))
+            .collect()
+    }
+
+    pub fn char_at(&self, x: usize, y: usize) -> char {
+        self.rope.char(self.pos_to_index(x, y))
+    }
+
+    pub fn pos_to_index(&self, x: usize, y: usize) -> usize {
+        self.rope.line_to_char(y) + x
+    }
+
+    pub fn index_to_pos(&self, index: usize) -> (usize, usize) {
+        let y = self.rope.char_to_line(index);
+        (index - self.rope.line_to_char(y), y)
+    }
+
+    pub fn insert(&mut self, x: usize, y: usize, text: &str) {
+        let index = self.pos_to_index(x, y);
+        self.rope.insert(index, text);
+    }
+
+    pub fn insert_char(&mut self, x: usize, y: usize, ch: char) {
+        let index = self.pos_to_index(x, y);
+        self.rope.insert_char(index, ch);
+    }
+
+    /// `(x, y)` から `len` 文字を削除し、削除した文字列を返す
+    pub fn remove(&mut self, x: usize, y: usize, len: usize) -> String {
+        let start = self.pos_to_index(x, y);
+        let end = (start + len).min(self.rope.len_chars());
+        let removed = self.rope.slice(start..end).to_string();
+        self.rope.remove(start..end);
+        removed
+    }
+
+    pub fn slice(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let start = self.pos_to_index(start.0, start.1);
+        let end = self.pos_to_index(end.0, end.1);
+        self.rope.slice(start..end).to_string()
+    }
+}
+
+impl From<&str> for TextStorage {
+    fn from(text: &str) -> Self {
+        Self {
+            rope: Rope::from_str(text),
+        }
+    }
+}
+
+impl Display for TextStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for chunk in self.rope.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextStorage;
+
+    #[test]
+    fn line_len_excludes_newline() {
+        let storage = TextStorage::from("abc\nde\n");
+
+        assert_eq!(storage.line_count(), 3);
+        assert_eq!(storage.line_len(0), 3);
+        assert_eq!(storage.line_len(1), 2);
+        assert_eq!(storage.line_len(2), 0);
+        assert_eq!(storage.line(1), "de");
+        assert_eq!(storage.line(2), "");
+    }
+
+    #[test]
+    fn lines_are_clamped_to_line_count() {
+        let storage = TextStorage::from("a\nb");
+
+        assert_eq!(storage.lines(1, 10), vec!["b".to_string()]);
+        assert!(storage.lines(5, 10).is_empty());
+    }
+
+    #[test]
+    fn pos_and_index_round_trip_at_line_ends() {
+        let storage = TextStorage::from("abc\nde");
+
+        // 改行の位置は行末 (x == line_len) として扱う
+        assert_eq!(storage.pos_to_index(3, 0), 3);
+        assert_eq!(storage.index_to_pos(3), (3, 0));
+        assert_eq!(storage.pos_to_index(0, 1), 4);
+        assert_eq!(storage.index_to_pos(4), (0, 1));
+
+        // 最終行の末尾 (テキストの末尾)
+        assert_eq!(storage.pos_to_index(2, 1), 6);
+        assert_eq!(storage.index_to_pos(6), (2, 1));
+    }
+
+    #[test]
+    fn multi_byte_text_is_indexed_by_chars() {
+        let storage = TextStorage::from("あいう\n😀x");
+
+        assert_eq!(storage.char_count(), 6);
+        assert_eq!(storage.line_len(0), 3);
+        assert_eq!(storage.line_len(1), 2);
+        assert_eq!(storage.char_at(2, 0), 'う');
+        assert_eq!(storage.char_at(1, 1), 'x');
+        assert_eq!(storage.pos_to_index(1, 1), 5);
+        assert_eq!(storage.index_to_pos(5), (1, 1));
+        assert_eq!(storage.slice((1, 0), (1, 1)), "いう\n😀");
+    }
+
+    #[test]
+    fn slice_across_lines() {
+        let storage = TextStorage::from("abc\nde\nf");
+
+        assert_eq!(storage.slice((3, 0), (0, 1)), "\n");
+        assert_eq!(storage.slice((1, 0), (1, 2)), "bc\nde\nf");
+        assert_eq!(storage.slice((0, 2), (0, 2)), "");
+    }
+
+    #[test]
+    fn remove_joins_lines_at_line_end() {
+        let mut storage = TextStorage::from("abc\nde");
+
+        assert_eq!(storage.remove(3, 0, 1), "\n");
+        assert_eq!(storage.to_string(), "abcde");
+        assert_eq!(storage.line_count(), 1);
+    }
+
+    #[test]
+    fn remove_is_clamped_at_end_of_text() {
+        let mut storage = TextStorage::from("abc\nde");
+
+        assert_eq!(storage.remove(1, 1, 10), "e");
+        assert_eq!(storage.remove(1, 1, 1), "");
+        assert_eq!(storage.to_string(), "abc\nd");
+    }
+
+    #[test]
+    fn remove_multi_byte_chars() {
+        let mut storage = TextStorage::from("aあ😀b");
+
+        assert_eq!(storage.remove(1, 0, 2), "あ😀");
+        assert_eq!(storage.to_string(), "ab");
+    }
+
+    #[test]
+    fn insert_at_end_of_last_line() {
+        let mut storage = TextStorage::from("abc\nde");
+
+        storage.insert(2, 1, "\nf");
+        storage.insert_char(1, 2, 'g');
+
+        assert_eq!(storage.to_string(), "abc\nde\nfg");
+    }
+}
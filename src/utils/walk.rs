@@ -0,0 +1,40 @@
+use std::{fs, path::Path};
+
+use ignore::{WalkBuilder, WalkState};
+
+// .gitignore などで除外されていないファイルを並列にたどる (f が false を返したら打ち切る)
+pub fn walk_files_parallel(root: &Path, f: impl Fn(&Path) -> bool + Sync) {
+    WalkBuilder::new(root).build_parallel().run(|| {
+        Box::new(|entry| {
+            let Ok(entry) = entry else {
+                return WalkState::Continue;
+            };
+            if !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                return WalkState::Continue;
+            }
+
+            if f(entry.path()) {
+                WalkState::Continue
+            } else {
+                WalkState::Quit
+            }
+        })
+    });
+}
+
+// UTF-8 として読めないファイルやバイナリファイルは None
+pub fn read_text_file(path: &Path) -> Option<String> {
+    let text = fs::read_to_string(path).ok()?;
+    (!text.contains('\0')).then_some(text)
+}
+
+// 表示用のパス ("./" を取り除く)
+pub fn display_path(path: &Path) -> String {
+    path.strip_prefix("./")
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
@@ -0,0 +1,676 @@
+// 言語サーバーの代わりに、このテスト自身を台本どおりに応答するサーバーとして起動する
+// (--fake-server <台本> <ログ> で起動すると受け取ったメッセージをログに書き出す)
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, Cursor, Write},
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
+
+use serde_json::{json, Value};
+use tui_studio::lsp::{
+    apply_text_edits, parse_completion_items, parse_hover, parse_locations, parse_workspace_edit,
+    path_to_uri, read_message, uri_to_path, write_message, ContentChange, DiagnosticSeverity,
+    LspClient, LspEvent, LspLocation, LspPosition, LspRange, TextEdit,
+};
+
+// 台本の1行
+// {"expect": method, "reply": result, "error": message, "notify": message} / {"send": message}
+// / {"send_raw": 本文} / {"expect_response": id}
+fn run_fake_server(script: &Path, log: &Path) {
+    let script: Vec<Value> = serde_json::from_str(&fs::read_to_string(script).unwrap()).unwrap();
+    let mut reader = BufReader::new(io::stdin());
+    let mut stdout = io::stdout();
+    let mut received = vec![];
+
+    let mut next = |received: &mut Vec<Value>| {
+        let message = read_message(&mut reader).unwrap()?.unwrap();
+        received.push(message.clone());
+        fs::write(log, serde_json::to_string(&received).unwrap()).unwrap();
+        Some(message)
+    };
+
+    for step in script {
+        if let Some(message) = step.get("send") {
+            write_message(&mut stdout, message).unwrap();
+        } else if let Some(body) = step["send_raw"].as_str() {
+            write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+            stdout.flush().unwrap();
+        } else if let Some(id) = step.get("expect_response") {
+            while let Some(message) = next(&mut received) {
+                if message.get("method").is_none() && message["id"] == *id {
+                    break;
+                }
+            }
+        } else if let Some(method) = step["expect"].as_str() {
+            let Some(message) = (|| loop {
+                let message = next(&mut received)?;
+                if message["method"] == method {
+                    return Some(message);
+                }
+            })() else {
+                return;
+            };
+
+            if let Some(result) = step.get("reply") {
+                let response = json!({ "jsonrpc": "2.0", "id": message["id"], "result": result });
+                write_message(&mut stdout, &response).unwrap();
+            }
+            if let Some(error) = step.get("error") {
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": { "code": -32603, "message": error },
+                });
+                write_message(&mut stdout, &response).unwrap();
+            }
+            if let Some(notification) = step.get("notify") {
+                write_message(&mut stdout, notification).unwrap();
+            }
+        }
+    }
+}
+
+struct FakeServer {
+    dir: PathBuf,
+}
+
+impl FakeServer {
+    fn new(name: &str, script: Value) -> Self {
+        let dir = env::temp_dir().join(format!("tui-studio-lsp-{}-{}", name, process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("script.json"), script.to_string()).unwrap();
+        Self { dir }
+    }
+
+    fn start(&self) -> LspClient {
+        let exe = env::current_exe().unwrap();
+        let script = self.dir.join("script.json");
+        let log = self.dir.join("log.json");
+
+        LspClient::start(
+            exe.to_str().unwrap(),
+            &[
+                "--fake-server",
+                script.to_str().unwrap(),
+                log.to_str().unwrap(),
+            ],
+            &self.dir,
+        )
+        .unwrap()
+    }
+
+    fn get_log(&self) -> Vec<Value> {
+        let log = fs::read_to_string(self.dir.join("log.json")).unwrap_or_default();
+        serde_json::from_str(&log).unwrap_or_default()
+    }
+
+    fn get_methods(&self) -> Vec<String> {
+        self.get_log()
+            .iter()
+            .filter_map(|message| message["method"].as_str().map(str::to_string))
+            .collect()
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn wait_until(client: &mut LspClient, message: &str, f: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    while !f() {
+        assert!(Instant::now() < deadline, "{}", message);
+        client.poll();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn wait_for_diagnostics(client: &mut LspClient) -> Option<(PathBuf, Vec<Value>)> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    while Instant::now() < deadline {
+        if let Some(LspEvent::Diagnostics { path, diagnostics }) = client.poll().into_iter().next()
+        {
+            let diagnostics = diagnostics
+                .into_iter()
+                .map(|diagnostic| {
+                    json!([
+                        diagnostic.range.start.line,
+                        diagnostic.range.start.character,
+                        diagnostic.severity == DiagnosticSeverity::Warning,
+                        diagnostic.message,
+                    ])
+                })
+                .collect();
+            return Some((path, diagnostics));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    None
+}
+
+fn test_framing() {
+    let mut buf = vec![];
+    write_message(&mut buf, &json!({ "text": "日本語" })).unwrap();
+    write_message(&mut buf, &json!({ "id": 1 })).unwrap();
+
+    // Content-Length はバイト数
+    let header = String::from_utf8_lossy(&buf[..22]).to_string();
+    assert!(
+        header.starts_with("Content-Length: 20\r\n\r\n"),
+        "{}",
+        header
+    );
+
+    let mut reader = Cursor::new(buf);
+    assert_eq!(read(&mut reader), Some(json!({ "text": "日本語" })));
+    assert_eq!(read(&mut reader), Some(json!({ "id": 1 })));
+    assert_eq!(read(&mut reader), None);
+
+    // ほかのヘッダーは読み飛ばす
+    let message =
+        "Content-Type: application/vscode-jsonrpc; charset=utf-8\r\ncontent-length: 2\r\n\r\n{}";
+    let mut reader = Cursor::new(message.as_bytes());
+    assert_eq!(read(&mut reader), Some(json!({})));
+
+    let mut reader = Cursor::new(b"\r\n{}".as_slice());
+    assert!(read_message(&mut reader).is_err());
+
+    // JSON として読めない本文の後も続けて読める
+    let mut reader =
+        Cursor::new(b"Content-Length: 1\r\n\r\n{Content-Length: 2\r\n\r\n{}".as_slice());
+    assert!(matches!(read_message(&mut reader), Ok(Some(Err(_)))));
+    assert_eq!(read(&mut reader), Some(json!({})));
+}
+
+fn read(reader: &mut impl BufRead) -> Option<Value> {
+    read_message(reader)
+        .unwrap()
+        .map(|message| message.unwrap())
+}
+
+fn test_uri() {
+    let path = PathBuf::from("/tmp/a b/日本.html");
+    let uri = path_to_uri(&path);
+    assert_eq!(uri, "file:///tmp/a%20b/%E6%97%A5%E6%9C%AC.html");
+    assert_eq!(uri_to_path(&uri), Some(path));
+    assert_eq!(uri_to_path("untitled:1"), None);
+}
+
+fn test_document_sync() {
+    let file = env::temp_dir().join("index.html");
+    let uri = path_to_uri(&file);
+
+    let server = FakeServer::new(
+        "sync",
+        json!([
+            {
+                "expect": "initialize",
+                "reply": { "capabilities": { "textDocumentSync": { "openClose": true, "change": 2 } } },
+            },
+            { "expect": "initialized" },
+            {
+                "expect": "textDocument/didOpen",
+                "notify": {
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": {
+                        "uri": uri,
+                        "diagnostics": [{
+                            "range": { "start": { "line": 1, "character": 2 }, "end": { "line": 1, "character": 5 } },
+                            "severity": 2,
+                            "message": "unclosed tag",
+                        }],
+                    },
+                },
+            },
+            {
+                "send": {
+                    "jsonrpc": "2.0",
+                    "id": "config",
+                    "method": "workspace/configuration",
+                    "params": { "items": [{}, {}] },
+                },
+            },
+            { "expect_response": "config" },
+            { "expect": "textDocument/didChange" },
+            { "expect": "textDocument/didSave" },
+            { "expect": "textDocument/didClose" },
+            { "expect": "shutdown", "reply": null },
+            { "expect": "exit" },
+        ]),
+    );
+
+    // initialize の応答より前に送った通知は initialized の後に届く
+    let mut client = server.start();
+    client
+        .did_open(&file, "html", 1, "<html>\n  <p>\n</html>")
+        .unwrap();
+
+    let (path, diagnostics) = wait_for_diagnostics(&mut client).expect("no diagnostics");
+    assert_eq!(path, file);
+    assert_eq!(diagnostics, vec![json!([1, 2, true, "unclosed tag"])]);
+
+    // サーバーからのリクエストに応答してから次の通知を送る
+    wait_until(
+        &mut client,
+        "no response to workspace/configuration",
+        || {
+            server
+                .get_log()
+                .iter()
+                .any(|message| message["id"] == "config")
+        },
+    );
+
+    let change = ContentChange {
+        range: LspRange {
+            start: LspPosition::new(1, 5),
+            end: LspPosition::new(1, 5),
+        },
+        text: "</p>".to_string(),
+    };
+    // 差分で送る場合は全体の文字列を作らない
+    client
+        .did_change(&file, 2, vec![change], || {
+            panic!("full text is not needed for incremental sync")
+        })
+        .unwrap();
+    client.did_save(&file).unwrap();
+    client.did_close(&file).unwrap();
+    client.shutdown();
+
+    assert_eq!(
+        server.get_methods(),
+        vec![
+            "initialize",
+            "initialized",
+            "textDocument/didOpen",
+            "textDocument/didChange",
+            "textDocument/didSave",
+            "textDocument/didClose",
+            "shutdown",
+            "exit",
+        ]
+    );
+
+    let log = server.get_log();
+    let response = log
+        .iter()
+        .find(|message| message["id"] == "config")
+        .unwrap();
+    assert_eq!(response["result"], json!([null, null]));
+
+    let did_change = &log[4]["params"];
+    assert_eq!(did_change["textDocument"]["version"], 2);
+    assert_eq!(
+        did_change["contentChanges"],
+        json!([{
+            "range": { "start": { "line": 1, "character": 5 }, "end": { "line": 1, "character": 5 } },
+            "text": "</p>",
+        }])
+    );
+}
+
+// 差分に対応していないサーバーや、initialize の応答が来る前の変更は全体を送る
+fn test_full_sync() {
+    let file = env::temp_dir().join("style.css");
+
+    let server = FakeServer::new(
+        "full",
+        json!([
+            { "expect": "initialize", "reply": { "capabilities": { "textDocumentSync": 1 } } },
+            { "expect": "textDocument/didChange" },
+            { "expect": "textDocument/didChange" },
+            { "expect": "shutdown", "reply": null },
+            { "expect": "exit" },
+        ]),
+    );
+
+    let mut client = server.start();
+    client.did_open(&file, "css", 1, "a {}").unwrap();
+    let change = ContentChange {
+        range: LspRange::default(),
+        text: "b".to_string(),
+    };
+    client
+        .did_change(&file, 2, vec![change], || "ba {}".to_string())
+        .unwrap();
+
+    wait_until(&mut client, "initialize was not answered", || {
+        server.get_methods().contains(&"initialized".to_string())
+    });
+    let change = ContentChange {
+        range: LspRange::default(),
+        text: "c".to_string(),
+    };
+    client
+        .did_change(&file, 3, vec![change], || "cba {}".to_string())
+        .unwrap();
+    client.shutdown();
+
+    let changes: Vec<Value> = server
+        .get_log()
+        .iter()
+        .filter(|message| message["method"] == "textDocument/didChange")
+        .map(|message| message["params"]["contentChanges"].clone())
+        .collect();
+    assert_eq!(
+        changes,
+        vec![json!([{ "text": "ba {}" }]), json!([{ "text": "cba {}" }])]
+    );
+}
+
+fn range(line: usize, start: usize, end: usize) -> LspRange {
+    LspRange {
+        start: LspPosition::new(line, start),
+        end: LspPosition::new(line, end),
+    }
+}
+
+fn test_parse() {
+    let uri = path_to_uri(Path::new("/tmp/a.css"));
+    let lsp_range =
+        json!({ "start": { "line": 1, "character": 2 }, "end": { "line": 1, "character": 4 } });
+    let location = LspLocation {
+        path: PathBuf::from("/tmp/a.css"),
+        range: range(1, 2, 4),
+    };
+
+    assert_eq!(parse_locations(&Value::Null), vec![]);
+    assert_eq!(
+        parse_locations(&json!({ "uri": uri, "range": lsp_range })),
+        vec![location.clone()]
+    );
+    assert_eq!(
+        parse_locations(&json!([{
+            "targetUri": uri,
+            "targetRange": { "start": { "line": 0, "character": 0 }, "end": { "line": 3, "character": 0 } },
+            "targetSelectionRange": lsp_range,
+        }])),
+        vec![location]
+    );
+
+    assert_eq!(parse_hover(&Value::Null), None);
+    assert_eq!(
+        parse_hover(
+            &json!({ "contents": { "kind": "markdown", "value": "```css\ncolor\n```\nText color" } })
+        ),
+        Some("color\nText color".to_string())
+    );
+    assert_eq!(
+        parse_hover(&json!({ "contents": ["a", { "language": "css", "value": "b" }] })),
+        Some("a\n\nb".to_string())
+    );
+
+    // insertText がなければ label、スニペットはプレースホルダーの文字列だけにする
+    let items = parse_completion_items(&json!({
+        "isIncomplete": false,
+        "items": [
+            { "label": "color", "detail": "Sets the color", "insertText": "color: " },
+            { "label": "div" },
+            { "label": "rgb", "insertText": "rgb(${1:0}, $2)$0", "insertTextFormat": 2 },
+            { "label": "margin", "textEdit": { "range": lsp_range, "newText": "margin: " } },
+        ],
+    }));
+    let items: Vec<_> = items
+        .iter()
+        .map(|item| {
+            (
+                item.label.as_str(),
+                item.detail.as_deref(),
+                item.insert_text.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        items,
+        vec![
+            ("color", Some("Sets the color"), "color: "),
+            ("div", None, "div"),
+            ("rgb", None, "rgb(0, )"),
+            ("margin", None, "margin: "),
+        ]
+    );
+    assert_eq!(parse_completion_items(&json!([{ "label": "a" }])).len(), 1);
+
+    let edit = json!({ "range": lsp_range, "newText": "x" });
+    let files = parse_workspace_edit(&json!({
+        "documentChanges": [
+            { "textDocument": { "uri": uri, "version": 1 }, "edits": [edit] },
+            { "textDocument": { "uri": uri, "version": 1 }, "edits": [edit] },
+        ],
+    }))
+    .unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].1.len(), 2);
+    assert_eq!(
+        parse_workspace_edit(&json!({ "changes": { uri.clone(): [edit] } })).unwrap()[0].0,
+        PathBuf::from("/tmp/a.css")
+    );
+    assert!(parse_workspace_edit(
+        &json!({ "documentChanges": [{ "kind": "create", "uri": uri }] })
+    )
+    .is_err());
+}
+
+// 位置は UTF-16 で数え、後ろの編集から適用する
+fn test_apply_text_edits() {
+    let edit = |range: LspRange, text: &str| TextEdit {
+        range,
+        new_text: text.to_string(),
+    };
+    let text = "let a = 1;\n// 😀 a\nf(a);\n";
+    let edits = [
+        edit(range(2, 2, 3), "count"),
+        edit(range(0, 4, 5), "count"),
+        edit(range(1, 6, 7), "count"),
+    ];
+
+    assert_eq!(
+        apply_text_edits(text, &edits),
+        "let count = 1;\n// 😀 count\nf(count);\n"
+    );
+
+    // 行をまたぐ編集と末尾への挿入
+    let edits = [
+        edit(
+            LspRange {
+                start: LspPosition::new(0, 9),
+                end: LspPosition::new(2, 0),
+            },
+            ";\n",
+        ),
+        edit(range(3, 0, 0), "end\n"),
+    ];
+    assert_eq!(apply_text_edits(text, &edits), "let a = 1;\nf(a);\nend\n");
+}
+
+// initialize の応答より前のリクエストも届き、エラーはメッセージとして受け取る
+fn test_requests() {
+    let file = env::temp_dir().join("requests.css");
+
+    let server = FakeServer::new(
+        "requests",
+        json!([
+            { "expect": "initialize", "reply": { "capabilities": {} } },
+            { "expect": "textDocument/hover", "reply": { "contents": "a color" } },
+            { "expect": "textDocument/rename", "error": "cannot rename" },
+            { "expect": "shutdown", "reply": null },
+            { "expect": "exit" },
+        ]),
+    );
+
+    let mut client = server.start();
+    client
+        .request_at(
+            "textDocument/hover",
+            &file,
+            LspPosition::new(0, 3),
+            json!({}),
+        )
+        .unwrap();
+    client
+        .request_at(
+            "textDocument/rename",
+            &file,
+            LspPosition::new(0, 3),
+            json!({ "newName": "b" }),
+        )
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut responses = vec![];
+    while responses.len() < 2 {
+        assert!(Instant::now() < deadline, "no responses");
+        for event in client.poll() {
+            if let LspEvent::Response { method, result } = event {
+                responses.push((method, result));
+            }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    client.shutdown();
+
+    assert_eq!(
+        responses,
+        vec![
+            (
+                "textDocument/hover".to_string(),
+                Ok(json!({ "contents": "a color" }))
+            ),
+            (
+                "textDocument/rename".to_string(),
+                Err("cannot rename".to_string())
+            ),
+        ]
+    );
+
+    let log = server.get_log();
+    let rename = &log
+        .iter()
+        .find(|message| message["method"] == "textDocument/rename")
+        .unwrap()["params"];
+    assert_eq!(rename["textDocument"]["uri"], path_to_uri(&file));
+    assert_eq!(rename["position"], json!({ "line": 0, "character": 3 }));
+    assert_eq!(rename["newName"], "b");
+}
+
+// 整形は応答を待つ (待っている間に届いた診断は次の poll で受け取る)
+fn test_formatting() {
+    let file = env::temp_dir().join("formatting.css");
+    let edit = json!({ "range": range(0, 1, 1), "newText": " " });
+
+    let server = FakeServer::new(
+        "formatting",
+        json!([
+            {
+                "expect": "initialize",
+                "reply": { "capabilities": { "documentFormattingProvider": true } },
+            },
+            {
+                "send": {
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": path_to_uri(&file), "diagnostics": [] },
+                },
+            },
+            { "expect": "textDocument/formatting", "reply": [edit] },
+            { "expect": "textDocument/formatting", "error": "cannot format" },
+            { "expect": "shutdown", "reply": null },
+            { "expect": "exit" },
+        ]),
+    );
+
+    let mut client = server.start();
+    let edits = client.formatting(&file, 4).unwrap();
+    assert_eq!(apply_text_edits("a{}", &edits), "a {}");
+
+    let events = client.poll();
+    assert!(matches!(
+        events.as_slice(),
+        [LspEvent::Diagnostics { path, diagnostics }] if *path == file && diagnostics.is_empty()
+    ));
+
+    let error = client.formatting(&file, 4).unwrap_err();
+    assert_eq!(error.to_string(), "cannot format");
+    client.shutdown();
+
+    let log = server.get_log();
+    let formatting = &log
+        .iter()
+        .find(|message| message["method"] == "textDocument/formatting")
+        .unwrap()["params"];
+    assert_eq!(formatting["textDocument"]["uri"], path_to_uri(&file));
+    assert_eq!(
+        formatting["options"],
+        json!({ "tabSize": 4, "insertSpaces": true })
+    );
+}
+
+fn test_invalid_message() {
+    let file = env::temp_dir().join("invalid.css");
+
+    let server = FakeServer::new(
+        "invalid",
+        json!([
+            { "expect": "initialize", "reply": { "capabilities": {} } },
+            { "send_raw": "{\"jsonrpc\": \"2.0\", " },
+            {
+                "send": {
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": {
+                        "uri": path_to_uri(&file),
+                        "diagnostics": [{
+                            "range": { "start": { "line": 0, "character": 1 }, "end": { "line": 0, "character": 2 } },
+                            "severity": 1,
+                            "message": "unexpected token",
+                        }],
+                    },
+                },
+            },
+            { "expect": "shutdown", "reply": null },
+            { "expect": "exit" },
+        ]),
+    );
+
+    // 読めないメッセージは読み飛ばし、その後のメッセージは受け取る
+    let mut client = server.start();
+    let (path, diagnostics) = wait_for_diagnostics(&mut client).expect("no diagnostics");
+    assert_eq!(path, file);
+    assert_eq!(diagnostics, vec![json!([0, 1, false, "unexpected token"])]);
+    client.shutdown();
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--fake-server") {
+        run_fake_server(Path::new(&args[2]), Path::new(&args[3]));
+        return;
+    }
+
+    let tests: [(&str, fn()); 9] = [
+        ("framing", test_framing),
+        ("uri", test_uri),
+        ("document_sync", test_document_sync),
+        ("full_sync", test_full_sync),
+        ("parse", test_parse),
+        ("apply_text_edits", test_apply_text_edits),
+        ("requests", test_requests),
+        ("formatting", test_formatting),
+        ("invalid_message", test_invalid_message),
+    ];
+
+    for (name, test) in tests {
+        test();
+        println!("test {} ... ok", name);
+    }
+    println!("\ntest result: ok. {} passed", tests.len());
+}